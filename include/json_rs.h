@@ -0,0 +1,62 @@
+/*
+ * C interface to json-rs, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Values are opaque handles owned by the caller until they are passed to
+ * json_rs_free.  Strings out of json_rs_to_string are freed with
+ * json_rs_string_free.
+ */
+#ifndef JSON_RS_H
+#define JSON_RS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct json_rs_value json_rs_value;
+
+typedef enum json_rs_status {
+    JSON_RS_OK = 0,
+    JSON_RS_NULL_ARGUMENT = 1,
+    JSON_RS_INVALID_UTF8 = 2,
+    JSON_RS_UNEXPECTED_TOKEN = 3,
+    JSON_RS_UNEXPECTED_EOF = 4,
+    JSON_RS_INVALID_POINTER = 5,
+    JSON_RS_NOT_FOUND = 6,
+    JSON_RS_NOT_A_CONTAINER = 7,
+    JSON_RS_INVALID_INDEX = 8,
+    JSON_RS_PANIC = 9,
+    JSON_RS_DEPTH_LIMIT_EXCEEDED = 10
+} json_rs_status;
+
+/* Parses len bytes of UTF-8, which need not end in a NUL.  On a syntax error
+ * the character index of the problem is stored in error_location, which may be
+ * NULL. */
+json_rs_status json_rs_parse(const uint8_t *text, size_t len, json_rs_value **out,
+                             size_t *error_location);
+
+/* Frees a value; NULL is ignored. */
+void json_rs_free(json_rs_value *value);
+
+/* Serializes a value as compact JSON into a new NUL-terminated string. */
+json_rs_status json_rs_to_string(const json_rs_value *value, char **out);
+
+/* Frees a string from json_rs_to_string; NULL is ignored. */
+void json_rs_string_free(char *string);
+
+/* Copies the value at a JSON Pointer such as "/items/0" into a new handle. */
+json_rs_status json_rs_get(const json_rs_value *value, const char *pointer, json_rs_value **out);
+
+/* Sets the value at a JSON Pointer to a copy of item, replacing what is there
+ * or adding an object key or array element ("-" appends). */
+json_rs_status json_rs_set(json_rs_value *value, const char *pointer, const json_rs_value *item);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,377 @@
+//! Derive macros for the `ToJson` and `FromJson` traits of `json_rs`.
+//!
+//! These are re-exported by `json_rs` when its `derive` feature is enabled, and documented
+//! there.
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as Tokens};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, ExprPath, Fields,
+    Generics, Ident, LitStr, Member,
+};
+
+/// Derives `ToJson`.
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_json(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `FromJson`.
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_json(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// What a field is missing from an object is replaced with.
+enum Missing {
+    /// Whatever the field type converts from `null`, so that `Option` fields may be left out
+    Null,
+    /// `Default::default()`
+    Trait,
+    /// A function returning the value
+    Path(ExprPath),
+}
+
+/// The `#[json(...)]` attributes of a field or variant.
+struct Attrs {
+    rename: Option<String>,
+    default: Missing,
+    skip: bool,
+    flatten: bool,
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self, Error> {
+        let mut parsed = Attrs {
+            rename: None,
+            default: Missing::Null,
+            skip: false,
+            flatten: false,
+        };
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    parsed.default = if meta.input.peek(syn::Token![=]) {
+                        Missing::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        Missing::Trait
+                    };
+                } else if meta.path.is_ident("skip") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = true;
+                } else {
+                    return Err(meta.error("unknown json attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// A field along with its attributes and the name it has in JSON.
+struct Field {
+    member: Member,
+    binding: Ident,
+    key: String,
+    attrs: Attrs,
+}
+
+fn fields(fields: &Fields) -> Result<Vec<Field>, Error> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(ind, field)| {
+            let attrs = Attrs::parse(&field.attrs)?;
+            let (member, name) = match &field.ident {
+                Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+                None => (Member::Unnamed(ind.into()), ind.to_string()),
+            };
+            if field.ident.is_none() && (attrs.rename.is_some() || attrs.flatten) {
+                return Err(Error::new_spanned(
+                    field,
+                    "tuple fields cannot be renamed or flattened",
+                ));
+            }
+            Ok(Field {
+                member,
+                binding: format_ident!("field{}", ind),
+                key: attrs.rename.clone().unwrap_or(name),
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// Adds `bound` to every type parameter.
+fn bounded(generics: &Generics, bound: Tokens) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn variant_key(variant: &syn::Variant) -> Result<String, Error> {
+    let attrs = Attrs::parse(&variant.attrs)?;
+    Ok(attrs.rename.unwrap_or_else(|| variant.ident.to_string()))
+}
+
+/// Builds the JSON for fields that have been bound to `field0`, `field1`, and so on.
+fn fields_to_json(fields: &[Field], named: bool) -> Tokens {
+    let fields = fields.iter().filter(|field| !field.attrs.skip);
+    if !named {
+        let bindings = fields.map(|field| &field.binding).collect::<Vec<_>>();
+        return if bindings.len() == 1 {
+            quote!(::json_rs::ToJson::to_json(#(#bindings)*))
+        } else {
+            quote!(::json_rs::JsonValue::Array(vec![
+                #(::json_rs::ToJson::to_json(#bindings)),*
+            ]))
+        };
+    }
+
+    let members = fields.map(|field| {
+        let (binding, key) = (&field.binding, &field.key);
+        if field.attrs.flatten {
+            quote! {
+                if let ::json_rs::JsonValue::Object(ref mut flattened) = ::json_rs::ToJson::to_json(#binding) {
+                    members.extend(::std::mem::take(&mut **flattened));
+                }
+            }
+        } else {
+            quote!(members.push((::json_rs::JsonString::from(#key), ::json_rs::ToJson::to_json(#binding)));)
+        }
+    });
+    quote!({
+        let mut members = ::std::vec::Vec::new();
+        #(#members)*
+        ::json_rs::JsonValue::Object(::std::boxed::Box::new(members.into_iter().collect()))
+    })
+}
+
+/// Reads fields out of `value`, producing a struct or variant body for `path`.
+fn fields_from_json(path: Tokens, fields: &[Field], named: bool) -> Tokens {
+    let with_values = fields.iter().filter(|field| !field.attrs.skip).count();
+    let values = fields.iter().scan(0usize, |ind, field| {
+        let expr = if field.attrs.skip {
+            quote!(::std::default::Default::default())
+        } else if !named && with_values == 1 {
+            quote!(::json_rs::FromJson::from_json(value)?)
+        } else if !named {
+            *ind += 1;
+            let ind = *ind - 1;
+            quote!(::json_rs::FromJson::from_json(&items[#ind])?)
+        } else if field.attrs.flatten {
+            quote!(::json_rs::FromJson::from_json(value)?)
+        } else {
+            let key = &field.key;
+            let missing = match &field.attrs.default {
+                Missing::Null => quote! {
+                    ::json_rs::FromJson::from_json(&::json_rs::JsonValue::Null)
+                        .map_err(|_| ::json_rs::FromJsonError::MissingField(#key.to_string()))?
+                },
+                Missing::Trait => quote!(::std::default::Default::default()),
+                Missing::Path(path) => quote!(#path()),
+            };
+            quote! {
+                match value.get(#key) {
+                    ::std::option::Option::Some(field) => ::json_rs::FromJson::from_json(field)?,
+                    ::std::option::Option::None => #missing,
+                }
+            }
+        };
+        let member = &field.member;
+        Some(quote!(#member: #expr))
+    });
+    let construct = quote!(#path { #(#values),* });
+
+    if named {
+        quote! {
+            match value {
+                ::json_rs::JsonValue::Object(_) => #construct,
+                _ => return ::std::result::Result::Err(
+                    ::json_rs::FromJsonError::invalid_type("an object", value)
+                ),
+            }
+        }
+    } else if with_values == 1 {
+        construct
+    } else {
+        let expected = format!("an array of {} elements", with_values);
+        quote! {
+            match value {
+                ::json_rs::JsonValue::Array(items) if items.len() == #with_values => #construct,
+                ::json_rs::JsonValue::Array(_) => return ::std::result::Result::Err(
+                    ::json_rs::FromJsonError::Message(#expected.to_string())
+                ),
+                _ => return ::std::result::Result::Err(
+                    ::json_rs::FromJsonError::invalid_type("an array", value)
+                ),
+            }
+        }
+    }
+}
+
+/// Binds every field of `path` by reference to `field0`, `field1`, and so on.
+fn pattern(path: Tokens, fields: &[Field]) -> Tokens {
+    let bindings = fields.iter().map(|field| {
+        let (member, binding) = (&field.member, &field.binding);
+        quote!(#member: ref #binding)
+    });
+    quote!(#path { #(#bindings),* })
+}
+
+fn to_json(input: &DeriveInput) -> Result<Tokens, Error> {
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => quote!(::json_rs::JsonValue::Null),
+            syn_fields => {
+                let fields = fields(syn_fields)?;
+                let pattern = pattern(quote!(#name), &fields);
+                let json = fields_to_json(&fields, matches!(syn_fields, Fields::Named(_)));
+                quote!(let #pattern = *self; #json)
+            }
+        },
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let (ident, key) = (&variant.ident, variant_key(variant)?);
+                    Ok(match &variant.fields {
+                        Fields::Unit => quote! {
+                            #name::#ident => ::json_rs::JsonValue::Text(::json_rs::JsonString::from(#key))
+                        },
+                        syn_fields => {
+                            let fields = fields(syn_fields)?;
+                            let pattern = pattern(quote!(#name::#ident), &fields);
+                            let json =
+                                fields_to_json(&fields, matches!(syn_fields, Fields::Named(_)));
+                            quote! {
+                                #pattern => ::json_rs::JsonValue::Object(::std::boxed::Box::new(
+                                    ::std::iter::once((::json_rs::JsonString::from(#key), #json)).collect()
+                                ))
+                            }
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            quote!(match *self { #(#arms,)* })
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "ToJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    let generics = bounded(&input.generics, quote!(::json_rs::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::json_rs::ToJson for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn to_json(&self) -> ::json_rs::JsonValue {
+                #body
+            }
+        }
+    })
+}
+
+fn from_json(input: &DeriveInput) -> Result<Tokens, Error> {
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => quote! {
+                <() as ::json_rs::FromJson>::from_json(value).map(|()| #name)
+            },
+            syn_fields => {
+                let fields = fields(syn_fields)?;
+                let construct = fields_from_json(
+                    quote!(#name),
+                    &fields,
+                    matches!(syn_fields, Fields::Named(_)),
+                );
+                quote!(::std::result::Result::Ok(#construct))
+            }
+        },
+        Data::Enum(data) => {
+            let mut units = Vec::new();
+            let mut others = Vec::new();
+            for variant in data.variants.iter() {
+                let (ident, key) = (&variant.ident, variant_key(variant)?);
+                match &variant.fields {
+                    Fields::Unit => units.push(quote!(#key => #name::#ident)),
+                    syn_fields => {
+                        let fields = fields(syn_fields)?;
+                        let construct = fields_from_json(
+                            quote!(#name::#ident),
+                            &fields,
+                            matches!(syn_fields, Fields::Named(_)),
+                        );
+                        others.push(quote!(#key => #construct));
+                    }
+                }
+            }
+            quote! {
+                let unknown = |variant: &str| ::json_rs::FromJsonError::Message(
+                    format!("unknown variant `{}`", variant)
+                );
+                match value {
+                    ::json_rs::JsonValue::Text(variant) => ::std::result::Result::Ok(
+                        match variant.as_str() {
+                            #(#units,)*
+                            _ => return ::std::result::Result::Err(unknown(variant)),
+                        }
+                    ),
+                    ::json_rs::JsonValue::Object(members) if members.len() == 1 => {
+                        let (variant, value) = members.iter().next().unwrap();
+                        ::std::result::Result::Ok(match variant.as_str() {
+                            #(#others,)*
+                            _ => return ::std::result::Result::Err(unknown(variant)),
+                        })
+                    }
+                    _ => ::std::result::Result::Err(::json_rs::FromJsonError::invalid_type(
+                        "a string or an object with a single key",
+                        value,
+                    )),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "FromJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    let generics = bounded(&input.generics, quote!(::json_rs::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::json_rs::FromJson for #name #ty_generics #where_clause {
+            #[allow(unreachable_code)]
+            fn from_json(
+                value: &::json_rs::JsonValue,
+            ) -> ::std::result::Result<Self, ::json_rs::FromJsonError> {
+                #body
+            }
+        }
+    })
+}
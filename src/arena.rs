@@ -0,0 +1,384 @@
+//! A JSON tree stored in a handful of flat buffers rather than a box per node.
+use super::events::{borrowed_text, EventParser, Step};
+use super::lexer::Punct;
+use super::{JsonError, JsonMap, JsonNumber, JsonValue};
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::slice;
+
+/// A handle to a node in a [`JsonArena`](struct.JsonArena.html).  Handles are only meaningful
+/// to the arena that made them, and only until it is cleared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+/// A range of the arena's strings.
+type Str = (u32, u32);
+
+#[derive(Clone, Copy, Debug)]
+enum Node {
+    Text(Str),
+    Number(JsonNumber),
+    Boolean(bool),
+    Null,
+    /// A range of `items`
+    Array(u32, u32),
+    /// A range of `members`
+    Object(u32, u32),
+}
+
+/// Storage for any number of parsed documents, whose nodes and strings are all freed together.
+///
+/// Every string of every document shares one buffer, and the elements of each array and the
+/// members of each object sit next to each other in one more, so parsing allocates only as the
+/// buffers grow and reading never chases a pointer per node.  Object members keep their source
+/// order; if a key repeats, lookups find the last one, as `json_parse` would keep.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, ArenaValue, JsonArena};
+///
+/// fn main() {
+///     let mut arena = JsonArena::new();
+///     let source = r#"{ "users": [{ "name": "ada" }, { "name": "alan" }] }"#;
+///     let root = arena.parse(source).unwrap();
+///
+///     let users = arena.get(root, "users").unwrap();
+///     if let ArenaValue::Array(users) = arena.value(users) {
+///         let names = users
+///             .iter()
+///             .map(|&user| arena.value(arena.get(user, "name").unwrap()))
+///             .collect::<Vec<_>>();
+///         assert_eq!(names, vec![ArenaValue::Text("ada"), ArenaValue::Text("alan")]);
+///     }
+///     let expected = json_parse(r#"[{ "name": "ada" }, { "name": "alan" }]"#).unwrap();
+///     assert_eq!(arena.to_json(users), expected);
+///
+///     arena.clear();
+///     assert!(arena.is_empty());
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct JsonArena {
+    nodes: Vec<Node>,
+    strings: String,
+    items: Vec<NodeId>,
+    members: Vec<(Str, NodeId)>,
+}
+
+/// A view of one node in an arena.  Strings borrow the arena, and containers list the handles
+/// of their children.
+#[derive(Clone, Debug)]
+pub enum ArenaValue<'a> {
+    /// A JSON string value.
+    Text(&'a str),
+    /// A numeric JSON value.
+    Number(JsonNumber),
+    /// A JSON boolean value.
+    Boolean(bool),
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array(&'a [NodeId]),
+    /// A JSON object.
+    Object(Members<'a>),
+}
+
+impl<'a> PartialEq for ArenaValue<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ArenaValue::Text(a), ArenaValue::Text(b)) => a == b,
+            (ArenaValue::Number(a), ArenaValue::Number(b)) => a == b,
+            (ArenaValue::Boolean(a), ArenaValue::Boolean(b)) => a == b,
+            (ArenaValue::Null, ArenaValue::Null) => true,
+            (ArenaValue::Array(a), ArenaValue::Array(b)) => a == b,
+            (ArenaValue::Object(a), ArenaValue::Object(b)) => a.clone().eq(b.clone()),
+            _ => false,
+        }
+    }
+}
+
+/// An iterator over the keys and values of an object in an arena, in source order.
+#[derive(Clone, Debug)]
+pub struct Members<'a> {
+    strings: &'a str,
+    members: slice::Iter<'a, (Str, NodeId)>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (&'a str, NodeId);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let strings = self.strings;
+        self.members
+            .next()
+            .map(|&(key, value)| (str_at(strings, key), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.members.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Members<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let strings = self.strings;
+        self.members
+            .next_back()
+            .map(|&(key, value)| (str_at(strings, key), value))
+    }
+}
+
+impl<'a> ExactSizeIterator for Members<'a> {}
+
+fn str_at(strings: &str, (start, end): Str) -> &str {
+    &strings[start as usize..end as usize]
+}
+
+enum Partial {
+    /// The start of the array's elements in the scratch stack
+    Array(usize),
+    /// The start of the object's members in the scratch stack, and the key being read
+    Object(usize, Option<Str>),
+}
+
+impl JsonArena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the arena has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Frees every node at once, keeping the buffers' memory for the next documents.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.strings.clear();
+        self.items.clear();
+        self.members.clear();
+    }
+
+    fn push_str(&mut self, text: &str) -> Str {
+        let start = self.strings.len() as u32;
+        self.strings.push_str(text);
+        (start, self.strings.len() as u32)
+    }
+
+    fn push_node(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    /// Parses a document into the arena, returning the handle of its root.  This accepts exactly
+    /// the documents [`json_parse`](fn.json_parse.html) does, nested at most 128 levels deep,
+    /// with errors reported in source order as in
+    /// [`json_parse_spanned`](fn.json_parse_spanned.html).  If parsing fails the arena is left
+    /// as it was.
+    pub fn parse(&mut self, json_str: &str) -> Result<NodeId, JsonError> {
+        let lens = (
+            self.nodes.len(),
+            self.strings.len(),
+            self.items.len(),
+            self.members.len(),
+        );
+        let root = self.parse_root(json_str);
+        if root.is_err() {
+            self.nodes.truncate(lens.0);
+            self.strings.truncate(lens.1);
+            self.items.truncate(lens.2);
+            self.members.truncate(lens.3);
+        }
+        root
+    }
+
+    fn parse_root(&mut self, json_str: &str) -> Result<NodeId, JsonError> {
+        let mut parser = EventParser::borrowing(json_str);
+        let mut partials = vec![];
+        let mut items = vec![];
+        let mut members = vec![];
+        let mut root = None;
+
+        while let Some(step) = parser.step()? {
+            let complete = match step {
+                Step::Punct(Punct::ArrayStart, _) => {
+                    partials.push(Partial::Array(items.len()));
+                    continue;
+                }
+                Step::Punct(Punct::ObjectStart, _) => {
+                    partials.push(Partial::Object(members.len(), None));
+                    continue;
+                }
+                Step::Key(key, span) => {
+                    let key = self.push_str(&borrowed_text(json_str, key, span));
+                    if let Some(Partial::Object(_, pending)) = partials.last_mut() {
+                        *pending = Some(key);
+                    }
+                    continue;
+                }
+                Step::Value(mut value, span) => {
+                    let node =
+                        match value {
+                            JsonValue::Text(ref mut decoded) => Node::Text(self.push_str(
+                                &borrowed_text(json_str, mem::take(decoded).into(), span),
+                            )),
+                            JsonValue::Number(n) => Node::Number(n),
+                            JsonValue::Boolean(b) => Node::Boolean(b),
+                            _ => Node::Null,
+                        };
+                    self.push_node(node)
+                }
+                Step::Punct(Punct::ArrayEnd, _) | Step::Punct(Punct::ObjectEnd, _) => {
+                    let node = match partials.pop() {
+                        Some(Partial::Array(start)) => {
+                            let range = self.items.len() as u32;
+                            self.items.extend(items.drain(start..));
+                            Node::Array(range, self.items.len() as u32)
+                        }
+                        Some(Partial::Object(start, _)) => {
+                            let range = self.members.len() as u32;
+                            self.members.extend(members.drain(start..));
+                            Node::Object(range, self.members.len() as u32)
+                        }
+                        None => unreachable!(),
+                    };
+                    self.push_node(node)
+                }
+                Step::Punct(_, _) | Step::Whitespace(_) | Step::Comment(_) => continue,
+            };
+
+            match partials.last_mut() {
+                Some(Partial::Array(_)) => items.push(complete),
+                Some(Partial::Object(_, key)) => members.push((key.take().unwrap(), complete)),
+                None => root = Some(complete),
+            }
+        }
+        root.ok_or_else(|| JsonError::eof(json_str))
+    }
+
+    /// Looks at a node.
+    ///
+    /// # Panics
+    /// If `id` is not a handle from this arena since it was last cleared.
+    pub fn value(&self, id: NodeId) -> ArenaValue<'_> {
+        match self.nodes[id.0 as usize] {
+            Node::Text(text) => ArenaValue::Text(str_at(&self.strings, text)),
+            Node::Number(n) => ArenaValue::Number(n),
+            Node::Boolean(b) => ArenaValue::Boolean(b),
+            Node::Null => ArenaValue::Null,
+            Node::Array(start, end) => ArenaValue::Array(&self.items[start as usize..end as usize]),
+            Node::Object(start, end) => ArenaValue::Object(Members {
+                strings: &self.strings,
+                members: self.members[start as usize..end as usize].iter(),
+            }),
+        }
+    }
+
+    /// Gets the value at a specific key of an object node or, for arrays, at a specific index,
+    /// as with [`JsonValue::get`](enum.JsonValue.html#method.get).
+    pub fn get(&self, id: NodeId, key: &str) -> Option<NodeId> {
+        match self.value(id) {
+            ArenaValue::Object(mut members) => members
+                .rfind(|&(member, _)| member == key)
+                .map(|(_, value)| value),
+            ArenaValue::Array(items) => key.parse().ok().and_then(|i: usize| items.get(i).cloned()),
+            _ => None,
+        }
+    }
+
+    /// Gets the value at a specific index of an array node, as with
+    /// [`JsonValue::get_ind`](enum.JsonValue.html#method.get_ind).
+    pub fn get_ind(&self, id: NodeId, ind: usize) -> Option<NodeId> {
+        match self.value(id) {
+            ArenaValue::Array(items) => items.get(ind).cloned(),
+            ArenaValue::Object(_) => self.get(id, &ind.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Copies a node and everything under it out into a `JsonValue`.
+    pub fn to_json(&self, id: NodeId) -> JsonValue {
+        match self.value(id) {
+            ArenaValue::Text(text) => JsonValue::Text(text.into()),
+            ArenaValue::Number(n) => JsonValue::Number(n),
+            ArenaValue::Boolean(b) => JsonValue::Boolean(b),
+            ArenaValue::Null => JsonValue::Null,
+            ArenaValue::Array(items) => {
+                JsonValue::Array(items.iter().map(|&item| self.to_json(item)).collect())
+            }
+            ArenaValue::Object(members) => JsonValue::Object(Box::new(
+                members
+                    .map(|(key, value)| (key.into(), self.to_json(value)))
+                    .collect::<JsonMap>(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArenaValue, JsonArena};
+    use crate::json_parse;
+
+    #[test]
+    fn arena_matches_json_parse() {
+        use std::fs::{read, read_dir};
+
+        let mut arena = JsonArena::new();
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            assert_eq!(
+                arena.parse(&source).map(|root| arena.to_json(root)),
+                json_parse(&source)
+            );
+        }
+
+        // both allow 128 levels and stop at the same bracket past them
+        for depth in [128, 129] {
+            let (open, close) = ("[{\"a\":".repeat(depth / 2), "}]".repeat(depth / 2));
+            let source = format!("{}{}{}", open, "[]".repeat(depth % 2), close);
+            assert_eq!(
+                arena.parse(&source).map(|root| arena.to_json(root)),
+                json_parse(&source)
+            );
+        }
+    }
+
+    #[test]
+    fn arena_documents() {
+        let mut arena = JsonArena::new();
+        let first = arena.parse(r#"{ "a": 1, "a!": [], "a": "ώ\n" }"#).unwrap();
+        let len = arena.len();
+        assert!(arena.parse(r#"[1, {"b": tru]"#).is_err());
+        assert_eq!(arena.len(), len);
+        let second = arena.parse("[null, false]").unwrap();
+
+        assert_eq!(
+            arena.get(first, "a").map(|id| arena.value(id)),
+            Some(ArenaValue::Text("ώ\n"))
+        );
+        assert_eq!(
+            arena.get(first, "a!").map(|id| arena.value(id)),
+            Some(ArenaValue::Array(&[]))
+        );
+        if let ArenaValue::Object(members) = arena.value(first) {
+            let keys = members.map(|(key, _)| key).collect::<Vec<_>>();
+            assert_eq!(keys, vec!["a", "a!", "a"]);
+        }
+        assert_eq!(
+            arena.get_ind(second, 1).map(|id| arena.value(id)),
+            Some(ArenaValue::Boolean(false))
+        );
+        assert_eq!(arena.get(second, "2"), None);
+        assert_eq!(
+            arena.to_json(first),
+            json_parse(r#"{ "a": "ώ\n", "a!": [] }"#).unwrap()
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! The `json-rs` command line tool, built with the `cli` feature.
+extern crate json_rs;
+
+use json_rs::filter::{PathFilter, PathPattern};
+use json_rs::stream::{json_stream, StreamError};
+use json_rs::{json_parse, tokenize, JsonError, JsonPointer, TokenKind};
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+usage: json-rs <command> [arguments]
+
+commands:
+  validate [FILE...]   check that each file holds one or more JSON values
+  pretty [FILE]        print a document indented by two spaces
+  minify [FILE]        print a document without whitespace
+  query EXPR [FILE]    print the value at a JSON Pointer such as /items/0, or the
+                       parts of a document matched by a path such as $.items[*].id
+
+FILE defaults to standard input, which can also be named as -.";
+
+/// Why a command failed, already worded for the user.
+struct Failure(String);
+
+impl From<io::Error> for Failure {
+    fn from(error: io::Error) -> Self {
+        Failure(error.to_string())
+    }
+}
+
+fn read(path: &str) -> Result<Vec<u8>, Failure> {
+    let mut bytes = Vec::new();
+    if path == "-" {
+        io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        bytes = fs::read(path).map_err(|e| Failure(format!("{}: {}", path, e)))?;
+    }
+    Ok(bytes)
+}
+
+fn read_text(path: &str) -> Result<String, Failure> {
+    String::from_utf8(read(path)?).map_err(|e| {
+        Failure(format!(
+            "{}: invalid UTF-8 at byte {}",
+            path,
+            e.utf8_error().valid_up_to()
+        ))
+    })
+}
+
+/// Describes a syntax error with its line and column and the line it is on.
+fn describe(path: &str, source: &str, error: &JsonError) -> Failure {
+    let (problem, line, column) = match *error {
+        JsonError::UnexpectedToken {
+            character,
+            line,
+            column,
+            ..
+        } => (format!("unexpected {:?}", character), line, column),
+        JsonError::DuplicateKey {
+            ref key,
+            line,
+            column,
+            ..
+        } => (format!("duplicate key {:?}", key), line, column),
+        JsonError::DepthLimitExceeded {
+            limit,
+            line,
+            column,
+            ..
+        } => (
+            format!("nested more than {} levels deep", limit),
+            line,
+            column,
+        ),
+        JsonError::NumberOutOfRange { line, column, .. } => (
+            "number too precise to read exactly".to_string(),
+            line,
+            column,
+        ),
+        JsonError::UnexpectedEOF { .. } => {
+            return Failure(format!("{}: unexpected end of input", path))
+        }
+    };
+    Failure(format!(
+        "{}:{}:{}: {}\n{}\n{}^",
+        path,
+        line + 1,
+        column + 1,
+        problem,
+        source.lines().nth(line).unwrap_or(""),
+        " ".repeat(column)
+    ))
+}
+
+fn validate(path: &str) -> Result<(), Failure> {
+    let bytes = read(path)?;
+    let mut count = 0;
+    for value in json_stream(&bytes[..]) {
+        match value {
+            Ok(_) => count += 1,
+            Err(StreamError::Parse(error)) => {
+                return Err(describe(path, &String::from_utf8_lossy(&bytes), &error))
+            }
+            Err(StreamError::InvalidUtf8 { offset }) => {
+                return Err(Failure(format!(
+                    "{}: invalid UTF-8 in the value at byte {}",
+                    path, offset
+                )))
+            }
+            Err(StreamError::Io(error)) => return Err(error.into()),
+        }
+    }
+    if count == 0 {
+        return Err(Failure(format!("{}: no JSON value", path)));
+    }
+    Ok(())
+}
+
+/// Validates every file, reporting each invalid one rather than stopping at the first.
+fn validate_all(paths: &[String]) -> Result<(), Failure> {
+    let mut valid = true;
+    for path in paths {
+        if let Err(Failure(message)) = validate(path) {
+            eprintln!("{}", message);
+            valid = false;
+        }
+    }
+    if valid {
+        Ok(())
+    } else {
+        Err(Failure(String::new()))
+    }
+}
+
+/// Writes a document again token by token, so keys keep their order and numbers their digits.
+fn reformat<W: Write>(path: &str, indent: Option<&str>, out: &mut W) -> Result<(), Failure> {
+    let source = read_text(path)?;
+    let mut tokens = tokenize(&source);
+    let mut significant = Vec::new();
+    for token in &mut tokens {
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => (),
+            TokenKind::Error => break,
+            _ => significant.push(token.text),
+        }
+    }
+    if let Some(error) = tokens.error() {
+        return Err(describe(path, &source, error));
+    }
+
+    let newline = |out: &mut W, depth: usize| -> io::Result<()> {
+        match indent {
+            Some(indent) => write!(out, "\n{}", indent.repeat(depth)),
+            None => Ok(()),
+        }
+    };
+    let mut depth = 0;
+    let mut previous = "";
+    for (index, &text) in significant.iter().enumerate() {
+        let next = significant.get(index + 1).cloned();
+        match text {
+            "{" | "[" if next != Some("}") && next != Some("]") => {
+                depth += 1;
+                out.write_all(text.as_bytes())?;
+                newline(out, depth)?;
+            }
+            "}" | "]" if previous != "{" && previous != "[" => {
+                depth -= 1;
+                newline(out, depth)?;
+                out.write_all(text.as_bytes())?;
+            }
+            "," => {
+                out.write_all(b",")?;
+                newline(out, depth)?;
+            }
+            ":" if indent.is_some() => out.write_all(b": ")?,
+            _ => out.write_all(text.as_bytes())?,
+        }
+        previous = text;
+    }
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+fn query<W: Write>(expr: &str, path: &str, out: &mut W) -> Result<(), Failure> {
+    if expr.starts_with('$') {
+        let pattern = expr
+            .parse::<PathPattern>()
+            .map_err(|e| Failure(format!("invalid path {}: {:?}", expr, e)))?;
+        let bytes = read(path)?;
+        let mut filtered = Vec::new();
+        return match PathFilter::new()
+            .include(pattern)
+            .filter(&bytes[..], &mut filtered)
+        {
+            Ok(()) => {
+                out.write_all(&filtered)?;
+                out.write_all(b"\n")?;
+                Ok(())
+            }
+            Err(StreamError::Parse(error)) => {
+                Err(describe(path, &String::from_utf8_lossy(&bytes), &error))
+            }
+            Err(error) => Err(Failure(format!("{}: {:?}", path, error))),
+        };
+    }
+
+    let pointer = expr
+        .parse::<JsonPointer>()
+        .map_err(|e| Failure(format!("invalid pointer {}: {:?}", expr, e)))?;
+    let source = read_text(path)?;
+    let value = json_parse(&source).map_err(|e| describe(path, &source, &e))?;
+    match pointer.resolve(&value) {
+        Some(found) => writeln!(out, "{}", found).map_err(Failure::from),
+        None => Err(Failure(format!("{}: nothing at {}", path, expr))),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let file = |index: usize| args.get(index).map_or("-", String::as_str);
+    let result = match args.first().map(String::as_str) {
+        Some("validate") if args.len() == 1 => validate_all(&["-".to_string()]),
+        Some("validate") => validate_all(&args[1..]),
+        Some("pretty") if args.len() <= 2 => reformat(file(1), Some("  "), &mut out),
+        Some("minify") if args.len() <= 2 => reformat(file(1), None, &mut out),
+        Some("query") if args.len() == 2 || args.len() == 3 => query(&args[1], file(2), &mut out),
+        _ => Err(Failure(USAGE.to_string())),
+    };
+    out.flush()?;
+    result
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(Failure(message)) = run(&args) {
+        if !message.is_empty() {
+            eprintln!("{}", message);
+        }
+        process::exit(if message == USAGE { 2 } else { 1 });
+    }
+}
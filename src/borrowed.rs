@@ -0,0 +1,309 @@
+//! A read-only JSON tree that borrows its strings from the source text.
+use super::events::{borrowed_text, EventParser, Step};
+use super::lexer::Punct;
+use super::{JsonError, JsonNumber, JsonValue};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A JSON value whose strings and keys point into the text it was parsed from.  See
+/// [`json_parse_borrowed`](fn.json_parse_borrowed.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValueRef<'a> {
+    /// A JSON string value, borrowed unless it had escapes to decode.
+    Text(Cow<'a, str>),
+    /// A numeric JSON value.
+    Number(JsonNumber),
+    /// A JSON boolean value.
+    Boolean(bool),
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array(Vec<JsonValueRef<'a>>),
+    /// A JSON object, whose keys are borrowed unless they had escapes to decode.
+    Object(HashMap<Cow<'a, str>, JsonValueRef<'a>>),
+}
+
+impl<'a> JsonValueRef<'a> {
+    /// Gets a reference to the JSON value at a specific key, as with
+    /// [`JsonValue::get`](enum.JsonValue.html#method.get).
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            JsonValueRef::Object(map) => map.get(key),
+            JsonValueRef::Array(array) => key.parse().ok().and_then(|i: usize| array.get(i)),
+            _ => None,
+        }
+    }
+
+    /// Gets a reference to the JSON value at a specific index, as with
+    /// [`JsonValue::get_ind`](enum.JsonValue.html#method.get_ind).
+    pub fn get_ind(&self, ind: usize) -> Option<&Self> {
+        match self {
+            JsonValueRef::Object(map) => map.get(&*ind.to_string()),
+            JsonValueRef::Array(array) => array.get(ind),
+            _ => None,
+        }
+    }
+
+    /// Gets the text of a string value, whether it was borrowed or decoded.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValueRef::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Gets a number value.
+    pub fn as_number(&self) -> Option<JsonNumber> {
+        match self {
+            JsonValueRef::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Gets a boolean value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValueRef::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValueRef::Null)
+    }
+
+    /// Copies the value into a `JsonValue` that no longer borrows the source.
+    pub fn to_owned(&self) -> JsonValue {
+        match self {
+            JsonValueRef::Text(text) => JsonValue::Text(text.as_ref().into()),
+            JsonValueRef::Number(n) => JsonValue::Number(*n),
+            JsonValueRef::Boolean(b) => JsonValue::Boolean(*b),
+            JsonValueRef::Null => JsonValue::Null,
+            JsonValueRef::Array(array) => {
+                JsonValue::Array(array.iter().map(JsonValueRef::to_owned).collect())
+            }
+            JsonValueRef::Object(map) => JsonValue::Object(Box::new(
+                map.iter()
+                    .map(|(key, value)| (key.as_ref().into(), value.to_owned()))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Converts the value into a `JsonValue`, reusing the strings that were decoded rather than
+    /// copying them again.
+    pub fn into_owned(mut self) -> JsonValue {
+        match self {
+            JsonValueRef::Text(ref mut text) => {
+                JsonValue::Text(mem::take(text).into_owned().into())
+            }
+            JsonValueRef::Number(n) => JsonValue::Number(n),
+            JsonValueRef::Boolean(b) => JsonValue::Boolean(b),
+            JsonValueRef::Null => JsonValue::Null,
+            JsonValueRef::Array(ref mut array) => JsonValue::Array(
+                mem::take(array)
+                    .into_iter()
+                    .map(JsonValueRef::into_owned)
+                    .collect(),
+            ),
+            JsonValueRef::Object(ref mut map) => JsonValue::Object(Box::new(
+                mem::take(map)
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned().into(), value.into_owned()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl<'a> Drop for JsonValueRef<'a> {
+    /// Drops nested arrays and objects from a worklist, as `JsonValue` does, so that a deeply
+    /// nested value cannot overflow the stack.
+    fn drop(&mut self) {
+        let mut pending = match self {
+            JsonValueRef::Array(array) if !array.is_empty() => mem::take(array),
+            JsonValueRef::Object(map) if !map.is_empty() => {
+                map.drain().map(|(_, value)| value).collect()
+            }
+            _ => return,
+        };
+        while let Some(mut value) = pending.pop() {
+            match value {
+                JsonValueRef::Array(ref mut array) => pending.append(array),
+                JsonValueRef::Object(ref mut map) => {
+                    pending.extend(map.drain().map(|(_, value)| value))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+enum Partial<'a> {
+    Array(Vec<JsonValueRef<'a>>),
+    Object(
+        HashMap<Cow<'a, str>, JsonValueRef<'a>>,
+        Option<Cow<'a, str>>,
+    ),
+}
+
+/// Deserializes a JSON string without copying the strings in it.
+///
+/// Strings and keys without escapes are slices of `json_str`, and only those with escapes are
+/// decoded into new strings, which saves most allocations when reading large documents.  Call
+/// [`to_owned`](enum.JsonValueRef.html#method.to_owned) for a `JsonValue` that outlives the
+/// source.  This accepts exactly the documents [`json_parse`](fn.json_parse.html) does, nested at
+/// most 128 levels deep, with errors reported in source order as in
+/// [`json_parse_spanned`](fn.json_parse_spanned.html).
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, json_parse_borrowed, JsonValueRef};
+/// use std::borrow::Cow;
+///
+/// fn main() {
+///     let source = r#"{ "name": "ada", "quote": "\"hi\"" }"#;
+///     let json = json_parse_borrowed(source).unwrap();
+///
+///     assert!(matches!(json.get("name"), Some(JsonValueRef::Text(Cow::Borrowed("ada")))));
+///     assert!(matches!(json.get("quote"), Some(JsonValueRef::Text(Cow::Owned(_)))));
+///     assert_eq!(json.to_owned(), json_parse(source).unwrap());
+/// }
+/// ```
+pub fn json_parse_borrowed(json_str: &str) -> Result<JsonValueRef<'_>, JsonError> {
+    let mut parser = EventParser::borrowing(json_str);
+    let mut partials = vec![];
+    let mut root = None;
+
+    while let Some(mut step) = parser.step()? {
+        let complete = match step {
+            Step::Punct(Punct::ArrayStart, _) => {
+                partials.push(Partial::Array(vec![]));
+                continue;
+            }
+            Step::Punct(Punct::ObjectStart, _) => {
+                partials.push(Partial::Object(HashMap::new(), None));
+                continue;
+            }
+            Step::Key(key, span) => {
+                if let Some(Partial::Object(_, pending)) = partials.last_mut() {
+                    *pending = Some(borrowed_text(json_str, key, span));
+                }
+                continue;
+            }
+            Step::Value(JsonValue::Text(ref mut decoded), span) => {
+                JsonValueRef::Text(borrowed_text(json_str, mem::take(decoded).into(), span))
+            }
+            Step::Value(JsonValue::Number(n), _) => JsonValueRef::Number(n),
+            Step::Value(JsonValue::Boolean(b), _) => JsonValueRef::Boolean(b),
+            Step::Value(_, _) => JsonValueRef::Null,
+            Step::Punct(Punct::ArrayEnd, _) | Step::Punct(Punct::ObjectEnd, _) => {
+                match partials.pop() {
+                    Some(Partial::Array(items)) => JsonValueRef::Array(items),
+                    Some(Partial::Object(map, _)) => JsonValueRef::Object(map),
+                    None => unreachable!(),
+                }
+            }
+            Step::Punct(_, _) | Step::Whitespace(_) | Step::Comment(_) => continue,
+        };
+
+        match partials.last_mut() {
+            Some(Partial::Array(items)) => items.push(complete),
+            Some(Partial::Object(map, key)) => {
+                map.insert(key.take().unwrap(), complete);
+            }
+            None => root = Some(complete),
+        }
+    }
+    root.ok_or_else(|| JsonError::eof(json_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_parse_borrowed, JsonValueRef};
+    use crate::json_parse;
+    use std::borrow::Cow;
+
+    #[test]
+    fn borrowed_matches_json_parse() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            let borrowed = json_parse_borrowed(&source);
+            assert_eq!(
+                borrowed.clone().map(|json| json.to_owned()),
+                json_parse(&source)
+            );
+            assert_eq!(borrowed.map(JsonValueRef::into_owned), json_parse(&source));
+        }
+    }
+
+    #[test]
+    fn borrowed_strings() {
+        let json =
+            json_parse_borrowed(r#"[ "", "ώ", "a\nb", { "k\u0065y": "x", "": [] } ]"#).unwrap();
+        let text = |ind: usize| match json.get_ind(ind) {
+            Some(JsonValueRef::Text(text)) => text.clone(),
+            other => panic!("{:?}", other),
+        };
+
+        assert!(matches!(text(0), Cow::Borrowed("")));
+        assert!(matches!(text(1), Cow::Borrowed("ώ")));
+        assert_eq!(text(2), Cow::<str>::Owned("a\nb".into()));
+        if let Some(JsonValueRef::Object(map)) = json.get_ind(3) {
+            assert!(map
+                .keys()
+                .any(|key| matches!(key, Cow::Owned(key) if key == "key")));
+            assert!(map.keys().any(|key| matches!(key, Cow::Borrowed(""))));
+        } else {
+            panic!("expected an object");
+        }
+        assert_eq!(
+            json.get("3").and_then(|o| o.get("key")),
+            Some(&JsonValueRef::Text("x".into()))
+        );
+        assert_eq!(json.get_ind(2).and_then(JsonValueRef::as_str), Some("a\nb"));
+        assert_eq!(json.get_ind(0).and_then(JsonValueRef::as_bool), None);
+        assert!(json_parse_borrowed(r#"["a"#).is_err());
+        assert!(json_parse_borrowed("[\"\t\"]").is_err());
+    }
+
+    #[test]
+    fn borrowed_depth() {
+        use crate::JsonError;
+        use std::thread;
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(json_parse_borrowed(&nested(128)).is_ok());
+        assert!(matches!(
+            json_parse_borrowed(&nested(129)),
+            Err(JsonError::DepthLimitExceeded {
+                limit: 128,
+                location: 128,
+                ..
+            })
+        ));
+
+        // nested far deeper than a recursive drop could go on this thread's stack
+        let dropped = thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                let mut json = JsonValueRef::Null;
+                for level in 0..200_000 {
+                    json = if level % 2 == 0 {
+                        JsonValueRef::Array(vec![json])
+                    } else {
+                        JsonValueRef::Object(Some(("next".into(), json)).into_iter().collect())
+                    };
+                }
+                drop(json);
+            })
+            .unwrap()
+            .join();
+        assert!(dropped.is_ok());
+    }
+}
@@ -0,0 +1,570 @@
+//! [CBOR](https://cbor.io) encoding of `JsonValue`, enabled by the `cbor` feature.
+use super::{JsonMap, JsonNumber, JsonValue};
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Describes all possible errors that could occur while decoding CBOR
+#[derive(Clone, Debug, PartialEq)]
+pub enum CborError {
+    /// The input ended in the middle of a value
+    UnexpectedEOF,
+    /// A byte that does not start any value JSON can represent, such as a simple value other than
+    /// `false`, `true`, `null` and `undefined`, a reserved length, a break outside of an
+    /// indefinite-length item, or a chunk of an indefinite-length string of the wrong type
+    InvalidByte {
+        /// The byte that was found
+        byte: u8,
+        /// Its offset in the input
+        offset: usize,
+    },
+    /// A text string was not valid UTF-8
+    InvalidUtf8 {
+        /// The offset of the string in the input
+        offset: usize,
+    },
+    /// A map key was neither a text string nor an integer
+    InvalidKey {
+        /// The offset of the key in the input
+        offset: usize,
+    },
+    /// Arrays, maps and tags were nested more deeply than the decoder allows
+    DepthLimitExceeded {
+        /// The depth that was allowed
+        limit: usize,
+        /// The offset of the item that went too deep
+        offset: usize,
+    },
+    /// More input followed the value
+    TrailingBytes {
+        /// The offset of the first byte after the value
+        offset: usize,
+    },
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CborError::UnexpectedEOF => f.write_str("unexpected end of CBOR input"),
+            CborError::InvalidByte { byte, offset } => {
+                write!(f, "invalid byte {:#04x} at offset {}", byte, offset)
+            }
+            CborError::InvalidUtf8 { offset } => {
+                write!(f, "text string at offset {} is not valid UTF-8", offset)
+            }
+            CborError::InvalidKey { offset } => write!(
+                f,
+                "map key at offset {} is neither a text string nor an integer",
+                offset
+            ),
+            CborError::DepthLimitExceeded { limit, offset } => write!(
+                f,
+                "nested more than {} levels deep at offset {}",
+                limit, offset
+            ),
+            CborError::TrailingBytes { offset } => {
+                write!(f, "unexpected bytes after the value at offset {}", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for CborError {}
+
+/// Encodes a value as CBOR.
+///
+/// Whole numbers that fit in 64 bits are written as integers with the shortest argument that
+/// holds them, and every other number as a 64-bit float.  Strings and containers are always
+/// written with their length up front.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::cbor::{from_cbor, to_cbor};
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let json = json_parse(r#"[1, -200, 0.5, "hi", null, true]"#).unwrap();
+///     let bytes = to_cbor(&json);
+///     assert_eq!(&bytes[..4], &[0x86, 0x01, 0x38, 0xc7]);
+///     assert_eq!(from_cbor(&bytes), Ok(json));
+/// }
+/// ```
+pub fn to_cbor(value: &JsonValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(value, &mut out);
+    out
+}
+
+/// Writes the initial byte of an item of major type `major`, followed by the shortest of the 8,
+/// 16, 32 and 64-bit arguments that holds `n` unless it is small enough to fit in the byte.
+fn head(out: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    match n {
+        n if n < 24 => out.push(major | n as u8),
+        n if n <= 0xff => out.extend_from_slice(&[major | 24, n as u8]),
+        n if n <= 0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n if n <= 0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        }
+        n => {
+            out.push(major | 27);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+fn encode_str(s: &str, out: &mut Vec<u8>) {
+    head(out, 3, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_number(n: JsonNumber, out: &mut Vec<u8>) {
+    // as an integer, `-0.0` would lose its sign
+    let negative_zero = matches!(n, JsonNumber::F64(f) if f == 0.0 && f.is_sign_negative());
+    if let (Some(n), false) = (n.as_u64(), negative_zero) {
+        head(out, 0, n);
+    } else if let (Some(n), false) = (n.as_i64(), negative_zero) {
+        // a negative integer is stored as `-1 - n`, which is `!n` in two's complement
+        head(out, 1, !n as u64);
+    } else {
+        out.push(0xfb);
+        out.extend_from_slice(&n.as_f64().to_bits().to_be_bytes());
+    }
+}
+
+fn encode(value: &JsonValue, out: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => out.push(0xf6),
+        JsonValue::Boolean(false) => out.push(0xf4),
+        JsonValue::Boolean(true) => out.push(0xf5),
+        JsonValue::Number(n) => encode_number(*n, out),
+        JsonValue::Text(s) => encode_str(s, out),
+        JsonValue::Raw(raw) => encode(&raw.parse(), out),
+        JsonValue::Array(array) => {
+            head(out, 4, array.len() as u64);
+            for item in array.iter() {
+                encode(item, out);
+            }
+        }
+        JsonValue::Object(map) => {
+            head(out, 5, map.len() as u64);
+            for (key, item) in map.iter() {
+                encode_str(key, out);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+/// How many arrays, maps and tags may be open at once while decoding, as many as
+/// [`ParseOptions::max_depth`](../options/struct.ParseOptions.html#structfield.max_depth) allows
+/// for arrays and objects by default.  The decoder recurses once per level, so this keeps
+/// hostile input from overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Decodes a single CBOR value, which must take up all of `bytes`.
+///
+/// Byte strings are decoded as arrays of byte values, `undefined` as `null`, and integer map
+/// keys as their decimal text.  Bignums (tags 2 and 3) are decoded as numbers, and every other
+/// tag is dropped in favor of the value it wraps, so a tagged date is just its string.  Simple
+/// values other than `false`, `true`, `null` and `undefined` have no JSON equivalent and are
+/// rejected, as are maps with keys that are not text strings or integers, and arrays, maps and
+/// tags nested more than 128 deep.
+pub fn from_cbor(bytes: &[u8]) -> Result<JsonValue, CborError> {
+    let mut decoder = Decoder {
+        bytes,
+        offset: 0,
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if decoder.offset < bytes.len() {
+        return Err(CborError::TrailingBytes {
+            offset: decoder.offset,
+        });
+    }
+    Ok(value)
+}
+
+/// Converts the bits of a 16-bit float.
+fn half(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    // 2^n for the `n` of a normal f64, built from its bits since `powi` needs `std`
+    let pow2 = |n: i32| f64::from_bits(((n + 1023) as u64) << 52);
+    sign * match exponent {
+        0 => mantissa * pow2(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (mantissa + 1024.0) * pow2(exponent - 25),
+    }
+}
+
+/// Converts the big-endian magnitude of a bignum, which for a negative bignum is `-1 - n`.
+/// Bignums beyond 128 bits are rounded to the nearest `f64`.
+fn bignum(bytes: &[u8], negative: bool) -> JsonValue {
+    let exact = bytes.iter().try_fold(0u128, |n, &byte| {
+        n.checked_mul(256).map(|n| n | u128::from(byte))
+    });
+    let approx = bytes
+        .iter()
+        .fold(0.0, |n, &byte| n * 256.0 + f64::from(byte));
+    match (exact, negative) {
+        (Some(n), false) => JsonValue::from(n),
+        (Some(n), true) => match i128::try_from(n) {
+            Ok(n) => JsonValue::from(-1 - n),
+            Err(_) => JsonValue::from(-1.0 - approx),
+        },
+        (None, false) => JsonValue::from(approx),
+        (None, true) => JsonValue::from(-1.0 - approx),
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// How many arrays, maps and tags are open
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CborError> {
+        if self.bytes.len() - self.offset < len {
+            return Err(CborError::UnexpectedEOF);
+        }
+        let taken = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(taken)
+    }
+
+    /// Reads a big-endian unsigned integer of `len` bytes.
+    fn uint(&mut self, len: usize) -> Result<u64, CborError> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |n, &byte| (n << 8) | u64::from(byte)))
+    }
+
+    /// Reads the initial byte of an item and its argument, which is `None` for an
+    /// indefinite-length item or a break.
+    fn head(&mut self) -> Result<(u8, Option<u64>), CborError> {
+        let offset = self.offset;
+        let byte = self.take(1)?[0];
+        let argument = match byte & 0x1f {
+            info @ 0..=23 => Some(u64::from(info)),
+            info @ 24..=27 => Some(self.uint(1 << (info - 24))?),
+            31 => None,
+            _ => return Err(CborError::InvalidByte { byte, offset }),
+        };
+        Ok((byte, argument))
+    }
+
+    /// Reads the contents of a byte or text string of major type `major`, joining the chunks of
+    /// an indefinite-length one.
+    fn string(&mut self, major: u8, len: Option<u64>) -> Result<Vec<u8>, CborError> {
+        let len = match len {
+            // a length beyond `usize` could never be taken anyway
+            Some(len) => usize::try_from(len).unwrap_or(usize::MAX),
+            None => {
+                let mut joined = vec![];
+                while !self.at_break()? {
+                    let offset = self.offset;
+                    match self.head()? {
+                        (byte, Some(len)) if byte >> 5 == major => {
+                            joined.extend(self.string(major, Some(len))?)
+                        }
+                        (byte, _) => return Err(CborError::InvalidByte { byte, offset }),
+                    }
+                }
+                return Ok(joined);
+            }
+        };
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Consumes the break that ends an indefinite-length item, if it is next.
+    fn at_break(&mut self) -> Result<bool, CborError> {
+        match self.bytes.get(self.offset) {
+            Some(0xff) => {
+                self.offset += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(CborError::UnexpectedEOF),
+        }
+    }
+
+    /// Whether a container with `remaining` items left, or `None` if it has an indefinite
+    /// length, has another item.
+    fn has_next(&mut self, remaining: &mut Option<u64>) -> Result<bool, CborError> {
+        match remaining {
+            Some(0) => Ok(false),
+            Some(n) => {
+                *n -= 1;
+                Ok(true)
+            }
+            None => self.at_break().map(|at_break| !at_break),
+        }
+    }
+
+    /// How many items to reserve room for.  Every item takes at least a byte, which bounds what
+    /// a bogus length can allocate.
+    fn capacity(&self, len: Option<u64>) -> usize {
+        let left = self.bytes.len() - self.offset;
+        len.map_or(0, |len| usize::try_from(len).unwrap_or(left).min(left))
+    }
+
+    /// Opens an array, map or tag that starts at `offset`, if that is not one too many.
+    fn enter(&mut self, offset: usize) -> Result<(), CborError> {
+        if self.depth == MAX_DEPTH {
+            return Err(CborError::DepthLimitExceeded {
+                limit: MAX_DEPTH,
+                offset,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn array(&mut self, mut len: Option<u64>, offset: usize) -> Result<JsonValue, CborError> {
+        self.enter(offset)?;
+        let mut array = Vec::with_capacity(self.capacity(len));
+        while self.has_next(&mut len)? {
+            array.push(self.value()?);
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Array(array))
+    }
+
+    fn map(&mut self, mut len: Option<u64>, offset: usize) -> Result<JsonValue, CborError> {
+        self.enter(offset)?;
+        let mut map = JsonMap::with_capacity(self.capacity(len));
+        while self.has_next(&mut len)? {
+            let offset = self.offset;
+            let key = match self.value()? {
+                JsonValue::Text(ref mut key) => mem::take(key),
+                JsonValue::Number(n) if n.is_integer() => n.to_string().into(),
+                _ => return Err(CborError::InvalidKey { offset }),
+            };
+            let value = self.value()?;
+            map.insert(key, value);
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Object(Box::new(map)))
+    }
+
+    fn tagged(&mut self, tag: u64, offset: usize) -> Result<JsonValue, CborError> {
+        self.enter(offset)?;
+        let is_bytes = self
+            .bytes
+            .get(self.offset)
+            .is_some_and(|byte| byte >> 5 == 2);
+        let value = match tag {
+            2 | 3 if is_bytes => {
+                let (_, len) = self.head()?;
+                bignum(&self.string(2, len)?, tag == 3)
+            }
+            _ => self.value()?,
+        };
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, CborError> {
+        let offset = self.offset;
+        let (byte, argument) = self.head()?;
+        Ok(match (byte >> 5, argument) {
+            (0, Some(n)) => JsonValue::from(n),
+            (1, Some(n)) => JsonValue::from(-1 - i128::from(n)),
+            (2, len) => {
+                let bytes = self.string(2, len)?;
+                JsonValue::Array(bytes.into_iter().map(JsonValue::from).collect())
+            }
+            (3, len) => {
+                let text = String::from_utf8(self.string(3, len)?)
+                    .map_err(|_| CborError::InvalidUtf8 { offset })?;
+                JsonValue::Text(text.into())
+            }
+            (4, len) => self.array(len, offset)?,
+            (5, len) => self.map(len, offset)?,
+            (6, Some(tag)) => self.tagged(tag, offset)?,
+            (7, Some(n)) => match byte & 0x1f {
+                20 => JsonValue::Boolean(false),
+                21 => JsonValue::Boolean(true),
+                22 | 23 => JsonValue::Null,
+                25 => JsonValue::from(half(n as u16)),
+                26 => JsonValue::from(f32::from_bits(n as u32)),
+                27 => JsonValue::from(f64::from_bits(n)),
+                _ => return Err(CborError::InvalidByte { byte, offset }),
+            },
+            _ => return Err(CborError::InvalidByte { byte, offset }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_cbor, to_cbor, CborError};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn cbor_round_trip() {
+        let json = json_parse(
+            r#"{
+                "ints": [0, 23, 24, 255, 256, 65535, 65536, 4294967296, 18446744073709551615,
+                    -1, -24, -25, -256, -257, -65537, -9223372036854775808],
+                "floats": [0.5, -0.0, 1e300, 18446744073709551616],
+                "text": ["", "a string that is longer than twenty-three bytes", "é"],
+                "nested": { "empty": {}, "list": [[], null, false] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(from_cbor(&to_cbor(&json)), Ok(json));
+
+        let long = JsonValue::Array(vec![JsonValue::Null; 70_000]);
+        let bytes = to_cbor(&long);
+        assert_eq!(&bytes[..5], &[0x9a, 0x00, 0x01, 0x11, 0x70]);
+        assert_eq!(from_cbor(&bytes), Ok(long));
+
+        assert_eq!(
+            to_cbor(&json_parse("[-1, -0.0]").unwrap()),
+            [0x82, 0x20, 0xfb, 0x80, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn cbor_decode() {
+        // examples from appendix A of RFC 8949
+        let decode = |bytes: &[u8]| from_cbor(bytes).unwrap();
+        assert_eq!(decode(&[0xf9, 0x3c, 0x00]), JsonValue::from(1.0));
+        assert_eq!(decode(&[0xf9, 0x7b, 0xff]), JsonValue::from(65504.0));
+        assert_eq!(
+            decode(&[0xf9, 0x00, 0x01]),
+            JsonValue::from(5.960464477539063e-8)
+        );
+        assert_eq!(decode(&[0xf9, 0xc4, 0x00]), JsonValue::from(-4.0));
+        assert_eq!(
+            decode(&[0xfa, 0x47, 0xc3, 0x50, 0x00]),
+            JsonValue::from(100000.0)
+        );
+        assert_eq!(
+            decode(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            JsonValue::from(-18446744073709551616.0)
+        );
+        assert_eq!(
+            decode(&[0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+            JsonValue::from(18446744073709551616.0)
+        );
+        assert_eq!(
+            decode(&[0xc3, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+            JsonValue::from(-18446744073709551617.0)
+        );
+        assert_eq!(decode(&[0xc3, 0x41, 0x05]), JsonValue::from(-6));
+        assert_eq!(decode(&[0xf7]), JsonValue::Null);
+        assert_eq!(
+            decode(b"\xc0\x742013-03-21T20:04:00Z"),
+            JsonValue::from("2013-03-21T20:04:00Z")
+        );
+        assert_eq!(
+            decode(&[0xa2, 0x01, 0x02, 0x20, 0x04]),
+            json_parse(r#"{ "1": 2, "-1": 4 }"#).unwrap()
+        );
+
+        // indefinite lengths
+        assert_eq!(
+            decode(&[0x9f, 0x01, 0x82, 0x02, 0x03, 0x9f, 0x04, 0x05, 0xff, 0xff]),
+            json_parse("[1, [2, 3], [4, 5]]").unwrap()
+        );
+        assert_eq!(
+            decode(b"\x7f\x65strea\x64ming\xff"),
+            JsonValue::from("streaming")
+        );
+        assert_eq!(
+            decode(&[0xbf, 0x61, b'a', 0x01, 0x61, b'b', 0x9f, 0x02, 0x03, 0xff, 0xff]),
+            json_parse(r#"{ "a": 1, "b": [2, 3] }"#).unwrap()
+        );
+        assert_eq!(
+            decode(&[0x5f, 0x42, 0x01, 0x02, 0x43, 0x03, 0x04, 0x05, 0xff]),
+            json_parse("[1, 2, 3, 4, 5]").unwrap()
+        );
+    }
+
+    #[test]
+    fn cbor_errors() {
+        assert_eq!(from_cbor(&[0x82, 0x01]), Err(CborError::UnexpectedEOF));
+        assert_eq!(from_cbor(&[0x9f, 0x01]), Err(CborError::UnexpectedEOF));
+        assert_eq!(
+            from_cbor(&[0xf6, 0xf6]),
+            Err(CborError::TrailingBytes { offset: 1 })
+        );
+        assert_eq!(
+            from_cbor(&[0x81, 0xf8, 0x20]),
+            Err(CborError::InvalidByte {
+                byte: 0xf8,
+                offset: 1,
+            })
+        );
+        assert_eq!(
+            from_cbor(&[0xff]),
+            Err(CborError::InvalidByte {
+                byte: 0xff,
+                offset: 0,
+            })
+        );
+        assert_eq!(
+            from_cbor(&[0x1c]),
+            Err(CborError::InvalidByte {
+                byte: 0x1c,
+                offset: 0,
+            })
+        );
+        assert_eq!(
+            from_cbor(&[0x7f, 0x41, 0x00, 0xff]),
+            Err(CborError::InvalidByte {
+                byte: 0x41,
+                offset: 1,
+            })
+        );
+        assert_eq!(
+            from_cbor(&[0xa1, 0xf6, 0x01]),
+            Err(CborError::InvalidKey { offset: 1 })
+        );
+        assert_eq!(
+            from_cbor(&[0x81, 0xf8, 0x20]).unwrap_err().to_string(),
+            "invalid byte 0xf8 at offset 1"
+        );
+        assert_eq!(
+            from_cbor(&[0x81, 0x61, 0xff]),
+            Err(CborError::InvalidUtf8 { offset: 1 })
+        );
+        assert_eq!(
+            from_cbor(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(CborError::UnexpectedEOF)
+        );
+
+        for &open in [0x81, 0x9f, 0xa1, 0xc6].iter() {
+            assert_eq!(
+                from_cbor(&vec![open; 1_000_000]),
+                Err(CborError::DepthLimitExceeded {
+                    limit: 128,
+                    offset: 128,
+                })
+            );
+        }
+        let mut nested = [0xa1, 0x60].repeat(129);
+        nested.push(0xf6);
+        assert!(from_cbor(&nested[2..]).is_ok());
+        assert!(matches!(
+            from_cbor(&nested),
+            Err(CborError::DepthLimitExceeded { offset: 256, .. })
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+//! Transparent gzip and zstd compression of input and output, enabled by the `gzip` and `zstd`
+//! features.
+//!
+//! Every reader-based API in this crate takes a `BufRead`, so wrapping a reader in
+//! `decompress` is all it takes to read compressed JSON Lines, sequences or streams.  Likewise
+//! `compress` wraps any writer.
+#[cfg(feature = "gzip")]
+use flate2::bufread::MultiGzDecoder;
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+/// The formats `decompress` can recognize and `compress` can write.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// Not compressed
+    None,
+    /// gzip, including several gzip members one after another
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+enum Inner<R: BufRead> {
+    Plain(R),
+    #[cfg(feature = "gzip")]
+    Gzip(BufReader<MultiGzDecoder<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(BufReader<zstd::stream::read::Decoder<'static, R>>),
+}
+
+/// A reader that decompresses its input if it is compressed, created by `decompress`.
+pub struct Decompressor<R: BufRead> {
+    inner: Inner<R>,
+}
+
+/// Wraps a reader so that gzip or zstd input is decompressed, recognizing the format by its
+/// leading magic bytes.  Input in neither format is passed through unchanged.
+///
+/// The magic bytes are looked for in what the reader buffers on its first fill, so a reader
+/// that returns fewer than four bytes at first may not be recognized.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::compress::{decompress, Compression};
+/// use json_rs::lines::json_lines;
+///
+/// fn main() {
+///     // a `.jsonl`, `.jsonl.gz` or `.jsonl.zst` file would be read the same way
+///     let export = &b"{\"n\":1}\n{\"n\":2}\n"[..];
+///     let reader = decompress(export).unwrap();
+///     assert_eq!(reader.compression(), Compression::None);
+///     assert_eq!(json_lines(reader).count(), 2);
+/// }
+/// ```
+pub fn decompress<R: BufRead>(mut reader: R) -> io::Result<Decompressor<R>> {
+    let compression = detect(reader.fill_buf()?);
+    let inner = match compression {
+        Compression::None => Inner::Plain(reader),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Inner::Gzip(BufReader::new(MultiGzDecoder::new(reader))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Inner::Zstd(BufReader::new(zstd::stream::read::Decoder::with_buffer(
+            reader,
+        )?)),
+    };
+    Ok(Decompressor { inner })
+}
+
+/// Recognizes the compression of data from its first bytes.
+pub fn detect(start: &[u8]) -> Compression {
+    #[cfg(feature = "gzip")]
+    {
+        if start.starts_with(GZIP_MAGIC) {
+            return Compression::Gzip;
+        }
+    }
+    #[cfg(feature = "zstd")]
+    {
+        if start.starts_with(ZSTD_MAGIC) {
+            return Compression::Zstd;
+        }
+    }
+    let _ = start;
+    Compression::None
+}
+
+impl<R: BufRead> Decompressor<R> {
+    /// The format that was recognized.
+    pub fn compression(&self) -> Compression {
+        match self.inner {
+            Inner::Plain(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(_) => Compression::Zstd,
+        }
+    }
+}
+
+impl<R: BufRead> fmt::Debug for Decompressor<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Decompressor")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+impl<R: BufRead> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            Inner::Plain(ref mut reader) => reader.read(buf),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(ref mut reader) => reader.read(buf),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(ref mut reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decompressor<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.inner {
+            Inner::Plain(ref mut reader) => reader.fill_buf(),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(ref mut reader) => reader.fill_buf(),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(ref mut reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.inner {
+            Inner::Plain(ref mut reader) => reader.consume(amt),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(ref mut reader) => reader.consume(amt),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(ref mut reader) => reader.consume(amt),
+        }
+    }
+}
+
+enum Output<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+/// A writer that compresses what is written to it, created by `compress`.
+///
+/// `finish` must be called once everything has been written, to write the end of the
+/// compressed data.
+pub struct Compressor<W: Write> {
+    output: Output<W>,
+}
+
+/// Wraps a writer so that everything written to it is compressed.  `level` is the compression
+/// level, which is 0 to 9 for gzip and 1 to 22 for zstd; `None` uses each format's default.
+/// Levels out of range are clamped.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::compress::{compress, decompress, Compression};
+/// use json_rs::seq::{SeqReader, SeqWriter};
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     // `Compression::Gzip` or `Compression::Zstd` with those features enabled
+///     let output = compress(Vec::new(), Compression::None, None).unwrap();
+///     let mut writer = SeqWriter::new(output);
+///     writer.write(&JsonValue::from(1)).unwrap();
+///     let bytes = writer.into_inner().finish().unwrap();
+///
+///     let reader = SeqReader::new(decompress(&bytes[..]).unwrap());
+///     assert_eq!(reader.count(), 1);
+/// }
+/// ```
+pub fn compress<W: Write>(
+    writer: W,
+    compression: Compression,
+    level: Option<i32>,
+) -> io::Result<Compressor<W>> {
+    let _ = level;
+    let output = match compression {
+        Compression::None => Output::Plain(writer),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let level = match level {
+                Some(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+                None => flate2::Compression::default(),
+            };
+            Output::Gzip(GzEncoder::new(writer, level))
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let level = level.map_or(zstd::DEFAULT_COMPRESSION_LEVEL, |level| level.clamp(1, 22));
+            Output::Zstd(zstd::stream::write::Encoder::new(writer, level)?)
+        }
+    };
+    Ok(Compressor { output })
+}
+
+impl<W: Write> Compressor<W> {
+    /// The format being written.
+    pub fn compression(&self) -> Compression {
+        match self.output {
+            Output::Plain(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            Output::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Output::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    /// Writes the end of the compressed data and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self.output {
+            Output::Plain(mut writer) => writer.flush().map(|()| writer),
+            #[cfg(feature = "gzip")]
+            Output::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            Output::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> fmt::Debug for Compressor<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compressor")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.output {
+            Output::Plain(ref mut writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Output::Gzip(ref mut writer) => writer.write(buf),
+            #[cfg(feature = "zstd")]
+            Output::Zstd(ref mut writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.output {
+            Output::Plain(ref mut writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Output::Gzip(ref mut writer) => writer.flush(),
+            #[cfg(feature = "zstd")]
+            Output::Zstd(ref mut writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress, Compression};
+    use crate::stream::parse_array_stream;
+    use std::io::{Read, Write};
+
+    fn round_trip(compression: Compression, level: Option<i32>) {
+        let mut writer = compress(Vec::new(), compression, level).unwrap();
+        assert_eq!(writer.compression(), compression);
+        writer.write_all(b"[1, 2, ").unwrap();
+        writer.write_all(b"3]").unwrap();
+        let bytes = writer.finish().unwrap();
+        assert_ne!(&bytes[..], b"[1, 2, 3]");
+
+        let reader = decompress(&bytes[..]).unwrap();
+        assert_eq!(reader.compression(), compression);
+        assert_eq!(parse_array_stream(reader).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn decompress_plain() {
+        let mut reader = decompress(&b"[1, 2]"[..]).unwrap();
+        assert_eq!(reader.compression(), Compression::None);
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "[1, 2]");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompress_gzip() {
+        use flate2::write::GzEncoder;
+
+        // two members, as `cat a.gz b.gz` would produce
+        let mut gzipped = Vec::new();
+        for part in [&b"[1, "[..], b"2, 3]"].iter() {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(part).unwrap();
+            gzipped.extend(encoder.finish().unwrap());
+        }
+        let reader = decompress(&gzipped[..]).unwrap();
+        assert_eq!(reader.compression(), Compression::Gzip);
+        assert_eq!(parse_array_stream(reader).unwrap().count(), 3);
+
+        round_trip(Compression::Gzip, None);
+        round_trip(Compression::Gzip, Some(12));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn decompress_zstd() {
+        let compressed = zstd::encode_all(&b"[true, false]"[..], 3).unwrap();
+        let reader = decompress(&compressed[..]).unwrap();
+        assert_eq!(reader.compression(), Compression::Zstd);
+        assert_eq!(parse_array_stream(reader).unwrap().count(), 2);
+
+        round_trip(Compression::Zstd, None);
+        round_trip(Compression::Zstd, Some(-5));
+    }
+}
@@ -0,0 +1,477 @@
+//! Layered configuration: a base file, overlay files deep-merged on top of it, and environment
+//! variable overrides on top of those.
+use super::options::ParseOptions;
+use super::span::parse_spanned;
+use super::{json_parse, JsonError, JsonMap, JsonPointer, JsonValue};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::{env, error, fmt, fs, io};
+
+/// Where a configuration value came from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigSource {
+    /// A configuration file
+    File(PathBuf),
+    /// An environment variable, by name
+    Env(String),
+}
+
+/// Describes the ways loading configuration can fail
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A file could not be read
+    Io {
+        /// The file being read
+        path: PathBuf,
+        /// What went wrong
+        error: io::Error,
+    },
+    /// A file was not valid JSON
+    Parse {
+        /// The file being parsed
+        path: PathBuf,
+        /// What went wrong
+        error: JsonError,
+    },
+    /// An environment variable with the prefix had a name or value that is not valid Unicode
+    NotUnicode {
+        /// The variable's name
+        name: OsString,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => {
+                write!(f, "could not read {}: {}", path.display(), error)
+            }
+            ConfigError::Parse { path, error } => write!(f, "in {}: {}", path.display(), error),
+            ConfigError::NotUnicode { name } => {
+                write!(f, "environment variable {:?} is not valid Unicode", name)
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Io { error, .. } => Some(error),
+            ConfigError::Parse { error, .. } => Some(error),
+            ConfigError::NotUnicode { .. } => None,
+        }
+    }
+}
+
+/// A loaded configuration along with where each part of it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The merged configuration
+    pub value: JsonValue,
+    provenance: BTreeMap<JsonPointer, ConfigSource>,
+}
+
+impl Config {
+    /// The source that set the value at `pointer`, or the part of the configuration containing
+    /// it.  Returns `None` if no source set anything there.
+    pub fn source_of(&self, pointer: &JsonPointer) -> Option<&ConfigSource> {
+        self.provenance
+            .iter()
+            .filter(|(path, _)| pointer.starts_with(path))
+            .max_by_key(|(path, _)| path.tokens().len())
+            .map(|(_, source)| source)
+    }
+
+    /// Every path that was set directly, with its source.  Parts of the configuration that were
+    /// only merged into are not listed themselves.
+    pub fn provenance(&self) -> &BTreeMap<JsonPointer, ConfigSource> {
+        &self.provenance
+    }
+
+    /// Puts `overlay` at `path`, merging objects key by key and replacing everything else.
+    fn merge(&mut self, path: JsonPointer, mut overlay: JsonValue, source: &ConfigSource) {
+        // merging into an empty object is the same as replacing it, which keeps provenance short
+        let overlay = match (pointer_mut_or_insert(&mut self.value, &path), &mut overlay) {
+            (JsonValue::Object(base), JsonValue::Object(overlay)) if !base.is_empty() => {
+                mem::take(&mut **overlay)
+            }
+            (target, _) => {
+                *target = overlay;
+                self.provenance
+                    .retain(|existing, _| !existing.starts_with(&path));
+                self.provenance.insert(path, source.clone());
+                return;
+            }
+        };
+
+        for (key, value) in overlay.into_iter() {
+            self.merge(path.child(key), value, source);
+        }
+    }
+}
+
+/// Finds the value at `path`, creating it along the way wherever the path leads through
+/// something missing.  Scalars in the way are replaced with objects, and an index one past the
+/// end of an array appends to it.
+fn pointer_mut_or_insert<'a>(value: &'a mut JsonValue, path: &JsonPointer) -> &'a mut JsonValue {
+    path.tokens().iter().fold(value, |value, token| {
+        if value.get(token).is_none() {
+            match value {
+                JsonValue::Array(array) if token.parse::<usize>().ok() == Some(array.len()) => {
+                    array.push(JsonValue::Null)
+                }
+                JsonValue::Object(map) => {
+                    map.insert(token.as_str().into(), JsonValue::Null);
+                }
+                _ => {
+                    let mut map = JsonMap::new();
+                    map.insert(token.as_str().into(), JsonValue::Null);
+                    *value = JsonValue::Object(Box::new(map));
+                }
+            }
+        }
+        value.get_mut(token).unwrap()
+    })
+}
+
+/// Loads configuration from several layers, each overriding the ones before it.
+///
+/// Files are deep-merged in the order they were added: objects are combined key by key, while
+/// any other value replaces what was there.  Environment variables named with the prefix are
+/// applied last.  Their names are split on `__` and lowercased to form a path, so
+/// `APP__SERVER__PORT=8080` sets `server.port`.  Values that parse as JSON are used as such,
+/// and anything else is taken as a string.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::config::{ConfigLoader, ConfigSource};
+/// use json_rs::{JsonPointer, JsonValue};
+/// use std::fs;
+///
+/// fn main() {
+///     let dir = std::env::temp_dir();
+///     let base = dir.join("json_rs_config_doc_base.json");
+///     fs::write(&base, r#"{ "server": { "host": "localhost", "port": 80 } }"#).unwrap();
+///
+///     let config = ConfigLoader::new()
+///         .file(&base)
+///         .optional_file(dir.join("json_rs_config_doc_missing.json"))
+///         .env_prefix("APP")
+///         .load_from_env(vec![("APP__SERVER__PORT".into(), "8080".into())])
+///         .unwrap();
+///
+///     let port: JsonPointer = "/server/port".parse().unwrap();
+///     assert_eq!(port.resolve(&config.value), Some(&JsonValue::from(8080)));
+///     assert_eq!(config.source_of(&port), Some(&ConfigSource::Env("APP__SERVER__PORT".into())));
+///     assert_eq!(
+///         config.source_of(&"/server/host".parse().unwrap()),
+///         Some(&ConfigSource::File(base.clone()))
+///     );
+///     fs::remove_file(base).unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConfigLoader {
+    files: Vec<(PathBuf, bool)>,
+    env_prefix: Option<String>,
+    options: ParseOptions,
+}
+
+impl ConfigLoader {
+    /// Creates a loader with no layers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file that must exist.
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.files.push((path.as_ref().to_path_buf(), true));
+        self
+    }
+
+    /// Adds a file that is skipped if it does not exist.
+    pub fn optional_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.files.push((path.as_ref().to_path_buf(), false));
+        self
+    }
+
+    /// Applies environment variables that start with `prefix` followed by `__`.
+    pub fn env_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets the syntax accepted in files, for instance to allow comments.
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Loads every layer, reading overrides from the process environment.  Variables that are
+    /// not valid Unicode are skipped unless they start with the prefix, in which case loading
+    /// fails with `ConfigError::NotUnicode`.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut vars = vec![];
+        for (name, value) in env::vars_os() {
+            match (name.to_str(), value.to_str()) {
+                (Some(name), Some(value)) => vars.push((name.to_string(), value.to_string())),
+                _ if self
+                    .env_var_prefix()
+                    .is_some_and(|prefix| name.to_string_lossy().starts_with(&prefix)) =>
+                {
+                    return Err(ConfigError::NotUnicode { name });
+                }
+                _ => (),
+            }
+        }
+        self.load_from_env(vars)
+    }
+
+    /// Loads every layer, reading overrides from `vars` instead of the process environment.
+    pub fn load_from_env<I>(&self, vars: I) -> Result<Config, ConfigError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = Config {
+            value: JsonValue::Object(Box::default()),
+            provenance: BTreeMap::new(),
+        };
+
+        for (path, required) in self.files.iter() {
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(ref error) if !required && error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => {
+                    return Err(ConfigError::Io {
+                        path: path.clone(),
+                        error,
+                    })
+                }
+            };
+            let value = parse_spanned(&text, self.options)
+                .map(|(root, _)| root.value.to_json())
+                .map_err(|error| ConfigError::Parse {
+                    path: path.clone(),
+                    error,
+                })?;
+            config.merge(
+                JsonPointer::root(),
+                value,
+                &ConfigSource::File(path.clone()),
+            );
+        }
+
+        if let Some(prefix) = self.env_var_prefix() {
+            let mut overrides = vars
+                .into_iter()
+                .filter(|(name, _)| name.starts_with(&prefix) && name.len() > prefix.len())
+                .collect::<Vec<_>>();
+            // apply in a fixed order so that overlapping variables behave predictably
+            overrides.sort();
+
+            for (name, raw) in overrides.into_iter() {
+                let path = JsonPointer::from_tokens(
+                    name[prefix.len()..].split("__").map(str::to_lowercase),
+                );
+                let value = json_parse(&raw).unwrap_or(JsonValue::Text(raw.into()));
+                config.merge(path, value, &ConfigSource::Env(name));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// What the names of variables that override the configuration start with.
+    fn env_var_prefix(&self) -> Option<String> {
+        self.env_prefix
+            .as_ref()
+            .map(|prefix| format!("{}__", prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigError, ConfigLoader, ConfigSource};
+    use crate::{json_parse, JsonError, JsonValue, ParseOptions};
+    use std::fs;
+
+    #[test]
+    fn config_layers() {
+        let dir = std::env::temp_dir().join("json_rs_config_layers");
+        fs::create_dir_all(&dir).unwrap();
+        let (base, overlay) = (dir.join("base.json"), dir.join("overlay.json"));
+        fs::write(
+            &base,
+            r#"{ "db": { "url": "x", "pool": 4 }, "tags": ["a"], "debug": false }"#,
+        )
+        .unwrap();
+        fs::write(
+            &overlay,
+            "// local settings\n{ \"db\": { \"pool\": 8 }, \"tags\": [\"b\"] }",
+        )
+        .unwrap();
+
+        let loader = ConfigLoader::new()
+            .file(&base)
+            .file(&overlay)
+            .env_prefix("APP")
+            .options(ParseOptions::lenient());
+        let config = loader
+            .load_from_env(vec![
+                ("APP__DEBUG".into(), "true".into()),
+                ("APP__DB__URL".into(), "postgres://db".into()),
+                ("APP__NEW__LIST".into(), "[1]".into()),
+                ("APP__TAGS__1".into(), "\"c\"".into()),
+                ("OTHER__DEBUG".into(), "1".into()),
+                ("APP__".into(), "1".into()),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            config.value,
+            json_parse(
+                r#"{
+                    "db": { "url": "postgres://db", "pool": 8 },
+                    "tags": ["b", "c"],
+                    "debug": true,
+                    "new": { "list": [1] }
+                }"#
+            )
+            .unwrap()
+        );
+
+        let source = |path: &str| config.source_of(&path.parse().unwrap()).cloned();
+        let env = |name: &str| Some(ConfigSource::Env(name.into()));
+        assert_eq!(
+            source("/db/pool"),
+            Some(ConfigSource::File(overlay.clone()))
+        );
+        assert_eq!(source("/db/url"), env("APP__DB__URL"));
+        assert_eq!(source("/tags/0"), Some(ConfigSource::File(overlay.clone())));
+        assert_eq!(source("/tags/1"), env("APP__TAGS__1"));
+        assert_eq!(source("/new/list/0"), env("APP__NEW__LIST"));
+        assert_eq!(source("/debug"), env("APP__DEBUG"));
+        assert_eq!(source(""), Some(ConfigSource::File(base.clone())));
+
+        match ConfigLoader::new().file(&overlay).load_from_env(vec![]) {
+            Err(ConfigError::Parse { path, error }) => {
+                assert_eq!(path, overlay);
+                assert_eq!(
+                    error,
+                    JsonError::UnexpectedToken {
+                        character: '/',
+                        location: 0,
+                        line: 0,
+                        column: 0,
+                        offset: 0,
+                    }
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+        match ConfigLoader::new()
+            .file(dir.join("missing.json"))
+            .load_from_env(vec![])
+        {
+            Err(ConfigError::Io { .. }) => (),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            ConfigLoader::new()
+                .optional_file(dir.join("missing.json"))
+                .load_from_env(vec![])
+                .unwrap()
+                .value,
+            JsonValue::Object(Default::default())
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_options() {
+        use crate::DuplicateKeys;
+
+        let dir = std::env::temp_dir().join("json_rs_config_options");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("config.json");
+        fs::write(
+            &file,
+            r#"{ "port": 80, "port": 8080, "tls": { "on": true } }"#,
+        )
+        .unwrap();
+
+        let load = |duplicate_keys, max_depth| {
+            ConfigLoader::new()
+                .file(&file)
+                .options(ParseOptions {
+                    duplicate_keys,
+                    max_depth,
+                    ..ParseOptions::default()
+                })
+                .load_from_env(vec![])
+                .map(|config| config.value)
+        };
+        assert_eq!(
+            load(DuplicateKeys::FirstWins, 128).unwrap(),
+            json_parse(r#"{ "port": 80, "tls": { "on": true } }"#).unwrap()
+        );
+        assert_eq!(
+            load(DuplicateKeys::LastWins, 128).unwrap(),
+            json_parse(r#"{ "port": 8080, "tls": { "on": true } }"#).unwrap()
+        );
+        match load(DuplicateKeys::Error, 128) {
+            Err(ConfigError::Parse { error, .. }) => {
+                assert!(matches!(
+                    error,
+                    JsonError::DuplicateKey { location: 14, .. }
+                ))
+            }
+            other => panic!("{:?}", other),
+        }
+        match load(DuplicateKeys::LastWins, 1) {
+            Err(ConfigError::Parse { error, .. }) => assert!(matches!(
+                error,
+                JsonError::DepthLimitExceeded { location: 35, .. }
+            )),
+            other => panic!("{:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn config_env_not_unicode() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+        use std::{env, error::Error};
+
+        let invalid = OsString::from_vec(vec![b'a', 0xff]);
+        env::set_var("JSON_RS_TEST_UNICODE__NAME", "\"ok\"");
+        env::set_var("JSON_RS_TEST_OTHER__NAME", &invalid);
+        let loader = ConfigLoader::new().env_prefix("JSON_RS_TEST_UNICODE");
+        let config = loader.load().unwrap();
+        assert_eq!(config.value, json_parse(r#"{ "name": "ok" }"#).unwrap());
+
+        env::set_var("JSON_RS_TEST_UNICODE__BAD", &invalid);
+        let error = loader.load().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "environment variable \"JSON_RS_TEST_UNICODE__BAD\" is not valid Unicode"
+        );
+        assert!(error.source().is_none());
+        env::remove_var("JSON_RS_TEST_UNICODE__BAD");
+        env::remove_var("JSON_RS_TEST_UNICODE__NAME");
+        env::remove_var("JSON_RS_TEST_OTHER__NAME");
+
+        let error = ConfigLoader::new().file("json_rs_missing.json").load();
+        let error = error.unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("could not read json_rs_missing.json: "));
+        assert!(error.source().is_some());
+    }
+}
@@ -0,0 +1,1008 @@
+//! Typed conversion between Rust values and `JsonValue` without any external dependencies.
+use super::outline::ValueKind;
+use super::{JsonMap, JsonNumber, JsonString, JsonValue, RawJson};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Describes all possible errors that could occur while converting from a `JsonValue`
+#[derive(Clone, Debug, PartialEq)]
+pub enum FromJsonError {
+    /// The value was not of the expected type
+    InvalidType {
+        /// What the target type can be converted from
+        expected: &'static str,
+        /// What was found instead
+        found: ValueKind,
+    },
+    /// The number could not be represented by the target type
+    InvalidNumber {
+        /// The target type
+        expected: &'static str,
+        /// The number that was found
+        found: f64,
+    },
+    /// An object was missing a key the target type requires
+    MissingField(String),
+    /// Any other reason a value could not be converted
+    Message(String),
+}
+
+impl FromJsonError {
+    /// Creates an `InvalidType` error for `value`.
+    pub fn invalid_type(expected: &'static str, value: &JsonValue) -> Self {
+        FromJsonError::InvalidType {
+            expected,
+            found: ValueKind::of(value),
+        }
+    }
+}
+
+impl fmt::Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromJsonError::InvalidType { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found.describe())
+            }
+            FromJsonError::InvalidNumber { expected, found } => {
+                write!(f, "{} does not fit in {}", found, expected)
+            }
+            FromJsonError::MissingField(key) => write!(f, "missing field {:?}", key),
+            FromJsonError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for FromJsonError {}
+
+/// A type that can be converted into a `JsonValue`.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::convert::{to_value, ToJson};
+/// use json_rs::{json_parse, JsonValue};
+///
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl ToJson for Point {
+///     fn to_json(&self) -> JsonValue {
+///         json!({ "x": self.x, "y": self.y })
+///     }
+/// }
+///
+/// fn main() {
+///     let points = vec![Point { x: 1, y: 2 }];
+///     assert_eq!(to_value(&points), json_parse(r#"[{ "x": 1, "y": 2 }]"#).unwrap());
+/// }
+/// ```
+pub trait ToJson {
+    /// Converts `self` into a `JsonValue`.
+    fn to_json(&self) -> JsonValue;
+}
+
+/// A type that can be converted from a `JsonValue`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::convert::{from_value, FromJson, FromJsonError};
+/// use json_rs::{json_parse, JsonValue};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl FromJson for Point {
+///     fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+///         let field = |key: &str| match value.get(key) {
+///             Some(field) => i32::from_json(field),
+///             None => Err(FromJsonError::MissingField(key.into())),
+///         };
+///         Ok(Point { x: field("x")?, y: field("y")? })
+///     }
+/// }
+///
+/// fn main() {
+///     let json = json_parse(r#"[{ "x": 1, "y": 2 }]"#).unwrap();
+///     assert_eq!(from_value::<Vec<Point>>(&json), Ok(vec![Point { x: 1, y: 2 }]));
+///     assert_eq!(
+///         from_value::<Point>(&json),
+///         Err(FromJsonError::MissingField("x".into()))
+///     );
+/// }
+/// ```
+pub trait FromJson: Sized {
+    /// Converts a `JsonValue` into `Self`.
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError>;
+}
+
+/// Converts a value into a `JsonValue`.
+pub fn to_value<T: ToJson + ?Sized>(value: &T) -> JsonValue {
+    value.to_json()
+}
+
+/// Converts a `JsonValue` into an instance of `T`.
+pub fn from_value<T: FromJson>(value: &JsonValue) -> Result<T, FromJsonError> {
+    T::from_json(value)
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        Ok(value.clone())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Boolean(*self)
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Boolean(b) => Ok(*b),
+            _ => Err(FromJsonError::invalid_type("a boolean", value)),
+        }
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Text(self.into())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Text(self.clone().into())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Text(s) => Ok(s.to_string()),
+            _ => Err(FromJsonError::invalid_type("a string", value)),
+        }
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Text(JsonString::from(*self))
+    }
+}
+
+impl FromJson for char {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        let mut chars = match value {
+            JsonValue::Text(s) => s.chars(),
+            _ => return Err(FromJsonError::invalid_type("a character", value)),
+        };
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(FromJsonError::Message(
+                "expected a string of one character".into(),
+            )),
+        }
+    }
+}
+
+/// Reads a JSON number, checking that it is a whole number that fits in the integer type.
+macro_rules! int {
+    ($($ty:ident)*) => {
+        $(
+            impl ToJson for $ty {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number((*self).into())
+                }
+            }
+
+            impl FromJson for $ty {
+                fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                    match value {
+                        JsonValue::Number(n) => n
+                            .as_i128()
+                            .and_then(|n| $ty::try_from(n).ok())
+                            .ok_or(FromJsonError::InvalidNumber {
+                                expected: stringify!($ty),
+                                found: n.as_f64(),
+                            }),
+                        _ => Err(FromJsonError::invalid_type("a number", value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+int! { i8 i16 i32 i64 isize u8 u16 u32 u64 usize }
+
+/// Reads a JSON number into a 128-bit integer.  Integers beyond 64 bits are parsed as `f64`s,
+/// which hold them exactly only up to 2^53, so larger ones read as the nearest integer the number
+/// rounds to; use the `decimal` feature's `ParseOptions::exact_numbers` to read every digit.
+macro_rules! wide_int {
+    ($($ty:ident)*) => {
+        $(
+            impl ToJson for $ty {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number((*self).into())
+                }
+            }
+
+            impl FromJson for $ty {
+                fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                    match value {
+                        JsonValue::Number(n) if n.is_integer() => n
+                            .as_i128()
+                            .and_then(|n| $ty::try_from(n).ok())
+                            .ok_or(FromJsonError::InvalidNumber {
+                                expected: stringify!($ty),
+                                found: n.as_f64(),
+                            }),
+                        // MAX rounds up to a power of two, which is just out of range
+                        JsonValue::Number(JsonNumber::F64(n))
+                            if *n % 1.0 == 0.0 && *n >= $ty::MIN as f64 && *n < $ty::MAX as f64 =>
+                        {
+                            Ok(*n as $ty)
+                        }
+                        #[cfg(feature = "decimal")]
+                        JsonValue::Number(JsonNumber::Decimal(d)) => d
+                            .to_i128()
+                            .and_then(|n| $ty::try_from(n).ok())
+                            .or_else(|| d.to_u128().and_then(|n| $ty::try_from(n).ok()))
+                            .ok_or(FromJsonError::InvalidNumber {
+                                expected: stringify!($ty),
+                                found: d.to_f64(),
+                            }),
+                        JsonValue::Number(n) => Err(FromJsonError::InvalidNumber {
+                            expected: stringify!($ty),
+                            found: n.as_f64(),
+                        }),
+                        _ => Err(FromJsonError::invalid_type("a number", value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+wide_int! { i128 u128 }
+
+impl ToJson for f64 {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(JsonNumber::F64(*self))
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(n) => Ok(n.as_f64()),
+            _ => Err(FromJsonError::invalid_type("a number", value)),
+        }
+    }
+}
+
+impl ToJson for JsonNumber {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(*self)
+    }
+}
+
+impl FromJson for JsonNumber {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(n) => Ok(*n),
+            _ => Err(FromJsonError::invalid_type("a number", value)),
+        }
+    }
+}
+
+impl ToJson for RawJson {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Raw(self.clone())
+    }
+}
+
+impl FromJson for RawJson {
+    /// Takes any value JSON can write, writing it out if it is not already raw text.
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Raw(raw) => Ok(raw.clone()),
+            _ => RawJson::from_value(value).ok_or_else(|| {
+                FromJsonError::Message("an infinite or NaN number can not be written".into())
+            }),
+        }
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(JsonNumber::F64(f64::from(*self)))
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        f64::from_json(value).map(|n| n as f32)
+    }
+}
+
+impl ToJson for () {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Null
+    }
+}
+
+impl FromJson for () {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Null => Ok(()),
+            _ => Err(FromJsonError::invalid_type("null", value)),
+        }
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+/// `None` is `null`, and `null` is always `None`.
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_ref().map_or(JsonValue::Null, ToJson::to_json)
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Null => Ok(None),
+            _ => T::from_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Array(array) => array.iter().map(T::from_json).collect(),
+            _ => Err(FromJsonError::invalid_type("an array", value)),
+        }
+    }
+}
+
+/// Converts tuples to and from arrays of exactly their length.
+macro_rules! tuple {
+    ($($len:tt => ($($n:tt $t:ident)+))+) => {$(
+        impl<$($t: ToJson),+> ToJson for ($($t,)+) {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::Array(vec![$(self.$n.to_json()),+])
+            }
+        }
+
+        impl<$($t: FromJson),+> FromJson for ($($t,)+) {
+            fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                match value {
+                    JsonValue::Array(array) if array.len() == $len => {
+                        Ok(($($t::from_json(&array[$n])?,)+))
+                    }
+                    JsonValue::Array(array) => Err(FromJsonError::Message(format!(
+                        "expected an array of {} elements, found {}",
+                        $len,
+                        array.len()
+                    ))),
+                    _ => Err(FromJsonError::invalid_type("an array", value)),
+                }
+            }
+        }
+    )+};
+}
+
+tuple! {
+    1 => (0 A)
+    2 => (0 A 1 B)
+    3 => (0 A 1 B 2 C)
+    4 => (0 A 1 B 2 C 3 D)
+    5 => (0 A 1 B 2 C 3 D 4 E)
+    6 => (0 A 1 B 2 C 3 D 4 E 5 F)
+    7 => (0 A 1 B 2 C 3 D 4 E 5 F 6 G)
+    8 => (0 A 1 B 2 C 3 D 4 E 5 F 6 G 7 H)
+}
+
+/// Collects the members of a JSON object into any map type.
+fn from_object<K, T, M>(value: &JsonValue) -> Result<M, FromJsonError>
+where
+    K: From<String>,
+    T: FromJson,
+    M: ::std::iter::FromIterator<(K, T)>,
+{
+    match value {
+        JsonValue::Object(map) => map
+            .iter()
+            .map(|(key, value)| Ok((K::from(key.to_string()), T::from_json(value)?)))
+            .collect(),
+        _ => Err(FromJsonError::invalid_type("an object", value)),
+    }
+}
+
+impl<K: AsRef<str> + Eq + Hash, T: ToJson> ToJson for HashMap<K, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(Box::new(
+            self.iter()
+                .map(|(key, value)| (key.as_ref().into(), value.to_json()))
+                .collect(),
+        ))
+    }
+}
+
+impl<K: From<String> + Eq + Hash, T: FromJson> FromJson for HashMap<K, T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        from_object(value)
+    }
+}
+
+impl<K: AsRef<str> + Ord, T: ToJson> ToJson for BTreeMap<K, T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(Box::new(
+            self.iter()
+                .map(|(key, value)| (key.as_ref().into(), value.to_json()))
+                .collect(),
+        ))
+    }
+}
+
+impl<K: From<String> + Ord, T: FromJson> FromJson for BTreeMap<K, T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        from_object(value)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Boolean(b)
+    }
+}
+
+impl<'a> From<&'a str> for JsonValue {
+    fn from(text: &'a str) -> Self {
+        JsonValue::Text(text.into())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(text: String) -> Self {
+        JsonValue::Text(text.into())
+    }
+}
+
+impl From<JsonString> for JsonValue {
+    fn from(text: JsonString) -> Self {
+        JsonValue::Text(text)
+    }
+}
+
+impl From<char> for JsonValue {
+    fn from(c: char) -> Self {
+        JsonValue::Text(c.into())
+    }
+}
+
+/// Converts a number to a `JsonNumber`, so only 128-bit integers beyond 64 bits may be rounded.
+macro_rules! from_number {
+    ($($ty:ident)*) => {
+        $(
+            impl From<$ty> for JsonValue {
+                fn from(n: $ty) -> Self {
+                    JsonValue::Number(n.into())
+                }
+            }
+        )*
+    };
+}
+
+from_number! { i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64 JsonNumber }
+
+/// `None` is `null`.
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::Null, Into::into)
+    }
+}
+
+/// Moves the items into an array, without copying them as
+/// [`ToJson`](trait.ToJson.html#impl-ToJson-for-Vec%3CT%3E) does.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonValue};
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///     let mut map = HashMap::new();
+///     map.insert("tags".to_string(), JsonValue::from(vec![Some("a"), None]));
+///     map.insert("count".to_string(), JsonValue::from(2u8));
+///
+///     assert_eq!(
+///         JsonValue::from(map),
+///         json_parse(r#"{ "tags": ["a", null], "count": 2 }"#).unwrap()
+///     );
+/// }
+/// ```
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(items: Vec<T>) -> Self {
+        JsonValue::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Moves the members into an object, without copying them as
+/// [`ToJson`](trait.ToJson.html#impl-ToJson-for-HashMap%3CK,+T%3E) does.
+impl<T: Into<JsonValue>> From<HashMap<String, T>> for JsonValue {
+    fn from(map: HashMap<String, T>) -> Self {
+        JsonValue::Object(Box::new(
+            map.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        ))
+    }
+}
+
+/// Moves the members into an object, without copying them as
+/// [`ToJson`](trait.ToJson.html#impl-ToJson-for-BTreeMap%3CK,+T%3E) does.
+impl<T: Into<JsonValue>> From<BTreeMap<String, T>> for JsonValue {
+    fn from(map: BTreeMap<String, T>) -> Self {
+        JsonValue::Object(Box::new(
+            map.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        ))
+    }
+}
+
+/// Implements `TryFrom<&JsonValue>` and `TryFrom<JsonValue>` with `FromJson`.
+macro_rules! try_from {
+    ($(<$($param:ident),*> $ty:ty;)*) => {
+        $(
+            impl<'a, $($param: FromJson),*> TryFrom<&'a JsonValue> for $ty {
+                type Error = FromJsonError;
+
+                fn try_from(value: &'a JsonValue) -> Result<Self, FromJsonError> {
+                    <$ty>::from_json(value)
+                }
+            }
+
+            impl<$($param: FromJson),*> TryFrom<JsonValue> for $ty {
+                type Error = FromJsonError;
+
+                fn try_from(value: JsonValue) -> Result<Self, FromJsonError> {
+                    <$ty>::from_json(&value)
+                }
+            }
+        )*
+    };
+}
+
+try_from! {
+    <> bool; <> char; <> f32; <> f64; <> (); <> i8; <> i16; <> i32; <> i64; <> i128; <> isize;
+    <> u8; <> u16; <> u32; <> u64; <> u128; <> usize;
+    <T> Vec<T>; <T> HashMap<String, T>;
+}
+
+/// Converts the members of an object into a map sorted by key.  Owned objects convert only into
+/// a map of `JsonValue`s, which they move into.
+impl<'a, T: FromJson> TryFrom<&'a JsonValue> for BTreeMap<String, T> {
+    type Error = FromJsonError;
+
+    fn try_from(value: &'a JsonValue) -> Result<Self, FromJsonError> {
+        from_object(value)
+    }
+}
+
+/// Extracts the text of a string value.  Fails with a `FromJsonError` that says what was found
+/// instead.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonValue};
+/// use std::collections::HashMap;
+/// use std::convert::TryFrom;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "name": "ada", "ids": [1, 2], "born": "1815" }"#).unwrap();
+///     assert_eq!(String::try_from(&json["name"]), Ok("ada".to_string()));
+///     assert_eq!(Vec::<u32>::try_from(&json["ids"]), Ok(vec![1, 2]));
+///
+///     let error = i64::try_from(&json["born"]).unwrap_err();
+///     assert_eq!(error.to_string(), "expected a number, found a string");
+///
+///     let map = HashMap::<String, JsonValue>::try_from(json).unwrap();
+///     assert_eq!(map.len(), 3);
+/// }
+/// ```
+impl<'a> TryFrom<&'a JsonValue> for String {
+    type Error = FromJsonError;
+
+    fn try_from(value: &'a JsonValue) -> Result<Self, FromJsonError> {
+        String::from_json(value)
+    }
+}
+
+/// Moves the text out of a string value, without copying it as `TryFrom<&JsonValue>` does.
+impl TryFrom<JsonValue> for String {
+    type Error = FromJsonError;
+
+    fn try_from(mut value: JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Text(ref mut text) => Ok(mem::take(text).into_string()),
+            _ => Err(FromJsonError::invalid_type("a string", &value)),
+        }
+    }
+}
+
+/// Moves the members of an object into a map sorted by key.  Fails if the value is not an object.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use std::collections::BTreeMap;
+/// use std::convert::TryFrom;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "b": 2, "a": 1, "c": [3] }"#).unwrap();
+///     let map = BTreeMap::try_from(json).unwrap();
+///     assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+/// }
+/// ```
+impl TryFrom<JsonValue> for BTreeMap<String, JsonValue> {
+    type Error = FromJsonError;
+
+    fn try_from(mut value: JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Object(ref mut map) => Ok(mem::take(&mut **map)
+                .into_iter()
+                .map(|(key, value)| (key.into_string(), value))
+                .collect()),
+            _ => Err(FromJsonError::invalid_type("an object", &value)),
+        }
+    }
+}
+
+impl JsonValue {
+    /// Builds an object from members whose keys are in strictly increasing order, as they come
+    /// out of a `BTreeMap` or a sorted `Vec`.  Fails on the first key that is out of order or
+    /// repeated, instead of silently keeping only the last of the duplicates as collecting into
+    /// an object does.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{FromJsonError, JsonValue};
+    ///
+    /// fn main() {
+    ///     let json = JsonValue::object_from_sorted(vec![("a", JsonValue::Null), ("b", JsonValue::Boolean(true))]);
+    ///     assert_eq!(json.unwrap().get("b"), Some(&JsonValue::Boolean(true)));
+    ///
+    ///     assert_eq!(
+    ///         JsonValue::object_from_sorted(vec![("b", JsonValue::Null), ("a", JsonValue::Null)]),
+    ///         Err(FromJsonError::Message("key \"a\" is not after \"b\"".into()))
+    ///     );
+    /// }
+    /// ```
+    pub fn object_from_sorted<I, K>(members: I) -> Result<JsonValue, FromJsonError>
+    where
+        I: IntoIterator<Item = (K, JsonValue)>,
+        K: Into<JsonString>,
+    {
+        let members = members.into_iter();
+        let mut map = JsonMap::with_capacity(members.size_hint().0);
+        let mut last: Option<JsonString> = None;
+
+        for (key, value) in members {
+            let key = key.into();
+            if let Some(last) = last.as_ref().filter(|last| key <= **last) {
+                return Err(FromJsonError::Message(format!(
+                    "key {:?} is not after {:?}",
+                    key.as_str(),
+                    last.as_str()
+                )));
+            }
+            last = Some(key.clone());
+            map.insert(key, value);
+        }
+        Ok(JsonValue::Object(Box::new(map)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_value, to_value, FromJsonError};
+    use crate::{json_parse, JsonValue, RawJson, ValueKind};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn convert_round_trip() {
+        let json = json_parse(r#"{ "a": [1, null, 3], "b": [] }"#).unwrap();
+        let map: BTreeMap<String, Vec<Option<u8>>> = from_value(&json).unwrap();
+        assert_eq!(map["a"], vec![Some(1), None, Some(3)]);
+        assert_eq!(to_value(&map), json);
+
+        let mut counts = HashMap::new();
+        counts.insert("n".to_string(), 2usize);
+        assert_eq!(from_value(&to_value(&counts)), Ok(counts));
+        assert_eq!(from_value::<char>(&JsonValue::Text("é".into())), Ok('é'));
+        assert_eq!(to_value("x"), JsonValue::Text("x".into()));
+        assert_eq!(from_value::<f32>(&JsonValue::from(0.5)), Ok(0.5));
+        assert_eq!(from_value::<i64>(&JsonValue::from(-7.0)), Ok(-7));
+        assert_eq!(to_value(&Box::new(())), JsonValue::Null);
+
+        let raw: RawJson = "[1,  2]".parse().unwrap();
+        assert_eq!(to_value(&raw).to_string(), "[1,  2]");
+        assert_eq!(from_value::<RawJson>(&JsonValue::Raw(raw.clone())), Ok(raw));
+        let raw: RawJson = from_value(&json_parse("[1,  2]").unwrap()).unwrap();
+        assert_eq!(raw.get(), "[1,2]");
+        assert!(from_value::<RawJson>(&JsonValue::from(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn convert_tuples() {
+        let json = json_parse(r#"{ "p": [["a", 1, true], ["b", 2, false]] }"#).unwrap();
+        let rows: HashMap<String, Vec<(String, u32, bool)>> = from_value(&json).unwrap();
+        assert_eq!(rows["p"][1], ("b".to_string(), 2, false));
+        assert_eq!(to_value(&rows), json);
+        assert_eq!(to_value(&(1u8,)), json_parse("[1]").unwrap());
+
+        assert_eq!(
+            from_value::<(u8, u8)>(&json_parse("[1, 2, 3]").unwrap()),
+            Err(FromJsonError::Message(
+                "expected an array of 2 elements, found 3".into()
+            ))
+        );
+        assert!(from_value::<(u8, bool)>(&json_parse("[1, 2]").unwrap()).is_err());
+        assert!(from_value::<(u8,)>(&JsonValue::from(1)).is_err());
+    }
+
+    #[test]
+    fn convert_from() {
+        let mut map = HashMap::new();
+        map.insert("n".to_string(), vec![Some(1u64), None]);
+        assert_eq!(
+            JsonValue::from(map),
+            json_parse(r#"{ "n": [1, null] }"#).unwrap()
+        );
+        assert_eq!(JsonValue::from(-3i8), JsonValue::from(-3.0));
+        assert_eq!(JsonValue::from(0.5f32), JsonValue::from(0.5));
+        assert_eq!(JsonValue::from(u128::MAX), JsonValue::from(2f64.powi(128)));
+        assert_eq!(JsonValue::from('c'), JsonValue::Text("c".into()));
+        assert_eq!(JsonValue::from(String::from("s")), JsonValue::from("s"));
+        assert_eq!(JsonValue::from(None::<bool>), JsonValue::Null);
+        assert_eq!(JsonValue::from(Some(true)), JsonValue::Boolean(true));
+        assert_eq!(
+            JsonValue::from(vec![vec![JsonValue::Null]]),
+            json_parse("[[null]]").unwrap()
+        );
+    }
+
+    #[test]
+    fn convert_errors() {
+        assert_eq!(
+            from_value::<u8>(&JsonValue::from(256.0)),
+            Err(FromJsonError::InvalidNumber {
+                expected: "u8",
+                found: 256.0,
+            })
+        );
+        assert!(from_value::<u64>(&JsonValue::from(1.5)).is_err());
+        assert!(from_value::<u64>(&JsonValue::from(18_446_744_073_709_551_616.0)).is_err());
+        assert_eq!(
+            from_value::<u128>(&JsonValue::from(2f64.powi(100))),
+            Ok(1 << 100)
+        );
+        assert_eq!(
+            from_value::<i128>(&JsonValue::from(-(2f64.powi(127)))),
+            Ok(i128::MIN)
+        );
+        assert_eq!(
+            from_value::<i128>(&JsonValue::from(2f64.powi(127))),
+            Err(FromJsonError::InvalidNumber {
+                expected: "i128",
+                found: 2f64.powi(127),
+            })
+        );
+        assert!(from_value::<u128>(&JsonValue::from(2f64.powi(128))).is_err());
+        assert!(from_value::<u128>(&JsonValue::from(-1.0)).is_err());
+        assert!(from_value::<char>(&JsonValue::Text("ab".into())).is_err());
+        assert_eq!(
+            from_value::<Vec<bool>>(&json_parse("[true, 1]").unwrap()),
+            Err(FromJsonError::InvalidType {
+                expected: "a boolean",
+                found: ValueKind::Number,
+            })
+        );
+    }
+
+    #[test]
+    fn convert_try_from() {
+        use std::convert::TryFrom;
+
+        let json = json_parse(r#"{ "a": [1.5, -2], "b": "text", "c": true }"#).unwrap();
+        assert_eq!(Vec::<f64>::try_from(&json["a"]), Ok(vec![1.5, -2.0]));
+        assert_eq!(bool::try_from(json["c"].clone()), Ok(true));
+        assert_eq!(String::try_from(json["b"].clone()), Ok("text".into()));
+        assert_eq!(
+            BTreeMap::<String, JsonValue>::try_from(&json).map(|map| map.len()),
+            Ok(3)
+        );
+        assert_eq!(
+            HashMap::<String, i64>::try_from(json["a"].clone()),
+            Err(FromJsonError::InvalidType {
+                expected: "an object",
+                found: ValueKind::Array,
+            })
+        );
+
+        let errors = [
+            (
+                i64::try_from(&json["a"][0]).unwrap_err(),
+                "1.5 does not fit in i64",
+            ),
+            (
+                String::try_from(json).unwrap_err(),
+                "expected a string, found an object",
+            ),
+            (
+                FromJsonError::MissingField("id".into()),
+                "missing field \"id\"",
+            ),
+        ];
+        for (error, message) in errors.iter() {
+            assert_eq!(error.to_string(), *message);
+        }
+    }
+
+    #[test]
+    fn convert_btree_map() {
+        use std::convert::TryFrom;
+
+        let json = json_parse(r#"{ "z": { "y": [1] }, "a": null }"#).unwrap();
+        let map = BTreeMap::try_from(json.clone()).unwrap();
+        assert_eq!(
+            map.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["a", "z"]
+        );
+        assert_eq!(
+            JsonValue::object_from_sorted(map.clone().into_iter()),
+            Ok(json.clone())
+        );
+        assert_eq!(JsonValue::from(map), json);
+
+        assert_eq!(
+            BTreeMap::try_from(JsonValue::Array(vec![])),
+            Err(FromJsonError::InvalidType {
+                expected: "an object",
+                found: ValueKind::Array,
+            })
+        );
+        assert!(JsonValue::object_from_sorted(vec![
+            ("a", JsonValue::Null),
+            ("a", JsonValue::Null)
+        ])
+        .is_err());
+        assert_eq!(
+            JsonValue::object_from_sorted(Vec::<(String, JsonValue)>::new()),
+            Ok(JsonValue::Object(Default::default()))
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn convert_derive() {
+        use crate::{FromJson, ToJson};
+
+        fn seven() -> u8 {
+            7
+        }
+
+        #[derive(ToJson, FromJson, Debug, PartialEq)]
+        struct Unit;
+
+        #[derive(ToJson, FromJson, Debug, PartialEq)]
+        struct Pair<T>(T, #[json(skip)] u8, T);
+
+        #[derive(ToJson, FromJson, Debug, PartialEq)]
+        struct Wrapper(Option<String>);
+
+        #[derive(ToJson, FromJson, Debug, PartialEq)]
+        enum Shape {
+            #[json(rename = "none")]
+            Empty,
+            Circle(f64),
+            Line(Pair<i8>),
+            Rect {
+                w: u32,
+                #[json(default = "seven")]
+                h: u8,
+            },
+        }
+
+        #[derive(ToJson, FromJson, Debug, PartialEq)]
+        enum Never {}
+
+        assert_eq!(to_value(&Unit), JsonValue::Null);
+        assert_eq!(from_value(&JsonValue::Null), Ok(Unit));
+        assert_eq!(to_value(&Wrapper(None)), JsonValue::Null);
+        assert_eq!(
+            from_value::<Pair<bool>>(&json_parse("[true, false]").unwrap()),
+            Ok(Pair(true, 0, false))
+        );
+
+        let shapes = json_parse(
+            r#"["none", {"Circle": 0.5}, {"Line": [-1, 1]}, {"Rect": {"w": 2, "h": 3}}]"#,
+        )
+        .unwrap();
+        let parsed: Vec<Shape> = from_value(&shapes).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                Shape::Empty,
+                Shape::Circle(0.5),
+                Shape::Line(Pair(-1, 0, 1)),
+                Shape::Rect { w: 2, h: 3 },
+            ]
+        );
+        assert_eq!(to_value(&parsed), shapes);
+        assert_eq!(
+            from_value(&json_parse(r#"{"Rect": {"w": 2}}"#).unwrap()),
+            Ok(Shape::Rect { w: 2, h: 7 })
+        );
+
+        assert_eq!(
+            from_value::<Shape>(&json_parse(r#"{"Rect": {"h": 2}}"#).unwrap()),
+            Err(FromJsonError::MissingField("w".into()))
+        );
+        assert_eq!(
+            from_value::<Shape>(&JsonValue::Text("Square".into())),
+            Err(FromJsonError::Message("unknown variant `Square`".into()))
+        );
+        assert!(from_value::<Pair<u8>>(&json_parse("[1, 2, 3]").unwrap()).is_err());
+        assert!(from_value::<Never>(&JsonValue::Text("x".into())).is_err());
+    }
+}
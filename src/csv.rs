@@ -0,0 +1,451 @@
+//! Conversion between CSV with a header row and arrays of flat JSON objects.
+use super::{json_parse, JsonMap, JsonValue};
+use std::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// How CSV is read and written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    /// The character separating fields, `,` by default
+    pub delimiter: char,
+    /// Whether unquoted fields that are JSON numbers are read as numbers, `true` by default
+    pub infer_numbers: bool,
+    /// Whether unquoted `true` and `false` are read as booleans, `true` by default
+    pub infer_booleans: bool,
+    /// Whether empty unquoted fields are read as `null`, `true` by default
+    pub empty_as_null: bool,
+    /// The columns to write, in order.  By default every key that appears in any row is written,
+    /// sorted.
+    pub columns: Option<Vec<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            infer_numbers: true,
+            infer_booleans: true,
+            empty_as_null: true,
+            columns: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Options that read every field as a string.
+    pub fn strings() -> Self {
+        Self {
+            infer_numbers: false,
+            infer_booleans: false,
+            empty_as_null: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Describes all possible errors that could occur while converting CSV
+#[derive(Clone, Debug, PartialEq)]
+pub enum CsvError {
+    /// A quoted field was never closed
+    UnterminatedQuote {
+        /// The zero-based record the field is in, counting the header
+        record: usize,
+    },
+    /// A quote appeared in the middle of an unquoted field, or text followed a closing quote
+    UnexpectedQuote {
+        /// The zero-based record the quote is in, counting the header
+        record: usize,
+    },
+    /// A record did not have as many fields as the header
+    FieldCount {
+        /// The zero-based record, counting the header
+        record: usize,
+        /// The number of fields in the header
+        expected: usize,
+        /// The number of fields in the record
+        found: usize,
+    },
+    /// The value to write was not an array
+    NotAnArray,
+    /// An item of the array to write was not an object
+    NotAnObject {
+        /// The index of the item
+        row: usize,
+    },
+    /// A member of an object to write was an array or object
+    NotFlat {
+        /// The index of the object
+        row: usize,
+        /// The key of the member
+        key: String,
+    },
+}
+
+/// Describes the error with records and rows counted from 0, as they are stored.
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::UnterminatedQuote { record } => {
+                write!(f, "unterminated quoted field in record {}", record)
+            }
+            CsvError::UnexpectedQuote { record } => {
+                write!(f, "unexpected quote in record {}", record)
+            }
+            CsvError::FieldCount {
+                record,
+                expected,
+                found,
+            } => write!(
+                f,
+                "record {} has {} fields, but the header has {}",
+                record, found, expected
+            ),
+            CsvError::NotAnArray => f.write_str("only an array can be written as CSV"),
+            CsvError::NotAnObject { row } => write!(f, "row {} is not an object", row),
+            CsvError::NotFlat { row, key } => {
+                write!(f, "member {:?} of row {} is an array or object", key, row)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for CsvError {}
+
+/// A field along with whether it was quoted.
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+/// Splits CSV into records of fields, accepting both `\n` and `\r\n` line endings.
+fn records(input: &str, delimiter: char) -> Result<Vec<Vec<Field>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        let mut field = Field {
+            text: String::new(),
+            quoted: chars.peek() == Some(&'"'),
+        };
+        if field.quoted {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.text.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.text.push(c),
+                    None => {
+                        return Err(CsvError::UnterminatedQuote {
+                            record: records.len(),
+                        })
+                    }
+                }
+            }
+        }
+
+        let end = loop {
+            match chars.next() {
+                Some(c) if c == delimiter => break Some(c),
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    break Some('\n');
+                }
+                Some('\n') => break Some('\n'),
+                None => break None,
+                Some(_) if field.quoted => {
+                    return Err(CsvError::UnexpectedQuote {
+                        record: records.len(),
+                    })
+                }
+                Some('"') => {
+                    return Err(CsvError::UnexpectedQuote {
+                        record: records.len(),
+                    })
+                }
+                Some(c) => field.text.push(c),
+            }
+        };
+
+        record.push(field);
+        match end {
+            Some('\n') => records.push(::std::mem::take(&mut record)),
+            Some(_) => (),
+            // a final line break does not start another record
+            None if record.len() == 1 && record[0].text.is_empty() && !record[0].quoted => break,
+            None => {
+                records.push(record);
+                break;
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn infer(field: Field, options: &CsvOptions) -> JsonValue {
+    if field.quoted {
+        return JsonValue::Text(field.text.into());
+    }
+    match field.text.as_str() {
+        "" if options.empty_as_null => JsonValue::Null,
+        "true" if options.infer_booleans => JsonValue::Boolean(true),
+        "false" if options.infer_booleans => JsonValue::Boolean(false),
+        text if options.infer_numbers && !text.starts_with(char::is_whitespace) => {
+            match json_parse(text) {
+                Ok(JsonValue::Number(n)) => JsonValue::Number(n),
+                _ => JsonValue::Text(field.text.into()),
+            }
+        }
+        _ => JsonValue::Text(field.text.into()),
+    }
+}
+
+/// Reads CSV with a header row as an array of objects keyed by the header.
+///
+/// Quoted fields are always strings, so quoting is how a CSV file can keep a field like `"42"`
+/// from becoming a number.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::csv::{from_csv, CsvOptions};
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let csv = "name,age,admin\nada,36,true\n\"Lovelace, A\",,\"false\"\n";
+///     assert_eq!(
+///         from_csv(csv, &CsvOptions::default()),
+///         Ok(json_parse(r#"[
+///             { "name": "ada", "age": 36, "admin": true },
+///             { "name": "Lovelace, A", "age": null, "admin": "false" }
+///         ]"#)
+///         .unwrap())
+///     );
+/// }
+/// ```
+pub fn from_csv(input: &str, options: &CsvOptions) -> Result<JsonValue, CsvError> {
+    let mut records = records(input, options.delimiter)?.into_iter();
+    let header = match records.next() {
+        Some(header) => header
+            .into_iter()
+            .map(|field| field.text)
+            .collect::<Vec<_>>(),
+        None => return Ok(JsonValue::Array(Vec::new())),
+    };
+
+    records
+        .enumerate()
+        .map(|(ind, record)| {
+            if record.len() != header.len() {
+                return Err(CsvError::FieldCount {
+                    record: ind + 1,
+                    expected: header.len(),
+                    found: record.len(),
+                });
+            }
+            Ok(JsonValue::Object(Box::new(
+                header
+                    .iter()
+                    .map(|column| column.as_str().into())
+                    .zip(record.into_iter().map(|field| infer(field, options)))
+                    .collect::<JsonMap>(),
+            )))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(JsonValue::Array)
+}
+
+fn write_field(out: &mut String, text: &str, delimiter: char) {
+    if text.contains(&[delimiter, '"', '\n', '\r'][..]) {
+        out.push('"');
+        out.push_str(&text.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(text);
+    }
+}
+
+/// Writes an array of flat objects as CSV with a header row.
+///
+/// Missing keys and `null` are written as empty fields.  Strings that would otherwise be read
+/// back as another type are quoted.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::csv::{to_csv, CsvOptions};
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let json = json_parse(r#"[{ "id": 1, "note": "a, b" }, { "id": 2, "extra": "42" }]"#).unwrap();
+///     assert_eq!(
+///         to_csv(&json, &CsvOptions::default()),
+///         Ok("extra,id,note\n,1,\"a, b\"\n\"42\",2,\n".to_string())
+///     );
+/// }
+/// ```
+pub fn to_csv(value: &JsonValue, options: &CsvOptions) -> Result<String, CsvError> {
+    let rows = match value {
+        JsonValue::Array(rows) => rows,
+        _ => return Err(CsvError::NotAnArray),
+    };
+    let mut objects = Vec::with_capacity(rows.len());
+    for (row, item) in rows.iter().enumerate() {
+        match item {
+            JsonValue::Object(map) => objects.push(map),
+            _ => return Err(CsvError::NotAnObject { row }),
+        }
+    }
+
+    let columns = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => objects
+            .iter()
+            .flat_map(|map| map.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|column| column.to_string())
+            .collect(),
+    };
+
+    let mut out = String::new();
+    for (ind, column) in columns.iter().enumerate() {
+        if ind > 0 {
+            out.push(options.delimiter);
+        }
+        write_field(&mut out, column, options.delimiter);
+    }
+    out.push('\n');
+
+    for (row, map) in objects.into_iter().enumerate() {
+        for (ind, column) in columns.iter().enumerate() {
+            if ind > 0 {
+                out.push(options.delimiter);
+            }
+            match map.get(column.as_str()) {
+                None | Some(JsonValue::Null) => (),
+                Some(JsonValue::Boolean(b)) => out.push_str(if *b { "true" } else { "false" }),
+                Some(JsonValue::Number(n)) => out.push_str(&n.to_string()),
+                Some(JsonValue::Text(text)) => {
+                    let field = Field {
+                        text: text.to_string(),
+                        quoted: false,
+                    };
+                    if let JsonValue::Text(_) = infer(field, options) {
+                        write_field(&mut out, text, options.delimiter);
+                    } else {
+                        out.push('"');
+                        out.push_str(text);
+                        out.push('"');
+                    }
+                }
+                Some(_) => {
+                    return Err(CsvError::NotFlat {
+                        row,
+                        key: column.clone(),
+                    })
+                }
+            }
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_csv, to_csv, CsvError, CsvOptions};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn csv_read() {
+        let csv = "a;b\r\n\"x\ny\";\"say \"\"hi\"\"\"\r\n -1;1e3\r\n;\"\"";
+        let options = CsvOptions {
+            delimiter: ';',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            from_csv(csv, &options),
+            Ok(json_parse(
+                r#"[
+                    { "a": "x\ny", "b": "say \"hi\"" },
+                    { "a": " -1", "b": 1000 },
+                    { "a": null, "b": "" }
+                ]"#
+            )
+            .unwrap())
+        );
+        assert_eq!(
+            from_csv("n,b\n1,true\n", &CsvOptions::strings()),
+            Ok(json_parse(r#"[{ "n": "1", "b": "true" }]"#).unwrap())
+        );
+        assert_eq!(from_csv("", &options), Ok(JsonValue::Array(vec![])));
+
+        assert_eq!(
+            from_csv("a,b\n1\n", &CsvOptions::default()),
+            Err(CsvError::FieldCount {
+                record: 1,
+                expected: 2,
+                found: 1,
+            })
+        );
+        assert_eq!(
+            from_csv("a,b\n1\n", &CsvOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "record 1 has 1 fields, but the header has 2"
+        );
+        assert_eq!(
+            from_csv("a\n\"1", &CsvOptions::default()),
+            Err(CsvError::UnterminatedQuote { record: 1 })
+        );
+        assert_eq!(
+            from_csv("a\n1\"", &CsvOptions::default()),
+            Err(CsvError::UnexpectedQuote { record: 1 })
+        );
+        assert_eq!(
+            from_csv("a\n\"1\"2", &CsvOptions::default()),
+            Err(CsvError::UnexpectedQuote { record: 1 })
+        );
+    }
+
+    #[test]
+    fn csv_write() {
+        let json =
+            json_parse(r#"[{ "a": "line\nbreak", "b": null, "c": false }, { "a": "", "c": 2.5 }]"#)
+                .unwrap();
+        let options = CsvOptions {
+            columns: Some(vec!["c".into(), "a".into()]),
+            ..CsvOptions::default()
+        };
+        let csv = to_csv(&json, &options).unwrap();
+        assert_eq!(csv, "c,a\nfalse,\"line\nbreak\"\n2.5,\"\"\n");
+        assert_eq!(
+            from_csv(&csv, &CsvOptions::default()),
+            Ok(
+                json_parse(r#"[{ "a": "line\nbreak", "c": false }, { "a": "", "c": 2.5 }]"#)
+                    .unwrap()
+            )
+        );
+
+        assert_eq!(
+            to_csv(&JsonValue::Null, &options),
+            Err(CsvError::NotAnArray)
+        );
+        assert_eq!(
+            to_csv(&json_parse("[{}, 1]").unwrap(), &options),
+            Err(CsvError::NotAnObject { row: 1 })
+        );
+        assert_eq!(
+            to_csv(&json_parse(r#"[{ "a": [] }]"#).unwrap(), &options),
+            Err(CsvError::NotFlat {
+                row: 0,
+                key: "a".into(),
+            })
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Exact decimal numbers, for documents whose numbers must not be rounded to `f64`.
+//!
+//! Parsing with
+//! [`ParseOptions::exact_numbers`](../options/struct.ParseOptions.html#structfield.exact_numbers)
+//! reads each number that is not an integer within 64 bits into a
+//! [`JsonNumber::Decimal`](../enum.JsonNumber.html#variant.Decimal), which is written back out
+//! digit for digit.
+//! ```
+//! extern crate json_rs;
+//! use json_rs::decimal::Decimal;
+//! use json_rs::{json_parse_with, JsonNumber, JsonValue, ParseOptions};
+//!
+//! fn main() {
+//!     let options = ParseOptions {
+//!         exact_numbers: true,
+//!         ..ParseOptions::default()
+//!     };
+//!     let source = r#"{"total":0.30000000000000000001,"items":[0.1,0.2]}"#;
+//!     let json = json_parse_with(source, options).unwrap();
+//!
+//!     assert_eq!(
+//!         json["total"],
+//!         JsonValue::Number(JsonNumber::Decimal(Decimal::new(30000000000000000001, -20)))
+//!     );
+//!     assert_eq!(json.to_string(), source);
+//! }
+//! ```
+use super::convert::{FromJson, FromJsonError, ToJson};
+use super::{JsonNumber, JsonValue};
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str::FromStr;
+
+/// A base 10 number stored as `±mantissa × 10^exponent` with a `u128` mantissa, so any JSON
+/// number with up to 38 significant digits, and every `i128` and `u128`, is held exactly.
+/// Trailing zeros are folded into the exponent, which makes equal numbers compare equal however
+/// they were written: `1.50`, `1.5` and `15e-1` are all the same `Decimal`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::decimal::Decimal;
+///
+/// fn main() {
+///     let price: Decimal = "19.990".parse().unwrap();
+///     assert_eq!((price.mantissa(), price.exponent()), (1999, -2));
+///     assert_eq!(price.to_string(), "19.99");
+///     assert_eq!(price, Decimal::new(1999, -2));
+///
+///     assert_eq!("0.1".parse::<Decimal>().unwrap().to_string(), "0.1");
+///     assert_eq!("-25e-30".parse::<Decimal>().unwrap().to_string(), "-2.5e-29");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    negative: bool,
+    /// The high and low halves of the mantissa, split so that a `Decimal` is only aligned to 8
+    /// bytes and `JsonNumber` stays small enough not to widen `JsonValue`
+    mantissa: [u64; 2],
+    exponent: i32,
+}
+
+/// Why text could not be read as a [`Decimal`](struct.Decimal.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    /// The text is not a JSON number
+    Invalid,
+    /// The number has more significant digits than fit in the mantissa, or an exponent too
+    /// large to store
+    OutOfRange,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParseDecimalError::Invalid => "not a JSON number",
+            ParseDecimalError::OutOfRange => "number too precise to hold exactly",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ParseDecimalError {}
+
+impl Decimal {
+    /// Creates the number `mantissa × 10^exponent`.
+    pub fn new(mantissa: i128, exponent: i32) -> Self {
+        Self::from_parts(mantissa < 0, mantissa.unsigned_abs(), exponent)
+    }
+
+    /// Creates the number `±mantissa × 10^exponent`, negative if `negative` is set.
+    pub fn from_parts(negative: bool, mantissa: u128, exponent: i32) -> Self {
+        let (mut mantissa, mut exponent) = (mantissa, exponent);
+        if mantissa == 0 {
+            return Self::default();
+        }
+        while mantissa % 10 == 0 && exponent < i32::MAX {
+            mantissa /= 10;
+            exponent += 1;
+        }
+        Self {
+            negative,
+            mantissa: [(mantissa >> 64) as u64, mantissa as u64],
+            exponent,
+        }
+    }
+
+    /// Whether the number is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The significant digits, with trailing zeros removed.
+    pub fn mantissa(&self) -> u128 {
+        let [high, low] = self.mantissa;
+        (u128::from(high) << 64) | u128::from(low)
+    }
+
+    /// The power of ten the mantissa is scaled by.
+    pub fn exponent(&self) -> i32 {
+        self.exponent
+    }
+
+    /// The nearest `f64`, which is what [`json_parse`](../fn.json_parse.html) would have read.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// The number as an `i128`, if it is a whole number in range.
+    pub fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.integer()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// The number as a `u128`, if it is a whole number in range.
+    pub fn to_u128(&self) -> Option<u128> {
+        match self.integer()? {
+            0 => Some(0),
+            magnitude if !self.negative => Some(magnitude),
+            _ => None,
+        }
+    }
+
+    /// The magnitude, if the number is whole and it fits in a `u128`.
+    fn integer(&self) -> Option<u128> {
+        u32::try_from(self.exponent)
+            .ok()
+            .and_then(|exponent| 10u128.checked_pow(exponent))
+            .and_then(|power| self.mantissa().checked_mul(power))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    /// Reads JSON number syntax exactly.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bytes = text.as_bytes();
+        let mut pos = 0;
+        let negative = bytes.first() == Some(&b'-');
+        if negative {
+            pos += 1;
+        }
+
+        let mut mantissa: u128 = 0;
+        // Zeros seen since the last nonzero digit, which are only multiplied in once another
+        // nonzero digit follows so that numbers like `1e40` written out in full still fit.
+        let mut zeros: i64 = 0;
+        let mut scale: i64 = 0;
+        let mut digits = 0;
+        let mut fraction = false;
+
+        while let Some(&byte) = bytes.get(pos) {
+            match byte {
+                b'0'..=b'9' => {
+                    if !fraction && digits == 1 && bytes[pos - 1] == b'0' {
+                        return Err(ParseDecimalError::Invalid);
+                    }
+                    digits += 1;
+                    if fraction {
+                        scale -= 1;
+                    }
+                    if byte == b'0' {
+                        zeros += 1;
+                    } else {
+                        let shifted = if mantissa == 0 {
+                            Some(0)
+                        } else {
+                            u32::try_from(zeros)
+                                .ok()
+                                .and_then(|zeros| 10u128.checked_pow(zeros))
+                                .and_then(|power| mantissa.checked_mul(power))
+                        };
+                        mantissa = shifted
+                            .and_then(|m| m.checked_mul(10))
+                            .and_then(|m| m.checked_add(u128::from(byte - b'0')))
+                            .ok_or(ParseDecimalError::OutOfRange)?;
+                        zeros = 0;
+                    }
+                }
+                b'.' if !fraction && digits > 0 => {
+                    fraction = true;
+                    digits = 0;
+                }
+                b'e' | b'E' if digits > 0 => break,
+                _ => return Err(ParseDecimalError::Invalid),
+            }
+            pos += 1;
+        }
+        if digits == 0 {
+            return Err(ParseDecimalError::Invalid);
+        }
+
+        let mut power: i64 = 0;
+        if pos < bytes.len() {
+            let rest = &text[pos + 1..];
+            let unsigned = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+            if unsigned.is_empty() || !unsigned.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ParseDecimalError::Invalid);
+            }
+            power = rest.parse().map_err(|_| ParseDecimalError::OutOfRange)?;
+        }
+
+        if mantissa == 0 {
+            return Ok(Self::default());
+        }
+        let exponent = power
+            .checked_add(scale)
+            .and_then(|e| e.checked_add(zeros))
+            .and_then(|e| i32::try_from(e).ok())
+            .ok_or(ParseDecimalError::OutOfRange)?;
+        Ok(Self::from_parts(negative, mantissa, exponent))
+    }
+}
+
+/// Writes the exact number as JSON number text.  Whole numbers of up to 39 digits, which covers
+/// every `i128` and `u128`, are written out in full, and other numbers of `1e21` or more or below
+/// `1e-6` are written with an exponent, as in JavaScript.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let digits = self.mantissa().to_string();
+        let magnitude = digits.len() as i64 + i64::from(self.exponent);
+
+        if self.exponent >= 0 && magnitude <= 39 {
+            write!(f, "{}{}", digits, "0".repeat(self.exponent as usize))
+        } else if self.exponent < 0 && magnitude > 0 {
+            let (int, frac) = digits.split_at(magnitude as usize);
+            write!(f, "{}.{}", int, frac)
+        } else if self.exponent < 0 && magnitude > -6 {
+            write!(f, "0.{}{}", "0".repeat(-magnitude as usize), digits)
+        } else {
+            let (first, rest) = digits.split_at(1);
+            let dot = if rest.is_empty() { "" } else { "." };
+            write!(f, "{}{}{}e{}", first, dot, rest, magnitude - 1)
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        Self::new(i128::from(n), 0)
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(n: u64) -> Self {
+        Self::new(i128::from(n), 0)
+    }
+}
+
+impl From<i128> for Decimal {
+    fn from(n: i128) -> Self {
+        Self::new(n, 0)
+    }
+}
+
+impl From<u128> for Decimal {
+    fn from(n: u128) -> Self {
+        Self::from_parts(false, n, 0)
+    }
+}
+
+/// Keeps the number exactly, as an `I64` or `U64` if it is an integer that fits and otherwise as a
+/// `JsonNumber::Decimal`.
+impl ToJson for Decimal {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(JsonNumber::from(*self))
+    }
+}
+
+/// Reads the shortest decimal that rounds to the number, so `0.1` reads as exactly `0.1`.
+impl FromJson for Decimal {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(n) => n.as_decimal().ok_or(FromJsonError::InvalidNumber {
+                expected: "a decimal",
+                found: n.as_f64(),
+            }),
+            _ => Err(FromJsonError::invalid_type("a number", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decimal, ParseDecimalError};
+    use crate::convert::{FromJson, ToJson};
+    use crate::options::ParseOptions;
+    use crate::{json_parse, json_parse_with, JsonError, JsonNumber, JsonValue};
+
+    fn exact() -> ParseOptions {
+        ParseOptions {
+            exact_numbers: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn decimal_matches_json_parse() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            assert_eq!(json_parse_with(&source, exact()), json_parse(&source));
+        }
+    }
+
+    #[test]
+    fn decimal_exact() {
+        let tests = vec![
+            ("0", (0, 0), "0"),
+            ("-0.0", (0, 0), "0"),
+            ("0.1", (1, -1), "0.1"),
+            ("100", (1, 2), "100"),
+            ("1.2500", (125, -2), "1.25"),
+            ("-3E+2", (-3, 2), "-300"),
+            ("12e-9", (12, -9), "1.2e-8"),
+            ("0.000001", (1, -6), "0.000001"),
+            ("0.0000001", (1, -7), "1e-7"),
+            ("1e21", (1, 21), "1000000000000000000000"),
+            ("1.5e39", (15, 38), "1.5e39"),
+            (
+                "99999999999999999999999999999999999999",
+                (99999999999999999999999999999999999999, 0),
+                "99999999999999999999999999999999999999",
+            ),
+            (
+                "1000000000000000000000000000000000000000000000000000000000000000",
+                (1, 63),
+                "1e63",
+            ),
+        ];
+        for (text, (mantissa, exponent), written) in tests.into_iter() {
+            let decimal: Decimal = text.parse().unwrap();
+            assert_eq!(decimal, Decimal::new(mantissa, exponent));
+            assert_eq!(decimal.to_string(), written);
+            assert_eq!(decimal.to_f64(), text.parse::<f64>().unwrap());
+        }
+
+        for text in [
+            "", "-", "01", "1.", ".5", "1e", "1e+", "+1", "1.5.2", "0x10",
+        ] {
+            assert_eq!(text.parse::<Decimal>(), Err(ParseDecimalError::Invalid));
+        }
+        for text in ["923456789012345678901234567890123456789", "1e99999999999"] {
+            assert_eq!(text.parse::<Decimal>(), Err(ParseDecimalError::OutOfRange));
+        }
+        assert_eq!(ParseDecimalError::Invalid.to_string(), "not a JSON number");
+
+        assert_eq!(
+            Decimal::from_json(&JsonValue::from(0.1)),
+            Ok(Decimal::new(1, -1))
+        );
+        assert_eq!(Decimal::new(15, -1).to_json(), JsonValue::from(1.5));
+        assert_eq!(
+            Decimal::new(2, 0).to_json(),
+            JsonValue::Number(JsonNumber::U64(2))
+        );
+    }
+
+    #[test]
+    fn decimal_parse() {
+        let exact_number = |n| JsonValue::Number(JsonNumber::Decimal(n));
+        assert_eq!(
+            json_parse_with("[1.10, -2, 3e2, -0]", exact()),
+            Ok(JsonValue::Array(vec![
+                exact_number(Decimal::new(11, -1)),
+                JsonValue::Number(JsonNumber::I64(-2)),
+                exact_number(Decimal::new(3, 2)),
+                JsonValue::from(-0.0),
+            ]))
+        );
+        assert_eq!(
+            json_parse_with(
+                ".5",
+                ParseOptions {
+                    json5: true,
+                    ..exact()
+                }
+            ),
+            Ok(exact_number(Decimal::new(5, -1)))
+        );
+        assert_eq!(
+            json_parse_with(
+                "[0x10, NaN]",
+                ParseOptions {
+                    json5: true,
+                    ..exact()
+                }
+            )
+            .map(|json| json.to_string()),
+            Ok("[16,null]".to_string())
+        );
+
+        let source = "[1, 923456789012345678901234567890123456789]";
+        let error = JsonError::NumberOutOfRange {
+            location: 4,
+            line: 0,
+            column: 4,
+            offset: 4,
+        };
+        assert_eq!(json_parse_with(source, exact()), Err(error.clone()));
+        assert_eq!(
+            crate::json_parse_document(source, exact()).map(|_| ()),
+            Err(error)
+        );
+        assert_eq!(
+            json_parse(source).map(|json| json[1].as_f64()),
+            Ok(Some(9.234567890123457e38))
+        );
+    }
+
+    #[test]
+    fn decimal_wide_integers() {
+        let tests = vec![
+            (
+                i128::MAX.to_string(),
+                Some(i128::MAX),
+                Some(i128::MAX as u128),
+            ),
+            (i128::MIN.to_string(), Some(i128::MIN), None),
+            (u128::MAX.to_string(), None, Some(u128::MAX)),
+            ("-0".to_string(), Some(0), Some(0)),
+            ("-1".to_string(), Some(-1), None),
+            ("1.5".to_string(), None, None),
+            ("3.4e38".to_string(), None, Some(34 * 10u128.pow(37))),
+            ("1e39".to_string(), None, None),
+        ];
+        for (text, signed, unsigned) in tests.into_iter() {
+            let decimal: Decimal = text.parse().unwrap();
+            assert_eq!((decimal.to_i128(), decimal.to_u128()), (signed, unsigned));
+            if let Some(n) = signed {
+                assert_eq!(Decimal::from(n), decimal);
+                assert_eq!(Decimal::from(n).to_string(), n.to_string());
+            }
+        }
+
+        for n in [i128::MIN.to_string(), u128::MAX.to_string()] {
+            let json = json_parse_with(&format!("[{}]", n), exact()).unwrap();
+            assert_eq!(json[0].as_i128(), n.parse().ok());
+            assert_eq!(json[0].as_u128(), n.parse().ok());
+            assert_eq!(json.to_string(), format!("[{}]", n));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::options::ParseOptions;
+use super::span::{parse_spanned, Span, Spanned, SpannedValue};
+use super::{JsonError, JsonPointer};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A comment found while parsing in lenient mode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    /// The text of the comment, without the `//` or `/* */` around it
+    pub text: String,
+    /// Where the whole comment is in the source
+    pub span: Span,
+    /// The path of the key or value that follows the comment, or `None` if nothing does
+    pub target: Option<JsonPointer>,
+}
+
+impl Comment {
+    pub(crate) fn new(source: &str, span: Span, target: Option<JsonPointer>) -> Self {
+        let raw = &source[span.start..span.end];
+        let text = if raw.starts_with("/*") {
+            &raw[2..raw.len() - 2]
+        } else {
+            &raw[2..]
+        };
+
+        Self {
+            text: text.to_string(),
+            span,
+            target,
+        }
+    }
+}
+
+/// A spanned tree along with the comments in its source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    /// The root value
+    pub root: Spanned<SpannedValue>,
+    /// Every comment, in source order
+    pub comments: Vec<Comment>,
+}
+
+impl Document {
+    /// The comments attached to the key or value at `pointer`.  A comment before an object
+    /// member is attached to that member's path.
+    pub fn comments_for<'a>(
+        &'a self,
+        pointer: &'a JsonPointer,
+    ) -> impl Iterator<Item = &'a Comment> {
+        self.comments
+            .iter()
+            .filter(move |comment| comment.target.as_ref() == Some(pointer))
+    }
+}
+
+/// Parses a JSON string into a spanned tree, collecting comments if `options` allows them.
+///
+/// Each comment is attached to the nearest key or value that follows it, so documentation
+/// written above a setting in a config file can be looked up by that setting's path.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse_document, ParseOptions};
+///
+/// fn main() {
+///     let source = r#"{
+///         // the port to listen on
+///         "port": 8080,
+///         "hosts": [
+///             "a", /* backup */ "b"
+///         ]
+///     }"#;
+///     let document = json_parse_document(source, ParseOptions::lenient()).unwrap();
+///
+///     let port = "/port".parse().unwrap();
+///     let comment = document.comments_for(&port).next().unwrap();
+///     assert_eq!(comment.text.trim(), "the port to listen on");
+///
+///     let backup = "/hosts/1".parse().unwrap();
+///     assert_eq!(document.comments_for(&backup).next().unwrap().text, " backup ");
+/// }
+/// ```
+pub fn json_parse_document(json_str: &str, options: ParseOptions) -> Result<Document, JsonError> {
+    trace_span!(
+        "json_parse_document",
+        bytes = json_str.len(),
+        comments = options.comments
+    );
+    trace_error!(parse_spanned(json_str, options))
+        .map(|(root, comments)| Document { root, comments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_parse_document, Comment};
+    use crate::options::ParseOptions;
+    use crate::span::{json_parse_spanned, Span};
+    use crate::{JsonError, JsonPointer};
+
+    #[test]
+    fn document_comments() {
+        let source = "/*a*/[1, {//b\n\"k\"/*c*/: [/*d*/]}, 2 //e\n]//f";
+        let document = json_parse_document(source, ParseOptions::lenient()).unwrap();
+        let pointer = |st: &str| Some(st.parse::<JsonPointer>().unwrap());
+
+        assert_eq!(
+            document.comments,
+            vec![
+                Comment {
+                    text: "a".into(),
+                    span: Span::new(0, 5),
+                    target: pointer(""),
+                },
+                Comment {
+                    text: "b".into(),
+                    span: Span::new(10, 13),
+                    target: pointer("/1/k"),
+                },
+                Comment {
+                    text: "c".into(),
+                    span: Span::new(17, 22),
+                    target: pointer("/1/k"),
+                },
+                Comment {
+                    text: "d".into(),
+                    span: Span::new(25, 30),
+                    target: pointer("/2"),
+                },
+                Comment {
+                    text: "e".into(),
+                    span: Span::new(36, 39),
+                    target: None,
+                },
+                Comment {
+                    text: "f".into(),
+                    span: Span::new(41, 44),
+                    target: None,
+                },
+            ]
+        );
+        assert_eq!(
+            json_parse_document(&source.replace("/*a*/", ""), ParseOptions::default())
+                .map(|document| document.root),
+            Err(JsonError::UnexpectedToken {
+                character: '/',
+                location: 5,
+                line: 0,
+                column: 5,
+                offset: 5,
+            })
+        );
+        assert_eq!(
+            json_parse_document("[1]", ParseOptions::lenient()).map(|document| document.root),
+            json_parse_spanned("[1]")
+        );
+    }
+
+    #[test]
+    fn document_options() {
+        use crate::DuplicateKeys;
+
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::FirstWins,
+            ..ParseOptions::lenient()
+        };
+        let document = json_parse_document("{\"a\": 1, \"a\": /* again */ 2}", options).unwrap();
+        assert_eq!(document.root.value.to_json(), json!({ "a": 1 }));
+        assert!(document.comments.is_empty());
+
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            max_depth: 1,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            json_parse_document("{\"a\": 1, \"a\": 2}", options),
+            Err(JsonError::DuplicateKey { location: 9, .. })
+        ));
+        assert!(matches!(
+            json_parse_document("[[]]", options),
+            Err(JsonError::DepthLimitExceeded { location: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+use super::{JsonPointer, JsonValue};
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A single reversible change to a `JsonValue`, addressed by a JSON Pointer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonEdit {
+    /// Adds a value.  Object keys are inserted or overwritten, while for arrays the value is
+    /// inserted before the element at the index (an index equal to the length, or `-`, appends).
+    Add {
+        /// Where to add the value
+        path: JsonPointer,
+        /// The value to add
+        value: JsonValue,
+    },
+    /// Adds a value as `Add` does, except that a new object key goes at `index` among the
+    /// object's members, or last if it has fewer.  This is what undoes removing a member with the
+    /// `preserve_order` feature, which puts the member back where it was; without the feature,
+    /// objects have no order, and this is the same as `Add`.
+    Insert {
+        /// Where to add the value
+        path: JsonPointer,
+        /// The position of a new object key
+        index: usize,
+        /// The value to add
+        value: JsonValue,
+    },
+    /// Removes an object key or array element.
+    Remove {
+        /// The value to remove
+        path: JsonPointer,
+    },
+    /// Replaces an existing value.
+    Replace {
+        /// The value to replace
+        path: JsonPointer,
+        /// The new value
+        value: JsonValue,
+    },
+}
+
+/// Describes all possible errors that could occur while applying a `JsonEdit`
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    /// Nothing exists at the path
+    NotFound(JsonPointer),
+    /// The parent of the path is neither an object nor an array
+    NotAContainer(JsonPointer),
+    /// The last token of the path is not a valid index into the parent array
+    InvalidIndex(JsonPointer),
+    /// The root of a document cannot be removed
+    RemoveRoot,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NotFound(path) => write!(f, "nothing exists at {:?}", path.to_string()),
+            EditError::NotAContainer(path) => write!(
+                f,
+                "the value at {:?} is neither an object nor an array",
+                path.to_string()
+            ),
+            EditError::InvalidIndex(path) => {
+                write!(f, "{:?} does not index into its array", path.to_string())
+            }
+            EditError::RemoveRoot => f.write_str("the root of a document cannot be removed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for EditError {}
+
+impl JsonEdit {
+    /// The location this edit applies to.
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            JsonEdit::Add { path, .. }
+            | JsonEdit::Insert { path, .. }
+            | JsonEdit::Remove { path }
+            | JsonEdit::Replace { path, .. } => path,
+        }
+    }
+
+    /// Applies the edit to `target`, returning the edit that would undo it.  `target` is left
+    /// untouched if the edit fails.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonEdit, JsonValue};
+    ///
+    /// fn main() {
+    ///     let mut json = json_parse("[1, 2]").unwrap();
+    ///     let edit = JsonEdit::Add {
+    ///         path: "/-".parse().unwrap(),
+    ///         value: JsonValue::Null,
+    ///     };
+    ///
+    ///     let undo = edit.apply(&mut json).unwrap();
+    ///     assert_eq!(json, json_parse("[1, 2, null]").unwrap());
+    ///     assert_eq!(undo, JsonEdit::Remove { path: "/2".parse().unwrap() });
+    ///
+    ///     undo.apply(&mut json).unwrap();
+    ///     assert_eq!(json, json_parse("[1, 2]").unwrap());
+    /// }
+    /// ```
+    pub fn apply(self, target: &mut JsonValue) -> Result<JsonEdit, EditError> {
+        match self {
+            JsonEdit::Add { path, value } => add(target, path, value),
+            JsonEdit::Insert { path, index, value } => insert(target, path, index, value),
+            JsonEdit::Remove { path } => remove(target, path),
+            JsonEdit::Replace { path, value } => {
+                let old = path
+                    .resolve_mut(target)
+                    .map(|old| ::std::mem::replace(old, value))
+                    .ok_or_else(|| EditError::NotFound(path.clone()))?;
+                Ok(JsonEdit::Replace { path, value: old })
+            }
+        }
+    }
+}
+
+fn parent_of<'a>(
+    target: &'a mut JsonValue,
+    path: &JsonPointer,
+) -> Result<(&'a mut JsonValue, JsonPointer, String), EditError> {
+    let (parent_path, last) = path
+        .split_last()
+        .map(|(parent, last)| (parent, last.to_string()))
+        .ok_or(EditError::RemoveRoot)?;
+    let parent = parent_path
+        .resolve_mut(target)
+        .ok_or_else(|| EditError::NotFound(parent_path.clone()))?;
+    Ok((parent, parent_path, last))
+}
+
+fn add(target: &mut JsonValue, path: JsonPointer, value: JsonValue) -> Result<JsonEdit, EditError> {
+    if path.is_root() {
+        let old = ::std::mem::replace(target, value);
+        return Ok(JsonEdit::Replace { path, value: old });
+    }
+
+    let (parent, parent_path, last) = parent_of(target, &path)?;
+    match parent {
+        JsonValue::Object(map) => Ok(match map.insert(last.into(), value) {
+            Some(old) => JsonEdit::Replace { path, value: old },
+            None => JsonEdit::Remove { path },
+        }),
+        JsonValue::Array(array) => {
+            let ind = if last == "-" {
+                array.len()
+            } else {
+                last.parse()
+                    .ok()
+                    .filter(|&i| i <= array.len())
+                    .ok_or_else(|| EditError::InvalidIndex(path.clone()))?
+            };
+            array.insert(ind, value);
+            Ok(JsonEdit::Remove {
+                path: parent_path.child(ind.to_string()),
+            })
+        }
+        _ => Err(EditError::NotAContainer(parent_path)),
+    }
+}
+
+fn insert(
+    target: &mut JsonValue,
+    path: JsonPointer,
+    index: usize,
+    value: JsonValue,
+) -> Result<JsonEdit, EditError> {
+    // objects only have an order to insert into with `preserve_order`
+    #[cfg(not(feature = "preserve_order"))]
+    let _ = index;
+    match parent_of(target, &path) {
+        #[cfg(feature = "preserve_order")]
+        Ok((JsonValue::Object(map), _, last)) => {
+            Ok(match map.insert_at(index, last.into(), value) {
+                Some(old) => JsonEdit::Replace { path, value: old },
+                None => JsonEdit::Remove { path },
+            })
+        }
+        _ => add(target, path, value),
+    }
+}
+
+fn remove(target: &mut JsonValue, path: JsonPointer) -> Result<JsonEdit, EditError> {
+    let (parent, parent_path, last) = parent_of(target, &path)?;
+    let value = match parent {
+        #[cfg(feature = "preserve_order")]
+        JsonValue::Object(map) => {
+            let index = map
+                .index_of(last.as_str())
+                .ok_or_else(|| EditError::NotFound(path.clone()))?;
+            let value = map.remove(last.as_str()).unwrap();
+            return Ok(JsonEdit::Insert { path, index, value });
+        }
+        #[cfg(not(feature = "preserve_order"))]
+        JsonValue::Object(map) => map
+            .remove(last.as_str())
+            .ok_or_else(|| EditError::NotFound(path.clone()))?,
+        JsonValue::Array(array) => last
+            .parse()
+            .ok()
+            .filter(|&i| i < array.len())
+            .map(|i| array.remove(i))
+            .ok_or_else(|| EditError::InvalidIndex(path.clone()))?,
+        _ => return Err(EditError::NotAContainer(parent_path)),
+    };
+    Ok(JsonEdit::Add { path, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditError, JsonEdit, JsonValue};
+    use crate::json_parse;
+
+    #[test]
+    fn edit_apply_inverse() {
+        let tests = vec![
+            (
+                r#"{ "a": 1 }"#,
+                JsonEdit::Add {
+                    path: "/b".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                r#"{ "a": 1, "b": null }"#,
+            ),
+            (
+                r#"{ "a": 1 }"#,
+                JsonEdit::Add {
+                    path: "/a".parse().unwrap(),
+                    value: JsonValue::Boolean(false),
+                },
+                r#"{ "a": false }"#,
+            ),
+            (
+                r#"[1, [2, 3]]"#,
+                JsonEdit::Add {
+                    path: "/1/0".parse().unwrap(),
+                    value: JsonValue::from(4.0),
+                },
+                r#"[1, [4, 2, 3]]"#,
+            ),
+            (
+                r#"{ "a": [1, 2, 3] }"#,
+                JsonEdit::Remove {
+                    path: "/a/1".parse().unwrap(),
+                },
+                r#"{ "a": [1, 3] }"#,
+            ),
+            (
+                r#"{ "a": { "b": "c" } }"#,
+                JsonEdit::Replace {
+                    path: "/a/b".parse().unwrap(),
+                    value: JsonValue::Array(vec![]),
+                },
+                r#"{ "a": { "b": [] } }"#,
+            ),
+            (
+                r#"true"#,
+                JsonEdit::Add {
+                    path: "".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                r#"null"#,
+            ),
+        ];
+
+        for (original, edit, edited) in tests.into_iter() {
+            let mut json = json_parse(original).unwrap();
+            let inverse = edit.apply(&mut json).unwrap();
+            assert_eq!(json, json_parse(edited).unwrap());
+            inverse.apply(&mut json).unwrap();
+            assert_eq!(json, json_parse(original).unwrap());
+        }
+    }
+
+    #[test]
+    fn edit_insert() {
+        let mut json = json_parse(r#"{ "a": 1, "b": 2 }"#).unwrap();
+        let edit = JsonEdit::Insert {
+            path: "/c".parse().unwrap(),
+            index: 0,
+            value: JsonValue::Null,
+        };
+        let undo = edit.apply(&mut json).unwrap();
+        assert_eq!(
+            json,
+            json_parse(r#"{ "c": null, "a": 1, "b": 2 }"#).unwrap()
+        );
+        assert_eq!(
+            undo,
+            JsonEdit::Remove {
+                path: "/c".parse().unwrap()
+            }
+        );
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(json.to_string(), r#"{"c":null,"a":1,"b":2}"#);
+
+        let redo = undo.apply(&mut json).unwrap();
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(
+            redo,
+            JsonEdit::Insert {
+                path: "/c".parse().unwrap(),
+                index: 0,
+                value: JsonValue::Null,
+            }
+        );
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(
+            redo,
+            JsonEdit::Add {
+                path: "/c".parse().unwrap(),
+                value: JsonValue::Null,
+            }
+        );
+
+        // in an array, the index in the path is what counts
+        let mut json = json_parse("[1, 2]").unwrap();
+        let edit = JsonEdit::Insert {
+            path: "/1".parse().unwrap(),
+            index: 0,
+            value: JsonValue::Null,
+        };
+        edit.apply(&mut json).unwrap();
+        assert_eq!(json, json_parse("[1, null, 2]").unwrap());
+    }
+
+    #[test]
+    fn edit_errors() {
+        let tests = vec![
+            (
+                JsonEdit::Remove {
+                    path: "/missing".parse().unwrap(),
+                },
+                EditError::NotFound("/missing".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Add {
+                    path: "/x/y/z".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                EditError::NotFound("/x/y".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Add {
+                    path: "/arr/5".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                EditError::InvalidIndex("/arr/5".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Remove {
+                    path: "/arr/-".parse().unwrap(),
+                },
+                EditError::InvalidIndex("/arr/-".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Add {
+                    path: "/num/0".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                EditError::NotAContainer("/num".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Replace {
+                    path: "/arr/2".parse().unwrap(),
+                    value: JsonValue::Null,
+                },
+                EditError::NotFound("/arr/2".parse().unwrap()),
+            ),
+            (
+                JsonEdit::Remove {
+                    path: "".parse().unwrap(),
+                },
+                EditError::RemoveRoot,
+            ),
+        ];
+
+        let original = json_parse(r#"{ "arr": [1, 2], "num": 3 }"#).unwrap();
+        for (edit, error) in tests.into_iter() {
+            let mut json = original.clone();
+            assert_eq!(edit.apply(&mut json), Err(error));
+            assert_eq!(json, original);
+        }
+
+        assert_eq!(
+            EditError::NotAContainer("/num".parse().unwrap()).to_string(),
+            r#"the value at "/num" is neither an object nor an array"#
+        );
+    }
+}
@@ -0,0 +1,491 @@
+use super::lexer::{Cursor, Punct};
+use super::options::{DuplicateKeys, ParseOptions};
+use super::span::{Place, Span};
+use super::{JsonError, JsonValue};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// One step of a document's structure.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Event {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Key(String),
+    Value(JsonValue),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+/// What may come next, which both `EventParser` and the recovering parser track.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Expect {
+    /// A value, or the end of the enclosing array if it is empty so far
+    Value {
+        first: bool,
+    },
+    /// A key, or the end of the enclosing object if it is empty so far
+    Key {
+        first: bool,
+    },
+    Colon,
+    /// A comma or the end of the enclosing container
+    Next,
+    /// Only whitespace may follow the root value
+    End,
+    /// The root value and the whitespace after it have been consumed
+    Done,
+}
+
+/// One token's worth of progress through a document, including the ones that produce no event.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Step {
+    Whitespace(Span),
+    Comment(Span),
+    Punct(Punct, Span),
+    Key(String, Span),
+    Value(JsonValue, Span),
+}
+
+/// Pulls structural events out of JSON source.  Open containers are tracked on an explicit stack,
+/// so nesting depth is limited by `ParseOptions::max_depth` rather than the call stack, and keys
+/// seen twice in one object are rejected or dropped as `ParseOptions::duplicate_keys` asks.
+#[derive(Debug)]
+pub(crate) struct EventParser<'a> {
+    cursor: Cursor<'a>,
+    options: ParseOptions,
+    containers: Vec<Container>,
+    /// The keys read so far in each open object, unless later values simply replace earlier ones
+    keys: Vec<HashSet<String>>,
+    expect: Expect,
+    /// The depth of the object whose current member repeats an earlier key and is being dropped
+    skip: Option<usize>,
+    /// Whether the last step belongs to a dropped member
+    skipped: bool,
+}
+
+impl<'a> EventParser<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Self {
+            cursor: Cursor::with_options(source, options),
+            options,
+            containers: vec![],
+            keys: vec![],
+            expect: Expect::Value { first: false },
+            skip: None,
+            skipped: false,
+        }
+    }
+
+    /// A parser that leaves strings without escapes empty, as `Cursor::borrow_text` describes.
+    pub fn borrowing(source: &'a str) -> Self {
+        let mut parser = Self::new(source);
+        parser.cursor.borrow_text();
+        parser
+    }
+
+    /// Whether the last step belongs to an object member whose key was already seen, which
+    /// `DuplicateKeys::FirstWins` asks to be left out.  `next_event` never produces these.
+    pub fn skipped(&self) -> bool {
+        self.skipped
+    }
+
+    /// The byte offset of the next unconsumed character.
+    pub fn offset(&mut self) -> usize {
+        self.cursor.offset()
+    }
+
+    fn after_value(&self) -> Expect {
+        if self.containers.is_empty() {
+            Expect::End
+        } else {
+            Expect::Next
+        }
+    }
+
+    fn tracks_keys(&self) -> bool {
+        self.options.duplicate_keys != DuplicateKeys::LastWins
+    }
+
+    fn open(&mut self, place: Place) -> Result<Step, JsonError> {
+        if self.containers.len() >= self.options.max_depth {
+            return Err(place.depth_err(self.options.max_depth));
+        }
+        let (punct, span) = self.cursor.punct().unwrap();
+        if punct == Punct::ArrayStart {
+            self.containers.push(Container::Array);
+            self.expect = Expect::Value { first: true };
+        } else {
+            self.containers.push(Container::Object);
+            self.expect = Expect::Key { first: true };
+            if self.tracks_keys() {
+                self.keys.push(HashSet::new());
+            }
+        }
+        Ok(Step::Punct(punct, span))
+    }
+
+    fn close(&mut self) -> Step {
+        let (punct, span) = self.cursor.punct().unwrap();
+        if self.containers.pop() == Some(Container::Object) && self.tracks_keys() {
+            self.keys.pop();
+        }
+        self.expect = self.after_value();
+        Step::Punct(punct, span)
+    }
+
+    /// Records a key of the innermost object, which is an error if it was already seen there
+    /// and `DuplicateKeys::Error` was chosen, or starts a member to drop for `FirstWins`.
+    fn see_key(&mut self, key: &str, span: Span, place: Place) -> Result<(), JsonError> {
+        let keys = match self.keys.last_mut() {
+            Some(keys) => keys,
+            None => return Ok(()),
+        };
+        // a borrowing parser leaves keys without escapes for the source to supply
+        let key = match key {
+            "" => &self.cursor.source()[span.start + 1..span.end - 1],
+            key => key,
+        };
+        if keys.insert(key.into()) {
+            return Ok(());
+        }
+        if self.options.duplicate_keys == DuplicateKeys::Error {
+            return Err(place.duplicate_err(key.into()));
+        }
+        self.skip = Some(self.containers.len());
+        self.skipped = true;
+        Ok(())
+    }
+
+    /// Consumes the next token, or returns `None` once the root value and any trailing
+    /// whitespace have been consumed.
+    pub fn step(&mut self) -> Result<Option<Step>, JsonError> {
+        use self::Expect::*;
+
+        if self.expect == Done {
+            return Ok(None);
+        }
+        self.skipped = self.skip.is_some();
+        if let Some(span) = self.cursor.whitespace() {
+            return Ok(Some(Step::Whitespace(span)));
+        }
+        if self.options.comments {
+            if let Some(span) = self.cursor.comment()? {
+                return Ok(Some(Step::Comment(span)));
+            }
+        }
+
+        let place = self.cursor.place();
+        let c = match (self.cursor.peek(), self.expect) {
+            (None, End) => {
+                self.expect = Done;
+                return Ok(None);
+            }
+            (None, _) => return Err(self.cursor.eof_err()),
+            (Some(c), _) => c,
+        };
+        let top = self.containers.last().cloned();
+
+        let step = match (self.expect, c) {
+            (Value { .. }, '[') | (Value { .. }, '{') => self.open(place)?,
+            (Value { first: true }, ']') | (Key { first: true }, '}') => self.close(),
+            (Next, ']') if top == Some(Container::Array) => self.close(),
+            (Next, '}') if top == Some(Container::Object) => self.close(),
+            (Value { .. }, _) => {
+                let scalar = self.cursor.scalar()?;
+                self.expect = self.after_value();
+                Step::Value(scalar.value, scalar.span)
+            }
+            (Key { .. }, '"') => {
+                let mut scalar = self.cursor.scalar()?;
+                let key: String = match scalar.value {
+                    JsonValue::Text(ref mut key) => mem::take(key).into(),
+                    _ => unreachable!(),
+                };
+                self.see_key(&key, scalar.span, place)?;
+                self.expect = Colon;
+                Step::Key(key, scalar.span)
+            }
+            (Colon, ':') => {
+                let (punct, span) = self.cursor.punct().unwrap();
+                self.expect = Value { first: false };
+                Step::Punct(punct, span)
+            }
+            (Next, ',') => {
+                let (punct, span) = self.cursor.punct().unwrap();
+                // a trailing comma lets the container close as if it were empty so far
+                let first = self.options.trailing_commas;
+                self.expect = match top {
+                    Some(Container::Array) => Value { first },
+                    _ => Key { first },
+                };
+                Step::Punct(punct, span)
+            }
+            _ => return Err(place.token_err(c)),
+        };
+        // a dropped member ends with its value
+        if self.expect == Next && self.skip == Some(self.containers.len()) {
+            self.skip = None;
+        }
+        Ok(Some(step))
+    }
+
+    /// Produces the next event, or `None` once the root value and any trailing whitespace have
+    /// been consumed.
+    pub fn next_event(&mut self) -> Result<Option<(Event, Span)>, JsonError> {
+        while let Some(step) = self.step()? {
+            if self.skipped {
+                continue;
+            }
+            let event = match step {
+                Step::Punct(Punct::ArrayStart, span) => (Event::ArrayStart, span),
+                Step::Punct(Punct::ArrayEnd, span) => (Event::ArrayEnd, span),
+                Step::Punct(Punct::ObjectStart, span) => (Event::ObjectStart, span),
+                Step::Punct(Punct::ObjectEnd, span) => (Event::ObjectEnd, span),
+                Step::Key(key, span) => (Event::Key(key), span),
+                Step::Value(value, span) => (Event::Value(value), span),
+                _ => continue,
+            };
+            return Ok(Some(event));
+        }
+        Ok(None)
+    }
+
+    /// Stops the parser so that no further steps or events are produced.
+    pub fn halt(&mut self) {
+        self.expect = Expect::Done;
+    }
+}
+
+/// The text of a string token from a parser made with `EventParser::borrowing`, which is
+/// borrowed from `source` when the parser left it undecoded.
+pub(crate) fn borrowed_text(source: &str, decoded: String, span: Span) -> Cow<'_, str> {
+    if decoded.is_empty() {
+        Cow::Borrowed(&source[span.start + 1..span.end - 1])
+    } else {
+        Cow::Owned(decoded)
+    }
+}
+
+impl<'a> Iterator for EventParser<'a> {
+    type Item = Result<(Event, Span), JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.halt();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, EventParser};
+    use crate::span::Span;
+    use crate::{DuplicateKeys, JsonError, JsonValue, ParseOptions};
+
+    #[test]
+    fn event_parser() {
+        let events = EventParser::new(r#" { "a": [1, {}], "b": null } "#)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                (Event::ObjectStart, Span::new(1, 2)),
+                (Event::Key("a".into()), Span::new(3, 6)),
+                (Event::ArrayStart, Span::new(8, 9)),
+                (Event::Value(JsonValue::from(1.0)), Span::new(9, 10)),
+                (Event::ObjectStart, Span::new(12, 13)),
+                (Event::ObjectEnd, Span::new(13, 14)),
+                (Event::ArrayEnd, Span::new(14, 15)),
+                (Event::Key("b".into()), Span::new(17, 20)),
+                (Event::Value(JsonValue::Null), Span::new(22, 26)),
+                (Event::ObjectEnd, Span::new(27, 28)),
+            ]
+        );
+    }
+
+    #[test]
+    fn event_parser_errors() {
+        let tests = vec![
+            (
+                "",
+                JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
+            ),
+            (
+                "[1, 2",
+                JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 5,
+                    offset: 5,
+                },
+            ),
+            (
+                "[1,]",
+                JsonError::UnexpectedToken {
+                    character: ']',
+                    location: 3,
+                    line: 0,
+                    column: 3,
+                    offset: 3,
+                },
+            ),
+            (
+                "[1 tx]",
+                JsonError::UnexpectedToken {
+                    character: 't',
+                    location: 3,
+                    line: 0,
+                    column: 3,
+                    offset: 3,
+                },
+            ),
+            (
+                r#"{"a" 1}"#,
+                JsonError::UnexpectedToken {
+                    character: '1',
+                    location: 5,
+                    line: 0,
+                    column: 5,
+                    offset: 5,
+                },
+            ),
+            (
+                r#"{1: 2}"#,
+                JsonError::UnexpectedToken {
+                    character: '1',
+                    location: 1,
+                    line: 0,
+                    column: 1,
+                    offset: 1,
+                },
+            ),
+            (
+                r#"{"a": 1]"#,
+                JsonError::UnexpectedToken {
+                    character: ']',
+                    location: 7,
+                    line: 0,
+                    column: 7,
+                    offset: 7,
+                },
+            ),
+            (
+                "true false",
+                JsonError::UnexpectedToken {
+                    character: 'f',
+                    location: 5,
+                    line: 0,
+                    column: 5,
+                    offset: 5,
+                },
+            ),
+            (
+                "]",
+                JsonError::UnexpectedToken {
+                    character: ']',
+                    location: 0,
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                },
+            ),
+        ];
+
+        for (st, error) in tests.into_iter() {
+            let mut parser = EventParser::new(st);
+            assert_eq!(parser.find(|e| e.is_err()), Some(Err(error)));
+            assert_eq!(parser.next(), None);
+        }
+    }
+
+    #[test]
+    fn event_parser_options() {
+        let with = |duplicate_keys, max_depth| ParseOptions {
+            duplicate_keys,
+            max_depth,
+            ..ParseOptions::default()
+        };
+        let events = |source, options| {
+            EventParser::with_options(source, options)
+                .map(|event| event.map(|(event, _)| event))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let source = r#"{"a": 1, "b": {"a": 2}, "a": [3, {"a": 4}], "c": 5}"#;
+        assert_eq!(
+            events(source, with(DuplicateKeys::FirstWins, 128)),
+            Ok(vec![
+                Event::ObjectStart,
+                Event::Key("a".into()),
+                Event::Value(JsonValue::from(1)),
+                Event::Key("b".into()),
+                Event::ObjectStart,
+                Event::Key("a".into()),
+                Event::Value(JsonValue::from(2)),
+                Event::ObjectEnd,
+                Event::Key("c".into()),
+                Event::Value(JsonValue::from(5)),
+                Event::ObjectEnd,
+            ])
+        );
+        assert_eq!(
+            events(source, with(DuplicateKeys::LastWins, 128)).map(|events| events.len()),
+            Ok(19)
+        );
+        assert_eq!(
+            events(source, with(DuplicateKeys::Error, 128)),
+            Err(JsonError::DuplicateKey {
+                key: "a".into(),
+                location: 24,
+                line: 0,
+                column: 24,
+                offset: 24,
+            })
+        );
+        let mut borrowing = EventParser::borrowing(r#"{"a": 1, "\u0061": 2}"#);
+        borrowing.options.duplicate_keys = DuplicateKeys::Error;
+        assert!(matches!(
+            borrowing.find(|e| e.is_err()),
+            Some(Err(JsonError::DuplicateKey { location: 9, .. }))
+        ));
+
+        assert!(events("[[{}]]", with(DuplicateKeys::LastWins, 3)).is_ok());
+        assert_eq!(
+            events("[[{}]]", with(DuplicateKeys::LastWins, 2)),
+            Err(JsonError::DepthLimitExceeded {
+                limit: 2,
+                location: 2,
+                line: 0,
+                column: 2,
+                offset: 2,
+            })
+        );
+        let nested = format!("{}{}", "[".repeat(129), "]".repeat(129));
+        assert!(matches!(
+            EventParser::new(&nested).find(|e| e.is_err()),
+            Some(Err(JsonError::DepthLimitExceeded { limit: 128, .. }))
+        ));
+    }
+}
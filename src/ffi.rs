@@ -0,0 +1,267 @@
+//! A C interface, enabled by the `ffi` feature, declared for C in `include/json_rs.h`.
+//!
+//! Values are opaque `json_rs_value` handles owned by the caller until passed to
+//! `json_rs_free`, and every function reports how it went with a `json_rs_status`.  Nothing
+//! unwinds into C: a panic is caught and reported as `JSON_RS_PANIC`.  Build a shared library
+//! with `cargo rustc --release --features ffi --crate-type cdylib`.
+use super::{json_parse, EditError, JsonEdit, JsonError, JsonPointer, JsonValue};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::str;
+
+/// The outcome of a call through the C interface.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The call succeeded
+    Ok = 0,
+    /// A required pointer argument was null
+    NullArgument = 1,
+    /// Text passed in was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// The JSON text had a character where it should not
+    UnexpectedToken = 3,
+    /// The JSON text ended too early
+    UnexpectedEof = 4,
+    /// A JSON Pointer was malformed
+    InvalidPointer = 5,
+    /// Nothing exists at a JSON Pointer
+    NotFound = 6,
+    /// The parent of a JSON Pointer is neither an object nor an array
+    NotAContainer = 7,
+    /// The last token of a JSON Pointer is not a valid index into its array
+    InvalidIndex = 8,
+    /// The library panicked, which is a bug
+    Panic = 9,
+    /// The JSON text nested arrays and objects too deeply
+    DepthLimitExceeded = 10,
+}
+
+/// Runs `f`, turning a panic into `Status::Panic`.
+fn guard<F: FnOnce() -> Result<(), Status>>(f: F) -> Status {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => Status::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => Status::Panic,
+    }
+}
+
+unsafe fn pointer(pointer: *const c_char) -> Result<JsonPointer, Status> {
+    if pointer.is_null() {
+        return Err(Status::NullArgument);
+    }
+    let pointer = CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|_| Status::InvalidUtf8)?;
+    pointer.parse().map_err(|_| Status::InvalidPointer)
+}
+
+/// Parses `len` bytes of UTF-8 at `text`, which need not end in a NUL, and stores a new value
+/// in `*out`.  If the text is not valid JSON and `error_location` is not null, the character
+/// index of the problem is stored there.
+///
+/// # Safety
+///
+/// `text` must point to `len` readable bytes, `out` must be valid for writes, and
+/// `error_location` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_parse(
+    text: *const u8,
+    len: usize,
+    out: *mut *mut JsonValue,
+    error_location: *mut usize,
+) -> Status {
+    guard(|| {
+        if text.is_null() || out.is_null() {
+            return Err(Status::NullArgument);
+        }
+        let text =
+            str::from_utf8(slice::from_raw_parts(text, len)).map_err(|_| Status::InvalidUtf8)?;
+        let (status, location) = match json_parse(text) {
+            Ok(value) => {
+                *out = Box::into_raw(Box::new(value));
+                return Ok(());
+            }
+            // `json_parse` keeps the last of repeated keys and rounds numbers it cannot hold, so
+            // neither is reported
+            Err(JsonError::UnexpectedToken { location, .. })
+            | Err(JsonError::DuplicateKey { location, .. })
+            | Err(JsonError::NumberOutOfRange { location, .. }) => {
+                (Status::UnexpectedToken, location)
+            }
+            Err(JsonError::UnexpectedEOF { .. }) => (Status::UnexpectedEof, text.chars().count()),
+            Err(JsonError::DepthLimitExceeded { location, .. }) => {
+                (Status::DepthLimitExceeded, location)
+            }
+        };
+        if !error_location.is_null() {
+            *error_location = location;
+        }
+        Err(status)
+    })
+}
+
+/// Frees a value.  Null is ignored.
+///
+/// # Safety
+///
+/// `value` must be null or a handle from this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_free(value: *mut JsonValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// Serializes a value as compact JSON and stores a new NUL-terminated string in `*out`, to be
+/// freed with `json_rs_string_free`.
+///
+/// # Safety
+///
+/// `value` must be a live handle and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_to_string(
+    value: *const JsonValue,
+    out: *mut *mut c_char,
+) -> Status {
+    guard(|| {
+        if value.is_null() || out.is_null() {
+            return Err(Status::NullArgument);
+        }
+        // control characters, NUL among them, are always escaped
+        let text = CString::new((*value).to_string()).map_err(|_| Status::Panic)?;
+        *out = text.into_raw();
+        Ok(())
+    })
+}
+
+/// Frees a string from `json_rs_to_string`.  Null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or a string from `json_rs_to_string` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Stores a new copy of the value at the NUL-terminated JSON Pointer `pointer` in `*out`.  Like
+/// every value, the copy is the caller's to free.
+///
+/// # Safety
+///
+/// `value` must be a live handle, `pointer` a NUL-terminated string and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_get(
+    value: *const JsonValue,
+    pointer: *const c_char,
+    out: *mut *mut JsonValue,
+) -> Status {
+    guard(|| {
+        if value.is_null() || out.is_null() {
+            return Err(Status::NullArgument);
+        }
+        let pointer = self::pointer(pointer)?;
+        let found = pointer.resolve(&*value).ok_or(Status::NotFound)?;
+        *out = Box::into_raw(Box::new(found.clone()));
+        Ok(())
+    })
+}
+
+/// Sets the value at the NUL-terminated JSON Pointer `pointer` to a copy of `item`, replacing
+/// what is there or, like a JSON Patch `add`, adding an object key or array element (`-`
+/// appends).  `item` stays the caller's.
+///
+/// # Safety
+///
+/// `value` and `item` must be live handles and `pointer` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn json_rs_set(
+    value: *mut JsonValue,
+    pointer: *const c_char,
+    item: *const JsonValue,
+) -> Status {
+    guard(|| {
+        if value.is_null() || item.is_null() {
+            return Err(Status::NullArgument);
+        }
+        let path = self::pointer(pointer)?;
+        // copied first, as `item` may be `value` itself or a part of it
+        let item = (*item).clone();
+        let value = &mut *value;
+        if let Some(target) = path.resolve_mut(value) {
+            *target = item;
+            return Ok(());
+        }
+        let edit = JsonEdit::Add { path, value: item };
+        edit.apply(value).map(drop).map_err(|error| match error {
+            EditError::NotFound(_) | EditError::RemoveRoot => Status::NotFound,
+            EditError::NotAContainer(_) => Status::NotAContainer,
+            EditError::InvalidIndex(_) => Status::InvalidIndex,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    unsafe fn parse(text: &str) -> Result<*mut JsonValue, (Status, usize)> {
+        let mut out = ptr::null_mut();
+        let mut location = 0;
+        match json_rs_parse(text.as_ptr(), text.len(), &mut out, &mut location) {
+            Status::Ok => Ok(out),
+            status => Err((status, location)),
+        }
+    }
+
+    unsafe fn text(value: *const JsonValue) -> String {
+        let mut out = ptr::null_mut();
+        assert_eq!(json_rs_to_string(value, &mut out), Status::Ok);
+        let text = CStr::from_ptr(out).to_str().unwrap().to_string();
+        json_rs_string_free(out);
+        text
+    }
+
+    #[test]
+    fn ffi_calls() {
+        unsafe {
+            let doc = parse(r#"{"a": [1, "\u0000"]}"#).unwrap();
+            let item = parse("true").unwrap();
+            assert_eq!(parse("[1, }"), Err((Status::UnexpectedToken, 4)));
+            assert_eq!(parse("[1"), Err((Status::UnexpectedEof, 2)));
+            let deep = "[".repeat(200);
+            assert_eq!(parse(&deep), Err((Status::DepthLimitExceeded, 128)));
+
+            let mut got = ptr::null_mut();
+            let path = b"/a/1\0".as_ptr() as *const c_char;
+            assert_eq!(json_rs_get(doc, path, &mut got), Status::Ok);
+            assert_eq!(text(got), r#""\u0000""#);
+            json_rs_free(got);
+            let missing = b"/b\0".as_ptr() as *const c_char;
+            assert_eq!(json_rs_get(doc, missing, &mut got), Status::NotFound);
+            let invalid = b"a\0".as_ptr() as *const c_char;
+            assert_eq!(json_rs_get(doc, invalid, &mut got), Status::InvalidPointer);
+
+            assert_eq!(json_rs_set(doc, path, item), Status::Ok);
+            let append = b"/a/-\0".as_ptr() as *const c_char;
+            assert_eq!(json_rs_set(doc, append, item), Status::Ok);
+            let deep = b"/b/c\0".as_ptr() as *const c_char;
+            assert_eq!(json_rs_set(doc, deep, item), Status::NotFound);
+            assert_eq!(text(doc), r#"{"a":[1,true,true]}"#);
+
+            assert_eq!(
+                json_rs_to_string(ptr::null(), &mut ptr::null_mut()),
+                Status::NullArgument
+            );
+            json_rs_free(item);
+            json_rs_free(doc);
+            json_rs_free(ptr::null_mut());
+        }
+    }
+}
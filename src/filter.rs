@@ -0,0 +1,953 @@
+//! Slimming down large documents while streaming them from a reader to a writer, keeping or
+//! dropping the values that match path patterns, and picking out the values that match them.
+use super::scan::Scanner;
+use super::stream::{check_raw, parse_raw, unexpected, StreamError};
+use super::{JsonPointer, JsonValue};
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::{error, fmt, mem};
+
+/// One step of a pattern.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    /// `.name` or `['name']`
+    Key(String),
+    /// `.{a,b}`
+    Keys(Vec<String>),
+    /// `[0]`
+    Index(usize),
+    /// `.*` or `[*]`, either of which matches any member or element
+    Any,
+    /// A JSON Pointer token, which matches a member by name or an element by index
+    Token(String),
+}
+
+/// One step of the path to a value.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl Segment {
+    fn matches(&self, step: &Step) -> bool {
+        match (self, step) {
+            (Segment::Any, _) => true,
+            (Segment::Key(key), Step::Key(step)) => key == step,
+            (Segment::Keys(keys), Step::Key(step)) => keys.contains(step),
+            (Segment::Index(ind), Step::Index(step)) => ind == step,
+            (Segment::Token(token), Step::Key(step)) => token == step,
+            (Segment::Token(token), Step::Index(step)) => *token == step.to_string(),
+            _ => false,
+        }
+    }
+}
+
+/// A pattern matching the paths of values in a document, written in a small subset of
+/// JSONPath: `$` for the root, followed by any of `.name`, `['name']`, `[0]`, `.*` or `[*]`,
+/// and `.{a,b}` for several names.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::filter::PathPattern;
+///
+/// fn main() {
+///     assert!("$.items[*].{id,price}".parse::<PathPattern>().is_ok());
+///     assert!("$['odd key'][0]".parse::<PathPattern>().is_ok());
+///     assert!("$.items[".parse::<PathPattern>().is_err());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+/// Describes the ways a path pattern can be malformed
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathPatternError {
+    /// A character that does not belong where it was found
+    UnexpectedChar {
+        /// The character
+        character: char,
+        /// The index of the character in the pattern
+        location: usize,
+    },
+    /// The pattern ended in the middle of a segment
+    UnexpectedEnd,
+}
+
+impl fmt::Display for PathPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathPatternError::UnexpectedChar {
+                character,
+                location,
+            } => write!(
+                f,
+                "unexpected character {:?} at index {} of the path pattern",
+                character, location
+            ),
+            PathPatternError::UnexpectedEnd => f.write_str("the path pattern ended too early"),
+        }
+    }
+}
+
+impl error::Error for PathPatternError {}
+
+impl PathPattern {
+    /// Whether the pattern matches `path` or one of its ancestors, putting `path` inside a
+    /// matched value.
+    fn covers(&self, path: &[Step]) -> bool {
+        self.segments.len() <= path.len()
+            && self
+                .segments
+                .iter()
+                .zip(path)
+                .all(|(seg, step)| seg.matches(step))
+    }
+
+    /// Whether the pattern could match something below `path`.
+    fn reaches_below(&self, path: &[Step]) -> bool {
+        self.segments.len() > path.len()
+            && self
+                .segments
+                .iter()
+                .zip(path)
+                .all(|(seg, step)| seg.matches(step))
+    }
+}
+
+/// Matches exactly the value the pointer refers to.
+impl<'a> From<&'a JsonPointer> for PathPattern {
+    fn from(pointer: &'a JsonPointer) -> Self {
+        Self {
+            segments: pointer
+                .tokens()
+                .iter()
+                .cloned()
+                .map(Segment::Token)
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PathPatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars = s.chars().collect::<Vec<_>>();
+        let unexpected = |ind: usize| match chars.get(ind) {
+            Some(&character) => PathPatternError::UnexpectedChar {
+                character,
+                location: ind,
+            },
+            None => PathPatternError::UnexpectedEnd,
+        };
+        let name_end = |start: usize, stops: &[char]| {
+            (start..chars.len())
+                .find(|ind| stops.contains(&chars[*ind]))
+                .unwrap_or(chars.len())
+        };
+
+        let mut segments = Vec::new();
+        let mut ind = if chars.first() == Some(&'$') { 1 } else { 0 };
+        while ind < chars.len() {
+            match (chars[ind], chars.get(ind + 1)) {
+                ('.', Some('*')) | ('[', Some('*')) => {
+                    if chars[ind] == '[' {
+                        if chars.get(ind + 2) != Some(&']') {
+                            return Err(unexpected(ind + 2));
+                        }
+                        ind += 1;
+                    }
+                    segments.push(Segment::Any);
+                    ind += 2;
+                }
+                ('.', Some('{')) => {
+                    let mut keys = Vec::new();
+                    ind += 2;
+                    loop {
+                        let end = name_end(ind, &[',', '}']);
+                        let key = chars[ind..end].iter().collect::<String>();
+                        if key.trim().is_empty() {
+                            return Err(unexpected(end));
+                        }
+                        keys.push(key.trim().to_string());
+                        ind = end + 1;
+                        match chars.get(end) {
+                            Some('}') => break,
+                            Some(_) => (),
+                            None => return Err(PathPatternError::UnexpectedEnd),
+                        }
+                    }
+                    segments.push(Segment::Keys(keys));
+                }
+                ('.', _) => {
+                    let end = name_end(ind + 1, &['.', '[']);
+                    if end == ind + 1 {
+                        return Err(unexpected(end));
+                    }
+                    segments.push(Segment::Key(chars[ind + 1..end].iter().collect()));
+                    ind = end;
+                }
+                ('[', Some(&quote)) if quote == '\'' || quote == '"' => {
+                    let mut key = String::new();
+                    ind += 2;
+                    loop {
+                        match chars.get(ind) {
+                            Some('\\') => {
+                                key.push(*chars.get(ind + 1).ok_or(unexpected(ind + 1))?);
+                                ind += 2;
+                            }
+                            Some(&c) if c == quote => break,
+                            Some(&c) => {
+                                key.push(c);
+                                ind += 1;
+                            }
+                            None => return Err(PathPatternError::UnexpectedEnd),
+                        }
+                    }
+                    if chars.get(ind + 1) != Some(&']') {
+                        return Err(unexpected(ind + 1));
+                    }
+                    segments.push(Segment::Key(key));
+                    ind += 2;
+                }
+                ('[', _) => {
+                    let end = name_end(ind + 1, &[']']);
+                    let digits = chars[ind + 1..end].iter().collect::<String>();
+                    let index = match digits.parse::<usize>() {
+                        Ok(index) if digits.chars().all(|c| c.is_ascii_digit()) => index,
+                        _ => return Err(unexpected(ind + 1)),
+                    };
+                    if end == chars.len() {
+                        return Err(PathPatternError::UnexpectedEnd);
+                    }
+                    segments.push(Segment::Index(index));
+                    ind = end + 1;
+                }
+                _ => return Err(unexpected(ind)),
+            }
+        }
+        Ok(Self { segments })
+    }
+}
+
+/// What a filter does with a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Skip,
+    Copy,
+    /// Write the container but decide about each of its members or elements.
+    Descend,
+}
+
+/// Filters a document by path while streaming it, keeping the values that match an include
+/// pattern, along with the containers around them, and dropping the values that match an
+/// exclude pattern.  With no include patterns, everything that is not excluded is kept.
+///
+/// Kept values are copied as they were written, at most one of them held in memory at a time,
+/// and the containers around them are written compactly.  Arrays are written with only their
+/// kept elements, so indices in the output may differ from those in the input.  Dropped values
+/// are skipped over without being held in memory or fully checked, only for balanced brackets
+/// and strings.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::filter::PathFilter;
+///
+/// fn main() {
+///     let input = r#"{
+///         "items": [
+///             { "id": 1, "price": 9.5, "description": "..." },
+///             { "id": 2, "price": 3, "tags": ["a", "b"] }
+///         ],
+///         "meta": { "generated": "2024-01-01" }
+///     }"#;
+///     let filter = PathFilter::new().include("$.items[*].{id,price}".parse().unwrap());
+///
+///     let mut output = Vec::new();
+///     filter.filter(input.as_bytes(), &mut output).unwrap();
+///     assert_eq!(
+///         String::from_utf8(output).unwrap(),
+///         r#"{"items":[{"id":1,"price":9.5},{"id":2,"price":3}]}"#
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathFilter {
+    include: Vec<PathPattern>,
+    exclude: Vec<PathPattern>,
+}
+
+impl PathFilter {
+    /// Creates a filter that keeps everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern for values to keep.
+    pub fn include(mut self, pattern: PathPattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    /// Adds a pattern for values to drop, even if an include pattern matches them.
+    pub fn exclude(mut self, pattern: PathPattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    fn action(&self, path: &[Step], container: bool) -> Action {
+        let reaches_below = |patterns: &[PathPattern]| {
+            container && patterns.iter().any(|pattern| pattern.reaches_below(path))
+        };
+        if self.exclude.iter().any(|pattern| pattern.covers(path)) {
+            return Action::Skip;
+        }
+        let included =
+            self.include.is_empty() || self.include.iter().any(|pattern| pattern.covers(path));
+        if included && !reaches_below(&self.exclude) {
+            Action::Copy
+        } else if included || reaches_below(&self.include) {
+            Action::Descend
+        } else {
+            Action::Skip
+        }
+    }
+
+    /// Reads a document from `reader` and writes what the filter keeps of it to `writer`.  A
+    /// root value that is dropped entirely is written as `null`.
+    pub fn filter<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+    ) -> Result<(), StreamError> {
+        let mut scanner = Scanner::new(reader);
+        let mut path = Vec::new();
+        if scanner.skip_whitespace()?.is_none() {
+            return Err(scanner.place().eof_err().into());
+        }
+        if !self.value(&mut scanner, &mut path, None, &mut writer)? {
+            writer.write_all(b"null")?;
+        }
+        if scanner.skip_whitespace()?.is_some() {
+            return Err(unexpected(&mut scanner));
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Handles the value at the scanner, writing `prefix` before it if it is kept.  Returns
+    /// whether it was kept.
+    fn value<R: BufRead, W: Write>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        prefix: Option<&[u8]>,
+        writer: &mut W,
+    ) -> Result<bool, StreamError> {
+        let first = scanner.peek()?;
+        let container = first == Some(b'{') || first == Some(b'[');
+        match self.action(path, container) {
+            Action::Copy => {
+                let start = scanner.place();
+                let text = scanner.raw_value()?;
+                check_raw(scanner, &text, start)?;
+                if let Some(prefix) = prefix {
+                    writer.write_all(prefix)?;
+                }
+                writer.write_all(&text)?;
+                Ok(true)
+            }
+            Action::Descend if container => {
+                if let Some(prefix) = prefix {
+                    writer.write_all(prefix)?;
+                }
+                if first == Some(b'{') {
+                    self.object(scanner, path, writer)?;
+                } else {
+                    self.array(scanner, path, writer)?;
+                }
+                Ok(true)
+            }
+            _ => {
+                scanner.copy_value(|_| ())?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Skips whitespace and consumes `expected`, which must come next.
+    fn expect<R: BufRead>(scanner: &mut Scanner<R>, expected: u8) -> Result<(), StreamError> {
+        match scanner.skip_whitespace()? {
+            Some(byte) if byte == expected => {
+                scanner.bump(byte);
+                Ok(())
+            }
+            Some(_) => Err(unexpected(scanner)),
+            None => Err(scanner.place().eof_err().into()),
+        }
+    }
+
+    /// Skips whitespace and consumes a `,` or the closing bracket, returning whether it was the
+    /// closing bracket.  With `first`, the bracket is the only choice that is not a value.
+    fn separator<R: BufRead>(
+        scanner: &mut Scanner<R>,
+        close: u8,
+        first: bool,
+    ) -> Result<bool, StreamError> {
+        match scanner.skip_whitespace()? {
+            Some(byte) if byte == close => {
+                scanner.bump(byte);
+                Ok(true)
+            }
+            Some(b',') if !first => {
+                scanner.bump(b',');
+                match scanner.skip_whitespace()? {
+                    Some(byte) if byte == close => Err(unexpected(scanner)),
+                    Some(_) => Ok(false),
+                    None => Err(scanner.place().eof_err().into()),
+                }
+            }
+            Some(_) if first => Ok(false),
+            Some(_) => Err(unexpected(scanner)),
+            None => Err(scanner.place().eof_err().into()),
+        }
+    }
+
+    fn object<R: BufRead, W: Write>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        writer: &mut W,
+    ) -> Result<(), StreamError> {
+        Self::expect(scanner, b'{')?;
+        writer.write_all(b"{")?;
+        let mut written = false;
+        let mut first = true;
+        while !Self::separator(scanner, b'}', first)? {
+            first = false;
+            if scanner.peek()? != Some(b'"') {
+                return Err(unexpected(scanner));
+            }
+            let start = scanner.place();
+            let raw_key = scanner.raw_value()?;
+            let key = match parse_raw(scanner, &raw_key, start)? {
+                JsonValue::Text(ref mut key) => mem::take(key),
+                _ => unreachable!(),
+            };
+            Self::expect(scanner, b':')?;
+            if scanner.skip_whitespace()?.is_none() {
+                return Err(scanner.place().eof_err().into());
+            }
+
+            let mut prefix = if written { vec![b','] } else { vec![] };
+            prefix.extend_from_slice(&raw_key);
+            prefix.push(b':');
+            path.push(Step::Key(key.into()));
+            written |= self.value(scanner, path, Some(&prefix), writer)?;
+            path.pop();
+        }
+        writer.write_all(b"}")?;
+        Ok(())
+    }
+
+    fn array<R: BufRead, W: Write>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        writer: &mut W,
+    ) -> Result<(), StreamError> {
+        Self::expect(scanner, b'[')?;
+        writer.write_all(b"[")?;
+        let mut written = false;
+        let mut ind = 0;
+        while !Self::separator(scanner, b']', ind == 0)? {
+            let prefix: &[u8] = if written { b"," } else { b"" };
+            path.push(Step::Index(ind));
+            written |= self.value(scanner, path, Some(prefix), writer)?;
+            path.pop();
+            ind += 1;
+        }
+        writer.write_all(b"]")?;
+        Ok(())
+    }
+}
+
+/// A value found by a [`PathQuery`](struct.PathQuery.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryMatch {
+    /// The index of the pattern that matched, in the order they were added
+    pub pattern: usize,
+    /// Where the value is in the document
+    pub path: JsonPointer,
+    /// The value
+    pub value: JsonValue,
+}
+
+/// A set of path patterns and pointers evaluated against a document as it is read, reporting
+/// each value that matches as soon as it has been read.
+///
+/// Only the value being matched is held in memory, so a query can pick a few values out of a
+/// document far larger than memory, or out of a network stream before it has ended.  Values
+/// that no pattern reaches into are skipped over without being fully checked, as with
+/// [`PathFilter`](struct.PathFilter.html).  When one match is inside another, the outer one is
+/// reported first.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::filter::PathQuery;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let input = r#"{
+///         "meta": { "count": 2 },
+///         "items": [{ "id": 1, "blob": "..." }, { "id": 2, "blob": "..." }]
+///     }"#;
+///     let query = PathQuery::new()
+///         .pattern("$.items[*].id".parse().unwrap())
+///         .pointer(&"/meta/count".parse().unwrap());
+///
+///     let mut found = vec![];
+///     query
+///         .run(input.as_bytes(), |found_match| {
+///             found.push((found_match.pattern, found_match.path.to_string(), found_match.value));
+///             true
+///         })
+///         .unwrap();
+///     assert_eq!(
+///         found,
+///         [
+///             (1, "/meta/count".to_string(), JsonValue::from(2)),
+///             (0, "/items/0/id".to_string(), JsonValue::from(1)),
+///             (0, "/items/1/id".to_string(), JsonValue::from(2)),
+///         ]
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathQuery {
+    patterns: Vec<PathPattern>,
+}
+
+impl PathQuery {
+    /// Creates a query that matches nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern to match.
+    pub fn pattern(mut self, pattern: PathPattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Adds a pointer to match, which counts as a pattern.
+    pub fn pointer(self, pointer: &JsonPointer) -> Self {
+        self.pattern(pointer.into())
+    }
+
+    /// Reads a document from `reader`, calling `on_match` with each match in the order they occur
+    /// in the document.  Reading stops early, without checking the rest of the document, once
+    /// `on_match` returns `false`.
+    pub fn run<R, F>(&self, reader: R, mut on_match: F) -> Result<(), StreamError>
+    where
+        R: BufRead,
+        F: FnMut(QueryMatch) -> bool,
+    {
+        let mut scanner = Scanner::new(reader);
+        if scanner.skip_whitespace()?.is_none() {
+            return Err(scanner.place().eof_err().into());
+        }
+        if self.value(&mut scanner, &mut Vec::new(), &mut on_match)?
+            && scanner.skip_whitespace()?.is_some()
+        {
+            return Err(unexpected(&mut scanner));
+        }
+        Ok(())
+    }
+
+    /// Handles the value at the scanner.  Returns `false` if `on_match` asked to stop.
+    fn value<R, F>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        on_match: &mut F,
+    ) -> Result<bool, StreamError>
+    where
+        R: BufRead,
+        F: FnMut(QueryMatch) -> bool,
+    {
+        let first = scanner.peek()?;
+        let container = first == Some(b'{') || first == Some(b'[');
+        let below = |pattern: &PathPattern| container && pattern.reaches_below(path);
+
+        if self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.segments.len() == path.len() && pattern.covers(path))
+        {
+            let start = scanner.place();
+            let text = scanner.raw_value()?;
+            let value = parse_raw(scanner, &text, start)?;
+            for (ind, pattern) in self.patterns.iter().enumerate() {
+                let mut found = vec![];
+                if pattern.segments.len() == path.len() && pattern.covers(path) {
+                    found.push((path.clone(), &value));
+                } else if below(pattern) {
+                    matches_in(&value, &pattern.segments[path.len()..], path, &mut found);
+                }
+                for (path, value) in found.into_iter() {
+                    let found_match = QueryMatch {
+                        pattern: ind,
+                        path: pointer_to(&path),
+                        value: value.clone(),
+                    };
+                    if !on_match(found_match) {
+                        return Ok(false);
+                    }
+                }
+            }
+            Ok(true)
+        } else if self.patterns.iter().any(below) {
+            if first == Some(b'{') {
+                self.object(scanner, path, on_match)
+            } else {
+                self.array(scanner, path, on_match)
+            }
+        } else {
+            scanner.copy_value(|_| ())?;
+            Ok(true)
+        }
+    }
+
+    fn object<R, F>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        on_match: &mut F,
+    ) -> Result<bool, StreamError>
+    where
+        R: BufRead,
+        F: FnMut(QueryMatch) -> bool,
+    {
+        PathFilter::expect(scanner, b'{')?;
+        let mut first = true;
+        while !PathFilter::separator(scanner, b'}', first)? {
+            first = false;
+            if scanner.peek()? != Some(b'"') {
+                return Err(unexpected(scanner));
+            }
+            let start = scanner.place();
+            let raw_key = scanner.raw_value()?;
+            let key = match parse_raw(scanner, &raw_key, start)? {
+                JsonValue::Text(ref mut key) => mem::take(key),
+                _ => unreachable!(),
+            };
+            PathFilter::expect(scanner, b':')?;
+            if scanner.skip_whitespace()?.is_none() {
+                return Err(scanner.place().eof_err().into());
+            }
+
+            path.push(Step::Key(key.into()));
+            let more = self.value(scanner, path, on_match)?;
+            path.pop();
+            if !more {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn array<R, F>(
+        &self,
+        scanner: &mut Scanner<R>,
+        path: &mut Vec<Step>,
+        on_match: &mut F,
+    ) -> Result<bool, StreamError>
+    where
+        R: BufRead,
+        F: FnMut(QueryMatch) -> bool,
+    {
+        PathFilter::expect(scanner, b'[')?;
+        let mut ind = 0;
+        while !PathFilter::separator(scanner, b']', ind == 0)? {
+            path.push(Step::Index(ind));
+            let more = self.value(scanner, path, on_match)?;
+            path.pop();
+            if !more {
+                return Ok(false);
+            }
+            ind += 1;
+        }
+        Ok(true)
+    }
+}
+
+/// Finds the values below `value`, which is at `path`, that `segments` match, in document order
+/// for arrays and key order for objects.
+fn matches_in<'a>(
+    value: &'a JsonValue,
+    segments: &[Segment],
+    path: &[Step],
+    found: &mut Vec<(Vec<Step>, &'a JsonValue)>,
+) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return found.push((path.to_vec(), value)),
+    };
+    let mut children = match value {
+        JsonValue::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(ind, item)| (Step::Index(ind), item))
+            .collect(),
+        JsonValue::Object(map) => map
+            .iter()
+            .map(|(key, value)| (Step::Key(key.to_string()), value))
+            .collect(),
+        _ => vec![],
+    };
+    if let JsonValue::Object(_) = value {
+        children.sort_by(|a, b| match (&a.0, &b.0) {
+            (Step::Key(a), Step::Key(b)) => a.cmp(b),
+            _ => ::std::cmp::Ordering::Equal,
+        });
+    }
+
+    for (step, child) in children.into_iter() {
+        if segment.matches(&step) {
+            let mut path = path.to_vec();
+            path.push(step);
+            matches_in(child, rest, &path, found);
+        }
+    }
+}
+
+fn pointer_to(path: &[Step]) -> JsonPointer {
+    JsonPointer::from_tokens(path.iter().map(|step| match step {
+        Step::Key(key) => key.clone(),
+        Step::Index(ind) => ind.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PathFilter, PathPattern, PathPatternError, PathQuery, QueryMatch, Segment};
+    use crate::stream::StreamError;
+    use crate::{json_parse, JsonError};
+
+    fn run(filter: &PathFilter, input: &str) -> Result<String, StreamError> {
+        let mut output = Vec::new();
+        filter.filter(input.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn filter_patterns() {
+        let pattern = "$.a['b.c'][2].*.{x, y}[*]".parse::<PathPattern>().unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![
+                Segment::Key("a".into()),
+                Segment::Key("b.c".into()),
+                Segment::Index(2),
+                Segment::Any,
+                Segment::Keys(vec!["x".into(), "y".into()]),
+                Segment::Any,
+            ]
+        );
+        assert_eq!("$".parse::<PathPattern>().unwrap().segments, vec![]);
+        assert_eq!(
+            "$.a.".parse::<PathPattern>(),
+            Err(PathPatternError::UnexpectedEnd)
+        );
+        assert_eq!(
+            "$[x]".parse::<PathPattern>(),
+            Err(PathPatternError::UnexpectedChar {
+                character: 'x',
+                location: 2
+            })
+        );
+        assert_eq!(
+            "$.{a,}".parse::<PathPattern>(),
+            Err(PathPatternError::UnexpectedChar {
+                character: '}',
+                location: 5
+            })
+        );
+        assert_eq!(
+            "$['a'".parse::<PathPattern>(),
+            Err(PathPatternError::UnexpectedEnd)
+        );
+        assert_eq!(
+            "$[x]".parse::<PathPattern>().unwrap_err().to_string(),
+            "unexpected character 'x' at index 2 of the path pattern"
+        );
+    }
+
+    #[test]
+    fn filter_stream() {
+        let input = r#" { "keep": { "deep": [1, 2] }, "drop": { "x": [{}] }, "list": [
+            { "id": 1, "secret": "s", "more": { "secret": 2, "ok": true } },
+            "scalar",
+            { "id": 2 }
+        ] } "#;
+
+        let exclude = PathFilter::new()
+            .exclude("$.drop".parse().unwrap())
+            .exclude("$.list[*].secret".parse().unwrap());
+        assert_eq!(
+            run(&exclude, input).unwrap(),
+            r#"{"keep":{ "deep": [1, 2] },"list":[{"id":1,"more":{ "secret": 2, "ok": true }},"scalar",{"id":2}]}"#
+        );
+
+        let include = PathFilter::new()
+            .include("$.list[*].id".parse().unwrap())
+            .include("$.keep.deep[1]".parse().unwrap());
+        assert_eq!(
+            run(&include, input).unwrap(),
+            r#"{"keep":{"deep":[2]},"list":[{"id":1},{"id":2}]}"#
+        );
+
+        let none = PathFilter::new().include("$.missing".parse().unwrap());
+        assert_eq!(run(&none, input).unwrap(), "{}");
+        assert_eq!(run(&none, "12").unwrap(), "null");
+        assert_eq!(run(&PathFilter::new(), " [1 , 2] ").unwrap(), "[1 , 2]");
+
+        for (input, expected) in vec![
+            (
+                r#"{"list": [1 2]}"#,
+                JsonError::UnexpectedToken {
+                    character: '2',
+                    location: 12,
+                    line: 0,
+                    column: 12,
+                    offset: 12,
+                },
+            ),
+            (
+                r#"{"a": 1,}"#,
+                JsonError::UnexpectedToken {
+                    character: '}',
+                    location: 8,
+                    line: 0,
+                    column: 8,
+                    offset: 8,
+                },
+            ),
+            (
+                r#"{"list": [{"id": tru}]}"#,
+                JsonError::UnexpectedToken {
+                    character: '}',
+                    location: 20,
+                    line: 0,
+                    column: 20,
+                    offset: 20,
+                },
+            ),
+            (
+                r#"{"list": 1} x"#,
+                JsonError::UnexpectedToken {
+                    character: 'x',
+                    location: 12,
+                    line: 0,
+                    column: 12,
+                    offset: 12,
+                },
+            ),
+            (
+                r#"{"list": [{"id": 1}"#,
+                JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 19,
+                    offset: 19,
+                },
+            ),
+            (
+                r#"{1: 2}"#,
+                JsonError::UnexpectedToken {
+                    character: '1',
+                    location: 1,
+                    line: 0,
+                    column: 1,
+                    offset: 1,
+                },
+            ),
+        ]
+        .into_iter()
+        {
+            match run(&include, input) {
+                Err(StreamError::Parse(error)) => assert_eq!(error, expected, "{}", input),
+                other => panic!("{}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn filter_query() {
+        let input = r#"{
+            "a": [{ "b": 1, "c": [2, 3] }, { "b": 4 }],
+            "d": { "0": "zero" },
+            "skipped": [1, 2, }
+        }"#;
+        let query = PathQuery::new()
+            .pattern("$.a[0]".parse().unwrap())
+            .pattern("$.a[*].{b,c}".parse().unwrap())
+            .pointer(&"/d/0".parse().unwrap())
+            .pointer(&"/a/1/b".parse().unwrap());
+
+        let mut found = vec![];
+        let result = query.run(input.as_bytes(), |found_match| {
+            found.push(found_match);
+            true
+        });
+        // nothing reaches into the malformed array, so it is skipped over without being checked
+        assert!(result.is_ok());
+
+        let found = found
+            .into_iter()
+            .map(
+                |QueryMatch {
+                     pattern,
+                     path,
+                     value,
+                 }| (pattern, path.to_string(), value),
+            )
+            .collect::<Vec<_>>();
+        let expected = vec![
+            (0, "/a/0", r#"{"b":1,"c":[2,3]}"#),
+            (1, "/a/0/b", "1"),
+            (1, "/a/0/c", "[2,3]"),
+            (1, "/a/1/b", "4"),
+            (3, "/a/1/b", "4"),
+            (2, "/d/0", r#""zero""#),
+        ];
+        assert_eq!(
+            found,
+            expected
+                .into_iter()
+                .map(|(pattern, path, value)| (
+                    pattern,
+                    path.to_string(),
+                    json_parse(value).unwrap()
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        let mut count = 0;
+        let query = PathQuery::new().pattern("$[*]".parse().unwrap());
+        assert!(query
+            .run("[1, 2, 3".as_bytes(), |_| {
+                count += 1;
+                count < 2
+            })
+            .is_ok());
+        assert_eq!(count, 2);
+        assert!(matches!(
+            query.run("[1, 2, 3".as_bytes(), |_| true),
+            Err(StreamError::Parse(JsonError::UnexpectedEOF { .. }))
+        ));
+
+        let pointer = PathFilter::new().include(PathPattern::from(&"/1".parse().unwrap()));
+        assert_eq!(run(&pointer, "[1, [2], 3]").unwrap(), "[[2]]");
+    }
+}
@@ -0,0 +1,142 @@
+use super::events::{Event, EventParser};
+use super::outline::ValueKind;
+use super::span::Span;
+use super::JsonError;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// The source range of an array or object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// Whether the range is an array or an object
+    pub kind: ValueKind,
+    /// The bytes from the opening bracket through the closing one
+    pub span: Span,
+    /// The zero-based line of the opening bracket
+    pub start_line: usize,
+    /// The zero-based line of the closing bracket
+    pub end_line: usize,
+}
+
+/// Counts lines up to increasing byte offsets without rescanning the source.
+struct Lines<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn at(&mut self, offset: usize) -> usize {
+        self.line += self.source.as_bytes()[self.offset..offset]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        self.offset = offset;
+        self.line
+    }
+}
+
+/// Finds every array and object in a document in a single pass, ordered by where they start.
+/// Ranges that begin and end on the same line are included; editors usually skip those.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{folding_ranges, ValueKind};
+///
+/// fn main() {
+///     let source = "{\n  \"list\": [1, 2],\n  \"nested\": {\n    \"a\": null\n  }\n}";
+///     let ranges = folding_ranges(source).unwrap();
+///
+///     let lines = ranges
+///         .iter()
+///         .map(|range| (range.kind, range.start_line, range.end_line))
+///         .collect::<Vec<_>>();
+///     assert_eq!(
+///         lines,
+///         vec![(ValueKind::Object, 0, 5), (ValueKind::Array, 1, 1), (ValueKind::Object, 2, 4)]
+///     );
+/// }
+/// ```
+pub fn folding_ranges(json_str: &str) -> Result<Vec<FoldingRange>, JsonError> {
+    let mut parser = EventParser::new(json_str);
+    let mut lines = Lines {
+        source: json_str,
+        offset: 0,
+        line: 0,
+    };
+    let mut ranges: Vec<FoldingRange> = vec![];
+    let mut open = vec![];
+
+    while let Some((event, span)) = parser.next_event()? {
+        match event {
+            Event::ArrayStart | Event::ObjectStart => {
+                let kind = if event == Event::ArrayStart {
+                    ValueKind::Array
+                } else {
+                    ValueKind::Object
+                };
+                let line = lines.at(span.start);
+                open.push(ranges.len());
+                ranges.push(FoldingRange {
+                    kind,
+                    span,
+                    start_line: line,
+                    end_line: line,
+                });
+            }
+            Event::ArrayEnd | Event::ObjectEnd => {
+                let range = &mut ranges[open.pop().unwrap()];
+                range.span.end = span.end;
+                range.end_line = lines.at(span.start);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{folding_ranges, FoldingRange};
+    use crate::outline::ValueKind;
+    use crate::span::Span;
+    use crate::JsonError;
+
+    #[test]
+    fn folding_ranges_spans() {
+        let source = "[\r\n  {},\n  [\n\n    \"ώ\\n\"\n  ]\n]";
+        assert_eq!(
+            folding_ranges(source),
+            Ok(vec![
+                FoldingRange {
+                    kind: ValueKind::Array,
+                    span: Span::new(0, source.len()),
+                    start_line: 0,
+                    end_line: 6,
+                },
+                FoldingRange {
+                    kind: ValueKind::Object,
+                    span: Span::new(5, 7),
+                    start_line: 1,
+                    end_line: 1,
+                },
+                FoldingRange {
+                    kind: ValueKind::Array,
+                    span: Span::new(11, 28),
+                    start_line: 2,
+                    end_line: 5,
+                },
+            ])
+        );
+
+        assert_eq!(folding_ranges("12"), Ok(vec![]));
+        assert_eq!(
+            folding_ranges("[{}"),
+            Err(JsonError::UnexpectedEOF {
+                line: 0,
+                column: 3,
+                offset: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Parsing HTTP bodies, enabled by the `http` feature.
+//!
+//! Any `http_body::Body`, which covers `hyper`, `axum` and async `reqwest` bodies, is parsed
+//! with the `parse_body` future, and with the `reqwest` feature blocking `reqwest` responses are
+//! read with `parse_response`.  Either way
+//! the body is taken in chunk by chunk as it arrives and given up on as soon as it grows past a
+//! size limit, so a service can read JSON from untrusted peers without first buffering whatever
+//! they send.
+use super::{json_parse, JsonError, JsonValue};
+use bytes::Buf;
+use http_body::Body;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+#[cfg(feature = "reqwest")]
+use std::io::Read;
+use std::mem;
+use std::pin::Pin;
+use std::str;
+use std::task::{Context, Poll};
+
+/// The size limit used by `parse_response` and the `web` extractors, 2 MiB.
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Describes all possible errors that could occur while parsing a response body.
+#[derive(Debug)]
+pub enum HttpError {
+    /// Reading a blocking response failed
+    Io(io::Error),
+    /// Reading an async body failed
+    Body(Box<dyn Error + Send + Sync>),
+    /// The body was larger than the limit, either by its declared length or once read
+    TooLarge {
+        /// The limit in bytes
+        limit: usize,
+    },
+    /// The body was not valid UTF-8
+    InvalidUtf8 {
+        /// The byte offset of the first invalid byte
+        offset: usize,
+    },
+    /// The body was not valid JSON
+    Parse(JsonError),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpError::Io(error) => error.fmt(f),
+            HttpError::Body(error) => error.fmt(f),
+            HttpError::TooLarge { limit } => write!(f, "body is larger than {} bytes", limit),
+            HttpError::InvalidUtf8 { offset } => {
+                write!(f, "body is not valid UTF-8 at byte offset {}", offset)
+            }
+            HttpError::Parse(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HttpError::Io(error) => Some(error),
+            HttpError::Body(error) => Some(error.as_ref()),
+            HttpError::Parse(error) => Some(error),
+            HttpError::TooLarge { .. } | HttpError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(error: io::Error) -> Self {
+        HttpError::Io(error)
+    }
+}
+
+/// Adds a chunk to the body read so far, unless that would take it past `limit`.
+pub(crate) fn push(bytes: &mut Vec<u8>, chunk: &[u8], limit: usize) -> Result<(), HttpError> {
+    if chunk.len() > limit - bytes.len() {
+        return Err(HttpError::TooLarge { limit });
+    }
+    bytes.extend_from_slice(chunk);
+    Ok(())
+}
+
+pub(crate) fn parse(bytes: &[u8]) -> Result<JsonValue, HttpError> {
+    let text = str::from_utf8(bytes).map_err(|e| HttpError::InvalidUtf8 {
+        offset: e.valid_up_to(),
+    })?;
+    json_parse(text).map_err(HttpError::Parse)
+}
+
+/// Parses the body of a blocking `reqwest` response, allowing at most `DEFAULT_BODY_LIMIT`
+/// bytes.
+///
+/// The status is not looked at, so error responses with JSON bodies can be parsed as well.
+/// ```no_run
+/// extern crate json_rs;
+/// extern crate reqwest;
+/// use json_rs::http::parse_response;
+///
+/// fn main() {
+///     let response = reqwest::blocking::get("https://example.com/api/items").unwrap();
+///     let items = parse_response(response).unwrap();
+///     println!("{}", items.to_string());
+/// }
+/// ```
+#[cfg(feature = "reqwest")]
+pub fn parse_response(response: reqwest::blocking::Response) -> Result<JsonValue, HttpError> {
+    parse_response_limited(response, DEFAULT_BODY_LIMIT)
+}
+
+/// Parses the body of a blocking `reqwest` response, failing as soon as it is known to be
+/// longer than `limit` bytes.
+#[cfg(feature = "reqwest")]
+pub fn parse_response_limited(
+    mut response: reqwest::blocking::Response,
+    limit: usize,
+) -> Result<JsonValue, HttpError> {
+    let mut bytes = match response.content_length() {
+        Some(len) if len > limit as u64 => return Err(HttpError::TooLarge { limit }),
+        Some(len) => Vec::with_capacity(len as usize),
+        None => Vec::new(),
+    };
+    let mut chunk = [0; 8192];
+    loop {
+        match response.read(&mut chunk) {
+            Ok(0) => return parse(&bytes),
+            Ok(len) => push(&mut bytes, &chunk[..len], limit)?,
+            Err(ref error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// A future that parses an async body, created by `parse_body`.
+pub struct ParseBody<B> {
+    body: Pin<Box<B>>,
+    bytes: Vec<u8>,
+    limit: usize,
+}
+
+/// Parses an async body, failing as soon as it is known to be longer than `limit` bytes.
+/// Trailers are ignored.
+///
+/// An async `reqwest::Response` becomes a body with `reqwest::Body::from`, and `hyper` and
+/// `axum` request and response bodies can be passed as they are.
+/// ```
+/// extern crate json_rs;
+/// extern crate tokio;
+/// use json_rs::http::{parse_body, HttpError};
+///
+/// fn main() {
+///     let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+///     let body = String::from("[1, 2, 3]");
+///     assert!(runtime.block_on(parse_body(body.clone(), 16)).is_ok());
+///     assert!(matches!(
+///         runtime.block_on(parse_body(body, 8)),
+///         Err(HttpError::TooLarge { limit: 8 })
+///     ));
+/// }
+/// ```
+pub fn parse_body<B>(body: B, limit: usize) -> ParseBody<B>
+where
+    B: Body,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    ParseBody {
+        body: Box::pin(body),
+        bytes: Vec::new(),
+        limit,
+    }
+}
+
+impl<B> fmt::Debug for ParseBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseBody")
+            .field("read", &self.bytes.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl<B> Future for ParseBody<B>
+where
+    B: Body,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    type Output = Result<JsonValue, HttpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            let remaining = this.body.size_hint().lower();
+            if remaining > (this.limit - this.bytes.len()) as u64 {
+                return Poll::Ready(Err(HttpError::TooLarge { limit: this.limit }));
+            }
+            let frame = match this.body.as_mut().poll_frame(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(parse(&mem::take(&mut this.bytes))),
+                Poll::Ready(Some(Err(error))) => {
+                    return Poll::Ready(Err(HttpError::Body(error.into())))
+                }
+                Poll::Ready(Some(Ok(frame))) => frame,
+            };
+            if let Ok(mut data) = frame.into_data() {
+                while data.has_remaining() {
+                    let len = data.chunk().len();
+                    if let Err(error) = push(&mut this.bytes, data.chunk(), this.limit) {
+                        return Poll::Ready(Err(error));
+                    }
+                    data.advance(len);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::{parse_body, parse_response, parse_response_limited, HttpError};
+    use crate::json_parse;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves each of `responses` to one connection and returns the address to request.
+    fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        address
+    }
+
+    const SIZED: &str =
+        "HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\n{\"a\": [1, 2]}";
+    const CHUNKED: &str =
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+                           5\r\n[1, 2\r\n5\r\n, 3, \r\n2\r\n4]\r\n0\r\n\r\n";
+
+    #[test]
+    fn http_blocking() {
+        let address = serve(vec![SIZED, SIZED, CHUNKED, CHUNKED]);
+        let get = || reqwest::blocking::get(&address).unwrap();
+        assert_eq!(
+            parse_response(get()).unwrap(),
+            json_parse(r#"{"a": [1, 2]}"#).unwrap()
+        );
+        assert!(matches!(
+            parse_response_limited(get(), 12),
+            Err(HttpError::TooLarge { limit: 12 })
+        ));
+        assert_eq!(
+            parse_response_limited(get(), 12).unwrap(),
+            json_parse("[1, 2, 3, 4]").unwrap()
+        );
+        assert!(matches!(
+            parse_response_limited(get(), 11),
+            Err(HttpError::TooLarge { limit: 11 })
+        ));
+        assert_eq!(
+            HttpError::TooLarge { limit: 11 }.to_string(),
+            "body is larger than 11 bytes"
+        );
+    }
+
+    #[test]
+    fn http_body() {
+        let address = serve(vec![SIZED, CHUNKED, CHUNKED]);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let get = || reqwest::Body::from(runtime.block_on(reqwest::get(&address)).unwrap());
+
+        assert!(matches!(
+            runtime.block_on(parse_body(get(), 12)),
+            Err(HttpError::TooLarge { limit: 12 })
+        ));
+        assert_eq!(
+            runtime.block_on(parse_body(get(), 12)).unwrap(),
+            json_parse("[1, 2, 3, 4]").unwrap()
+        );
+        assert!(matches!(
+            runtime.block_on(parse_body(get(), 8)),
+            Err(HttpError::TooLarge { limit: 8 })
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+use super::span::{json_parse_spanned, Span, Spanned, SpannedValue};
+use super::JsonError;
+use std::ops::Range;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A change to source text: the bytes in `range` are replaced with `replacement`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    /// The byte range being replaced
+    pub range: Range<usize>,
+    /// The text inserted in its place
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Creates an edit replacing `range` with `replacement`.
+    pub fn new<S: Into<String>>(range: Range<usize>, replacement: S) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Returns `source` with the edit applied.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source[..self.range.start].to_string();
+        edited.push_str(&self.replacement);
+        edited.push_str(&source[self.range.end..]);
+        edited
+    }
+
+    /// How much the edit grows (or, if negative, shrinks) the source by.
+    fn delta(&self) -> isize {
+        self.replacement.len() as isize - (self.range.end - self.range.start) as isize
+    }
+}
+
+/// Whether `node` could be reparsed on its own after `edit`.  Containers only qualify when
+/// their brackets are untouched.
+fn encloses(node: &Spanned<SpannedValue>, edit: &TextEdit) -> bool {
+    let Span { start, end } = node.span;
+    match node.value {
+        SpannedValue::Array(_) | SpannedValue::Object(_) => {
+            start < edit.range.start && edit.range.end < end
+        }
+        _ => start <= edit.range.start && edit.range.end <= end,
+    }
+}
+
+fn children(node: &Spanned<SpannedValue>) -> Vec<&Spanned<SpannedValue>> {
+    match &node.value {
+        SpannedValue::Array(items) => items.iter().collect(),
+        SpannedValue::Object(members) => members.iter().map(|(_, value)| value).collect(),
+        _ => vec![],
+    }
+}
+
+fn child_mut(node: &mut Spanned<SpannedValue>, ind: usize) -> &mut Spanned<SpannedValue> {
+    match &mut node.value {
+        SpannedValue::Array(items) => &mut items[ind],
+        SpannedValue::Object(members) => &mut members[ind].1,
+        _ => unreachable!(),
+    }
+}
+
+/// Moves every span in `node` that lies at or after `from` by `delta` bytes.
+fn shift(node: &mut Spanned<SpannedValue>, from: usize, delta: isize) {
+    let move_offset = |offset: &mut usize| {
+        if *offset >= from {
+            *offset = (*offset as isize + delta) as usize;
+        }
+    };
+
+    let mut pending = vec![node];
+    while let Some(node) = pending.pop() {
+        if node.span.end < from {
+            continue;
+        }
+        move_offset(&mut node.span.start);
+        move_offset(&mut node.span.end);
+
+        match &mut node.value {
+            SpannedValue::Array(items) => pending.extend(items.iter_mut()),
+            SpannedValue::Object(members) => {
+                for (key, value) in members.iter_mut() {
+                    move_offset(&mut key.span.start);
+                    move_offset(&mut key.span.end);
+                    pending.push(value);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Updates a spanned tree after an edit to its source, reparsing only the smallest value that
+/// encloses the edit.
+///
+/// `source` and `tree` describe the document before the edit.  If the enclosing value no longer
+/// parses on its own (for instance because the edit added a sibling), progressively larger
+/// values are tried, up to the whole document.  The result and any error are always the same
+/// as running [`json_parse_spanned`](fn.json_parse_spanned.html) on the edited text.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse_spanned, reparse, TextEdit};
+///
+/// fn main() {
+///     let source = r#"{ "a": [1, 2], "b": "text" }"#;
+///     let tree = json_parse_spanned(source).unwrap();
+///
+///     let edit = TextEdit::new(11..12, "20");
+///     let updated = reparse(source, &tree, &edit).unwrap();
+///     assert_eq!(updated, json_parse_spanned(&edit.apply(source)).unwrap());
+/// }
+/// ```
+pub fn reparse(
+    source: &str,
+    tree: &Spanned<SpannedValue>,
+    edit: &TextEdit,
+) -> Result<Spanned<SpannedValue>, JsonError> {
+    // the chain of enclosing values, from the root down, as child indices
+    let mut path = vec![];
+    let mut enclosing = vec![tree];
+    if encloses(tree, edit) {
+        while let Some((ind, child)) = children(enclosing[enclosing.len() - 1])
+            .into_iter()
+            .enumerate()
+            .find(|(_, child)| encloses(child, edit))
+        {
+            path.push(ind);
+            enclosing.push(child);
+        }
+    } else {
+        enclosing.clear();
+    }
+
+    while let Some(node) = enclosing.pop() {
+        let Span { start, end } = node.span;
+        let mut region = source[start..edit.range.start].to_string();
+        region.push_str(&edit.replacement);
+        region.push_str(&source[edit.range.end..end]);
+
+        if let Ok(mut replacement) = json_parse_spanned(&region) {
+            shift(&mut replacement, 0, start as isize);
+
+            let mut updated = tree.clone();
+            shift(&mut updated, edit.range.end, edit.delta());
+            *path
+                .iter()
+                .fold(&mut updated, |node, &ind| child_mut(node, ind)) = replacement;
+            return Ok(updated);
+        }
+        path.pop();
+    }
+
+    json_parse_spanned(&edit.apply(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reparse, TextEdit};
+    use crate::span::json_parse_spanned;
+
+    #[test]
+    fn reparse_edits() {
+        let source = r#"{ "a": [1, 2, {"x": "yy"}], "b": "text", "c": null }"#;
+        let tree = json_parse_spanned(source).unwrap();
+
+        let tests = vec![
+            TextEdit::new(8..9, "100"),
+            TextEdit::new(9..9, ", 5"),
+            TextEdit::new(21..23, ""),
+            TextEdit::new(21..21, "\\u00e9"),
+            TextEdit::new(34..34, "more "),
+            TextEdit::new(47..51, "[true]"),
+            TextEdit::new(47..51, "tru"),
+            TextEdit::new(0..1, "["),
+            TextEdit::new(2..5, "\"renamed\""),
+            TextEdit::new(52..52, " "),
+            TextEdit::new(12..13, "]"),
+            TextEdit::new(0..source.len(), "1"),
+        ];
+
+        for edit in tests.into_iter() {
+            assert_eq!(
+                reparse(source, &tree, &edit),
+                json_parse_spanned(&edit.apply(source)),
+                "{:?}",
+                edit
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_random_edits() {
+        let source = r#"[{"id": 1, "tags": ["a", "b"]}, {"id": 22, "tags": []}, -3.5e2, false]"#;
+        let tree = json_parse_spanned(source).unwrap();
+        let replacements = ["", "0", "9", ",", "\"", " ", "]", "{}", "null", "\"q\": 1,"];
+
+        let mut seed = 0x2545_f491u32;
+        let mut next = move |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize % bound
+        };
+
+        for _ in 0..2000 {
+            let start = next(source.len() + 1);
+            let end = start + next(source.len() + 1 - start).min(4);
+            let edit = TextEdit::new(start..end, replacements[next(replacements.len())]);
+            assert_eq!(
+                reparse(source, &tree, &edit),
+                json_parse_spanned(&edit.apply(source)),
+                "{:?}",
+                edit
+            );
+        }
+    }
+}
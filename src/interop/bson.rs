@@ -0,0 +1,126 @@
+use super::super::JsonValue;
+use bson_crate::extjson::de::Error;
+use bson_crate::{Bson, Document};
+use serde_json_crate::Value;
+use std::convert::TryFrom;
+
+/// Converts BSON to relaxed [Extended JSON].  Types that JSON lacks become objects such as
+/// `{ "$oid": "..." }` and `{ "$date": "..." }`, while numbers are plain numbers where possible.
+///
+/// [Extended JSON]: https://www.mongodb.com/docs/manual/reference/mongodb-extended-json/
+/// ```
+/// extern crate bson;
+/// extern crate json_rs;
+/// use bson::oid::ObjectId;
+/// use bson::{doc, Bson};
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let id = ObjectId::parse_str("5f1d6f8e1c9d440000a1b2c3").unwrap();
+///     let doc = Bson::Document(doc! { "_id": id, "n": 3 });
+///     assert_eq!(
+///         json_rs::JsonValue::from(doc),
+///         json_parse(r#"{ "_id": { "$oid": "5f1d6f8e1c9d440000a1b2c3" }, "n": 3 }"#).unwrap()
+///     );
+/// }
+/// ```
+impl From<Bson> for JsonValue {
+    fn from(bson: Bson) -> Self {
+        JsonValue::from(bson.into_relaxed_extjson())
+    }
+}
+
+/// Converts a BSON document to relaxed Extended JSON.
+impl From<Document> for JsonValue {
+    fn from(document: Document) -> Self {
+        JsonValue::from(Bson::Document(document))
+    }
+}
+
+/// Reads Extended JSON, in either its canonical or relaxed form, as BSON.  Fails if an
+/// Extended JSON object such as `{ "$oid": "..." }` is malformed.
+/// ```
+/// extern crate bson;
+/// extern crate json_rs;
+/// use bson::{Bson, Document};
+/// use json_rs::json_parse;
+/// use std::convert::TryFrom;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "at": { "$date": { "$numberLong": "1500000000000" } } }"#).unwrap();
+///     let doc = Document::try_from(json).unwrap();
+///     assert_eq!(
+///         doc.get_datetime("at").unwrap().timestamp_millis(),
+///         1_500_000_000_000
+///     );
+///
+///     assert!(Bson::try_from(json_parse(r#"{ "$oid": "not hex" }"#).unwrap()).is_err());
+/// }
+/// ```
+impl TryFrom<JsonValue> for Bson {
+    type Error = Error;
+
+    fn try_from(value: JsonValue) -> Result<Self, Error> {
+        Bson::try_from(Value::from(value))
+    }
+}
+
+/// Reads an Extended JSON object as a BSON document.  Fails if the value is not an object, or
+/// contains malformed Extended JSON.
+impl TryFrom<JsonValue> for Document {
+    type Error = Error;
+
+    fn try_from(value: JsonValue) -> Result<Self, Error> {
+        match Value::from(value) {
+            Value::Object(map) => Document::try_from(map),
+            _ => Err(Error::DeserializationError {
+                message: "expected an object".into(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json_parse, JsonValue};
+    use bson_crate::spec::BinarySubtype;
+    use bson_crate::{Binary, Bson, DateTime, Document};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn bson_extended_json() {
+        let json = json_parse(
+            r#"{
+                "name": "a",
+                "count": 3,
+                "ratio": 0.5,
+                "tags": ["x", null, true],
+                "at": { "$date": "2020-07-14T02:40:00Z" },
+                "data": { "$binary": { "base64": "AQID", "subType": "00" } }
+            }"#,
+        )
+        .unwrap();
+
+        let doc = Document::try_from(json.clone()).unwrap();
+        assert_eq!(doc.get_str("name"), Ok("a"));
+        assert_eq!(doc.get_i32("count"), Ok(3));
+        assert_eq!(
+            doc.get("at"),
+            Some(&Bson::DateTime(DateTime::from_millis(1_594_694_400_000)))
+        );
+        assert_eq!(
+            doc.get("data"),
+            Some(&Bson::Binary(Binary {
+                subtype: BinarySubtype::Generic,
+                bytes: vec![1, 2, 3],
+            }))
+        );
+        assert_eq!(JsonValue::from(doc), json);
+
+        assert!(Document::try_from(JsonValue::Array(vec![])).is_err());
+        assert_eq!(
+            Bson::try_from(JsonValue::from(2.5)).ok(),
+            Some(Bson::Double(2.5))
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! Conversions between `JsonValue` and the value types of other crates, each behind a feature
+//! named after that crate.
+#[cfg(feature = "bson")]
+mod bson;
+#[cfg(feature = "prost")]
+mod prost;
+#[cfg(feature = "serde_json")]
+mod serde_json;
+#[cfg(feature = "time")]
+mod time;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "uuid")]
+mod uuid;
@@ -0,0 +1,121 @@
+use super::super::convert::FromJsonError;
+use super::super::{JsonNumber, JsonString, JsonValue};
+use prost_types_crate::value::Kind;
+use prost_types_crate::{ListValue, NullValue, Struct, Value};
+use std::convert::TryFrom;
+use std::mem;
+
+/// Converts a `google.protobuf.Value`.  A value with no kind set is `null`.
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value.kind {
+            None | Some(Kind::NullValue(_)) => JsonValue::Null,
+            Some(Kind::NumberValue(n)) => JsonValue::Number(JsonNumber::F64(n)),
+            Some(Kind::StringValue(s)) => JsonValue::Text(s.into()),
+            Some(Kind::BoolValue(b)) => JsonValue::Boolean(b),
+            Some(Kind::StructValue(s)) => JsonValue::from(s),
+            Some(Kind::ListValue(list)) => {
+                JsonValue::Array(list.values.into_iter().map(JsonValue::from).collect())
+            }
+        }
+    }
+}
+
+/// Converts a `google.protobuf.Struct` into an object.
+impl From<Struct> for JsonValue {
+    fn from(s: Struct) -> Self {
+        JsonValue::Object(Box::new(
+            s.fields
+                .into_iter()
+                .map(|(key, value)| (key.into(), JsonValue::from(value)))
+                .collect(),
+        ))
+    }
+}
+
+/// Converts into a `google.protobuf.Value`.
+/// ```
+/// extern crate json_rs;
+/// extern crate prost_types;
+/// use json_rs::{json_parse, JsonValue};
+/// use prost_types::value::Kind;
+/// use prost_types::Value;
+///
+/// fn main() {
+///     let value = Value::from(json_parse(r#"{ "tags": ["a"], "n": 1 }"#).unwrap());
+///     let fields = match &value.kind {
+///         Some(Kind::StructValue(s)) => &s.fields,
+///         _ => unreachable!(),
+///     };
+///     assert_eq!(fields["n"].kind, Some(Kind::NumberValue(1.0)));
+///     assert_eq!(JsonValue::from(value), json_parse(r#"{ "tags": ["a"], "n": 1 }"#).unwrap());
+/// }
+/// ```
+impl From<JsonValue> for Value {
+    fn from(mut value: JsonValue) -> Self {
+        let kind = match value {
+            JsonValue::Null => Kind::NullValue(NullValue::NullValue as i32),
+            JsonValue::Number(n) => Kind::NumberValue(n.as_f64()),
+            JsonValue::Text(ref mut s) => Kind::StringValue(mem::take(s).into()),
+            JsonValue::Boolean(b) => Kind::BoolValue(b),
+            JsonValue::Raw(ref raw) => return Value::from(raw.parse()),
+            JsonValue::Array(ref mut array) => Kind::ListValue(ListValue {
+                values: mem::take(array).into_iter().map(Value::from).collect(),
+            }),
+            JsonValue::Object(ref mut map) => {
+                Kind::StructValue(object(mem::take(&mut **map).into_iter()))
+            }
+        };
+        Value { kind: Some(kind) }
+    }
+}
+
+fn object<I: Iterator<Item = (JsonString, JsonValue)>>(members: I) -> Struct {
+    Struct {
+        fields: members
+            .map(|(key, value)| (key.into(), Value::from(value)))
+            .collect(),
+    }
+}
+
+/// Converts an object into a `google.protobuf.Struct`.  Fails for anything but an object.
+impl TryFrom<JsonValue> for Struct {
+    type Error = FromJsonError;
+
+    fn try_from(mut value: JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Object(ref mut map) => Ok(object(mem::take(&mut **map).into_iter())),
+            _ => Err(FromJsonError::invalid_type("an object", &value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json_parse, FromJsonError, JsonValue, ValueKind};
+    use prost_types_crate::value::Kind;
+    use prost_types_crate::{Struct, Value};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn prost_struct_round_trip() {
+        let json =
+            json_parse(r#"{ "a": [1.5, "x", true, null], "b": { "c": {} }, "d": [] }"#).unwrap();
+        let s = Struct::try_from(json.clone()).unwrap();
+        assert_eq!(s.fields.len(), 3);
+        assert_eq!(JsonValue::from(s), json);
+
+        assert_eq!(JsonValue::from(Value { kind: None }), JsonValue::Null);
+        assert_eq!(
+            Value::from(JsonValue::Boolean(false)).kind,
+            Some(Kind::BoolValue(false))
+        );
+        assert_eq!(
+            Struct::try_from(JsonValue::from(1.0)),
+            Err(FromJsonError::InvalidType {
+                expected: "an object",
+                found: ValueKind::Number,
+            })
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use super::super::{JsonNumber, JsonValue};
+use serde_json_crate::{Map, Number, Value};
+use std::mem;
+
+/// Converts a `serde_json::Value`.  Numbers are converted to `f64`, so integers beyond 2<sup>53</sup>
+/// lose precision.
+/// ```
+/// extern crate json_rs;
+/// extern crate serde_json;
+/// use json_rs::{json_parse, JsonValue};
+///
+/// fn main() {
+///     let value: serde_json::Value = serde_json::from_str(r#"{ "a": [1, true, null] }"#).unwrap();
+///     assert_eq!(
+///         JsonValue::from(value),
+///         json_parse(r#"{ "a": [1, true, null] }"#).unwrap()
+///     );
+/// }
+/// ```
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => JsonValue::Null,
+            Value::Bool(b) => JsonValue::Boolean(b),
+            Value::Number(n) => JsonValue::Number(match (n.as_u64(), n.as_i64()) {
+                (Some(n), _) => JsonNumber::U64(n),
+                (_, Some(n)) => JsonNumber::I64(n),
+                // only numbers with arbitrary precision can fail to convert
+                _ => JsonNumber::F64(n.as_f64().unwrap_or(f64::NAN)),
+            }),
+            Value::String(s) => JsonValue::Text(s.into()),
+            Value::Array(array) => JsonValue::Array(array.into_iter().map(Self::from).collect()),
+            Value::Object(map) => JsonValue::Object(Box::new(
+                map.into_iter()
+                    .map(|(key, value)| (key.into(), Self::from(value)))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// Converts into a `serde_json::Value`.  Integral numbers become integers, and `NaN` and the
+/// infinities, which `serde_json` cannot represent, become `null`.
+/// ```
+/// extern crate json_rs;
+/// extern crate serde_json;
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let value = serde_json::Value::from(json_parse(r#"{ "a": [1, 2.5, "x"] }"#).unwrap());
+///     assert_eq!(value.to_string(), r#"{"a":[1,2.5,"x"]}"#);
+/// }
+/// ```
+impl From<JsonValue> for Value {
+    fn from(mut value: JsonValue) -> Self {
+        match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::Boolean(b) => Value::Bool(b),
+            JsonValue::Number(n) => number(n).map_or(Value::Null, Value::Number),
+            JsonValue::Raw(ref raw) => Self::from(raw.parse()),
+            JsonValue::Text(ref mut s) => Value::String(mem::take(s).into()),
+            JsonValue::Array(ref mut array) => {
+                Value::Array(mem::take(array).into_iter().map(Self::from).collect())
+            }
+            JsonValue::Object(ref mut map) => Value::Object(
+                mem::take(&mut **map)
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), Self::from(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+fn number(n: JsonNumber) -> Option<Number> {
+    let n = match n {
+        JsonNumber::I64(n) => return Some(Number::from(n)),
+        JsonNumber::U64(n) => return Some(Number::from(n)),
+        JsonNumber::F64(n) => n,
+        #[cfg(feature = "decimal")]
+        JsonNumber::Decimal(n) => n.to_f64(),
+    };
+    if n.fract() == 0.0 && (0.0..u64::MAX as f64).contains(&n) {
+        Some(Number::from(n as u64))
+    } else if n.fract() == 0.0 && (i64::MIN as f64..0.0).contains(&n) {
+        Some(Number::from(n as i64))
+    } else {
+        Number::from_f64(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json_parse, JsonValue};
+    use serde_json_crate::{self, Value};
+
+    #[test]
+    fn serde_json_round_trip() {
+        let source = r#"{
+            "text": "a\nb",
+            "numbers": [0, -3, 2.5, 1e300, 9007199254740992],
+            "flags": [true, false, null],
+            "nested": { "empty": {}, "list": [] }
+        }"#;
+        let ours = json_parse(source).unwrap();
+        let theirs: Value = serde_json_crate::from_str(source).unwrap();
+
+        assert_eq!(JsonValue::from(theirs.clone()), ours);
+        assert_eq!(Value::from(ours), theirs);
+
+        assert_eq!(Value::from(JsonValue::from(-0.0)), Value::from(0));
+        assert_eq!(Value::from(JsonValue::from(f64::NAN)), Value::Null);
+        assert_eq!(Value::from(JsonValue::from(f64::INFINITY)), Value::Null);
+    }
+}
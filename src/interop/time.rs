@@ -0,0 +1,97 @@
+use super::super::convert::{FromJson, FromJsonError, ToJson};
+use super::super::JsonValue;
+use time_crate::format_description::well_known::Rfc3339;
+use time_crate::OffsetDateTime;
+
+impl JsonValue {
+    /// Reads an RFC 3339 timestamp out of a string value.  Returns `None` for anything else.
+    /// Enabled by the `time` feature.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::json_parse;
+    ///
+    /// fn main() {
+    ///     let json = json_parse(r#"{ "at": "2024-05-01T12:30:00+02:00", "n": 1 }"#).unwrap();
+    ///     let at = json.get("at").and_then(|at| at.as_datetime()).unwrap();
+    ///     assert_eq!((at.hour(), at.offset().whole_hours()), (12, 2));
+    ///     assert_eq!(json.get("n").and_then(|n| n.as_datetime()), None);
+    /// }
+    /// ```
+    pub fn as_datetime(&self) -> Option<OffsetDateTime> {
+        match self {
+            JsonValue::Text(text) => OffsetDateTime::parse(text, &Rfc3339).ok(),
+            _ => None,
+        }
+    }
+
+    /// Writes a timestamp as an RFC 3339 string value, which [`as_datetime`](#method.as_datetime)
+    /// reads back.  Timestamps RFC 3339 cannot express, those outside the years 0 to 9999 or
+    /// with an offset of a fraction of a minute, fall back to the `time` crate's own format.
+    /// Enabled by the `time` feature.
+    pub fn from_datetime(datetime: &OffsetDateTime) -> Self {
+        JsonValue::Text(
+            datetime
+                .format(&Rfc3339)
+                .unwrap_or_else(|_| datetime.to_string())
+                .into(),
+        )
+    }
+}
+
+/// Writes the timestamp as an RFC 3339 string, as with
+/// [`JsonValue::from_datetime`](../enum.JsonValue.html#method.from_datetime).
+impl ToJson for OffsetDateTime {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::from_datetime(self)
+    }
+}
+
+/// Reads an RFC 3339 string.
+impl FromJson for OffsetDateTime {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Text(text) => OffsetDateTime::parse(text, &Rfc3339).map_err(|err| {
+                FromJsonError::Message(format!("invalid RFC 3339 timestamp: {}", err))
+            }),
+            _ => Err(FromJsonError::invalid_type("an RFC 3339 string", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{FromJson, FromJsonError, ToJson};
+    use crate::{JsonValue, ValueKind};
+    use time_crate::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+    #[test]
+    fn time_rfc3339() {
+        let datetime = PrimitiveDateTime::new(
+            Date::from_calendar_date(2024, Month::February, 29).unwrap(),
+            Time::from_hms_milli(23, 59, 58, 250).unwrap(),
+        )
+        .assume_offset(UtcOffset::from_hms(-5, -30, 0).unwrap());
+
+        let json = datetime.to_json();
+        assert_eq!(json, JsonValue::Text("2024-02-29T23:59:58.25-05:30".into()));
+        assert_eq!(json.as_datetime(), Some(datetime));
+        assert_eq!(OffsetDateTime::from_json(&json), Ok(datetime));
+
+        let odd_offset = datetime.replace_offset(UtcOffset::from_hms(1, 0, 30).unwrap());
+        assert!(JsonValue::from_datetime(&odd_offset)
+            .as_datetime()
+            .is_none());
+
+        assert!(matches!(
+            OffsetDateTime::from_json(&JsonValue::Text("2024-02-30T00:00:00Z".into())),
+            Err(FromJsonError::Message(_))
+        ));
+        assert_eq!(
+            OffsetDateTime::from_json(&JsonValue::from(0.0)),
+            Err(FromJsonError::InvalidType {
+                expected: "an RFC 3339 string",
+                found: ValueKind::Number,
+            })
+        );
+    }
+}
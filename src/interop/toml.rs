@@ -0,0 +1,145 @@
+use super::super::convert::FromJsonError;
+use super::super::{JsonNumber, JsonValue};
+use std::convert::TryFrom;
+use std::mem;
+use toml_crate::{Table, Value};
+
+/// Converts a TOML value.  Datetimes become strings in their RFC 3339 form.
+impl From<Value> for JsonValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::String(s) => JsonValue::Text(s.into()),
+            Value::Integer(i) => JsonValue::from(i),
+            Value::Float(f) => JsonValue::Number(JsonNumber::F64(f)),
+            Value::Boolean(b) => JsonValue::Boolean(b),
+            Value::Datetime(datetime) => JsonValue::Text(datetime.to_string().into()),
+            Value::Array(array) => JsonValue::Array(array.into_iter().map(Self::from).collect()),
+            Value::Table(table) => JsonValue::from(table),
+        }
+    }
+}
+
+/// Converts a TOML document or table into an object.
+/// ```
+/// extern crate json_rs;
+/// extern crate toml;
+/// use json_rs::{json_parse, JsonValue};
+///
+/// fn main() {
+///     let table: toml::Table = "[server]\nport = 8080\nstarted = 2024-05-01T12:00:00Z"
+///         .parse()
+///         .unwrap();
+///     assert_eq!(
+///         JsonValue::from(table),
+///         json_parse(r#"{ "server": { "port": 8080, "started": "2024-05-01T12:00:00Z" } }"#)
+///             .unwrap()
+///     );
+/// }
+/// ```
+impl From<Table> for JsonValue {
+    fn from(table: Table) -> Self {
+        JsonValue::Object(Box::new(
+            table
+                .into_iter()
+                .map(|(key, value)| (key.into(), Self::from(value)))
+                .collect(),
+        ))
+    }
+}
+
+/// Converts into a TOML value.  Whole numbers that fit in 64 bits become integers.  Fails for
+/// `null`, which TOML cannot represent.
+impl TryFrom<JsonValue> for Value {
+    type Error = FromJsonError;
+
+    fn try_from(mut value: JsonValue) -> Result<Self, FromJsonError> {
+        Ok(match value {
+            JsonValue::Text(ref mut s) => Value::String(mem::take(s).into()),
+            JsonValue::Number(n) => match n.as_i64() {
+                Some(n) => Value::Integer(n),
+                None => Value::Float(n.as_f64()),
+            },
+            JsonValue::Boolean(b) => Value::Boolean(b),
+            JsonValue::Null => {
+                return Err(FromJsonError::invalid_type(
+                    "a value other than null",
+                    &value,
+                ))
+            }
+            JsonValue::Array(ref mut array) => Value::Array(
+                mem::take(array)
+                    .into_iter()
+                    .map(TryFrom::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsonValue::Object(_) => Value::Table(TryFrom::try_from(value)?),
+            JsonValue::Raw(ref raw) => TryFrom::try_from(raw.parse())?,
+        })
+    }
+}
+
+/// Converts an object into a TOML table, which can be written as a document.
+///
+/// `toml::Table` and `toml::Value` have inherent `try_from` methods that go through serde, so
+/// this conversion is easiest to reach through `try_into`.
+/// ```
+/// extern crate json_rs;
+/// extern crate toml;
+/// use json_rs::json_parse;
+/// use std::convert::TryInto;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "name": "app", "deps": { "json": "1.0" } }"#).unwrap();
+///     let table: toml::Table = json.try_into().unwrap();
+///     assert_eq!(
+///         toml::to_string(&table).unwrap(),
+///         "name = \"app\"\n\n[deps]\njson = \"1.0\"\n"
+///     );
+/// }
+/// ```
+impl TryFrom<JsonValue> for Table {
+    type Error = FromJsonError;
+
+    fn try_from(mut value: JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Object(ref mut map) => mem::take(&mut **map)
+                .into_iter()
+                .map(|(key, value)| Ok((key.into(), TryFrom::try_from(value)?)))
+                .collect(),
+            _ => Err(FromJsonError::invalid_type("an object", &value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json_parse, FromJsonError, JsonValue, ValueKind};
+    use std::convert::TryInto;
+    use toml_crate::{Table, Value};
+
+    #[test]
+    fn toml_round_trip() {
+        let json =
+            json_parse(r#"{ "a": [1, 2.5, "x", true], "b": { "c": -3, "d": [{ "e": {} }] } }"#)
+                .unwrap();
+        let table: Table = json.clone().try_into().unwrap();
+        assert_eq!(table["b"]["c"], Value::Integer(-3));
+        assert_eq!(table["a"][1], Value::Float(2.5));
+        assert_eq!(JsonValue::from(table), json);
+
+        let local: Table = "t = 07:32:00".parse().unwrap();
+        assert_eq!(
+            JsonValue::from(local),
+            json_parse(r#"{ "t": "07:32:00" }"#).unwrap()
+        );
+
+        assert_eq!(
+            TryInto::<Table>::try_into(json_parse(r#"{ "a": [null] }"#).unwrap()),
+            Err(FromJsonError::InvalidType {
+                expected: "a value other than null",
+                found: ValueKind::Null,
+            })
+        );
+        assert!(TryInto::<Table>::try_into(JsonValue::Array(vec![])).is_err());
+    }
+}
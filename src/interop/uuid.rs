@@ -0,0 +1,110 @@
+use super::super::convert::{FromJson, FromJsonError, ToJson};
+use super::super::{JsonPointer, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use uuid_crate::Uuid;
+
+impl JsonValue {
+    /// Reads a UUID out of a string value, in any of the forms `Uuid::parse_str` accepts.
+    /// Returns `None` for anything else.  Enabled by the `uuid` feature.
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        match self {
+            JsonValue::Text(text) => Uuid::parse_str(text).ok(),
+            _ => None,
+        }
+    }
+
+    /// Reads the UUID that `path` points to, with an error naming the path if it is missing or
+    /// is not a UUID.  Enabled by the `uuid` feature.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, FromJsonError};
+    ///
+    /// fn main() {
+    ///     let json = json_parse(r#"{ "users": [{ "id": "67e55044-10b1-426f-9247-bb680e5fe0c8" }, { "id": 7 }] }"#).unwrap();
+    ///
+    ///     let id = json.uuid_at(&"/users/0/id".parse().unwrap()).unwrap();
+    ///     assert_eq!(id.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    ///
+    ///     assert_eq!(
+    ///         json.uuid_at(&"/users/1/id".parse().unwrap()),
+    ///         Err(FromJsonError::Message("/users/1/id: expected a UUID string, found Number".into()))
+    ///     );
+    ///     assert_eq!(
+    ///         json.uuid_at(&"/users/2/id".parse().unwrap()),
+    ///         Err(FromJsonError::MissingField("/users/2/id".into()))
+    ///     );
+    /// }
+    /// ```
+    pub fn uuid_at(&self, path: &JsonPointer) -> Result<Uuid, FromJsonError> {
+        let value = path
+            .resolve(self)
+            .ok_or_else(|| FromJsonError::MissingField(path.to_string()))?;
+        Uuid::from_json(value).map_err(|err| {
+            FromJsonError::Message(match err {
+                FromJsonError::InvalidType { expected, found } => {
+                    format!("{}: expected {}, found {:?}", path, expected, found)
+                }
+                FromJsonError::Message(message) => format!("{}: {}", path, message),
+                err => format!("{}: {:?}", path, err),
+            })
+        })
+    }
+}
+
+/// Writes the UUID in its hyphenated lowercase form.
+impl From<Uuid> for JsonValue {
+    fn from(uuid: Uuid) -> Self {
+        JsonValue::Text(uuid.hyphenated().to_string().into())
+    }
+}
+
+impl ToJson for Uuid {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::from(*self)
+    }
+}
+
+/// Reads a UUID string, as with [`JsonValue::as_uuid`](../enum.JsonValue.html#method.as_uuid).
+impl FromJson for Uuid {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Text(text) => Uuid::parse_str(text)
+                .map_err(|err| FromJsonError::Message(format!("invalid UUID: {}", err))),
+            _ => Err(FromJsonError::invalid_type("a UUID string", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{FromJson, FromJsonError};
+    use crate::{json_parse, JsonValue};
+    use uuid_crate::Uuid;
+
+    #[test]
+    fn uuid_conversions() {
+        let uuid = Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        let json = JsonValue::from(uuid);
+        assert_eq!(
+            json,
+            JsonValue::Text("67e55044-10b1-426f-9247-bb680e5fe0c8".into())
+        );
+        assert_eq!(json.as_uuid(), Some(uuid));
+        assert_eq!(
+            JsonValue::Text("67E5504410B1426F9247BB680E5FE0C8".into()).as_uuid(),
+            Some(uuid)
+        );
+        assert_eq!(JsonValue::Null.as_uuid(), None);
+
+        let json = json_parse(r#"{ "ids": ["not-a-uuid"] }"#).unwrap();
+        assert!(matches!(
+            Uuid::from_json(json.get("ids").unwrap().get_ind(0).unwrap()),
+            Err(FromJsonError::Message(ref message)) if message.starts_with("invalid UUID: ")
+        ));
+        assert!(matches!(
+            json.uuid_at(&"/ids/0".parse().unwrap()),
+            Err(FromJsonError::Message(ref message)) if message.starts_with("/ids/0: invalid UUID: ")
+        ));
+    }
+}
@@ -0,0 +1,449 @@
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) messages, with the envelope checked
+//! against the specification and params, results and error data left as `JsonValue`s.
+//!
+//! Validation failures are reported as the `Error` a server is meant to answer them with, so an
+//! invalid message can be turned straight into a response.
+use super::convert::ToJson;
+use super::{json_parse, JsonMap, JsonNumber, JsonValue};
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Identifies a request so its response can be matched to it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Id {
+    /// A numeric id; the specification advises against fractions
+    Number(JsonNumber),
+    /// A string id
+    Text(String),
+    /// A null id, which responses use when the request's id could not be read
+    Null,
+}
+
+/// A call that expects a response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    /// The name of the method to call
+    pub method: String,
+    /// An array of positional or an object of named arguments
+    pub params: Option<JsonValue>,
+    /// The id the response will carry
+    pub id: Id,
+}
+
+/// A call that does not expect a response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// The name of the method to call
+    pub method: String,
+    /// An array of positional or an object of named arguments
+    pub params: Option<JsonValue>,
+}
+
+/// The answer to a request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    /// The id of the request this answers
+    pub id: Id,
+    /// The result of the call, or why it failed
+    pub result: Result<JsonValue, Error>,
+}
+
+/// Why a call failed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    /// What kind of error occurred; the codes from -32768 to -32000 are reserved
+    pub code: i64,
+    /// A short description of the error
+    pub message: String,
+    /// Further information about the error
+    pub data: Option<JsonValue>,
+}
+
+/// Writes the message and code, followed by the data if it is a string.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)?;
+        match self.data {
+            Some(JsonValue::Text(ref data)) => write!(f, ": {}", data),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+/// Any single JSON-RPC message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// A call that expects a response
+    Request(Request),
+    /// A call that does not expect a response
+    Notification(Notification),
+    /// The answer to a request
+    Response(Response),
+}
+
+/// What a JSON-RPC text holds: one message, or a batch of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Payload {
+    /// A single message
+    Single(Message),
+    /// A batch, in which each member is checked on its own
+    Batch(Vec<Result<Message, Error>>),
+}
+
+impl Error {
+    /// Invalid JSON was received.
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The JSON sent is not a valid message.
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// The method does not exist or is not available.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// The params are not valid for the method.
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// An internal JSON-RPC error.
+    pub const INTERNAL_ERROR: i64 = -32603;
+
+    /// Creates an error without data.
+    pub fn new<S: Into<String>>(code: i64, message: S) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Creates one of the errors defined by the specification, with its usual message.
+    pub fn standard(code: i64) -> Self {
+        let message = match code {
+            Error::PARSE_ERROR => "Parse error",
+            Error::INVALID_REQUEST => "Invalid Request",
+            Error::METHOD_NOT_FOUND => "Method not found",
+            Error::INVALID_PARAMS => "Invalid params",
+            Error::INTERNAL_ERROR => "Internal error",
+            _ => "Server error",
+        };
+        Self::new(code, message)
+    }
+
+    fn invalid_request(reason: &str) -> Self {
+        Self {
+            data: Some(JsonValue::Text(reason.into())),
+            ..Self::standard(Error::INVALID_REQUEST)
+        }
+    }
+
+    /// Reads an error object.
+    pub fn from_json(value: &JsonValue) -> Result<Self, Error> {
+        let code = match value.get("code").and_then(JsonValue::as_i64) {
+            Some(code) => code,
+            None => return Err(Error::invalid_request("error code must be an integer")),
+        };
+        let message = match value.get("message") {
+            Some(JsonValue::Text(message)) => message.clone(),
+            _ => return Err(Error::invalid_request("error message must be a string")),
+        };
+        Ok(Self {
+            code,
+            message: message.into(),
+            data: value.get("data").cloned(),
+        })
+    }
+}
+
+impl Id {
+    fn from_json(value: &JsonValue) -> Result<Self, Error> {
+        match *value {
+            JsonValue::Number(n) => Ok(Id::Number(n)),
+            JsonValue::Text(ref s) => Ok(Id::Text(s.to_string())),
+            JsonValue::Null => Ok(Id::Null),
+            _ => Err(Error::invalid_request(
+                "id must be a string, number or null",
+            )),
+        }
+    }
+}
+
+impl Response {
+    /// Creates a successful response.
+    pub fn success(id: Id, result: JsonValue) -> Self {
+        Self {
+            id,
+            result: Ok(result),
+        }
+    }
+
+    /// Creates a failed response.
+    pub fn error(id: Id, error: Error) -> Self {
+        Self {
+            id,
+            result: Err(error),
+        }
+    }
+}
+
+impl Message {
+    /// Reads and validates one message.
+    pub fn from_json(value: &JsonValue) -> Result<Self, Error> {
+        let object = match *value {
+            JsonValue::Object(ref object) => object,
+            _ => return Err(Error::invalid_request("message must be an object")),
+        };
+        if object.get("jsonrpc") != Some(&JsonValue::Text("2.0".into())) {
+            return Err(Error::invalid_request("jsonrpc must be \"2.0\""));
+        }
+
+        let id = match object.get("id") {
+            Some(id) => Some(Id::from_json(id)?),
+            None => None,
+        };
+        if let Some(method) = object.get("method") {
+            let method = match *method {
+                JsonValue::Text(ref method) => method.clone(),
+                _ => return Err(Error::invalid_request("method must be a string")),
+            };
+            let params = match object.get("params") {
+                Some(params @ &JsonValue::Array(_)) | Some(params @ &JsonValue::Object(_)) => {
+                    Some(params.clone())
+                }
+                Some(_) => return Err(Error::invalid_request("params must be structured")),
+                None => None,
+            };
+            return Ok(match id {
+                Some(id) => Message::Request(Request {
+                    method: method.into(),
+                    params,
+                    id,
+                }),
+                None => Message::Notification(Notification {
+                    method: method.into(),
+                    params,
+                }),
+            });
+        }
+
+        let id = id.ok_or_else(|| Error::invalid_request("response must have an id"))?;
+        let result = match (object.get("result"), object.get("error")) {
+            (Some(result), None) => Ok(result.clone()),
+            (None, Some(error)) => Err(Error::from_json(error)?),
+            _ => {
+                return Err(Error::invalid_request(
+                    "response must have exactly one of result and error",
+                ))
+            }
+        };
+        Ok(Message::Response(Response { id, result }))
+    }
+}
+
+/// Parses a JSON-RPC text, which holds a single message or a batch of them.
+///
+/// Text that is not JSON fails with a parse error, and anything that cannot be read as a message
+/// or batch at all with an invalid request error.  A server answers both of those with a single
+/// response with a null id.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::convert::ToJson;
+/// use json_rs::jsonrpc::{parse, Error, Id, Message, Payload, Response};
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let text = r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#;
+///     let request = match parse(text).unwrap() {
+///         Payload::Single(Message::Request(request)) => request,
+///         _ => unreachable!(),
+///     };
+///     assert_eq!(request.method, "subtract");
+///
+///     let response = Response::success(request.id, JsonValue::from(19));
+///     assert_eq!(response.to_json().get("result"), Some(&JsonValue::from(19)));
+///
+///     let error = parse("[]").unwrap_err();
+///     assert_eq!(error.code, Error::INVALID_REQUEST);
+///     let response = Response::error(Id::Null, error);
+///     assert_eq!(response.to_json().get("id"), Some(&JsonValue::Null));
+/// }
+/// ```
+pub fn parse(text: &str) -> Result<Payload, Error> {
+    let value = json_parse(text).map_err(|_| Error::standard(Error::PARSE_ERROR))?;
+    match value {
+        JsonValue::Array(ref members) if members.is_empty() => {
+            Err(Error::invalid_request("batch must not be empty"))
+        }
+        JsonValue::Array(ref members) => Ok(Payload::Batch(
+            members.iter().map(Message::from_json).collect(),
+        )),
+        value => Message::from_json(&value).map(Payload::Single),
+    }
+}
+
+/// Starts a message object with its version member.
+fn envelope() -> JsonMap {
+    let mut object = JsonMap::new();
+    object.insert("jsonrpc".into(), JsonValue::Text("2.0".into()));
+    object
+}
+
+fn call(object: &mut JsonMap, method: &str, params: &Option<JsonValue>) {
+    object.insert("method".into(), JsonValue::Text(method.into()));
+    if let Some(ref params) = *params {
+        object.insert("params".into(), params.clone());
+    }
+}
+
+impl ToJson for Id {
+    fn to_json(&self) -> JsonValue {
+        match *self {
+            Id::Number(n) => JsonValue::Number(n),
+            Id::Text(ref s) => JsonValue::Text(s.as_str().into()),
+            Id::Null => JsonValue::Null,
+        }
+    }
+}
+
+impl ToJson for Request {
+    fn to_json(&self) -> JsonValue {
+        let mut object = envelope();
+        call(&mut object, &self.method, &self.params);
+        object.insert("id".into(), self.id.to_json());
+        JsonValue::Object(Box::new(object))
+    }
+}
+
+impl ToJson for Notification {
+    fn to_json(&self) -> JsonValue {
+        let mut object = envelope();
+        call(&mut object, &self.method, &self.params);
+        JsonValue::Object(Box::new(object))
+    }
+}
+
+impl ToJson for Error {
+    fn to_json(&self) -> JsonValue {
+        let mut object = JsonMap::new();
+        object.insert("code".into(), JsonValue::Number(self.code.into()));
+        object.insert(
+            "message".into(),
+            JsonValue::Text(self.message.as_str().into()),
+        );
+        if let Some(ref data) = self.data {
+            object.insert("data".into(), data.clone());
+        }
+        JsonValue::Object(Box::new(object))
+    }
+}
+
+impl ToJson for Response {
+    fn to_json(&self) -> JsonValue {
+        let mut object = envelope();
+        match self.result {
+            Ok(ref result) => object.insert("result".into(), result.clone()),
+            Err(ref error) => object.insert("error".into(), error.to_json()),
+        };
+        object.insert("id".into(), self.id.to_json());
+        JsonValue::Object(Box::new(object))
+    }
+}
+
+impl ToJson for Message {
+    fn to_json(&self) -> JsonValue {
+        match *self {
+            Message::Request(ref request) => request.to_json(),
+            Message::Notification(ref notification) => notification.to_json(),
+            Message::Response(ref response) => response.to_json(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Error, Id, Message, Notification, Payload, Request, Response};
+    use crate::convert::ToJson;
+    use crate::{json_parse, JsonNumber, JsonValue};
+
+    #[test]
+    fn jsonrpc_messages() {
+        let request = Request {
+            method: "update".into(),
+            params: Some(json_parse("[1, 2]").unwrap()),
+            id: Id::Text("a".into()),
+        };
+        let text = request.to_json().to_string();
+        assert_eq!(
+            parse(&text).unwrap(),
+            Payload::Single(Message::Request(request))
+        );
+
+        let notification = Notification {
+            method: "ping".into(),
+            params: None,
+        };
+        let value = notification.to_json();
+        assert_eq!(value.get("id"), None);
+        assert_eq!(
+            Message::from_json(&value).unwrap(),
+            Message::Notification(notification)
+        );
+
+        let response = Response::error(
+            Id::Number(JsonNumber::U64(1)),
+            Error::standard(Error::METHOD_NOT_FOUND),
+        );
+        let value = response.to_json();
+        assert_eq!(
+            value.get("error").and_then(|e| e.get("code")),
+            Some(&JsonValue::from(-32601.0))
+        );
+        assert_eq!(
+            Message::from_json(&value).unwrap(),
+            Message::Response(response)
+        );
+    }
+
+    #[test]
+    fn jsonrpc_validation() {
+        let code = |text: &str| parse(text).unwrap_err().code;
+        assert_eq!(
+            code(r#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar", "baz]"#),
+            -32700
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc": "2.0", "method": 1, "params": "bar"}"#),
+            -32600
+        );
+        assert_eq!(code(r#"{"jsonrpc": "1.0", "method": "a"}"#), -32600);
+        assert_eq!(
+            code(r#"{"jsonrpc": "2.0", "method": "a", "params": 3}"#),
+            -32600
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc": "2.0", "method": "a", "id": {}}"#),
+            -32600
+        );
+        assert_eq!(
+            code(r#"{"jsonrpc": "2.0", "result": 1, "error": null, "id": 1}"#),
+            -32600
+        );
+        assert_eq!(code(r#"{"jsonrpc": "2.0", "result": 1}"#), -32600);
+        assert_eq!(code("[]"), -32600);
+        assert_eq!(code("1"), -32600);
+        assert_eq!(
+            parse("[]").unwrap_err().to_string(),
+            "Invalid Request (-32600): batch must not be empty"
+        );
+
+        let batch = match parse(r#"[1, {"jsonrpc": "2.0", "method": "a", "id": null}]"#).unwrap() {
+            Payload::Batch(batch) => batch,
+            _ => unreachable!(),
+        };
+        assert_eq!(batch[0].as_ref().unwrap_err().code, Error::INVALID_REQUEST);
+        assert!(matches!(
+            batch[1],
+            Ok(Message::Request(Request { id: Id::Null, .. }))
+        ));
+    }
+}
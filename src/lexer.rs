@@ -0,0 +1,327 @@
+use super::options::ParseOptions;
+use super::span::{Place, Span, Spanned};
+use super::stack::pending::{BoolStack, NullStack, NumberStack, PendingStack, TextStack};
+use super::stack::IntoJson;
+use super::{JsonError, JsonString, JsonValue};
+use std::iter::Peekable;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str::CharIndices;
+
+/// A structural character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Punct {
+    ArrayStart,
+    ArrayEnd,
+    ObjectStart,
+    ObjectEnd,
+    Colon,
+    Comma,
+}
+
+impl Punct {
+    pub fn from_char(c: char) -> Option<Self> {
+        Some(match c {
+            '[' => Punct::ArrayStart,
+            ']' => Punct::ArrayEnd,
+            '{' => Punct::ObjectStart,
+            '}' => Punct::ObjectEnd,
+            ':' => Punct::Colon,
+            ',' => Punct::Comma,
+            _ => return None,
+        })
+    }
+}
+
+/// Walks over JSON source one character at a time, tracking both the byte offset used in spans
+/// and the character index, line and column used in `JsonError`s.  Scalars are recognized with
+/// the same pending stacks `json_parse` uses, so the two always agree on what a valid token is.
+#[derive(Debug)]
+pub(crate) struct Cursor<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    place: Place,
+    borrow_text: bool,
+    /// Only how precisely numbers are read is taken from these; the caller handles the rest
+    options: ParseOptions,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Self {
+            source,
+            chars: source.char_indices().peekable(),
+            place: Place::default(),
+            borrow_text: false,
+            options,
+        }
+    }
+
+    /// Makes `scalar` skip over strings without escapes instead of copying them, returning an
+    /// empty `Text` whose contents the caller slices out of the source by span.  A string that
+    /// is really empty slices to the same thing, and one with escapes is never empty.
+    pub fn borrow_text(&mut self) {
+        self.borrow_text = true;
+    }
+
+    /// The text being read.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The byte offset of the next character.
+    pub fn offset(&mut self) -> usize {
+        let len = self.source.len();
+        self.chars.peek().map(|&(i, _)| i).unwrap_or(len)
+    }
+
+    /// Where the next character is.
+    pub fn place(&self) -> Place {
+        self.place
+    }
+
+    /// An error for the source ending.
+    pub fn eof_err(&self) -> JsonError {
+        let rest = &self.source[self.place.offset..];
+        self.place.then(Place::find(rest, usize::MAX)).eof_err()
+    }
+
+    pub fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let place = &mut self.place;
+        self.chars.next().map(|(_, c)| {
+            place.advance(c);
+            c
+        })
+    }
+
+    /// Consumes any whitespace, returning its span if there was some.
+    pub fn whitespace(&mut self) -> Option<Span> {
+        let start = self.offset();
+        while self.peek().filter(|c| c.is_whitespace()).is_some() {
+            self.bump();
+        }
+        Some(Span::new(start, self.offset())).filter(|span| !span.is_empty())
+    }
+
+    /// Consumes a `//` or `/* */` comment, returning its span if there was one.
+    pub fn comment(&mut self) -> Result<Option<Span>, JsonError> {
+        let start = self.offset();
+        let rest = &self.source[start..];
+        let len = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(ind) => ind + 4,
+                None => return Err(self.eof_err()),
+            }
+        } else {
+            return Ok(None);
+        };
+
+        while self.offset() < start + len {
+            self.bump();
+        }
+        Ok(Some(Span::new(start, start + len)))
+    }
+
+    /// Consumes everything left in the source.
+    pub fn skip_rest(&mut self) {
+        while self.bump().is_some() {}
+    }
+
+    /// Consumes the next character if it is structural.
+    pub fn punct(&mut self) -> Option<(Punct, Span)> {
+        let start = self.offset();
+        self.peek().and_then(Punct::from_char).map(|punct| {
+            self.bump();
+            (punct, Span::new(start, self.offset()))
+        })
+    }
+
+    /// Consumes a string, number, boolean, or null.
+    pub fn scalar(&mut self) -> Result<Spanned<JsonValue>, JsonError> {
+        let start = self.offset();
+        let place = self.place;
+        let value = match self.bump() {
+            Some('"') if self.borrow_text && self.plain_text() => {
+                JsonValue::Text(JsonString::new())
+            }
+            Some('"') => self.finish(Box::new(TextStack::new()))?,
+            Some('t') => self.finish(Box::new(BoolStack::init_true()))?,
+            Some('f') => self.finish(Box::new(BoolStack::init_false()))?,
+            Some('n') => self.finish(Box::new(NullStack::init_n()))?,
+            Some(c @ '-') | Some(c @ '0'..='9') => self.number(c, place)?,
+            Some(c) => return Err(place.token_err(c)),
+            None => return Err(self.eof_err()),
+        };
+        Ok(Spanned::new(value, Span::new(start, self.offset())))
+    }
+
+    /// Consumes the rest of a string that has no escapes, leaving the cursor where it was if the
+    /// string has one or is not valid.
+    fn plain_text(&mut self) -> bool {
+        let mut chars = self.chars.clone();
+        let mut place = self.place;
+        while let Some((_, c)) = chars.next() {
+            place.advance(c);
+            match c {
+                '"' => {
+                    self.chars = chars;
+                    self.place = place;
+                    return true;
+                }
+                '\\' => return false,
+                _ if c < ' ' => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
+    fn finish<S: PendingStack<char>>(&mut self, mut stack: Box<S>) -> Result<JsonValue, JsonError> {
+        loop {
+            let place = self.place;
+            match self.bump().map(|c| stack.push(c)) {
+                Some(Ok(true)) => return Ok(stack.into_json().unwrap()),
+                Some(Ok(false)) => (),
+                Some(Err(c)) => return Err(place.token_err(c)),
+                None => return Err(self.eof_err()),
+            }
+        }
+    }
+
+    /// Reads the rest of a number that starts with `first` at `start`.
+    fn number(&mut self, first: char, start: Place) -> Result<JsonValue, JsonError> {
+        let mut stack = NumberStack::with_options(ParseOptions {
+            json5: false,
+            ..self.options
+        });
+        stack.push(first).unwrap();
+
+        while let Some(c) = self.peek().filter(|c| stack.can_push(*c)) {
+            let place = self.place;
+            self.bump();
+            stack.push(c).map_err(|c| place.token_err(c))?;
+        }
+
+        if stack.out_of_range() {
+            return Err(start.range_err());
+        }
+        let place = self.place;
+        let next = self.peek();
+        Box::new(stack).into_json().map_err(|()| match next {
+            Some(c) => place.token_err(c),
+            None => self.eof_err(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cursor, Punct};
+    use crate::span::Span;
+    use crate::{JsonError, JsonValue};
+
+    #[test]
+    fn cursor_scalar() {
+        let tests = vec![
+            (r#""a\"b" "#, Ok((JsonValue::Text("a\"b".into()), 6))),
+            ("-0.5e3,", Ok((JsonValue::from(-500.0), 6))),
+            ("0123", Ok((JsonValue::from(0.0), 1))),
+            ("null", Ok((JsonValue::Null, 4))),
+            ("falsey", Ok((JsonValue::Boolean(false), 5))),
+            (
+                "nul",
+                Err(JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 3,
+                    offset: 3,
+                }),
+            ),
+            (
+                "trUe",
+                Err(JsonError::UnexpectedToken {
+                    character: 'U',
+                    location: 2,
+                    line: 0,
+                    column: 2,
+                    offset: 2,
+                }),
+            ),
+            (
+                "1.]",
+                Err(JsonError::UnexpectedToken {
+                    character: ']',
+                    location: 2,
+                    line: 0,
+                    column: 2,
+                    offset: 2,
+                }),
+            ),
+            (
+                "-",
+                Err(JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 1,
+                    offset: 1,
+                }),
+            ),
+            (
+                "ώ",
+                Err(JsonError::UnexpectedToken {
+                    character: 'ώ',
+                    location: 0,
+                    line: 0,
+                    column: 0,
+                    offset: 0,
+                }),
+            ),
+        ];
+
+        for (st, result) in tests.into_iter() {
+            let mut cursor = Cursor::new(st);
+            assert_eq!(cursor.scalar().map(|s| (s.value, s.span.end)), result);
+        }
+    }
+
+    #[test]
+    fn cursor_positions() {
+        let mut cursor = Cursor::new(" ώ\t[");
+        assert_eq!(cursor.whitespace(), Some(Span::new(0, 1)));
+        assert_eq!(cursor.whitespace(), None);
+        assert!(cursor.scalar().is_err());
+        assert_eq!((cursor.offset(), cursor.place().location), (3, 2));
+        assert_eq!(cursor.whitespace(), Some(Span::new(3, 4)));
+        assert_eq!(cursor.punct(), Some((Punct::ArrayStart, Span::new(4, 5))));
+        assert_eq!(cursor.punct(), None);
+    }
+
+    #[test]
+    fn cursor_comments() {
+        let mut cursor = Cursor::new("// ώ\n/* a */1/ ");
+        assert_eq!(cursor.comment(), Ok(Some(Span::new(0, 5))));
+        assert_eq!(cursor.whitespace(), Some(Span::new(5, 6)));
+        assert_eq!(cursor.comment(), Ok(Some(Span::new(6, 13))));
+        assert_eq!(cursor.place().location, 12);
+        assert_eq!(cursor.comment(), Ok(None));
+        cursor.scalar().unwrap();
+        assert_eq!(cursor.comment(), Ok(None));
+        assert_eq!(cursor.peek(), Some('/'));
+        assert_eq!(
+            Cursor::new("/* a *").comment(),
+            Err(JsonError::UnexpectedEOF {
+                line: 0,
+                column: 6,
+                offset: 6,
+            })
+        );
+    }
+}
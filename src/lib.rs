@@ -1,38 +1,327 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("json-rs needs either the `std` or the `alloc` feature");
+
+#[cfg(feature = "actix-web")]
+extern crate actix_web;
+#[cfg(feature = "axum")]
+extern crate axum;
+#[cfg(feature = "bson")]
+extern crate bson as bson_crate;
+#[cfg(feature = "http")]
+extern crate bytes;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "actix-web")]
+extern crate futures_core;
+#[cfg(feature = "http")]
+extern crate http_body;
+#[cfg(feature = "derive")]
+extern crate json_rs_derive;
+#[cfg(feature = "proptest")]
+#[macro_use]
+extern crate proptest;
+#[cfg(feature = "prost")]
+extern crate prost_types as prost_types_crate;
+#[cfg(feature = "xml")]
+extern crate quick_xml;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "reqwest")]
+extern crate reqwest;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde as serde_crate;
+#[cfg(all(test, feature = "derive"))]
+extern crate self as json_rs;
+#[cfg(feature = "serde_json")]
+extern crate serde_json as serde_json_crate;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
+#[cfg(feature = "time")]
+extern crate time as time_crate;
+#[cfg(all(test, feature = "http"))]
+extern crate tokio;
+#[cfg(feature = "toml")]
+extern crate toml as toml_crate;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "uuid")]
+extern crate uuid as uuid_crate;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+extern crate hashbrown;
+
+/// Stands in for the standard library without the `std` feature, so that the rest of the crate
+/// can name `std` paths either way.  Objects are then `hashbrown` maps.
+#[cfg(not(feature = "std"))]
+#[allow(unused_imports)]
+mod std {
+    pub use alloc::{borrow, boxed, fmt, rc, slice, str, string, sync, vec};
+    pub use core::*;
+
+    pub mod collections {
+        pub use alloc::collections::*;
+        pub use hashbrown::{hash_map, HashMap, HashSet};
+    }
+
+    pub mod prelude {
+        pub mod v1 {
+            pub use alloc::borrow::ToOwned;
+            pub use alloc::boxed::Box;
+            pub use alloc::string::{String, ToString};
+            pub use alloc::vec::Vec;
+            pub use core::prelude::v1::*;
+        }
+    }
+}
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+#[cfg(not(feature = "preserve_order"))]
 use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(feature = "std")]
+use std::io;
+use std::mem;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
 
+#[macro_use]
+mod trace;
+#[macro_use]
+mod macros;
+
+pub mod arena;
+pub mod borrowed;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compress;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod convert;
+pub mod csv;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod document;
+pub mod edit;
+mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod filter;
+pub mod folding;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod incremental;
+mod interop;
+pub mod jsonrpc;
+mod lexer;
+#[cfg(feature = "std")]
+pub mod lines;
+#[cfg(feature = "preserve_order")]
+pub mod map;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+mod number;
+pub mod observe;
+pub mod options;
+pub mod outline;
+pub mod pointer;
+mod preview;
+pub mod push;
+#[cfg(feature = "rand")]
+pub mod random;
+mod raw;
+pub mod recover;
+pub mod rewrite;
+#[cfg(feature = "std")]
+mod scan;
+#[cfg(feature = "std")]
+pub mod seq;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod shared;
+pub mod span;
+#[cfg(feature = "std")]
+pub mod sse;
 mod stack;
+#[cfg(feature = "std")]
+pub mod stream;
+mod string;
+pub mod symbols;
+#[cfg(feature = "std")]
+pub mod template;
+pub mod tokens;
+mod total;
+pub mod transaction;
+#[cfg(feature = "ubjson")]
+pub mod ubjson;
+pub mod urlencoded;
+pub mod visit;
+#[cfg(any(feature = "actix-web", feature = "axum"))]
+pub mod web;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
 use self::stack::{IntoJson, PendingItem};
 
+// what `json!` expands to refers to, which is in `alloc` rather than the prelude without `std`
+#[doc(hidden)]
+pub mod __private {
+    pub use std::boxed::Box;
+    pub use std::vec::Vec;
+}
+
+pub use self::arena::{ArenaValue, JsonArena, Members, NodeId};
+pub use self::borrowed::{json_parse_borrowed, JsonValueRef};
+pub use self::convert::{FromJson, FromJsonError, ToJson};
+pub use self::document::{json_parse_document, Comment, Document};
+pub use self::edit::{EditError, JsonEdit};
+pub use self::folding::{folding_ranges, FoldingRange};
+pub use self::incremental::{reparse, TextEdit};
+#[cfg(feature = "preserve_order")]
+pub use self::map::JsonMap;
+pub use self::number::JsonNumber;
+pub use self::observe::{JsonChange, ObservedJson};
+pub use self::options::{DuplicateKeys, FormatConfig, ParseOptions};
+pub use self::outline::{outline, OutlineKey, OutlineNode, ValueKind};
+pub use self::pointer::{JsonPointer, JsonPointerError};
+pub use self::push::PushParser;
+pub use self::raw::RawJson;
+pub use self::recover::json_parse_all_errors;
+pub use self::rewrite::TokenRewriter;
+pub use self::shared::ArcJson;
+use self::span::Place;
+pub use self::span::{
+    json_parse_spanned, LineIndex, Location, Position, Span, Spanned, SpannedValue,
+};
+pub use self::string::JsonString;
+pub use self::tokens::{tokenize, tokenize_with, JsonLexer, Token, TokenKind, Tokens};
+pub use self::total::TotalJson;
+pub use self::transaction::JsonTransaction;
+pub use self::visit::{json_visit, json_visit_with, JsonVisitor};
+
+/// Derives `ToJson` and `FromJson` for structs and enums, enabled by the `derive` feature.
+///
+/// Structs become objects, tuple structs become arrays (or the single value they wrap), and unit
+/// structs become `null`.  Unit enum variants become their name as a string, while any other
+/// variant becomes an object with the variant name as its only key.  Fields and variants accept
+/// these attributes:
+///
+/// * `#[json(rename = "name")]` uses a different key or variant name
+/// * `#[json(default)]` uses `Default::default()` when the key is missing, and
+///   `#[json(default = "path")]` calls a function instead.  Otherwise a missing key is only
+///   allowed for types that convert from `null`, such as `Option`
+/// * `#[json(skip)]` leaves the field out of the JSON, filling it with `Default::default()`
+/// * `#[json(flatten)]` merges the members of the field into the surrounding object
+/// ```
+/// extern crate json_rs;
+/// use json_rs::convert::{from_value, to_value};
+/// use json_rs::{json_parse, FromJson, ToJson};
+///
+/// #[derive(ToJson, FromJson, Debug, PartialEq)]
+/// enum Role {
+///     Admin,
+///     Guest { until: u32 },
+/// }
+///
+/// #[derive(ToJson, FromJson, Debug, PartialEq)]
+/// struct Meta {
+///     created: u32,
+/// }
+///
+/// #[derive(ToJson, FromJson, Debug, PartialEq)]
+/// struct User {
+///     #[json(rename = "userName")]
+///     name: String,
+///     #[json(default)]
+///     roles: Vec<Role>,
+///     email: Option<String>,
+///     #[json(skip)]
+///     cache: Vec<u8>,
+///     #[json(flatten)]
+///     meta: Meta,
+/// }
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "userName": "ada", "created": 1815 }"#).unwrap();
+///     let mut user: User = from_value(&json).unwrap();
+///     assert_eq!(user.roles, vec![]);
+///     assert_eq!(user.email, None);
+///
+///     user.roles = vec![Role::Admin, Role::Guest { until: 1852 }];
+///     assert_eq!(
+///         to_value(&user),
+///         json_parse(r#"{
+///             "userName": "ada",
+///             "roles": ["Admin", { "Guest": { "until": 1852 } }],
+///             "email": null,
+///             "created": 1815
+///         }"#)
+///         .unwrap()
+///     );
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use json_rs_derive::{FromJson, ToJson};
+
+/// The map an object's members are kept in: a `HashMap`, which keeps them in no particular order.
+/// With the `preserve_order` feature it is instead `map::JsonMap`, which keeps keys in the order
+/// they were inserted, so that parsing and serializing a document leaves its keys where they were.
+#[cfg(not(feature = "preserve_order"))]
+pub type JsonMap = HashMap<JsonString, JsonValue>;
+
+/// The bytes a hash table with room for `capacity` entries of `entry` bytes allocates.  A table
+/// keeps at least an eighth of its buckets empty, and has a control byte per bucket plus one
+/// trailing group of them.
+fn table_size(capacity: usize, entry: usize) -> usize {
+    match capacity {
+        0 => 0,
+        capacity => {
+            let buckets = (capacity * 8 / 7).next_power_of_two();
+            buckets * (entry + 1) + 16
+        }
+    }
+}
+
 /// A JSON value.
 #[derive(Debug, Clone, PartialEq)]
 pub enum JsonValue {
-    /// A JSON string value.
-    Text(String),
-    /// A numeric JSON value.
-    Number(f64),
+    /// A JSON string value.  Like object keys, short strings are stored inline.
+    Text(JsonString),
+    /// A numeric JSON value, with integers kept exactly.
+    Number(JsonNumber),
     /// A JSON boolean value.
     Boolean(bool),
     /// The JSON null value.
     Null,
     /// A JSON array.
     Array(Vec<JsonValue>),
-    /// A JSON object.
-    Object(HashMap<String, JsonValue>),
+    /// A JSON object, boxed so that it does not widen every other variant as well.
+    Object(Box<JsonMap>),
+    /// Checked JSON text that is written out as it is, without being parsed.
+    Raw(RawJson),
 }
 
 impl JsonValue {
     /// Gets a reference to the JSON value at a specific key.
     /// ```
+    /// #[macro_use]
     /// extern crate json_rs;
     /// use json_rs::JsonValue;
-    /// use std::collections::HashMap;
     ///
     /// fn main() {
-    ///     let json = JsonValue::Object({
-    ///         let mut map = HashMap::new();
-    ///         map.insert("key".into(), JsonValue::Boolean(true));
-    ///         map
-    ///     });
+    ///     let json = json!({ "key": true });
     ///     assert_eq!(json.get("key"), Some(&JsonValue::Boolean(true)));
     ///     assert_eq!(json.get("nonexistent"), None);
     ///
@@ -50,22 +339,18 @@ impl JsonValue {
 
     /// Gets a mutable reference to the JSON value at a specific key.
     /// ```
+    /// #[macro_use]
     /// extern crate json_rs;
-    /// use std::collections::HashMap;
     /// use json_rs::JsonValue;
     ///
     /// fn main() {
-    ///     let mut json = JsonValue::Object({
-    ///         let mut map = HashMap::new();
-    ///         map.insert("number".into(), JsonValue::Number(10f64));
-    ///         map
-    ///     });
-    ///     assert_eq!(json.get("number"), Some(&JsonValue::Number(10f64)));
+    ///     let mut json = json!({ "number": 10 });
+    ///     assert_eq!(json.get("number"), Some(&JsonValue::from(10)));
     ///
-    ///     if let Some(JsonValue::Number(value)) = json.get_mut("number") {
-    ///         *value += 5.0;
+    ///     if let Some(value) = json.get_mut("number") {
+    ///         *value = JsonValue::from(15);
     ///     }
-    ///     assert_eq!(json.get("number"), Some(&JsonValue::Number(15f64)));
+    ///     assert_eq!(json.get("number"), Some(&JsonValue::from(15)));
     /// }
     /// ```
     pub fn get_mut(&mut self, key: &str) -> Option<&mut Self> {
@@ -85,7 +370,7 @@ impl JsonValue {
     ///     let json = JsonValue::Array(vec![
     ///         JsonValue::Null,
     ///         JsonValue::Text("aaaskjw".into()),
-    ///         JsonValue::Number(10.0),
+    ///         JsonValue::from(10),
     ///         JsonValue::Boolean(true),
     ///     ]);
     ///     assert_eq!(json.get_ind(1), Some(&JsonValue::Text("aaaskjw".into())));
@@ -95,7 +380,7 @@ impl JsonValue {
     /// ```
     pub fn get_ind(&self, ind: usize) -> Option<&Self> {
         match self {
-            JsonValue::Object(map) => map.get(&ind.to_string()),
+            JsonValue::Object(map) => map.get(ind.to_string().as_str()),
             JsonValue::Array(array) => array.get(ind),
             _ => None,
         }
@@ -118,68 +403,741 @@ impl JsonValue {
     /// ```
     pub fn get_ind_mut(&mut self, ind: usize) -> Option<&mut Self> {
         match self {
-            JsonValue::Object(map) => map.get_mut(&ind.to_string()),
+            JsonValue::Object(map) => map.get_mut(ind.to_string().as_str()),
             JsonValue::Array(array) => array.get_mut(ind),
             _ => None,
         }
     }
-}
 
-fn unicode_escape(c: char) -> String {
-    format!("\\u{:0>4}", format!("{:x}", c as u32))
+    /// Gets the text of a string value.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let json = json!({ "name": "ada", "born": 1815 });
+    ///     assert_eq!(json["name"].as_str(), Some("ada"));
+    ///     assert_eq!(json["born"].as_str(), None);
+    /// }
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to the text of a string value.
+    pub fn as_text_mut(&mut self) -> Option<&mut JsonString> {
+        match self {
+            JsonValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Gets a number value.
+    pub fn as_number(&self) -> Option<JsonNumber> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Gets a number value as an `f64`, which rounds integers beyond 2^53.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(|n| n.as_f64())
+    }
+
+    /// Gets a number value as an `i64`, if it is a whole number in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(|n| n.as_i64())
+    }
+
+    /// Gets a number value as a `u64`, if it is a whole number in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(|n| n.as_u64())
+    }
+
+    /// Gets a mutable reference to a number value held as an `F64`.  Integers are held as `I64`
+    /// or `U64` and give `None`; reach them with [`as_i64_mut`](#method.as_i64_mut) or
+    /// [`as_u64_mut`](#method.as_u64_mut), or replace the number to change how it is held.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut json = json!({ "ratio": 0.5, "count": 1 });
+    ///     if let Some(ratio) = json["ratio"].as_f64_mut() {
+    ///         *ratio *= 3.0;
+    ///     }
+    ///     assert_eq!(json["ratio"].as_f64(), Some(1.5));
+    ///     assert_eq!(json["count"].as_f64_mut(), None);
+    /// }
+    /// ```
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            JsonValue::Number(JsonNumber::F64(n)) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to a number value held as an `I64`, which parsing uses for
+    /// negative whole numbers.
+    pub fn as_i64_mut(&mut self) -> Option<&mut i64> {
+        match self {
+            JsonValue::Number(JsonNumber::I64(n)) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to a number value held as a `U64`, which parsing uses for whole
+    /// numbers from zero up.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut json = json!({ "count": 1 });
+    ///     if let Some(count) = json["count"].as_u64_mut() {
+    ///         *count += 1;
+    ///     }
+    ///     assert_eq!(json["count"].as_u64(), Some(2));
+    /// }
+    /// ```
+    pub fn as_u64_mut(&mut self) -> Option<&mut u64> {
+        match self {
+            JsonValue::Number(JsonNumber::U64(n)) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Gets a boolean value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to a boolean value.
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            JsonValue::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Gets the items of an array.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut json = json!({ "tags": ["a", "b"] });
+    ///     assert_eq!(json["tags"].as_array().map(Vec::len), Some(2));
+    ///
+    ///     json["tags"].as_array_mut().unwrap().push(json!("c"));
+    ///     assert_eq!(json, json!({ "tags": ["a", "b", "c"] }));
+    ///     assert_eq!(json.as_array(), None);
+    /// }
+    /// ```
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to the items of an array.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Gets the members of an object.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut json = json!({ "a": 1, "b": 2 });
+    ///     assert_eq!(json.as_object().map(|map| map.len()), Some(2));
+    ///
+    ///     json.as_object_mut().unwrap().remove("a");
+    ///     assert_eq!(json, json!({ "b": 2 }));
+    ///     assert_eq!(json["b"].as_object(), None);
+    /// }
+    /// ```
+    pub fn as_object(&self) -> Option<&JsonMap> {
+        match self {
+            JsonValue::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to the members of an object.
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonMap> {
+        match self {
+            JsonValue::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Gets the text of a raw value, which is not parsed.
+    pub fn as_raw(&self) -> Option<&RawJson> {
+        match self {
+            JsonValue::Raw(raw) => Some(raw),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is `null`.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let json = json!({ "a": null, "b": [1] });
+    ///     assert!(json["a"].is_null());
+    ///     assert!(json["missing"].is_null());
+    ///     assert!(json["b"].is_array() && !json["b"].is_object());
+    ///     assert!(json["b"][0].is_number());
+    /// }
+    /// ```
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    /// Whether the value is a string.
+    pub fn is_string(&self) -> bool {
+        matches!(self, JsonValue::Text(_))
+    }
+
+    /// Whether the value is a number.
+    pub fn is_number(&self) -> bool {
+        matches!(self, JsonValue::Number(_))
+    }
+
+    /// Whether the value is a boolean.
+    pub fn is_boolean(&self) -> bool {
+        matches!(self, JsonValue::Boolean(_))
+    }
+
+    /// Whether the value is an array.
+    pub fn is_array(&self) -> bool {
+        matches!(self, JsonValue::Array(_))
+    }
+
+    /// Whether the value is an object.
+    pub fn is_object(&self) -> bool {
+        matches!(self, JsonValue::Object(_))
+    }
+
+    /// Gets the number as an `i128`, if it is a whole number in range.  Integers beyond 64 bits
+    /// are parsed as `f64`s, so for those this is the integer the number was rounded to, unless
+    /// the `decimal` feature's
+    /// [`ParseOptions::exact_numbers`](options/struct.ParseOptions.html#structfield.exact_numbers)
+    /// kept every digit.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::JsonValue;
+    ///
+    /// fn main() {
+    ///     assert_eq!(JsonValue::from(-2f64.powi(100)).as_i128(), Some(-(1 << 100)));
+    ///     assert_eq!(JsonValue::from(1e39).as_i128(), None);
+    ///     assert_eq!(JsonValue::from(0.5).as_i128(), None);
+    /// }
+    /// ```
+    pub fn as_i128(&self) -> Option<i128> {
+        i128::from_json(self).ok()
+    }
+
+    /// Gets the number as a `u128`, if it is a whole number in range, as with
+    /// [`as_i128`](#method.as_i128).
+    pub fn as_u128(&self) -> Option<u128> {
+        u128::from_json(self).ok()
+    }
+
+    /// Compares two values using a total equivalence on numbers.
+    ///
+    /// Unlike `==`, every `NaN` is equal to every other `NaN` and `-0.0` is distinct from `0.0`,
+    /// so this comparison is reflexive and suitable for deduplication.  Call
+    /// [`normalize`](#method.normalize) on both sides first if `-0.0` and `0.0` should match.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::JsonValue;
+    ///
+    /// fn main() {
+    ///     let nan = JsonValue::Array(vec![JsonValue::from(f64::NAN)]);
+    ///     assert_ne!(nan, nan.clone());
+    ///     assert!(nan.total_eq(&nan.clone()));
+    ///
+    ///     assert_eq!(JsonValue::from(-0.0), JsonValue::from(0.0));
+    ///     assert!(!JsonValue::from(-0.0).total_eq(&JsonValue::from(0.0)));
+    /// }
+    /// ```
+    pub fn total_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => a.total_eq(b),
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.total_eq(b))
+            }
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(key, a)| b.get(key).filter(|b| a.total_eq(b)).is_some())
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Rewrites every number in the value to a canonical form: `-0.0` becomes `0.0` and all
+    /// `NaN`s become the same `NaN`.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::JsonValue;
+    ///
+    /// fn main() {
+    ///     let mut json = JsonValue::Array(vec![JsonValue::from(-0.0)]);
+    ///     json.normalize();
+    ///     assert!(json.total_eq(&JsonValue::Array(vec![JsonValue::from(0.0)])));
+    /// }
+    /// ```
+    pub fn normalize(&mut self) {
+        match self {
+            JsonValue::Number(JsonNumber::F64(n)) if n.is_nan() => *n = f64::NAN,
+            JsonValue::Number(JsonNumber::F64(n)) if *n == 0.0 => *n = 0.0,
+            JsonValue::Array(array) => array.iter_mut().for_each(JsonValue::normalize),
+            JsonValue::Object(map) => map.values_mut().for_each(JsonValue::normalize),
+            _ => (),
+        }
+    }
+
+    /// Estimates the heap memory, in bytes, owned by the value and everything nested in it:
+    /// string buffers, array buffers including their spare capacity, and each object's box and
+    /// hash table.  The value itself is not counted, so scalars and short strings report zero.
+    /// Hash tables are sized from their capacity, so their share is approximate.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonValue};
+    ///
+    /// fn main() {
+    ///     assert_eq!(JsonValue::Text("short".into()).deep_size_of(), 0);
+    ///     assert!(JsonValue::Text("x".repeat(100).into()).deep_size_of() >= 100);
+    ///
+    ///     let small = json_parse(r#"{ "list": [1, 2] }"#).unwrap();
+    ///     let large = json_parse(r#"{ "list": [1, 2], "more": [3, 4, 5, 6] }"#).unwrap();
+    ///     assert!(large.deep_size_of() > small.deep_size_of());
+    /// }
+    /// ```
+    pub fn deep_size_of(&self) -> usize {
+        use std::mem::size_of;
+
+        match self {
+            JsonValue::Text(text) => text.heap_size(),
+            JsonValue::Array(array) => {
+                array.capacity() * size_of::<JsonValue>()
+                    + array.iter().map(JsonValue::deep_size_of).sum::<usize>()
+            }
+            JsonValue::Object(map) => {
+                #[cfg(not(feature = "preserve_order"))]
+                let buffers = table_size(map.capacity(), size_of::<(JsonString, JsonValue)>());
+                #[cfg(feature = "preserve_order")]
+                let buffers = map.buffer_size();
+                size_of::<JsonMap>()
+                    + buffers
+                    + map
+                        .iter()
+                        .map(|(key, value)| key.heap_size() + value.deep_size_of())
+                        .sum::<usize>()
+            }
+            JsonValue::Raw(raw) => raw.get().len(),
+            _ => 0,
+        }
+    }
+
+    /// Takes the value out, leaving `Null` in its place.  Since `JsonValue` implements `Drop`,
+    /// its contents can't be moved out of it by a pattern; match on a taken value's variant by
+    /// reference instead and take what it holds with `std::mem::take`.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonValue};
+    /// use std::mem;
+    ///
+    /// fn main() {
+    ///     let mut json = json_parse(r#"{ "list": [1, 2] }"#).unwrap();
+    ///     let mut list = json["list"].take();
+    ///     assert_eq!(json, json_parse(r#"{ "list": null }"#).unwrap());
+    ///
+    ///     if let JsonValue::Array(ref mut items) = list {
+    ///         let items: Vec<JsonValue> = mem::take(items);
+    ///         assert_eq!(items.len(), 2);
+    ///     }
+    /// }
+    /// ```
+    pub fn take(&mut self) -> JsonValue {
+        ::std::mem::replace(self, JsonValue::Null)
+    }
 }
 
-fn escape_str(text: &str) -> String {
-    use std::borrow::Cow;
-
-    format!(
-        "\"{}\"",
-        text.chars()
-            .map(|c| -> Cow<str> {
-                match c {
-                    '\n' => "\\n".into(),
-                    '\t' => "\\t".into(),
-                    '\r' => "\\r".into(),
-                    '\x08' => "\\b".into(),
-                    '\x0C' => "\\f".into(),
-                    '\\' => "\\\\".into(),
-                    '"' => "\\\"".into(),
-                    '\x00'...'\x1F' => unicode_escape(c).into(),
-                    _ => c.to_string().into(),
+impl Drop for JsonValue {
+    /// Drops nested arrays and objects from a worklist instead of recursing into them, so that
+    /// dropping a value nested thousands of levels deep cannot overflow the stack.
+    fn drop(&mut self) {
+        let mut pending = match self {
+            JsonValue::Array(array) if !array.is_empty() => ::std::mem::take(array),
+            JsonValue::Object(map) if !map.is_empty() => {
+                map.drain().map(|(_, value)| value).collect()
+            }
+            _ => return,
+        };
+        while let Some(mut value) = pending.pop() {
+            match value {
+                JsonValue::Array(ref mut array) => pending.append(array),
+                JsonValue::Object(ref mut map) => {
+                    pending.extend(map.drain().map(|(_, value)| value))
                 }
-            })
-            .collect::<String>()
-    )
+                _ => {}
+            }
+        }
+    }
+}
+
+static NULL: JsonValue = JsonValue::Null;
+
+/// Indexes by key, as [`get`](enum.JsonValue.html#method.get) does.  Keys that aren't there, and
+/// anything that isn't an object or an array, index to `Null` rather than panicking, so indexing
+/// can be chained as deep as needed.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let json = json!({ "user": { "name": "ada", "roles": ["admin"] } });
+///     assert_eq!(json["user"]["name"], json!("ada"));
+///     assert_eq!(json["user"]["roles"][0], json!("admin"));
+///     assert_eq!(json["user"]["roles"]["0"], json!("admin"));
+///     assert_eq!(json["nobody"]["name"], JsonValue::Null);
+/// }
+/// ```
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Indexes by array index, as [`get_ind`](enum.JsonValue.html#method.get_ind) does, with the
+/// same policy as indexing by key: anything that isn't there is `Null`.
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, ind: usize) -> &JsonValue {
+        self.get_ind(ind).unwrap_or(&NULL)
+    }
 }
 
-impl ToString for JsonValue {
-    /// Serializes a JsonValue
-    fn to_string(&self) -> String {
+/// Indexes an object mutably by key, inserting `Null` if the key isn't there.  `Null` itself is
+/// first replaced by an empty object, so assigning through a chain of missing keys builds the
+/// objects along the way.
+///
+/// # Panics
+///
+/// Panics if the value is an array and the key isn't one of its indices, or if it is a string,
+/// number or boolean.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let mut json = JsonValue::Null;
+///     json["server"]["port"] = json!(8080);
+///     json["server"]["hosts"] = json!(["a"]);
+///     json["server"]["hosts"][0] = json!("b");
+///     assert_eq!(json, json!({ "server": { "port": 8080, "hosts": ["b"] } }));
+/// }
+/// ```
+impl IndexMut<&str> for JsonValue {
+    fn index_mut(&mut self, key: &str) -> &mut JsonValue {
+        if let JsonValue::Null = self {
+            *self = JsonValue::Object(Box::default());
+        }
         match self {
-            JsonValue::Text(text) => escape_str(text),
-            JsonValue::Null => "null".to_string(),
-            JsonValue::Boolean(b) => b.to_string(),
-            JsonValue::Number(n) => n.to_string(),
-            JsonValue::Array(array) => format!(
-                "[{}]",
-                array
-                    .iter()
-                    .map(|json| json.to_string())
-                    .collect::<Vec<String>>()
-                    .join(",")
+            JsonValue::Object(map) => map.entry(key.into()).or_insert(JsonValue::Null),
+            JsonValue::Array(array) => {
+                let len = array.len();
+                match key.parse::<usize>() {
+                    Ok(ind) if ind < len => &mut array[ind],
+                    _ => panic!("key {:?} is not an index of an array of {} items", key, len),
+                }
+            }
+            other => panic!(
+                "cannot index into a {:?} value with key {:?}",
+                ValueKind::of(other),
+                key
             ),
-            JsonValue::Object(map) => format!(
-                "{{{}}}",
-                map.iter()
-                    .map(|(key, val)| format!("{}:{}", escape_str(key), val.to_string()))
-                    .collect::<Vec<String>>()
-                    .join(",")
+        }
+    }
+}
+
+/// Indexes an array mutably.  Objects are indexed by the index written as a key, which is
+/// inserted as `Null` if it isn't there.
+///
+/// # Panics
+///
+/// Panics if the index is out of bounds of an array, or if the value is not an array or an object.
+impl IndexMut<usize> for JsonValue {
+    fn index_mut(&mut self, ind: usize) -> &mut JsonValue {
+        match self {
+            JsonValue::Array(array) => {
+                let len = array.len();
+                array.get_mut(ind).unwrap_or_else(|| {
+                    panic!(
+                        "index {} is out of bounds of an array of {} items",
+                        ind, len
+                    )
+                })
+            }
+            JsonValue::Object(map) => map.entry(ind.to_string().into()).or_insert(JsonValue::Null),
+            other => panic!(
+                "cannot index into a {:?} value with index {}",
+                ValueKind::of(other),
+                ind
             ),
         }
     }
 }
 
+/// Writes `text` as a quoted JSON string, copying runs of characters that need no escape in one
+/// piece.
+fn write_escaped<W: fmt::Write>(out: &mut W, text: &str) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (ind, c) in text.char_indices() {
+        let escape = match c {
+            '\n' => "\\n",
+            '\t' => "\\t",
+            '\r' => "\\r",
+            '\x08' => "\\b",
+            '\x0C' => "\\f",
+            '\\' => "\\\\",
+            '"' => "\\\"",
+            '\x00'..='\x1F' => "",
+            _ => continue,
+        };
+        out.write_str(&text[start..ind])?;
+        if escape.is_empty() {
+            write!(out, "\\u{:04x}", c as u32)?;
+        } else {
+            out.write_str(escape)?;
+        }
+        start = ind + c.len_utf8();
+    }
+    out.write_str(&text[start..])?;
+    out.write_char('"')
+}
+
+fn escape_str(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    write_escaped(&mut escaped, text).unwrap();
+    escaped
+}
+
+/// Writes a value as JSON, on one line without a `format` and otherwise laid out by it.
+fn write_json<W: fmt::Write>(
+    out: &mut W,
+    json: &JsonValue,
+    format: Option<&FormatConfig>,
+    depth: usize,
+) -> fmt::Result {
+    let newline = |out: &mut W, depth: usize| -> fmt::Result {
+        if let Some(format) = format {
+            out.write_char('\n')?;
+            let indent = if format.use_tabs { '\t' } else { ' ' };
+            for _ in 0..depth * format.indent_width {
+                out.write_char(indent)?;
+            }
+        }
+        Ok(())
+    };
+    match json {
+        JsonValue::Text(text) => write_escaped(out, text),
+        JsonValue::Null => out.write_str("null"),
+        JsonValue::Boolean(b) => write!(out, "{}", b),
+        // JSON has no way to write infinities or `NaN`, which JSON5 parses
+        JsonValue::Number(JsonNumber::F64(n)) if !n.is_finite() => out.write_str("null"),
+        JsonValue::Number(n) => write!(out, "{}", n),
+        JsonValue::Raw(raw) => out.write_str(raw.get()),
+        JsonValue::Array(array) if array.is_empty() => out.write_str("[]"),
+        JsonValue::Array(array) => {
+            out.write_char('[')?;
+            for (ind, item) in array.iter().enumerate() {
+                if ind > 0 {
+                    out.write_char(',')?;
+                }
+                newline(out, depth + 1)?;
+                write_json(out, item, format, depth + 1)?;
+            }
+            newline(out, depth)?;
+            out.write_char(']')
+        }
+        JsonValue::Object(map) if map.is_empty() => out.write_str("{}"),
+        JsonValue::Object(map) => {
+            let colon = match format {
+                Some(format) if format.space_after_colon => ": ",
+                _ => ":",
+            };
+            out.write_char('{')?;
+            for (ind, (key, value)) in map.iter().enumerate() {
+                if ind > 0 {
+                    out.write_char(',')?;
+                }
+                newline(out, depth + 1)?;
+                write_escaped(out, key)?;
+                out.write_str(colon)?;
+                write_json(out, value, format, depth + 1)?;
+            }
+            newline(out, depth)?;
+            out.write_char('}')
+        }
+    }
+}
+
+/// Serializes a whole value, within a `tracing` span when the feature is on.
+fn serialize<W: fmt::Write>(
+    out: &mut W,
+    json: &JsonValue,
+    format: Option<&FormatConfig>,
+) -> fmt::Result {
+    trace_span!("json_serialize");
+    #[cfg(feature = "tracing")]
+    let out = &mut trace::Counted::new(out);
+    write_json(out, json, format, 0)?;
+    trace_event!(bytes = out.bytes, "serialized");
+    Ok(())
+}
+
+/// Serializes a `JsonValue` on one line, or with the alternate flag, `{:#}`, laid out by the
+/// default [`FormatConfig`](options/struct.FormatConfig.html).  Object members are written in
+/// the order the object's map iterates them, and infinite and `NaN` numbers as `null`.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+///
+/// fn main() {
+///     let json = json!({ "list": [1, "two"], "empty": {} });
+///     assert_eq!(json["list"].to_string(), r#"[1,"two"]"#);
+///     assert_eq!(format!("{:#}", json["list"]), "[\n  1,\n  \"two\"\n]");
+///     assert_eq!(format!("{:#}", json["empty"]), "{}");
+/// }
+/// ```
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            serialize(f, self, Some(&FormatConfig::default()))
+        } else {
+            serialize(f, self, None)
+        }
+    }
+}
+
+impl JsonValue {
+    /// Serializes the value laid out by the default
+    /// [`FormatConfig`](options/struct.FormatConfig.html): indented by two spaces, with a space
+    /// after each colon.
+    pub fn to_string_pretty(&self) -> String {
+        self.to_string_with(FormatConfig::default())
+    }
+
+    /// Serializes the value laid out by `format`.
+    pub fn to_string_with(&self, format: FormatConfig) -> String {
+        let mut json = String::new();
+        serialize(&mut json, self, Some(&format)).unwrap();
+        json
+    }
+
+    /// Serializes the value on one line into `writer`, without building the whole text in
+    /// memory first.  Output is buffered, and flushed before this returns.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut out = Vec::new();
+    ///     json!([1, { "a": null }]).to_writer(&mut out).unwrap();
+    ///     assert_eq!(out, br#"[1,{"a":null}]"#);
+    ///
+    ///     out.clear();
+    ///     json!([true]).to_writer_pretty(&mut out).unwrap();
+    ///     assert_eq!(out, b"[\n  true\n]");
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_writer<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_io(writer, self, None)
+    }
+
+    /// Serializes the value into `writer` laid out by the default
+    /// [`FormatConfig`](options/struct.FormatConfig.html), as
+    /// [`to_writer`](#method.to_writer) does.
+    #[cfg(feature = "std")]
+    pub fn to_writer_pretty<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_io(writer, self, Some(&FormatConfig::default()))
+    }
+
+    /// Serializes the value into `writer` laid out by `format`, as
+    /// [`to_writer`](#method.to_writer) does.
+    #[cfg(feature = "std")]
+    pub fn to_writer_with<W: io::Write>(&self, writer: W, format: FormatConfig) -> io::Result<()> {
+        write_io(writer, self, Some(&format))
+    }
+}
+
+/// Serializes a value into an `io::Write` through a buffer, keeping the first error it returns,
+/// since `fmt::Write` can't carry one.
+#[cfg(feature = "std")]
+fn write_io<W: io::Write>(
+    writer: W,
+    json: &JsonValue,
+    format: Option<&FormatConfig>,
+) -> io::Result<()> {
+    use std::io::Write;
+
+    struct Adapter<W: io::Write> {
+        inner: io::BufWriter<W>,
+        error: Option<io::Error>,
+    }
+
+    impl<W: io::Write> fmt::Write for Adapter<W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        inner: io::BufWriter::new(writer),
+        error: None,
+    };
+    match serialize(&mut adapter, json, format) {
+        Ok(()) => adapter.inner.flush(),
+        Err(fmt::Error) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed"))),
+    }
+}
+
 /// Describes all possible errors that could occur while parsing a JSON string
+///
+/// Lines and columns start from 0, and columns count characters, as in
+/// [`Position`](span/struct.Position.html).
 #[derive(Clone, Debug, PartialEq)]
 pub enum JsonError {
     /// An unexpected character was found in the JSON
@@ -188,16 +1146,168 @@ pub enum JsonError {
         character: char,
         /// The index where the char was found
         location: usize,
+        /// The line the char is on
+        line: usize,
+        /// The index of the char within its line
+        column: usize,
+        /// The byte offset of the char
+        offset: usize,
     },
     /// Unexpected end of input
-    UnexpectedEOF,
+    UnexpectedEOF {
+        /// The last line of the input
+        line: usize,
+        /// The number of chars on the last line
+        column: usize,
+        /// The length of the input in bytes
+        offset: usize,
+    },
+    /// An object had a key it already had, and
+    /// [`DuplicateKeys::Error`](options/enum.DuplicateKeys.html) was chosen
+    DuplicateKey {
+        /// The repeated key
+        key: String,
+        /// The index where the second occurrence of the key starts
+        location: usize,
+        /// The line the key is on
+        line: usize,
+        /// The index of the key within its line
+        column: usize,
+        /// The byte offset of the key
+        offset: usize,
+    },
+    /// An array or object opened more levels deep than
+    /// [`ParseOptions::max_depth`](options/struct.ParseOptions.html#structfield.max_depth)
+    /// allows
+    DepthLimitExceeded {
+        /// The depth that was allowed
+        limit: usize,
+        /// The index of the bracket that went too deep
+        location: usize,
+        /// The line the bracket is on
+        line: usize,
+        /// The index of the bracket within its line
+        column: usize,
+        /// The byte offset of the bracket
+        offset: usize,
+    },
+    /// A number had more significant digits, or a larger or smaller exponent, than a
+    /// [`Decimal`](decimal/struct.Decimal.html) holds, and
+    /// [`ParseOptions::exact_numbers`](options/struct.ParseOptions.html#structfield.exact_numbers)
+    /// asked for it to be read exactly
+    NumberOutOfRange {
+        /// The index where the number starts
+        location: usize,
+        /// The line the number is on
+        line: usize,
+        /// The index of the number within its line
+        column: usize,
+        /// The byte offset of the number
+        offset: usize,
+    },
+}
+
+impl JsonError {
+    /// Fills in the line, column and offset of an error made by `tok_err` or `eof_err`, which
+    /// only know the character location, from the source it was found in.
+    pub(crate) fn locate(self, source: &str) -> Self {
+        match self {
+            JsonError::UnexpectedToken {
+                character,
+                location,
+                ..
+            } => Place::find(source, location).token_err(character),
+            JsonError::UnexpectedEOF { .. } => JsonError::eof(source),
+            JsonError::DuplicateKey { key, location, .. } => {
+                Place::find(source, location).duplicate_err(key)
+            }
+            JsonError::DepthLimitExceeded {
+                limit, location, ..
+            } => Place::find(source, location).depth_err(limit),
+            JsonError::NumberOutOfRange { location, .. } => {
+                Place::find(source, location).range_err()
+            }
+        }
+    }
+
+    /// An error for `source` ending before its value does.
+    pub(crate) fn eof(source: &str) -> Self {
+        Place::find(source, usize::MAX).eof_err()
+    }
 }
 
+/// Describes the error with a line and column counted from 1, as editors show them.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let error = json_parse("[1,\n 2 3]").unwrap_err();
+///     assert_eq!(error.to_string(), "unexpected character '3' at line 2, column 4");
+///
+///     let error = json_parse("[1,").unwrap_err();
+///     assert_eq!(error.to_string(), "unexpected end of input at line 1, column 4");
+/// }
+/// ```
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::UnexpectedToken {
+                character,
+                line,
+                column,
+                ..
+            } => write!(
+                f,
+                "unexpected character {:?} at line {}, column {}",
+                character,
+                line + 1,
+                column + 1
+            ),
+            JsonError::UnexpectedEOF { line, column, .. } => write!(
+                f,
+                "unexpected end of input at line {}, column {}",
+                line + 1,
+                column + 1
+            ),
+            JsonError::DuplicateKey {
+                key, line, column, ..
+            } => write!(
+                f,
+                "duplicate key {:?} at line {}, column {}",
+                key,
+                line + 1,
+                column + 1
+            ),
+            JsonError::DepthLimitExceeded {
+                limit,
+                line,
+                column,
+                ..
+            } => write!(
+                f,
+                "nested more than {} levels deep at line {}, column {}",
+                limit,
+                line + 1,
+                column + 1
+            ),
+            JsonError::NumberOutOfRange { line, column, .. } => write!(
+                f,
+                "number too precise to read exactly at line {}, column {}",
+                line + 1,
+                column + 1
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for JsonError {}
+
 /// Deserializes a JSON string.
 /// ```
+/// #[macro_use]
 /// extern crate json_rs;
-/// use json_rs::JsonValue;
-/// use std::collections::HashMap;
 ///
 /// fn main() {
 ///     let json = json_rs::json_parse(
@@ -210,176 +1320,445 @@ pub enum JsonError {
 ///
 ///     assert_eq!(
 ///         json,
-///         Ok(JsonValue::Object({
-///             let mut map = HashMap::new();
-///             map.insert("key".into(), JsonValue::Number(10.0));
-///             map.insert("otherKey".into(), JsonValue::Text("value".into()));
-///             map.insert(
-///                 "aaa".into(),
-///                 JsonValue::Array(vec![
-///                     JsonValue::Number(1.0),
-///                     JsonValue::Number(2.0),
-///                     JsonValue::Number(3.0),
-///                 ]),
-///             );
-///             map
+///         Ok(json!({
+///             "key": 10,
+///             "otherKey": "value",
+///             "aaa": [1, 2, 3]
 ///         }))
 ///     );
 /// }
 /// ```
 pub fn json_parse(json_str: &str) -> Result<JsonValue, JsonError> {
-    json_parse_internal(json_str, 0)
+    json_parse_with(json_str, ParseOptions::default())
 }
 
+/// Deserializes a JSON string, accepting the syntax allowed by `options`.  Comments are read as
+/// whitespace, so errors are located in the source as it was written.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::{json_parse_with, ParseOptions};
+///
+/// fn main() {
+///     let options = ParseOptions {
+///         comments: true,
+///         ..ParseOptions::default()
+///     };
+///     let config = r#"{
+///         // seconds
+///         "timeout": 30, /* "retries": 3, */
+///         "url": "http://example.com/*"
+///     }"#;
+///     assert_eq!(
+///         json_parse_with(config, options),
+///         Ok(json!({ "timeout": 30, "url": "http://example.com/*" }))
+///     );
+///     assert!(json_parse_with(config, ParseOptions::default()).is_err());
+/// }
+/// ```
+pub fn json_parse_with(json_str: &str, options: ParseOptions) -> Result<JsonValue, JsonError> {
+    trace_span!("json_parse", bytes = json_str.len());
+    trace_error!(json_parse_internal(json_str, options).map_err(|e| e.locate(json_str)))
+}
+
+/// Parses a JSON string, as [`json_parse`](fn.json_parse.html) does.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::{JsonError, JsonValue};
+///
+/// fn main() {
+///     assert_eq!("[1,2,3]".parse::<JsonValue>(), Ok(json!([1, 2, 3])));
+///     assert_eq!(
+///         "[1,".parse::<JsonValue>(),
+///         Err(JsonError::UnexpectedEOF { line: 0, column: 3, offset: 3 })
+///     );
+/// }
+/// ```
+impl FromStr for JsonValue {
+    type Err = JsonError;
+
+    fn from_str(json_str: &str) -> Result<Self, JsonError> {
+        json_parse(json_str)
+    }
+}
+
+/// An error for `c` at character `loc`, whose line, column and offset are filled in by
+/// `JsonError::locate`.
 fn tok_err(c: char, loc: usize) -> JsonError {
-    JsonError::UnexpectedToken {
-        character: c,
+    Place {
         location: loc,
+        ..Place::default()
     }
+    .token_err(c)
 }
 
-fn json_parse_internal(json_str: &str, mut pos: usize) -> Result<JsonValue, JsonError> {
+/// An error for the end of input, whose position is filled in by `JsonError::locate`.
+fn eof_err() -> JsonError {
+    Place::default().eof_err()
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+/// Whether `key` can be left unquoted in JSON5.  Escapes in identifiers are not supported.
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(is_identifier_start)
+        && chars.all(|c| is_identifier_start(c) || c.is_alphanumeric())
+}
+
+/// Where a comment being skipped by `json_parse_internal` is.
+#[derive(Clone, Copy)]
+enum InComment {
+    Line,
+    /// At the `*` that opens a block comment
+    Opening,
+    Block {
+        /// Whether the last character was a `*`
+        star: bool,
+    },
+}
+
+/// What has been read of the member an array or object is in the middle of.
+#[derive(Default)]
+struct Member {
+    /// Whether any of the member has been read, whitespace included
+    started: bool,
+    /// Where the first character other than whitespace is
+    first: Option<usize>,
+    /// Whether the member must be a key, and only whitespace has been read
+    next_must_be_quote: bool,
+    /// The text so far of a member that could still be an unquoted JSON5 key
+    key: Option<String>,
+}
+
+/// A value being parsed at one level of nesting.
+struct Frame {
+    processing: Option<stack::PendingItem>,
+    /// The member being read, if the value is an array or object
+    member: Member,
+    /// The first error in the value.  It is only reported when the member the value is in ends,
+    /// so errors come out in the order they would if each member were parsed on its own.
+    failed: Option<JsonError>,
+}
+
+impl Frame {
+    fn new() -> Self {
+        Frame {
+            processing: None,
+            member: Member::default(),
+            failed: None,
+        }
+    }
+
+    fn obj_arr(&mut self) -> &mut dyn stack::ObjArrStack {
+        match self.processing {
+            Some(PendingItem::ObjArr(ref mut stack)) => &mut **stack,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reads `c` as part of the member an array or object is in the middle of, checking that a
+    /// key starts with a quote.
+    fn push_member(&mut self, c: char, pos: usize, options: ParseOptions) -> Result<(), JsonError> {
+        let next_must_be_key = self.obj_arr().next_must_be_key();
+        let member = &mut self.member;
+        if !member.started {
+            member.started = true;
+            member.next_must_be_quote = next_must_be_key;
+            member.key = Some(String::new()).filter(|_| options.json5 && next_must_be_key);
+        }
+
+        let starts_key = c == '"' || (options.json5 && (c == '\'' || is_identifier_start(c)));
+        if member.next_must_be_quote && !c.is_whitespace() && !starts_key {
+            return Err(tok_err(c, pos));
+        }
+        member.next_must_be_quote = member.next_must_be_quote && c.is_whitespace();
+        if !c.is_whitespace() && member.first.is_none() {
+            member.first = Some(pos);
+        }
+        if c.is_whitespace() || is_identifier_start(c) || c.is_alphanumeric() {
+            if let Some(key) = &mut member.key {
+                key.push(c);
+            }
+        } else {
+            member.key = None;
+        }
+        Ok(())
+    }
+}
+
+/// Finishes the member the array or object in `frames[ind]` is in the middle of, at a delimiter
+/// or closing bracket `c`: a value, or a key, which JSON5 may leave unquoted and which may not
+/// repeat one the object already has.  Gives `None` if the member is empty.
+fn take_member(
+    frames: &mut Vec<Frame>,
+    ind: usize,
+    c: char,
+    pos: usize,
+) -> Result<Option<stack::ObjArrItem>, JsonError> {
+    let child = frames.drain(ind + 1..).next();
+    let frame = &mut frames[ind];
+    let member = mem::take(&mut frame.member);
+    let first = match member.first {
+        Some(first) => first,
+        None => return Ok(None),
+    };
+    let item = match member.key.filter(|key| is_identifier(key.trim())) {
+        Some(key) => stack::ObjArrItem::Key(key.trim().into()),
+        None => match child.map(|child| (child.failed, child.processing)) {
+            Some((Some(e), _)) => return Err(e),
+            Some((None, Some(PendingItem::FinalizedJsonValue(value)))) => value.into(),
+            // a member can only end unfinished where the input would have
+            _ => return Err(tok_err(c, pos)),
+        },
+    };
+    if let Some(key) = frame.obj_arr().duplicate_key(&item) {
+        let place = Place {
+            location: first,
+            ..Place::default()
+        };
+        return Err(place.duplicate_err(key.to_string()));
+    }
+    Ok(Some(item))
+}
+
+/// Reads `c` as part of the value in `frame`, which is not an array or object.  `next` is the
+/// character after it.
+fn push_value(
+    frame: &mut Frame,
+    c: char,
+    pos: usize,
+    next: Option<char>,
+    options: ParseOptions,
+) -> Result<(), JsonError> {
     use self::stack::{
         array::ArrayStack,
         object::ObjectStack,
         pending::{BoolStack, NullStack, NumberStack, PendingStack, TextStack},
         PendingItem::*,
-        StackCounter,
     };
-    let mut processing = None;
-    let mut counter = StackCounter::new();
-    let mut chars = json_str.chars().peekable();
+    let finish_number = |stack: NumberStack| -> Result<_, JsonError> {
+        Ok(if next.filter(|c| stack.can_push(*c)).is_some() {
+            Number(stack)
+        } else {
+            if stack.out_of_range() {
+                let start = Place {
+                    location: pos + 1 - stack.chars_read(),
+                    ..Place::default()
+                };
+                return Err(start.range_err());
+            }
+            FinalizedJsonValue(
+                Box::new(stack)
+                    .into_json()
+                    .map_err(|()| next.map(|c| tok_err(c, pos + 1)).unwrap_or_else(eof_err))?,
+            )
+        })
+    };
 
-    let mut error_ind = None;
-    let mut content_str = String::new();
-    let mut next_must_be_quote = false;
+    let last = frame.processing.take();
+    frame.processing = match last {
+        None => match c {
+            '"' | '\'' if options.json5 => Some(Simple(Box::new(TextStack::json5(c)))),
+            '"' => Some(Simple(Box::new(TextStack::new()))),
+            '[' => {
+                let stack = ArrayStack::with_trailing_comma(options.trailing_commas);
+                Some(ObjArr(Box::new(stack)))
+            }
+            '{' => Some(ObjArr(Box::new(ObjectStack::with_options(options)))),
+            't' => Some(Simple(Box::new(BoolStack::init_true()))),
+            'f' => Some(Simple(Box::new(BoolStack::init_false()))),
+            'n' => Some(Simple(Box::new(NullStack::init_n()))),
+            // JSON5 numbers may also start with `+`, a decimal point, `Infinity` or `NaN`
+            '-' | '0'..='9' | '+' | '.' | 'I' | 'N'
+                if c == '-' || c.is_ascii_digit() || options.json5 =>
+            {
+                let mut stack = NumberStack::with_options(options);
+                stack.push(c).unwrap();
+                Some(finish_number(stack)?)
+            }
+            _ if c.is_whitespace() => None,
+            _ => return Err(tok_err(c, pos)),
+        },
+        Some(Simple(mut stack)) => Some(if stack.push(c).map_err(|c| tok_err(c, pos))? {
+            FinalizedJsonValue(stack.into_json().unwrap())
+        } else {
+            stack.into()
+        }),
+        Some(Number(mut stack)) => {
+            stack.push(c).map_err(|_| tok_err(c, pos))?;
+            Some(finish_number(stack)?)
+        }
+        Some(FinalizedJsonValue(_)) if !c.is_whitespace() => return Err(tok_err(c, pos)),
+        Some(ObjArr(_)) => unreachable!(),
+        finalized => finalized,
+    };
+    Ok(())
+}
 
-    while let Some(c) = chars.next() {
-        counter.push(c).map_err(|()| tok_err(c, pos))?;
+/// Reads `c` into the values being parsed, one for each level of nesting it is in, giving the
+/// level whose value has an error.  `level` is how deeply nested `c` is once read, and `next`
+/// is the character after it, or a space if that starts a comment.
+fn push_char(
+    frames: &mut Vec<Frame>,
+    c: char,
+    pos: usize,
+    level: usize,
+    in_string: bool,
+    next: (Option<char>, Option<char>),
+    options: ParseOptions,
+) -> Result<(), (usize, JsonError)> {
+    let live =
+        |frames: &Vec<Frame>, ind: usize| frames.get(ind).is_some_and(|f| f.failed.is_none());
+    let is_bracket = !in_string && (c == ']' || c == '}');
+    let delimiter = Some(level)
+        .filter(|level| !in_string && matches!(c, ',' | ':') && *level > 0)
+        .map(|level| level - 1)
+        .filter(|ind| live(frames, *ind))
+        .and_then(|ind| frames[ind].obj_arr().get_delimiter(c).map(|d| (ind, d)));
 
-        let mut last = processing.take();
-        match last {
-            None => match c {
-                '"' => processing = Some(Simple(Box::new(TextStack::new()))),
-                '[' => processing = Some(ObjArr(Box::new(ArrayStack::new()))),
-                '{' => processing = Some(ObjArr(Box::new(ObjectStack::new()))),
-                't' => processing = Some(Simple(Box::new(BoolStack::init_true()))),
-                'f' => processing = Some(Simple(Box::new(BoolStack::init_false()))),
-                'n' => processing = Some(Simple(Box::new(NullStack::init_n()))),
-                '-' | '0'...'9' => {
-                    let mut stack = NumberStack::new();
-                    stack.push(c).unwrap();
-                    processing = Some(if chars.peek().filter(|c| stack.can_push(**c)).is_some() {
-                        Number(stack)
-                    } else {
-                        FinalizedJsonValue(Box::new(stack).into_json().map_err(|_| {
-                            chars
-                                .peek()
-                                .map(|c| tok_err(*c, pos + 1))
-                                .unwrap_or(JsonError::UnexpectedEOF)
-                        })?)
-                    })
-                }
-                _ if c.is_whitespace() => (),
-                _ => return Err(tok_err(c, pos)),
-            },
-            Some(Simple(mut stack)) => {
-                processing = Some(if stack.push(c).map_err(|c| tok_err(c, pos))? {
-                    FinalizedJsonValue(stack.into_json().unwrap())
-                } else {
-                    stack.into()
-                })
+    if is_bracket {
+        // closes the array or object at `level`, unless that has already failed
+        if live(frames, level) {
+            let item = take_member(frames, level, c, pos).map_err(|e| (level, e))?;
+            let frame = &mut frames[level];
+            if let Some(item) = item {
+                frame
+                    .obj_arr()
+                    .push(item)
+                    .map_err(|_| (level, tok_err(c, pos)))?;
             }
-            Some(ObjArr(mut stack)) => if let Some(delimiter) = stack
-                .get_delimiter(c)
-                .filter(|_| counter.level() == 1 && !counter.in_string())
-            {
-                error_ind
-                    .take()
-                    .filter(|_| content_str.trim().len() > 0)
-                    .ok_or(tok_err(c, pos))
-                    .and_then(|ind| {
-                        json_parse_internal(&content_str, ind).map_err(|e| {
-                            if e == JsonError::UnexpectedEOF {
-                                tok_err(c, pos)
-                            } else {
-                                e
-                            }
-                        })
-                    })
-                    .and_then(|json| {
-                        stack
-                            .push(json.into())
-                            .and_then(|()| stack.push(delimiter))
-                            .map(|_| {
-                                processing = Some(stack.into());
-                                content_str.clear();
-                            })
-                            .map_err(|_| tok_err(c, pos))
-                    })?
-            } else if stack.is_end_char(c) && counter.level() == 0 && !counter.in_string() {
-                if let Some(ind) = error_ind.take().filter(|_| content_str.trim().len() > 0) {
-                    stack
-                        .push(
-                            json_parse_internal(&content_str, ind)
-                                .map_err(|e| {
-                                    if e == JsonError::UnexpectedEOF {
-                                        tok_err(c, pos)
-                                    } else {
-                                        e
-                                    }
-                                })?
-                                .into(),
-                        )
-                        .map_err(|_| tok_err(c, pos))?;
-                }
-                processing = Some(PendingItem::FinalizedJsonValue(
-                    stack.into_json().map_err(|_| tok_err(c, pos))?,
-                ));
-                content_str.clear();
-            } else {
-                if error_ind.is_none() {
-                    error_ind = Some(pos);
-                    next_must_be_quote = stack.next_must_be_key();
-                }
+            let stack = match frame.processing.take() {
+                Some(PendingItem::ObjArr(stack)) => stack,
+                _ => unreachable!(),
+            };
+            let value = stack.into_json().map_err(|_| (level, tok_err(c, pos)))?;
+            frame.processing = Some(PendingItem::FinalizedJsonValue(value));
+        }
+        return Ok(());
+    }
+    if let Some((ind, delimiter)) = delimiter {
+        let item = take_member(frames, ind, c, pos)
+            .and_then(|item| item.ok_or_else(|| tok_err(c, pos)))
+            .map_err(|e| (ind, e))?;
+        let stack = frames[ind].obj_arr();
+        return stack
+            .push(item)
+            .and_then(|()| stack.push(delimiter))
+            .map_err(|_| (ind, tok_err(c, pos)));
+    }
 
-                if next_must_be_quote && !c.is_whitespace() && c != '"' {
-                    return Err(tok_err(c, pos));
-                }
+    // only the innermost array or object can still be at the start of its member
+    let last = frames.len() - 1;
+    if last > 0 {
+        frames[last - 1]
+            .push_member(c, pos, options)
+            .map_err(|e| (last - 1, e))?;
+    }
+    let frame = &mut frames[last];
+    if frame.failed.is_some() {
+        return Ok(());
+    }
+    if let Some(PendingItem::ObjArr(_)) = frame.processing {
+        frame.push_member(c, pos, options).map_err(|e| (last, e))?;
+        frames.push(Frame::new());
+    }
+    // a value in an array or object sees a comment after it as the space it reads as, where the
+    // document's own value sees the comment
+    let ind = frames.len() - 1;
+    let next = if ind == 0 { next.0 } else { next.1 };
+    push_value(&mut frames[ind], c, pos, next, options).map_err(|e| (ind, e))
+}
 
-                content_str.push(c);
-                next_must_be_quote = next_must_be_quote && c.is_whitespace();
-                processing = Some(stack.into());
-            },
-            Some(Number(mut stack)) => {
-                stack.push(c).map_err(|_| tok_err(c, pos))?;
-                processing = Some(if chars.peek().filter(|c| stack.can_push(**c)).is_some() {
-                    Number(stack)
-                } else {
-                    FinalizedJsonValue(Box::new(stack).into_json().map_err(|()| {
-                        chars
-                            .peek()
-                            .map(|c| tok_err(*c, pos + 1))
-                            .unwrap_or(JsonError::UnexpectedEOF)
-                    })?)
-                })
+/// Parses a document in one pass, keeping the value being parsed at each level of nesting on a
+/// stack of its own.  Errors are reported as if each member of an array or object were parsed
+/// separately once it ends, so an error in a member can be overtaken by one in the array or
+/// object around it.
+fn json_parse_internal(json_str: &str, options: ParseOptions) -> Result<JsonValue, JsonError> {
+    use self::stack::{PendingItem::*, StackCounter};
+    let mut counter = if options.json5 {
+        StackCounter::json5()
+    } else {
+        StackCounter::new()
+    };
+    let mut chars = json_str.chars().peekable();
+    let mut frames = vec![Frame::new()];
+    let mut comment = None;
+    let mut pos = 0;
+
+    while let Some(mut c) = chars.next() {
+        // comments read as spaces, so every character after them keeps its position
+        let skip = match (comment, c) {
+            (None, '/') if options.comments && !counter.in_string() => {
+                comment = match chars.peek() {
+                    Some('/') => Some(InComment::Line),
+                    Some('*') => Some(InComment::Opening),
+                    _ => None,
+                };
+                comment.is_some()
+            }
+            (None, _) => false,
+            (Some(InComment::Line), '\n') => {
+                comment = None;
+                false
+            }
+            (Some(InComment::Line), _) => true,
+            (Some(InComment::Opening), _) => {
+                comment = Some(InComment::Block { star: false });
+                true
             }
-            Some(FinalizedJsonValue(_)) if !c.is_whitespace() => {
-                return Err(tok_err(c, pos));
+            (Some(InComment::Block { star }), _) => {
+                comment = match (star, c) {
+                    (true, '/') => None,
+                    _ => Some(InComment::Block { star: c == '*' }),
+                };
+                true
             }
-            Some(FinalizedJsonValue(_)) => processing = last,
+        };
+        if skip {
+            c = ' ';
+        }
+        counter.push(c).map_err(|()| tok_err(c, pos))?;
+        if counter.level() > options.max_depth {
+            let place = Place {
+                location: pos,
+                ..Place::default()
+            };
+            return Err(place.depth_err(options.max_depth));
+        }
+
+        let next = chars.peek().copied();
+        let starts_comment = options.comments
+            && next == Some('/')
+            && matches!(chars.clone().nth(1), Some('/') | Some('*'));
+        let next = (next, if starts_comment { Some(' ') } else { next });
+        let (level, in_string) = (counter.level(), counter.in_string());
+        if let Err((ind, e)) = push_char(&mut frames, c, pos, level, in_string, next, options) {
+            if ind == 0 {
+                return Err(e);
+            }
+            frames.truncate(ind + 1);
+            frames[ind].failed = Some(e);
         }
         pos += 1;
     }
-    if let Some(FinalizedJsonValue(value)) = processing {
-        Ok(value)
-    } else {
-        if let Some(ind) = error_ind.filter(|_| content_str.len() > 0) {
-            // check for syntax errors in any remaining unparsed content_str
-            json_parse_internal(&content_str, ind)?;
+    if let Some(InComment::Opening) | Some(InComment::Block { .. }) = comment {
+        return Err(eof_err());
+    }
+    // an array or object left open reports the first error in the member it was reading, if
+    // there is one
+    for (depth, frame) in frames.into_iter().enumerate() {
+        match (frame.failed, frame.processing) {
+            (Some(e), _) => return Err(e),
+            (None, Some(FinalizedJsonValue(value))) if depth == 0 => return Ok(value),
+            (None, Some(ObjArr(_))) if frame.member.started => (),
+            _ => break,
         }
-        Err(JsonError::UnexpectedEOF)
     }
+    Err(eof_err())
 }
 
 #[cfg(test)]
@@ -0,0 +1,452 @@
+//! Reading and writing [JSON Lines](https://jsonlines.org) (also known as NDJSON), where each
+//! line holds one JSON value, either once through or following a file as it grows.
+use super::{json_parse, JsonError, JsonValue};
+use std::io::{self, BufRead, Read, Write};
+use std::thread;
+use std::time::Duration;
+use std::{error, fmt, str};
+
+/// What a `JsonLines` reader does with lines that are empty or only whitespace.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlankLines {
+    /// Blank lines are passed over, which is the default
+    #[default]
+    Skip,
+    /// Blank lines are reported as `LinesError::Blank`
+    Reject,
+}
+
+/// Describes all possible errors that could occur while reading JSON Lines.
+///
+/// Every error but `Io` is confined to one line, and the reader carries on with the next.
+#[derive(Debug)]
+pub enum LinesError {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// A line was not valid UTF-8
+    InvalidUtf8 {
+        /// The one-based line number
+        line: usize,
+    },
+    /// A line was not a single valid JSON value
+    Parse {
+        /// The one-based line number
+        line: usize,
+        /// Why the line could not be parsed
+        error: JsonError,
+    },
+    /// A line was blank and blank lines are rejected
+    Blank {
+        /// The one-based line number
+        line: usize,
+    },
+}
+
+impl fmt::Display for LinesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinesError::Io(error) => error.fmt(f),
+            LinesError::InvalidUtf8 { line } => write!(f, "line {} is not valid UTF-8", line),
+            LinesError::Parse { line, error } => write!(f, "on line {}: {}", line, error),
+            LinesError::Blank { line } => write!(f, "line {} is blank", line),
+        }
+    }
+}
+
+impl error::Error for LinesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LinesError::Io(error) => Some(error),
+            LinesError::Parse { error, .. } => Some(error),
+            LinesError::InvalidUtf8 { .. } | LinesError::Blank { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for LinesError {
+    fn from(error: io::Error) -> Self {
+        LinesError::Io(error)
+    }
+}
+
+/// An iterator over the values of JSON Lines, created by `json_lines`.
+#[derive(Debug)]
+pub struct JsonLines<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    blank_lines: BlankLines,
+}
+
+/// Reads JSON Lines, yielding one value per line.  Lines may end in `\n` or `\r\n`, and the last
+/// line does not need to end in either.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::lines::{json_lines, BlankLines, LinesError};
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let export = "{\"id\":1}\n\n{\"id\":\n[2]\n";
+///
+///     let values = json_lines(export.as_bytes()).collect::<Vec<_>>();
+///     assert_eq!(values.len(), 3);
+///     assert_eq!(values[0].as_ref().unwrap().get("id"), Some(&JsonValue::from(1)));
+///     assert!(matches!(values[1], Err(LinesError::Parse { line: 3, .. })));
+///
+///     let strict = json_lines(export.as_bytes()).blank_lines(BlankLines::Reject);
+///     assert!(matches!(strict.skip(1).next(), Some(Err(LinesError::Blank { line: 2 }))));
+/// }
+/// ```
+pub fn json_lines<R: BufRead>(reader: R) -> JsonLines<R> {
+    JsonLines {
+        reader,
+        buffer: Vec::new(),
+        line: 0,
+        blank_lines: BlankLines::default(),
+    }
+}
+
+impl<R: BufRead> JsonLines<R> {
+    /// Sets what is done with blank lines.
+    pub fn blank_lines(mut self, policy: BlankLines) -> Self {
+        self.blank_lines = policy;
+        self
+    }
+
+    /// The number of lines read so far, which is the line number of the last value or error.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for JsonLines<R> {
+    type Item = Result<JsonValue, LinesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(error) => return Some(Err(error.into())),
+            }
+            self.line += 1;
+            if let Some(result) = parse_line(&self.buffer, self.line, self.blank_lines) {
+                return Some(result);
+            }
+        }
+    }
+}
+
+/// Parses one line, or returns `None` if it is blank and blank lines are skipped.
+fn parse_line(
+    bytes: &[u8],
+    line: usize,
+    blank_lines: BlankLines,
+) -> Option<Result<JsonValue, LinesError>> {
+    let text = match str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return Some(Err(LinesError::InvalidUtf8 { line })),
+    };
+    if text.trim().is_empty() {
+        return match blank_lines {
+            BlankLines::Skip => None,
+            BlankLines::Reject => Some(Err(LinesError::Blank { line })),
+        };
+    }
+    Some(json_parse(text).map_err(|error| LinesError::Parse { line, error }))
+}
+
+/// Follows JSON Lines that are still being written, created by `tail_lines`.
+#[derive(Debug)]
+pub struct TailLines<R> {
+    reader: R,
+    pending: Vec<u8>,
+    scanned: usize,
+    line: usize,
+    blank_lines: BlankLines,
+    interval: Duration,
+}
+
+/// Follows a growing JSON Lines file or stream, like `tail -f`.  Reaching the end of the reader
+/// is not the end of the lines; `try_next` reports that nothing new is there yet, and the
+/// iterator waits and reads again.
+///
+/// A line is only parsed once its line break arrives, so a record that is in the middle of
+/// being written is held back until the writer finishes it.  Reading starts wherever the reader
+/// is, so seek a file to its end first to only see new records.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::lines::tail_lines;
+/// use json_rs::JsonValue;
+/// use std::io::Cursor;
+///
+/// fn main() {
+///     let mut tail = tail_lines(Cursor::new(b"{\"n\":1}\n{\"n\":".to_vec()));
+///     assert!(tail.try_next().unwrap().is_ok());
+///     assert!(tail.try_next().is_none());
+///
+///     // the writer finishes the second record
+///     tail.get_mut().get_mut().extend_from_slice(b"2}\n");
+///     let value = tail.try_next().unwrap().unwrap();
+///     assert_eq!(value.get("n"), Some(&JsonValue::from(2)));
+/// }
+/// ```
+pub fn tail_lines<R: Read>(reader: R) -> TailLines<R> {
+    TailLines {
+        reader,
+        pending: Vec::new(),
+        scanned: 0,
+        line: 0,
+        blank_lines: BlankLines::default(),
+        interval: Duration::from_millis(250),
+    }
+}
+
+impl<R: Read> TailLines<R> {
+    /// Sets what is done with blank lines.
+    pub fn blank_lines(mut self, policy: BlankLines) -> Self {
+        self.blank_lines = policy;
+        self
+    }
+
+    /// Sets how long the iterator waits before reading again when there is nothing new, 250
+    /// milliseconds by default.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The number of complete lines read so far, which is the line number of the last value or
+    /// error.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Whether part of a line has been read and is waiting for the rest.
+    pub fn has_partial_line(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.  Any partial line is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the next record if a complete one can be read now, or `None` if the reader has
+    /// nothing more for the moment.
+    pub fn try_next(&mut self) -> Option<Result<JsonValue, LinesError>> {
+        let mut chunk = [0; 8192];
+        loop {
+            if let Some(end) = self.pending[self.scanned..]
+                .iter()
+                .position(|b| *b == b'\n')
+            {
+                let bytes = self
+                    .pending
+                    .drain(..=self.scanned + end)
+                    .collect::<Vec<_>>();
+                self.scanned = 0;
+                self.line += 1;
+                match parse_line(&bytes, self.line, self.blank_lines) {
+                    Some(result) => return Some(result),
+                    None => continue,
+                }
+            }
+            self.scanned = self.pending.len();
+
+            match self.reader.read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(len) => self.pending.extend_from_slice(&chunk[..len]),
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+    }
+}
+
+/// Never ends; when there is nothing new it sleeps for the poll interval and tries again.
+impl<R: Read> Iterator for TailLines<R> {
+    type Item = Result<JsonValue, LinesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.try_next() {
+                Some(result) => return Some(result),
+                None => thread::sleep(self.interval),
+            }
+        }
+    }
+}
+
+/// Writes JSON Lines, created by `lines_writer`.
+#[derive(Debug)]
+pub struct LinesWriter<W> {
+    writer: W,
+    line: usize,
+}
+
+/// Writes values to `writer` as JSON Lines, each compact and followed by `\n`.  Line breaks in
+/// strings are escaped, so every value takes exactly one line and `json_lines` reads them back.
+///
+/// Each value is written with several small writes, so wrap unbuffered writers such as files in
+/// a `BufWriter`.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::lines::{json_lines, lines_writer};
+///
+/// fn main() {
+///     let mut writer = lines_writer(Vec::new());
+///     writer.write(&json!({ "msg": "two\nlines" })).unwrap();
+///     writer.write(&json!([1, 2])).unwrap();
+///     let log = writer.into_inner();
+///     assert_eq!(log, &b"{\"msg\":\"two\\nlines\"}\n[1,2]\n"[..]);
+///
+///     let values = json_lines(&log[..]).collect::<Result<Vec<_>, _>>().unwrap();
+///     assert_eq!(values[1], json!([1, 2]));
+/// }
+/// ```
+pub fn lines_writer<W: Write>(writer: W) -> LinesWriter<W> {
+    LinesWriter { writer, line: 0 }
+}
+
+impl<W: Write> LinesWriter<W> {
+    /// Writes one value and the line break after it.
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        value.to_writer(&mut self.writer)?;
+        self.writer.write_all(b"\n")?;
+        self.line += 1;
+        Ok(())
+    }
+
+    /// Writes every value in `values`, stopping at the first error.
+    pub fn write_values<'a, I>(&mut self, values: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a JsonValue>,
+    {
+        values.into_iter().try_for_each(|value| self.write(value))
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// The number of lines written so far.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_lines, lines_writer, tail_lines, BlankLines, LinesError};
+    use crate::{json_parse, JsonValue};
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    #[test]
+    fn lines_read() {
+        let input = b"{\"a\": [1, 2]}\r\n  \n\"text\"\n\xff\ntrue x\nnull" as &[u8];
+        let mut lines = json_lines(input);
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            json_parse(r#"{ "a": [1, 2] }"#).unwrap()
+        );
+        assert_eq!(lines.line(), 1);
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            JsonValue::Text("text".into())
+        );
+        assert_eq!(lines.line(), 3);
+        assert!(matches!(
+            lines.next(),
+            Some(Err(LinesError::InvalidUtf8 { line: 4 }))
+        ));
+        assert!(matches!(
+            lines.next(),
+            Some(Err(LinesError::Parse { line: 5, .. }))
+        ));
+        assert_eq!(lines.next().unwrap().unwrap(), JsonValue::Null);
+        assert!(lines.next().is_none());
+
+        let strict = json_lines(input).blank_lines(BlankLines::Reject);
+        let errors = strict
+            .filter_map(Result::err)
+            .map(|e| match e {
+                LinesError::Blank { line } => line,
+                _ => 0,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec![2, 0, 0]);
+    }
+
+    #[test]
+    fn lines_tail() {
+        let mut tail = tail_lines(Cursor::new(Vec::new()))
+            .blank_lines(BlankLines::Reject)
+            .poll_interval(Duration::from_millis(1));
+        assert!(tail.try_next().is_none());
+
+        let append = |tail: &mut super::TailLines<Cursor<Vec<u8>>>, bytes: &[u8]| {
+            tail.get_mut().get_mut().extend_from_slice(bytes)
+        };
+        append(&mut tail, b"[1,");
+        assert!(tail.try_next().is_none());
+        assert!(tail.has_partial_line());
+        append(&mut tail, b" 2]");
+        assert!(tail.try_next().is_none());
+        append(&mut tail, b"\r\n\n\"x\"\n{");
+        assert_eq!(tail.next().unwrap().unwrap(), json_parse("[1, 2]").unwrap());
+        match tail.next() {
+            Some(Err(error)) => assert_eq!(error.to_string(), "line 2 is blank"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(tail.next().unwrap().unwrap(), JsonValue::Text("x".into()));
+        assert!(tail.try_next().is_none());
+        assert_eq!(tail.line(), 3);
+        append(&mut tail, b"}\n");
+        assert_eq!(tail.next().unwrap().unwrap(), json_parse("{}").unwrap());
+        assert!(!tail.has_partial_line());
+    }
+
+    #[test]
+    fn lines_write() {
+        let values = [
+            json_parse(r#"{ "a": "multi\nline", "b": [1, {}] }"#).unwrap(),
+            JsonValue::Text("\r\n".into()),
+            JsonValue::Null,
+        ];
+        let mut writer = lines_writer(Vec::new());
+        writer.write_values(values.iter()).unwrap();
+        assert_eq!(writer.line(), 3);
+        let written = writer.into_inner();
+        assert_eq!(written.iter().filter(|b| **b == b'\n').count(), 3);
+
+        let read = json_lines(&written[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read, values);
+    }
+}
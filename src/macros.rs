@@ -0,0 +1,152 @@
+//! The `json!` macro, for writing JSON values as literals.
+
+/// Builds a `JsonValue` from a JSON literal.
+///
+/// Arrays, objects, `null`, `true`, and `false` are written as they are in JSON, and anything
+/// else is a Rust expression that is converted with [`ToJson`](convert/trait.ToJson.html).
+/// Expressions are borrowed rather than moved.  Object keys are string literals or expressions
+/// that convert into a [`JsonString`](struct.JsonString.html), and as with `json_parse`, a later
+/// duplicate key overwrites an earlier one.  Long literals may need a higher `recursion_limit`.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let port = 8080;
+///     let region = String::from("eu-west-1");
+///     let json = json!({
+///         "name": "app",
+///         "port": port,
+///         "tags": ["web", region, null],
+///         "replicas": { "min": port / 4000, "max": 8 },
+///         region.clone(): true
+///     });
+///
+///     assert_eq!(
+///         json,
+///         json_parse(r#"{
+///             "name": "app",
+///             "port": 8080,
+///             "tags": ["web", "eu-west-1", null],
+///             "replicas": { "min": 2, "max": 8 },
+///             "eu-west-1": true
+///         }"#)
+///         .unwrap()
+///     );
+/// }
+/// ```
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::json_internal!($($json)+)
+    };
+}
+
+// Munches the tokens of arrays and objects one value at a time, since a value is either a single
+// token tree or an expression running up to the next comma.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! json_internal {
+    // the items of an array, collected as expressions in the brackets
+    (@array [$($items:expr,)*]) => {
+        $crate::__private::Vec::from([$($items,)*])
+    };
+    (@array [$($items:expr),*]) => {
+        $crate::__private::Vec::from([$($items),*])
+    };
+    (@array [$($items:expr,)*] null $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!(null)] $($rest)*)
+    };
+    (@array [$($items:expr,)*] true $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!(true)] $($rest)*)
+    };
+    (@array [$($items:expr,)*] false $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!(false)] $($rest)*)
+    };
+    (@array [$($items:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!([$($array)*])] $($rest)*)
+    };
+    (@array [$($items:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!({$($object)*})] $($rest)*)
+    };
+    (@array [$($items:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!($next),] $($rest)*)
+    };
+    (@array [$($items:expr,)*] $last:expr) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json_internal!($last)])
+    };
+    (@array [$($items:expr),*] , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)*] $($rest)*)
+    };
+
+    // the members of an object, inserted into `$object` as they are munched; a key is collected
+    // in parentheses, then the value in parentheses after the key moves into brackets
+    (@object $object:ident () ()) => {};
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        $object.insert($crate::JsonString::from($($key)+), $value);
+        $crate::json_internal!(@object $object () ($($rest)*));
+    };
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        $object.insert($crate::JsonString::from($($key)+), $value);
+    };
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*)) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*)) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*)) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*)) => {
+        $crate::json_internal!(
+            @object $object [$($key)+] ($crate::json_internal!([$($array)*])) $($rest)*
+        );
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($inner:tt)*} $($rest:tt)*)) => {
+        $crate::json_internal!(
+            @object $object [$($key)+] ($crate::json_internal!({$($inner)*})) $($rest)*
+        );
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*)) => {
+        $crate::json_internal!(
+            @object $object [$($key)+] ($crate::json_internal!($value)) , $($rest)*
+        );
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr)) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json_internal!($value)));
+    };
+    (@object $object:ident ($($key:tt)*) ($next:tt $($rest:tt)*)) => {
+        $crate::json_internal!(@object $object ($($key)* $next) ($($rest)*));
+    };
+
+    (null) => {
+        $crate::JsonValue::Null
+    };
+    (true) => {
+        $crate::JsonValue::Boolean(true)
+    };
+    (false) => {
+        $crate::JsonValue::Boolean(false)
+    };
+    ([]) => {
+        $crate::JsonValue::Array($crate::__private::Vec::new())
+    };
+    ([ $($items:tt)+ ]) => {
+        $crate::JsonValue::Array($crate::json_internal!(@array [] $($items)+))
+    };
+    ({}) => {
+        $crate::JsonValue::Object($crate::__private::Box::default())
+    };
+    ({ $($members:tt)+ }) => {
+        $crate::JsonValue::Object($crate::__private::Box::new({
+            let mut object = $crate::JsonMap::new();
+            $crate::json_internal!(@object object () ($($members)+));
+            object
+        }))
+    };
+    ($other:expr) => {
+        $crate::ToJson::to_json(&$other)
+    };
+}
@@ -0,0 +1,565 @@
+//! The map an object's members are kept in with the `preserve_order` feature, which remembers
+//! the order keys were first inserted in.
+//!
+//! Without the feature, `JsonMap` is an alias of `HashMap<JsonString, JsonValue>`, and this
+//! module is not built.  `JsonMap` offers the part of `HashMap`'s interface the crate and its
+//! users rely on, so code written against one compiles against the other.
+use super::{table_size, JsonString, JsonValue};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Index;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::slice;
+use std::vec;
+
+/// An object's members in the order their keys were first inserted.  Replacing the value of a
+/// key keeps its place; removing a key closes the gap, keeping the others in order.
+///
+/// Equality ignores order, as it does for the members of a JSON object.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+///
+/// fn main() {
+///     let source = r#"{"name":"ada","id":1,"roles":["admin"]}"#;
+///     let json = json_parse(source).unwrap();
+///     assert_eq!(json.to_string(), source);
+///
+///     let keys = json.as_object().unwrap().keys().collect::<Vec<_>>();
+///     assert_eq!(keys, ["name", "id", "roles"]);
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct JsonMap {
+    entries: Vec<(JsonString, JsonValue)>,
+    /// Where each key is in `entries`
+    indices: HashMap<JsonString, usize>,
+}
+
+impl JsonMap {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map with room for `capacity` members.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// The number of members the map can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity().min(self.indices.capacity())
+    }
+
+    /// The bytes allocated for the list of members and the index into it, including the index's
+    /// copies of the keys but not the members' own keys and values.
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.entries.capacity() * mem::size_of::<(JsonString, JsonValue)>()
+            + table_size(
+                self.indices.capacity(),
+                mem::size_of::<(JsonString, usize)>(),
+            )
+            + self
+                .indices
+                .keys()
+                .map(JsonString::heap_size)
+                .sum::<usize>()
+    }
+
+    /// The number of members.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no members.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every member.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    /// Whether `key` has a value.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.contains_key(key)
+    }
+
+    /// The value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&JsonValue>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    /// The value of `key`, mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut JsonValue>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = *self.indices.get(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    /// The key stored for `key`, with its value.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&JsonString, &JsonValue)>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.get(key).map(|&i| {
+            let (key, value) = &self.entries[i];
+            (key, value)
+        })
+    }
+
+    /// Sets the value of `key`, returning the value it replaced.  A new key goes last.
+    pub fn insert(&mut self, key: JsonString, value: JsonValue) -> Option<JsonValue> {
+        match self.indices.get(&key) {
+            Some(&i) => Some(mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Sets the value of `key` as `insert` does, except that a new key goes at `index`, or last if
+    /// there are fewer members than that, moving the members after it down one place.  This takes
+    /// time proportional to the number of members moved.
+    pub fn insert_at(
+        &mut self,
+        index: usize,
+        key: JsonString,
+        value: JsonValue,
+    ) -> Option<JsonValue> {
+        if let Some(&i) = self.indices.get(&key) {
+            return Some(mem::replace(&mut self.entries[i].1, value));
+        }
+        let index = index.min(self.entries.len());
+        for (key, _) in &self.entries[index..] {
+            *self.indices.get_mut::<JsonString>(key).unwrap() += 1;
+        }
+        self.indices.insert(key.clone(), index);
+        self.entries.insert(index, (key, value));
+        None
+    }
+
+    /// Where `key` is among the members.
+    pub fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.get(key).copied()
+    }
+
+    /// Removes `key`, returning its value and moving the members after it up one place.
+    ///
+    /// Unlike `HashMap::remove`, this is O(n): every later member is moved and reindexed, so
+    /// emptying a large map one key at a time is quadratic.  `retain` removes many keys in one
+    /// pass.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<JsonValue>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key` and returns it with its value, moving the members after it up one place.
+    /// This is O(n), as `remove` is.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(JsonString, JsonValue)>
+    where
+        JsonString: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.indices.remove(key)?;
+        let entry = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.indices.get_mut::<JsonString>(key).unwrap() -= 1;
+        }
+        Some(entry)
+    }
+
+    /// Keeps only the members `keep` returns `true` for, in their order.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&JsonString, &mut JsonValue) -> bool,
+    {
+        self.entries.retain_mut(|(key, value)| keep(key, value));
+        self.reindex();
+    }
+
+    /// Sorts the members by key.
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.indices.clear();
+        for (i, (key, _)) in self.entries.iter().enumerate() {
+            self.indices.insert(key.clone(), i);
+        }
+    }
+
+    /// The entry for `key`, for inserting or updating it in place.
+    pub fn entry(&mut self, key: JsonString) -> Entry<'_> {
+        match self.indices.get(&key) {
+            Some(&index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Removes every member, returning them in order.
+    pub fn drain(&mut self) -> vec::Drain<'_, (JsonString, JsonValue)> {
+        self.indices.clear();
+        self.entries.drain(..)
+    }
+
+    /// The members in order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    /// The members in order, with their values mutable.
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    /// The keys in order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &JsonString> + ExactSizeIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    /// The values in order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &JsonValue> + ExactSizeIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    /// The values in order, mutably.
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut JsonValue> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+
+    /// Consumes the map, returning its keys in order.
+    pub fn into_keys(self) -> impl Iterator<Item = JsonString> {
+        self.into_iter().map(|(key, _)| key)
+    }
+
+    /// Consumes the map, returning its values in order.
+    pub fn into_values(self) -> impl Iterator<Item = JsonValue> {
+        self.into_iter().map(|(_, value)| value)
+    }
+}
+
+/// A place in a `JsonMap` for a key, which may or may not have a value yet.
+pub enum Entry<'a> {
+    /// The key has a value
+    Occupied(OccupiedEntry<'a>),
+    /// The key has no value yet
+    Vacant(VacantEntry<'a>),
+}
+
+/// A key with a value in a `JsonMap`.
+pub struct OccupiedEntry<'a> {
+    map: &'a mut JsonMap,
+    index: usize,
+}
+
+/// A key without a value in a `JsonMap`.
+pub struct VacantEntry<'a> {
+    map: &'a mut JsonMap,
+    key: JsonString,
+}
+
+impl<'a> Entry<'a> {
+    /// The key the entry is for.
+    pub fn key(&self) -> &JsonString {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    /// The value, after setting it to `default` if there was none.
+    pub fn or_insert(self, default: JsonValue) -> &'a mut JsonValue {
+        self.or_insert_with(|| default)
+    }
+
+    /// The value, after setting it to what `default` returns if there was none.
+    pub fn or_insert_with<F: FnOnce() -> JsonValue>(self, default: F) -> &'a mut JsonValue {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// The value, after setting it to `null` if there was none.
+    pub fn or_default(self) -> &'a mut JsonValue {
+        self.or_insert_with(|| JsonValue::Null)
+    }
+
+    /// Calls `f` on the value if there is one.
+    pub fn and_modify<F: FnOnce(&mut JsonValue)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// The key of the member.
+    pub fn key(&self) -> &JsonString {
+        &self.map.entries[self.index].0
+    }
+
+    /// The value of the member.
+    pub fn get(&self) -> &JsonValue {
+        &self.map.entries[self.index].1
+    }
+
+    /// The value of the member, mutably.
+    pub fn get_mut(&mut self) -> &mut JsonValue {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// The value of the member, mutably for as long as the map is borrowed.
+    pub fn into_mut(self) -> &'a mut JsonValue {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// Replaces the value of the member, returning the old one.  The member keeps its place.
+    pub fn insert(&mut self, value: JsonValue) -> JsonValue {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the member as `JsonMap::remove` does, returning its value.
+    pub fn remove(self) -> JsonValue {
+        let key = self.map.entries[self.index].0.clone();
+        self.map.remove(&key).unwrap()
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// The key that would be inserted.
+    pub fn key(&self) -> &JsonString {
+        &self.key
+    }
+
+    /// Inserts the key last with `value`, returning the value.
+    pub fn insert(self, value: JsonValue) -> &'a mut JsonValue {
+        let index = self.map.entries.len();
+        self.map.insert(self.key, value);
+        &mut self.map.entries[index].1
+    }
+}
+
+/// Ignores order, as the members of a JSON object have none.
+impl PartialEq for JsonMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(&**key) == Some(value))
+    }
+}
+
+impl fmt::Debug for JsonMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<Q> Index<&Q> for JsonMap
+where
+    JsonString: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = JsonValue;
+
+    /// Panics if `key` isn't in the map.
+    fn index(&self, key: &Q) -> &JsonValue {
+        self.get(key).expect("no member for the key")
+    }
+}
+
+impl FromIterator<(JsonString, JsonValue)> for JsonMap {
+    fn from_iter<I: IntoIterator<Item = (JsonString, JsonValue)>>(members: I) -> Self {
+        let mut map = JsonMap::new();
+        map.extend(members);
+        map
+    }
+}
+
+impl Extend<(JsonString, JsonValue)> for JsonMap {
+    fn extend<I: IntoIterator<Item = (JsonString, JsonValue)>>(&mut self, members: I) {
+        for (key, value) in members {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Takes the members of a `HashMap` in whatever order it holds them.
+impl From<HashMap<JsonString, JsonValue>> for JsonMap {
+    fn from(map: HashMap<JsonString, JsonValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// The members of a `JsonMap` by reference, in order.
+pub struct Iter<'a>(slice::Iter<'a, (JsonString, JsonValue)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a JsonString, &'a JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, value)| (key, value))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+/// The members of a `JsonMap` with their values mutable, in order.
+pub struct IterMut<'a>(slice::IterMut<'a, (JsonString, JsonValue)>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a JsonString, &'a mut JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (&*key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for IterMut<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, value)| (&*key, value))
+    }
+}
+
+impl<'a> ExactSizeIterator for IterMut<'a> {}
+
+impl IntoIterator for JsonMap {
+    type Item = (JsonString, JsonValue);
+    type IntoIter = vec::IntoIter<(JsonString, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonMap {
+    type Item = (&'a JsonString, &'a JsonValue);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut JsonMap {
+    type Item = (&'a JsonString, &'a mut JsonValue);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, JsonMap};
+    use crate::JsonValue;
+
+    #[test]
+    fn map_order() {
+        let mut map = (0..5)
+            .map(|i| (i.to_string().into(), JsonValue::from(i as f64)))
+            .rev()
+            .collect::<JsonMap>();
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["4", "3", "2", "1", "0"]);
+
+        assert_eq!(
+            map.insert("3".into(), JsonValue::Null),
+            Some(JsonValue::from(3.0))
+        );
+        assert_eq!(map.remove("2"), Some(JsonValue::from(2.0)));
+        map.insert("2".into(), JsonValue::Boolean(true));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["4", "3", "1", "0", "2"]);
+        assert_eq!(map["1"], JsonValue::from(1.0));
+        assert_eq!(map.get("3"), Some(&JsonValue::Null));
+
+        map.retain(|key, _| key != "4");
+        *map.entry("0".into()).or_default() = JsonValue::Null;
+        map.entry("5".into()).or_insert(JsonValue::Null);
+        assert!(matches!(map.entry("1".into()), Entry::Occupied(_)));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["3", "1", "0", "2", "5"]);
+        assert_eq!(map.get("0"), Some(&JsonValue::Null));
+
+        let mut reordered = map.clone();
+        reordered.sort_keys();
+        assert_eq!(
+            reordered.keys().collect::<Vec<_>>(),
+            ["0", "1", "2", "3", "5"]
+        );
+        assert_eq!(reordered, map);
+        assert_eq!(reordered.get("5"), Some(&JsonValue::Null));
+
+        assert_eq!(map.index_of("0"), Some(2));
+        assert_eq!(map.insert_at(1, "6".into(), JsonValue::Null), None);
+        assert_eq!(
+            map.insert_at(0, "0".into(), JsonValue::Boolean(false)),
+            Some(JsonValue::Null)
+        );
+        map.insert_at(9, "7".into(), JsonValue::Null);
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            ["3", "6", "1", "0", "2", "5", "7"]
+        );
+        assert_eq!(map.index_of("2"), Some(4));
+        assert_eq!(map["2"], JsonValue::Boolean(true));
+        map.remove("6");
+        map.remove("7");
+
+        let drained = map.drain().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(drained, ["3", "1", "0", "2", "5"]);
+        assert!(map.is_empty() && map.get("3").is_none());
+    }
+}
@@ -0,0 +1,420 @@
+//! [MessagePack](https://msgpack.org) encoding of `JsonValue`, enabled by the `msgpack` feature.
+use super::{JsonMap, JsonNumber, JsonValue};
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Describes all possible errors that could occur while decoding MessagePack
+#[derive(Clone, Debug, PartialEq)]
+pub enum MsgpackError {
+    /// The input ended in the middle of a value
+    UnexpectedEOF,
+    /// A byte that does not start any value JSON can represent, such as an extension type
+    UnsupportedMarker {
+        /// The byte that was found
+        marker: u8,
+        /// Its offset in the input
+        offset: usize,
+    },
+    /// A string was not valid UTF-8
+    InvalidUtf8 {
+        /// The offset of the string in the input
+        offset: usize,
+    },
+    /// A map key was not a string
+    InvalidKey {
+        /// The offset of the key in the input
+        offset: usize,
+    },
+    /// Arrays and maps were nested more deeply than the decoder allows
+    DepthLimitExceeded {
+        /// The depth that was allowed
+        limit: usize,
+        /// The offset of the array or map that went too deep
+        offset: usize,
+    },
+    /// More input followed the value
+    TrailingBytes {
+        /// The offset of the first byte after the value
+        offset: usize,
+    },
+}
+
+impl fmt::Display for MsgpackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MsgpackError::UnexpectedEOF => f.write_str("unexpected end of MessagePack input"),
+            MsgpackError::UnsupportedMarker { marker, offset } => {
+                write!(f, "unsupported marker {:#04x} at offset {}", marker, offset)
+            }
+            MsgpackError::InvalidUtf8 { offset } => {
+                write!(f, "string at offset {} is not valid UTF-8", offset)
+            }
+            MsgpackError::InvalidKey { offset } => {
+                write!(f, "map key at offset {} is not a string", offset)
+            }
+            MsgpackError::DepthLimitExceeded { limit, offset } => write!(
+                f,
+                "nested more than {} levels deep at offset {}",
+                limit, offset
+            ),
+            MsgpackError::TrailingBytes { offset } => {
+                write!(f, "unexpected bytes after the value at offset {}", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for MsgpackError {}
+
+/// Encodes a value as MessagePack.
+///
+/// Whole numbers that fit in 64 bits are written as the smallest integer type that holds them,
+/// and every other number as a 64-bit float.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::msgpack::{from_msgpack, to_msgpack};
+///
+/// fn main() {
+///     let json = json_parse(r#"[1, -200, 0.5, "hi", null, true]"#).unwrap();
+///     let bytes = to_msgpack(&json);
+///     assert_eq!(&bytes[..5], &[0x96, 0x01, 0xd1, 0xff, 0x38]);
+///     assert_eq!(from_msgpack(&bytes), Ok(json));
+/// }
+/// ```
+pub fn to_msgpack(value: &JsonValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(value, &mut out);
+    out
+}
+
+/// Writes the marker for a string or container of `len` items.  Lengths below `fix_max` are
+/// stored in the `fix` marker itself, and longer ones follow the smallest of the 8, 16 and
+/// 32-bit markers that fits.  Containers have no 8-bit form.
+fn length(
+    out: &mut Vec<u8>,
+    len: usize,
+    (fix, fix_max): (u8, usize),
+    (len8, len16, len32): (Option<u8>, u8, u8),
+) {
+    match len8 {
+        _ if len < fix_max => out.push(fix | len as u8),
+        Some(marker) if len <= 0xff => out.extend_from_slice(&[marker, len as u8]),
+        _ if len <= 0xffff => {
+            out.push(len16);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(len32);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+fn encode_str(s: &str, out: &mut Vec<u8>) {
+    length(out, s.len(), (0xa0, 32), (Some(0xd9), 0xda, 0xdb));
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_number(n: JsonNumber, out: &mut Vec<u8>) {
+    // as an integer, `-0.0` would lose its sign
+    let negative_zero = matches!(n, JsonNumber::F64(f) if f == 0.0 && f.is_sign_negative());
+    if let (Some(n), false) = (n.as_u64(), negative_zero) {
+        match n {
+            i if i < 0x80 => out.push(i as u8),
+            i if i <= 0xff => out.extend_from_slice(&[0xcc, i as u8]),
+            i if i <= 0xffff => {
+                out.push(0xcd);
+                out.extend_from_slice(&(i as u16).to_be_bytes());
+            }
+            i if i <= 0xffff_ffff => {
+                out.push(0xce);
+                out.extend_from_slice(&(i as u32).to_be_bytes());
+            }
+            i => {
+                out.push(0xcf);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+        }
+    } else if let (Some(n), false) = (n.as_i64(), negative_zero) {
+        match n {
+            i if i >= -32 => out.push(i as u8),
+            i if i >= i64::from(i8::MIN) => out.extend_from_slice(&[0xd0, i as u8]),
+            i if i >= i64::from(i16::MIN) => {
+                out.push(0xd1);
+                out.extend_from_slice(&(i as i16).to_be_bytes());
+            }
+            i if i >= i64::from(i32::MIN) => {
+                out.push(0xd2);
+                out.extend_from_slice(&(i as i32).to_be_bytes());
+            }
+            i => {
+                out.push(0xd3);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+        }
+    } else {
+        out.push(0xcb);
+        out.extend_from_slice(&n.as_f64().to_bits().to_be_bytes());
+    }
+}
+
+fn encode(value: &JsonValue, out: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => out.push(0xc0),
+        JsonValue::Boolean(false) => out.push(0xc2),
+        JsonValue::Boolean(true) => out.push(0xc3),
+        JsonValue::Number(n) => encode_number(*n, out),
+        JsonValue::Text(s) => encode_str(s, out),
+        JsonValue::Raw(raw) => encode(&raw.parse(), out),
+        JsonValue::Array(array) => {
+            length(out, array.len(), (0x90, 16), (None, 0xdc, 0xdd));
+            for item in array.iter() {
+                encode(item, out);
+            }
+        }
+        JsonValue::Object(map) => {
+            length(out, map.len(), (0x80, 16), (None, 0xde, 0xdf));
+            for (key, item) in map.iter() {
+                encode_str(key, out);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+/// How many arrays and maps may be open at once while decoding, as many as
+/// [`ParseOptions::max_depth`](../options/struct.ParseOptions.html#structfield.max_depth) allows
+/// by default.  The decoder recurses once per level, so this keeps hostile input from
+/// overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Decodes a single MessagePack value, which must take up all of `bytes`.
+///
+/// Binary data is decoded as an array of byte values.  Extension types have no JSON equivalent
+/// and are rejected, as are maps with keys that are not strings and arrays and maps nested more
+/// than 128 deep.
+pub fn from_msgpack(bytes: &[u8]) -> Result<JsonValue, MsgpackError> {
+    let mut decoder = Decoder {
+        bytes,
+        offset: 0,
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if decoder.offset < bytes.len() {
+        return Err(MsgpackError::TrailingBytes {
+            offset: decoder.offset,
+        });
+    }
+    Ok(value)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// How many arrays and maps are open
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MsgpackError> {
+        if self.bytes.len() - self.offset < len {
+            return Err(MsgpackError::UnexpectedEOF);
+        }
+        let taken = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(taken)
+    }
+
+    /// Reads a big-endian unsigned integer of `len` bytes.
+    fn uint(&mut self, len: usize) -> Result<u64, MsgpackError> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |n, &byte| (n << 8) | u64::from(byte)))
+    }
+
+    /// Reads a big-endian two's complement integer of `len` bytes.
+    fn int(&mut self, len: usize) -> Result<i64, MsgpackError> {
+        let shift = 64 - 8 * len as u32;
+        Ok(((self.uint(len)? << shift) as i64) >> shift)
+    }
+
+    fn str(&mut self, len: usize) -> Result<String, MsgpackError> {
+        let offset = self.offset;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| MsgpackError::InvalidUtf8 { offset })
+    }
+
+    /// Opens an array or map that starts at `offset`, if that is not one too many.
+    fn enter(&mut self, offset: usize) -> Result<(), MsgpackError> {
+        if self.depth == MAX_DEPTH {
+            return Err(MsgpackError::DepthLimitExceeded {
+                limit: MAX_DEPTH,
+                offset,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn array(&mut self, len: usize, offset: usize) -> Result<JsonValue, MsgpackError> {
+        self.enter(offset)?;
+        // every item takes at least a byte, which bounds what a bogus length can allocate
+        let mut array = Vec::with_capacity(len.min(self.bytes.len() - self.offset));
+        for _ in 0..len {
+            array.push(self.value()?);
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Array(array))
+    }
+
+    fn map(&mut self, len: usize, offset: usize) -> Result<JsonValue, MsgpackError> {
+        self.enter(offset)?;
+        let mut map = JsonMap::with_capacity(len.min(self.bytes.len() - self.offset));
+        for _ in 0..len {
+            let offset = self.offset;
+            let key = match self.value()? {
+                JsonValue::Text(ref mut key) => mem::take(key),
+                _ => return Err(MsgpackError::InvalidKey { offset }),
+            };
+            let value = self.value()?;
+            map.insert(key, value);
+        }
+        self.depth -= 1;
+        Ok(JsonValue::Object(Box::new(map)))
+    }
+
+    fn value(&mut self) -> Result<JsonValue, MsgpackError> {
+        let offset = self.offset;
+        let marker = self.take(1)?[0];
+        Ok(match marker {
+            0x00..=0x7f => JsonValue::from(marker),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f), offset)?,
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f), offset)?,
+            0xa0..=0xbf => JsonValue::Text(self.str(usize::from(marker & 0x1f))?.into()),
+            0xc0 => JsonValue::Null,
+            0xc2 => JsonValue::Boolean(false),
+            0xc3 => JsonValue::Boolean(true),
+            0xc4..=0xc6 => {
+                let len = self.uint(1 << (marker - 0xc4))? as usize;
+                let bytes = self.take(len)?;
+                JsonValue::Array(bytes.iter().map(|&b| JsonValue::from(b)).collect())
+            }
+            0xca => JsonValue::from(f32::from_bits(self.uint(4)? as u32)),
+            0xcb => JsonValue::from(f64::from_bits(self.uint(8)?)),
+            0xcc..=0xcf => JsonValue::from(self.uint(1 << (marker - 0xcc))?),
+            0xd0..=0xd3 => JsonValue::from(self.int(1 << (marker - 0xd0))?),
+            0xd9..=0xdb => {
+                let len = self.uint(1 << (marker - 0xd9))? as usize;
+                JsonValue::Text(self.str(len)?.into())
+            }
+            0xdc | 0xdd => {
+                let len = self.uint(2 << (marker - 0xdc))? as usize;
+                self.array(len, offset)?
+            }
+            0xde | 0xdf => {
+                let len = self.uint(2 << (marker - 0xde))? as usize;
+                self.map(len, offset)?
+            }
+            0xe0..=0xff => JsonValue::from(marker as i8),
+            _ => return Err(MsgpackError::UnsupportedMarker { marker, offset }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_msgpack, to_msgpack, MsgpackError};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn msgpack_round_trip() {
+        let json = json_parse(
+            r#"{
+                "ints": [0, 127, 128, 255, 256, 65535, 65536, 4294967296, -1, -32, -33, -128,
+                    -129, -32768, -32769, -2147483649],
+                "floats": [0.5, -0.0, 1e300, 18446744073709551616],
+                "text": ["", "a string that is longer than thirty-one bytes", "é"],
+                "nested": { "empty": {}, "list": [[], null, false] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(from_msgpack(&to_msgpack(&json)), Ok(json));
+
+        let long = JsonValue::Array(vec![JsonValue::Null; 70_000]);
+        let bytes = to_msgpack(&long);
+        assert_eq!(&bytes[..5], &[0xdd, 0x00, 0x01, 0x11, 0x70]);
+        assert_eq!(from_msgpack(&bytes), Ok(long));
+
+        assert_eq!(
+            &to_msgpack(&JsonValue::Text("x".repeat(40).into()))[..2],
+            &[0xd9, 40]
+        );
+    }
+
+    #[test]
+    fn msgpack_decode() {
+        // float 32, bin 8, and a str 8 key in a fixmap
+        assert_eq!(
+            from_msgpack(&[
+                0x82, 0xa1, b'f', 0xca, 0x3f, 0x00, 0x00, 0x00, 0xd9, 0x01, b'b', 0xc4, 0x02, 1, 2,
+            ]),
+            Ok(json_parse(r#"{ "f": 0.5, "b": [1, 2] }"#).unwrap())
+        );
+
+        assert_eq!(
+            from_msgpack(&[0x92, 0x01]),
+            Err(MsgpackError::UnexpectedEOF)
+        );
+        assert_eq!(
+            from_msgpack(&[0xc0, 0xc0]),
+            Err(MsgpackError::TrailingBytes { offset: 1 })
+        );
+        assert_eq!(
+            from_msgpack(&[0x91, 0xd4, 0x01, 0x00]),
+            Err(MsgpackError::UnsupportedMarker {
+                marker: 0xd4,
+                offset: 1,
+            })
+        );
+        assert_eq!(
+            from_msgpack(&[0x91, 0xd4]).unwrap_err().to_string(),
+            "unsupported marker 0xd4 at offset 1"
+        );
+        assert_eq!(
+            from_msgpack(&[0x81, 0x01, 0xc0]),
+            Err(MsgpackError::InvalidKey { offset: 1 })
+        );
+        assert_eq!(
+            from_msgpack(&[0xa1, 0xff]),
+            Err(MsgpackError::InvalidUtf8 { offset: 1 })
+        );
+        assert_eq!(
+            from_msgpack(&[0xdd, 0xff, 0xff, 0xff, 0xff]),
+            Err(MsgpackError::UnexpectedEOF)
+        );
+
+        let mut nested = vec![0x91; 1_000_000];
+        nested.push(0xc0);
+        assert_eq!(
+            from_msgpack(&nested),
+            Err(MsgpackError::DepthLimitExceeded {
+                limit: 128,
+                offset: 128,
+            })
+        );
+        let mut nested = [0x81, 0xa0].repeat(129);
+        nested.push(0xc0);
+        assert!(from_msgpack(&nested[2..]).is_ok());
+        assert!(matches!(
+            from_msgpack(&nested),
+            Err(MsgpackError::DepthLimitExceeded { offset: 256, .. })
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+//! The number type of `JsonValue`, which keeps integers exactly.
+#[cfg(feature = "decimal")]
+use super::decimal::Decimal;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+#[cfg(all(feature = "decimal", not(feature = "std")))]
+use std::prelude::v1::*;
+
+/// A JSON number: a whole number that fits in 64 bits, kept exactly, or otherwise an `f64`.
+///
+/// Parsing reads a number written without a fraction or exponent into `U64` if it is not
+/// negative, or `I64` if it is, so IDs beyond 2^53 survive a round trip.  Every other number,
+/// and any integer too large for either, is an `F64`.  Conversions from Rust integers follow the
+/// same rule, so each integer has one representation unless a variant is built directly.  With
+/// the `decimal` feature and
+/// [`ParseOptions::exact_numbers`](options/struct.ParseOptions.html#structfield.exact_numbers),
+/// numbers other than those integers are read into an exact `Decimal` instead of an `F64`.
+///
+/// Numbers are compared by value: `U64(1)`, `I64(1)` and `F64(1.0)` are all equal.  A `Decimal`
+/// equals an `F64` when it is the shortest decimal that rounds to the float, so the `Decimal` read
+/// from `0.1` equals `F64(0.1)`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonNumber, JsonValue};
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "id": 1152921504606846977, "ratio": 0.5 }"#).unwrap();
+///     assert_eq!(json["id"].as_u64(), Some(1152921504606846977));
+///     assert_eq!(json["ratio"].as_f64(), Some(0.5));
+///     assert_eq!(json["ratio"].as_i64(), None);
+///     assert_eq!(json.to_string().contains("1152921504606846977"), true);
+///
+///     assert_eq!(JsonNumber::from(-3), JsonNumber::I64(-3));
+///     assert_eq!(JsonNumber::from(3), JsonNumber::U64(3));
+///     assert_eq!(JsonValue::Number(JsonNumber::F64(3.0)), JsonValue::from(3));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub enum JsonNumber {
+    /// A whole number below zero, or any `i64` built directly.
+    I64(i64),
+    /// A whole number from zero up.
+    U64(u64),
+    /// Any other number.
+    F64(f64),
+    /// Any other number, kept exactly as written.  Parsing with
+    /// [`ParseOptions::exact_numbers`](options/struct.ParseOptions.html#structfield.exact_numbers)
+    /// produces these, as does converting a `Decimal` that is not a 64-bit integer.
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
+}
+
+impl JsonNumber {
+    /// Gets the number as an `i64`, if it is a whole number in range.  An `F64` qualifies when
+    /// it holds a whole number exactly.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    /// Gets the number as a `u64`, if it is a whole number in range, as with
+    /// [`as_i64`](#method.as_i64).
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// Gets the number as an `f64`, rounding integers beyond 2^53, and decimals that have no
+    /// exact `f64`, to the nearest `f64`.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            JsonNumber::I64(n) => n as f64,
+            JsonNumber::U64(n) => n as f64,
+            JsonNumber::F64(n) => n,
+            #[cfg(feature = "decimal")]
+            JsonNumber::Decimal(n) => n.to_f64(),
+        }
+    }
+
+    /// Gets the number as an exact `Decimal`.  An `F64` is read as the shortest decimal that
+    /// rounds to it, and has none if it is infinite or `NaN`.
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match *self {
+            JsonNumber::I64(n) => Some(n.into()),
+            JsonNumber::U64(n) => Some(n.into()),
+            JsonNumber::F64(_) => self.to_string().parse().ok(),
+            JsonNumber::Decimal(n) => Some(n),
+        }
+    }
+
+    /// Gets the number as an `i128`, if it is a whole number in range.
+    pub(crate) fn as_i128(&self) -> Option<i128> {
+        match *self {
+            JsonNumber::I64(n) => Some(i128::from(n)),
+            JsonNumber::U64(n) => Some(i128::from(n)),
+            // MAX rounds up to a power of two, which is just out of range
+            JsonNumber::F64(n)
+                if n % 1.0 == 0.0 && n >= i128::MIN as f64 && n < i128::MAX as f64 =>
+            {
+                Some(n as i128)
+            }
+            JsonNumber::F64(_) => None,
+            #[cfg(feature = "decimal")]
+            JsonNumber::Decimal(n) => n.to_i128(),
+        }
+    }
+
+    /// Whether the number is an `I64` or `U64`.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, JsonNumber::I64(_) | JsonNumber::U64(_))
+    }
+
+    /// Compares two numbers as `JsonValue::total_eq` does: every `NaN` matches every other, and
+    /// `-0.0` is distinct from `0.0`.
+    pub(crate) fn total_eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.as_f64(), other.as_f64());
+        (a.is_nan() && b.is_nan()) || (self == other && a.to_bits() == b.to_bits())
+    }
+
+    /// Orders two numbers consistently with `total_eq`: by value, with `-0.0` before `0.0` and
+    /// every `NaN` after every other number.
+    pub(crate) fn total_cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.as_f64(), other.as_f64());
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Greater,
+            (false, true) => return Ordering::Less,
+            (false, false) => (),
+        }
+        // numbers that round to the same `f64` are told apart the way `==` tells them apart,
+        // which for decimals is in some fixed order rather than by value
+        a.total_cmp(&b).then_with(|| match (self, other) {
+            #[cfg(feature = "decimal")]
+            (JsonNumber::Decimal(_), _) | (_, JsonNumber::Decimal(_)) => {
+                let key = |n: &Self| {
+                    n.as_decimal()
+                        .map(|n| (n.is_negative(), n.mantissa(), n.exponent()))
+                };
+                key(self).cmp(&key(other))
+            }
+            _ => self.as_i128().cmp(&other.as_i128()),
+        })
+    }
+}
+
+impl PartialEq for JsonNumber {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (JsonNumber::F64(a), JsonNumber::F64(b)) => a == b,
+            #[cfg(feature = "decimal")]
+            (JsonNumber::Decimal(_), _) | (_, JsonNumber::Decimal(_)) => {
+                self.as_decimal().is_some() && self.as_decimal() == other.as_decimal()
+            }
+            _ => self.as_i128().is_some() && self.as_i128() == other.as_i128(),
+        }
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonNumber::I64(n) => fmt::Display::fmt(n, f),
+            JsonNumber::U64(n) => fmt::Display::fmt(n, f),
+            // from 2^53 up, a whole float written out in full would read back as an integer that
+            // may not be the float's exact value
+            JsonNumber::F64(n) if n % 1.0 == 0.0 && n.abs() >= 9_007_199_254_740_992.0 => {
+                fmt::LowerExp::fmt(n, f)
+            }
+            JsonNumber::F64(n) => fmt::Display::fmt(n, f),
+            #[cfg(feature = "decimal")]
+            JsonNumber::Decimal(n) => fmt::Display::fmt(n, f),
+        }
+    }
+}
+
+impl Default for JsonNumber {
+    fn default() -> Self {
+        JsonNumber::U64(0)
+    }
+}
+
+macro_rules! from_signed {
+    ($($t:ty)*) => {$(
+        impl From<$t> for JsonNumber {
+            fn from(n: $t) -> Self {
+                match u64::try_from(n) {
+                    Ok(n) => JsonNumber::U64(n),
+                    Err(_) => JsonNumber::I64(n as i64),
+                }
+            }
+        }
+    )*};
+}
+
+macro_rules! from_unsigned {
+    ($($t:ty)*) => {$(
+        impl From<$t> for JsonNumber {
+            fn from(n: $t) -> Self {
+                JsonNumber::U64(n as u64)
+            }
+        }
+    )*};
+}
+
+from_signed!(i8 i16 i32 i64 isize);
+from_unsigned!(u8 u16 u32 u64 usize);
+
+/// Converts a 128-bit integer exactly if it fits in 64 bits, and otherwise to the nearest `f64`.
+macro_rules! from_wide {
+    ($($t:ty)*) => {$(
+        impl From<$t> for JsonNumber {
+            fn from(n: $t) -> Self {
+                match (u64::try_from(n), i64::try_from(n)) {
+                    (Ok(n), _) => JsonNumber::U64(n),
+                    (_, Ok(n)) => JsonNumber::I64(n),
+                    _ => JsonNumber::F64(n as f64),
+                }
+            }
+        }
+    )*};
+}
+
+from_wide!(i128 u128);
+
+impl From<f32> for JsonNumber {
+    fn from(n: f32) -> Self {
+        JsonNumber::F64(f64::from(n))
+    }
+}
+
+impl From<f64> for JsonNumber {
+    fn from(n: f64) -> Self {
+        JsonNumber::F64(n)
+    }
+}
+
+/// Keeps a whole number that fits in 64 bits as an `I64` or `U64`, as parsing does, and any
+/// other number as a `Decimal`.
+#[cfg(feature = "decimal")]
+impl From<Decimal> for JsonNumber {
+    fn from(n: Decimal) -> Self {
+        match n.to_i128() {
+            Some(n) if i64::try_from(n).is_ok() || u64::try_from(n).is_ok() => n.into(),
+            _ => JsonNumber::Decimal(n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonNumber;
+
+    #[test]
+    fn number_accessors() {
+        let big = JsonNumber::U64(u64::MAX);
+        assert_eq!(big.as_u64(), Some(u64::MAX));
+        assert_eq!(big.as_i64(), None);
+        assert_eq!(big.to_string(), "18446744073709551615");
+
+        assert_eq!(JsonNumber::I64(i64::MIN).as_i64(), Some(i64::MIN));
+        assert_eq!(JsonNumber::I64(-1).as_u64(), None);
+        assert_eq!(JsonNumber::F64(4.0).as_u64(), Some(4));
+        assert_eq!(JsonNumber::F64(4.5).as_u64(), None);
+        assert_eq!(JsonNumber::F64(1e300).as_i64(), None);
+        assert_eq!(JsonNumber::F64(f64::NAN).as_i64(), None);
+
+        assert_eq!(JsonNumber::I64(7), JsonNumber::U64(7));
+        assert_eq!(JsonNumber::U64(7), JsonNumber::F64(7.0));
+        assert_ne!(JsonNumber::U64(u64::MAX), JsonNumber::F64(u64::MAX as f64));
+        assert_ne!(JsonNumber::F64(f64::NAN), JsonNumber::F64(f64::NAN));
+        assert!(JsonNumber::F64(f64::NAN).total_eq(&JsonNumber::F64(-f64::NAN)));
+        assert!(!JsonNumber::F64(-0.0).total_eq(&JsonNumber::U64(0)));
+        assert!(JsonNumber::F64(0.0).total_eq(&JsonNumber::U64(0)));
+    }
+}
@@ -0,0 +1,356 @@
+use super::{EditError, JsonEdit, JsonPointer, JsonValue};
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A single recorded mutation of an `ObservedJson` document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonChange {
+    /// Where the change happened
+    pub path: JsonPointer,
+    /// The value before the change, or `None` if it was added
+    pub old: Option<JsonValue>,
+    /// The value after the change, or `None` if it was removed
+    pub new: Option<JsonValue>,
+}
+
+impl JsonChange {
+    /// The edit that replays this change on another copy of the original document.
+    pub fn to_edit(&self) -> JsonEdit {
+        let path = self.path.clone();
+        match (&self.old, &self.new) {
+            (_, None) => JsonEdit::Remove { path },
+            (None, Some(value)) => JsonEdit::Add {
+                path,
+                value: value.clone(),
+            },
+            (Some(_), Some(value)) => JsonEdit::Replace {
+                path,
+                value: value.clone(),
+            },
+        }
+    }
+}
+
+/// Identifies a watcher registered with [`ObservedJson::watch`](struct.ObservedJson.html#method.watch).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchId(usize);
+
+type Watcher = Box<dyn FnMut(&JsonChange, Option<&JsonValue>)>;
+
+/// A JSON document that records every mutation made through it.
+///
+/// The change log can be used as an audit trail or converted into edits with
+/// [`JsonChange::to_edit`](struct.JsonChange.html#method.to_edit) to keep another copy of the
+/// document in sync.  Callbacks can also be registered to run whenever the value under a
+/// particular path changes.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonValue, ObservedJson};
+///
+/// fn main() {
+///     let mut doc = ObservedJson::new(json_parse(r#"{ "tags": [] }"#).unwrap());
+///     let mut replica = doc.value().clone();
+///
+///     doc.add("/tags/-".parse().unwrap(), JsonValue::Text("new".into())).unwrap();
+///     doc.add("/owner".parse().unwrap(), JsonValue::Null).unwrap();
+///
+///     let changes = doc.take_changes();
+///     assert_eq!(changes[0].path.to_string(), "/tags/0");
+///     assert_eq!(changes[1].old, None);
+///
+///     for change in changes.iter() {
+///         change.to_edit().apply(&mut replica).unwrap();
+///     }
+///     assert_eq!(&replica, doc.value());
+/// }
+/// ```
+pub struct ObservedJson {
+    value: JsonValue,
+    changes: Vec<JsonChange>,
+    watchers: Vec<(WatchId, JsonPointer, Watcher)>,
+    next_watch_id: usize,
+}
+
+impl ObservedJson {
+    /// Starts observing `value` with an empty change log.
+    pub fn new(value: JsonValue) -> Self {
+        Self {
+            value,
+            changes: vec![],
+            watchers: vec![],
+            next_watch_id: 0,
+        }
+    }
+
+    /// The current state of the document.
+    pub fn value(&self) -> &JsonValue {
+        &self.value
+    }
+
+    /// Stops observing and returns the document.
+    pub fn into_inner(self) -> JsonValue {
+        self.value
+    }
+
+    /// Every change made since the document was created or the log was last taken, oldest first.
+    pub fn changes(&self) -> &[JsonChange] {
+        &self.changes
+    }
+
+    /// Returns the change log, leaving it empty.
+    pub fn take_changes(&mut self) -> Vec<JsonChange> {
+        ::std::mem::take(&mut self.changes)
+    }
+
+    /// Applies an edit and records the resulting change.
+    pub fn apply(&mut self, edit: JsonEdit) -> Result<&JsonChange, EditError> {
+        let new = match &edit {
+            JsonEdit::Add { value, .. }
+            | JsonEdit::Insert { value, .. }
+            | JsonEdit::Replace { value, .. } => Some(value.clone()),
+            JsonEdit::Remove { .. } => None,
+        };
+
+        let change = match edit.apply(&mut self.value)? {
+            JsonEdit::Remove { path } => JsonChange {
+                path,
+                old: None,
+                new,
+            },
+            JsonEdit::Add { path, value }
+            | JsonEdit::Insert { path, value, .. }
+            | JsonEdit::Replace { path, value } => JsonChange {
+                path,
+                old: Some(value),
+                new,
+            },
+        };
+
+        for (_, path, watcher) in self.watchers.iter_mut() {
+            if affects(&change, path, &self.value) {
+                watcher(&change, path.resolve(&self.value));
+            }
+        }
+
+        self.changes.push(change);
+        Ok(&self.changes[self.changes.len() - 1])
+    }
+
+    /// Registers a callback that runs after every change that could alter the value at `path`:
+    /// changes to the value itself, to anything underneath it, or to any of its ancestors.  The
+    /// callback receives the change and the new value at `path`, if there is one.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonValue, ObservedJson};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let mut config = ObservedJson::new(json_parse(r#"{ "server": { "port": 80 } }"#).unwrap());
+    ///     let port = Rc::new(RefCell::new(None));
+    ///
+    ///     let seen = port.clone();
+    ///     config.watch("/server/port".parse().unwrap(), move |_, value| {
+    ///         *seen.borrow_mut() = value.cloned();
+    ///     });
+    ///
+    ///     config.add("/debug".parse().unwrap(), JsonValue::Boolean(true)).unwrap();
+    ///     assert_eq!(*port.borrow(), None);
+    ///
+    ///     config.replace("/server".parse().unwrap(), json_parse(r#"{ "port": 8080 }"#).unwrap()).unwrap();
+    ///     assert_eq!(*port.borrow(), Some(JsonValue::from(8080)));
+    /// }
+    /// ```
+    pub fn watch<F>(&mut self, path: JsonPointer, watcher: F) -> WatchId
+    where
+        F: FnMut(&JsonChange, Option<&JsonValue>) + 'static,
+    {
+        let id = WatchId(self.next_watch_id);
+        self.next_watch_id += 1;
+        self.watchers.push((id, path, Box::new(watcher)));
+        id
+    }
+
+    /// Removes a watcher.  Returns `false` if it had already been removed.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let count = self.watchers.len();
+        self.watchers.retain(|(watch_id, _, _)| *watch_id != id);
+        self.watchers.len() != count
+    }
+
+    /// Adds a value at `path`.  See [`JsonEdit::Add`](enum.JsonEdit.html#variant.Add).
+    pub fn add(&mut self, path: JsonPointer, value: JsonValue) -> Result<&JsonChange, EditError> {
+        self.apply(JsonEdit::Add { path, value })
+    }
+
+    /// Removes the value at `path`.
+    pub fn remove(&mut self, path: JsonPointer) -> Result<&JsonChange, EditError> {
+        self.apply(JsonEdit::Remove { path })
+    }
+
+    /// Replaces the existing value at `path`.
+    pub fn replace(
+        &mut self,
+        path: JsonPointer,
+        value: JsonValue,
+    ) -> Result<&JsonChange, EditError> {
+        self.apply(JsonEdit::Replace { path, value })
+    }
+}
+
+/// Whether `change` could have altered the value at `watched`.  Inserting into or removing
+/// from an array also shifts every later element, so those count as well.
+fn affects(change: &JsonChange, watched: &JsonPointer, value: &JsonValue) -> bool {
+    if watched.starts_with(&change.path) || change.path.starts_with(watched) {
+        return true;
+    }
+
+    match (change.path.split_last(), &change.old, &change.new) {
+        (Some((parent, changed)), None, _) | (Some((parent, changed)), _, None) => {
+            let depth = parent.tokens().len();
+            watched.starts_with(&parent)
+                && parent
+                    .resolve(value)
+                    .filter(|parent| matches!(parent, JsonValue::Array(_)))
+                    .and_then(|_| changed.parse::<usize>().ok())
+                    .and_then(|changed| {
+                        watched.tokens()[depth]
+                            .parse::<usize>()
+                            .ok()
+                            .map(|i| i >= changed)
+                    })
+                    .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+impl fmt::Debug for ObservedJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ObservedJson")
+            .field("value", &self.value)
+            .field("changes", &self.changes)
+            .field("watchers", &self.watchers.len())
+            .finish()
+    }
+}
+
+impl From<JsonValue> for ObservedJson {
+    fn from(value: JsonValue) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonChange, JsonValue, ObservedJson};
+    use crate::json_parse;
+
+    #[test]
+    fn observed_changes() {
+        let mut doc = ObservedJson::new(json_parse(r#"{ "a": 1, "b": [true] }"#).unwrap());
+
+        doc.replace("/a".parse().unwrap(), JsonValue::from(2.0))
+            .unwrap();
+        doc.remove("/b/0".parse().unwrap()).unwrap();
+        doc.add("/a".parse().unwrap(), JsonValue::Null).unwrap();
+        assert!(doc.remove("/c".parse().unwrap()).is_err());
+
+        assert_eq!(
+            doc.changes(),
+            &[
+                JsonChange {
+                    path: "/a".parse().unwrap(),
+                    old: Some(JsonValue::from(1.0)),
+                    new: Some(JsonValue::from(2.0)),
+                },
+                JsonChange {
+                    path: "/b/0".parse().unwrap(),
+                    old: Some(JsonValue::Boolean(true)),
+                    new: None,
+                },
+                JsonChange {
+                    path: "/a".parse().unwrap(),
+                    old: Some(JsonValue::from(2.0)),
+                    new: Some(JsonValue::Null),
+                },
+            ]
+        );
+
+        assert_eq!(doc.take_changes().len(), 3);
+        assert!(doc.changes().is_empty());
+        assert_eq!(
+            doc.into_inner(),
+            json_parse(r#"{ "a": null, "b": [] }"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn observed_watchers() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut doc = ObservedJson::new(
+            json_parse(r#"{ "a": { "b": 1 }, "list": [0, 1, 2], "c": null }"#).unwrap(),
+        );
+        let calls = Rc::new(RefCell::new(vec![]));
+
+        let watched = vec!["/a/b", "/a", "/list/1", "/list"];
+        let ids = watched
+            .into_iter()
+            .map(|path| {
+                let calls = calls.clone();
+                doc.watch(path.parse().unwrap(), move |change, value| {
+                    calls
+                        .borrow_mut()
+                        .push((path, change.path.to_string(), value.cloned()));
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let edits = vec![
+            ("/c", Some(JsonValue::Boolean(true))),
+            ("/a/b", Some(JsonValue::from(2.0))),
+            ("/list/2", None),
+            ("/list/0", None),
+        ];
+        for (path, value) in edits.into_iter() {
+            match value {
+                Some(value) => doc.replace(path.parse().unwrap(), value),
+                None => doc.remove(path.parse().unwrap()),
+            }
+            .unwrap();
+        }
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                ("/a/b", "/a/b".to_string(), Some(JsonValue::from(2.0))),
+                (
+                    "/a",
+                    "/a/b".to_string(),
+                    Some(json_parse(r#"{ "b": 2 }"#).unwrap())
+                ),
+                (
+                    "/list",
+                    "/list/2".to_string(),
+                    Some(json_parse("[0, 1]").unwrap())
+                ),
+                ("/list/1", "/list/0".to_string(), None),
+                (
+                    "/list",
+                    "/list/0".to_string(),
+                    Some(json_parse("[1]").unwrap())
+                ),
+            ]
+        );
+
+        assert!(doc.unwatch(ids[0]));
+        assert!(!doc.unwatch(ids[0]));
+        calls.borrow_mut().clear();
+        doc.replace("/a/b".parse().unwrap(), JsonValue::Null)
+            .unwrap();
+        assert_eq!(calls.borrow().len(), 1);
+    }
+}
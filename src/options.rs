@@ -0,0 +1,112 @@
+/// Relaxations of strict JSON syntax, and limits on what is accepted.  The default accepts only
+/// standard JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    /// Whether `// line` and `/* block */` comments may appear wherever whitespace can
+    pub comments: bool,
+    /// Whether the last item of an array or member of an object may be followed by a comma
+    pub trailing_commas: bool,
+    /// Whether the rest of JSON5's syntax is accepted: unquoted keys, single-quoted and
+    /// multi-line strings, hexadecimal numbers, leading `+` and decimal points, and `Infinity`
+    /// and `NaN`.  Only [`json_parse_with`](fn.json_parse_with.html) reads JSON5; other parsers
+    /// reject it as they would without this option.
+    pub json5: bool,
+    /// What is done with a key that appears twice in one object.
+    pub duplicate_keys: DuplicateKeys,
+    /// How many arrays and objects may be open at once, 128 by default.  Open arrays and objects
+    /// are kept on the heap, so with a higher limit, up to `usize::MAX`, depth is bounded only by
+    /// memory.
+    pub max_depth: usize,
+    /// Whether numbers that are not integers within 64 bits are read into an exact
+    /// [`JsonNumber::Decimal`](../enum.JsonNumber.html#variant.Decimal) rather than rounded to
+    /// an `f64`.  A number too precise for a `Decimal` is rejected with
+    /// `JsonError::NumberOutOfRange`.  Hexadecimal numbers, `Infinity` and `NaN` are still read
+    /// as floats.  Enabled by the `decimal` feature.
+    #[cfg(feature = "decimal")]
+    pub exact_numbers: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            comments: false,
+            trailing_commas: false,
+            json5: false,
+            duplicate_keys: DuplicateKeys::default(),
+            max_depth: 128,
+            #[cfg(feature = "decimal")]
+            exact_numbers: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options that accept comments and trailing commas, which every parser supports.
+    pub fn lenient() -> Self {
+        Self {
+            comments: true,
+            trailing_commas: true,
+            ..Self::default()
+        }
+    }
+
+    /// Options that accept all of JSON5.
+    pub fn json5() -> Self {
+        Self {
+            json5: true,
+            ..Self::lenient()
+        }
+    }
+}
+
+/// What a parser does when an object has the same key more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateKeys {
+    /// The last value for the key is kept, which is the default
+    #[default]
+    LastWins,
+    /// The first value for the key is kept
+    FirstWins,
+    /// The document is rejected with `JsonError::DuplicateKey`
+    Error,
+}
+
+/// How pretty-printed JSON is laid out.  Every array item and object member goes on its own line,
+/// indented once more than the array or object it is in.
+/// ```
+/// #[macro_use]
+/// extern crate json_rs;
+/// use json_rs::FormatConfig;
+///
+/// fn main() {
+///     let json = json!({ "list": [1] });
+///     assert_eq!(json.to_string_pretty(), "{\n  \"list\": [\n    1\n  ]\n}");
+///
+///     let tabs = FormatConfig {
+///         indent_width: 1,
+///         use_tabs: true,
+///         space_after_colon: false,
+///     };
+///     assert_eq!(json.to_string_with(tabs), "{\n\t\"list\":[\n\t\t1\n\t]\n}");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FormatConfig {
+    /// How many spaces or tabs each level is indented by
+    pub indent_width: usize,
+    /// Whether to indent with tabs rather than spaces
+    pub use_tabs: bool,
+    /// Whether a space follows the colon after each key
+    pub space_after_colon: bool,
+}
+
+impl Default for FormatConfig {
+    /// Two spaces of indentation and a space after each colon.
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            use_tabs: false,
+            space_after_colon: true,
+        }
+    }
+}
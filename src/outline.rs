@@ -0,0 +1,228 @@
+use super::events::{Event, EventParser};
+use super::span::{Span, Spanned};
+use super::{JsonError, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// The type of a JSON value, without its contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    /// A JSON string value.
+    Text,
+    /// A numeric JSON value.
+    Number,
+    /// A JSON boolean value.
+    Boolean,
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array,
+    /// A JSON object.
+    Object,
+}
+
+impl ValueKind {
+    pub(crate) fn of(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Text(_) => ValueKind::Text,
+            JsonValue::Number(_) => ValueKind::Number,
+            JsonValue::Boolean(_) => ValueKind::Boolean,
+            JsonValue::Null => ValueKind::Null,
+            JsonValue::Array(_) => ValueKind::Array,
+            JsonValue::Object(_) => ValueKind::Object,
+            JsonValue::Raw(raw) => ValueKind::of(&raw.parse()),
+        }
+    }
+
+    /// The kind as a noun with its article, for error messages.
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            ValueKind::Text => "a string",
+            ValueKind::Number => "a number",
+            ValueKind::Boolean => "a boolean",
+            ValueKind::Null => "null",
+            ValueKind::Array => "an array",
+            ValueKind::Object => "an object",
+        }
+    }
+}
+
+/// How an outline node is reached from its parent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutlineKey {
+    /// An element of an array
+    Index(usize),
+    /// A member of an object, along with the span of the key in the source
+    Key(Spanned<String>),
+}
+
+/// One value in a document outline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineNode {
+    /// How the node is reached from its parent, or `None` for the root
+    pub key: Option<OutlineKey>,
+    /// The type of the value
+    pub kind: ValueKind,
+    /// Where the value is in the source
+    pub span: Span,
+    /// The array elements or object members, in source order
+    pub children: Vec<OutlineNode>,
+}
+
+impl OutlineNode {
+    fn new(key: Option<OutlineKey>, kind: ValueKind, span: Span) -> Self {
+        Self {
+            key,
+            kind,
+            span,
+            children: vec![],
+        }
+    }
+}
+
+/// Extracts the structure of a JSON document: every key and index along with the kind and span
+/// of the value under it.  Scalars are only checked, never kept, so the outline of a large
+/// document stays small.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{outline, OutlineKey, ValueKind};
+///
+/// fn main() {
+///     let source = r#"{ "users": [{ "name": "a" }, { "name": "b" }] }"#;
+///     let root = outline(source).unwrap();
+///     assert_eq!(root.kind, ValueKind::Object);
+///
+///     let users = &root.children[0];
+///     if let Some(OutlineKey::Key(key)) = &users.key {
+///         assert_eq!(key.value, "users");
+///     }
+///     assert_eq!(users.children.len(), 2);
+///     assert_eq!(users.children[1].key, Some(OutlineKey::Index(1)));
+///     assert_eq!(&source[users.span.start..users.span.end], r#"[{ "name": "a" }, { "name": "b" }]"#);
+/// }
+/// ```
+pub fn outline(json_str: &str) -> Result<OutlineNode, JsonError> {
+    let mut parser = EventParser::new(json_str);
+    let mut open: Vec<(OutlineNode, Option<Spanned<String>>)> = vec![];
+    let mut root = None;
+
+    while let Some((event, span)) = parser.next_event()? {
+        let complete = match event {
+            Event::ArrayStart | Event::ObjectStart => {
+                let kind = if event == Event::ArrayStart {
+                    ValueKind::Array
+                } else {
+                    ValueKind::Object
+                };
+                let key = next_key(&mut open);
+                open.push((OutlineNode::new(key, kind, span), None));
+                continue;
+            }
+            Event::Key(key) => {
+                if let Some((_, pending)) = open.last_mut() {
+                    *pending = Some(Spanned::new(key, span));
+                }
+                continue;
+            }
+            Event::Value(value) => {
+                OutlineNode::new(next_key(&mut open), ValueKind::of(&value), span)
+            }
+            Event::ArrayEnd | Event::ObjectEnd => {
+                let (mut node, _) = open.pop().unwrap();
+                node.span.end = span.end;
+                node
+            }
+        };
+
+        match open.last_mut() {
+            Some((parent, _)) => parent.children.push(complete),
+            None => root = Some(complete),
+        }
+    }
+
+    root.ok_or_else(|| JsonError::eof(json_str))
+}
+
+/// The key for the next child of the innermost open container.
+fn next_key(open: &mut [(OutlineNode, Option<Spanned<String>>)]) -> Option<OutlineKey> {
+    open.last_mut()
+        .map(|(parent, pending)| match pending.take() {
+            Some(key) => OutlineKey::Key(key),
+            None => OutlineKey::Index(parent.children.len()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outline, OutlineKey, OutlineNode, ValueKind};
+    use crate::span::{Span, Spanned};
+    use crate::{json_parse, JsonError};
+
+    #[test]
+    fn outline_structure() {
+        let source = r#"[1, {"a": "x", "b": []}, null]"#;
+        let node = |key, kind, span: (usize, usize), children| OutlineNode {
+            key,
+            kind,
+            span: Span::new(span.0, span.1),
+            children,
+        };
+        let key = |name: &str, start| {
+            Some(OutlineKey::Key(Spanned::new(
+                name.into(),
+                Span::new(start, start + 3),
+            )))
+        };
+
+        assert_eq!(
+            outline(source),
+            Ok(node(
+                None,
+                ValueKind::Array,
+                (0, 30),
+                vec![
+                    node(
+                        Some(OutlineKey::Index(0)),
+                        ValueKind::Number,
+                        (1, 2),
+                        vec![]
+                    ),
+                    node(
+                        Some(OutlineKey::Index(1)),
+                        ValueKind::Object,
+                        (4, 23),
+                        vec![
+                            node(key("a", 5), ValueKind::Text, (10, 13), vec![]),
+                            node(key("b", 15), ValueKind::Array, (20, 22), vec![]),
+                        ]
+                    ),
+                    node(
+                        Some(OutlineKey::Index(2)),
+                        ValueKind::Null,
+                        (25, 29),
+                        vec![]
+                    ),
+                ]
+            ))
+        );
+        assert_eq!(outline("\"top\"").map(|n| n.kind), Ok(ValueKind::Text));
+    }
+
+    #[test]
+    fn outline_errors() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            assert_eq!(outline(&source).is_ok(), json_parse(&source).is_ok());
+        }
+        assert_eq!(
+            outline("[1, "),
+            Err(JsonError::UnexpectedEOF {
+                line: 0,
+                column: 4,
+                offset: 4,
+            })
+        );
+    }
+}
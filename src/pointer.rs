@@ -0,0 +1,406 @@
+use super::JsonValue;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str::FromStr;
+
+/// A JSON Pointer ([RFC 6901](https://tools.ietf.org/html/rfc6901)) identifying a location
+/// within a `JsonValue`.
+///
+/// A reference token addresses an object key or, when the value is an array, an index.  As the
+/// RFC requires, an index is written in decimal without leading zeros, and `-` (the element past
+/// the end) never refers to anything.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::JsonPointer;
+///
+/// fn main() {
+///     let pointer: JsonPointer = "/a~1b/0".parse().unwrap();
+///     assert_eq!(pointer.tokens(), &["a/b".to_string(), "0".to_string()]);
+///     assert_eq!(pointer.to_string(), "/a~1b/0");
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+/// Describes the ways a JSON Pointer string can be malformed
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonPointerError {
+    /// A non-empty pointer did not begin with `/`
+    MissingSlash,
+    /// A `~` was not followed by `0` or `1`
+    InvalidEscape {
+        /// The index of the `~` character
+        location: usize,
+    },
+}
+
+impl fmt::Display for JsonPointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonPointerError::MissingSlash => f.write_str("JSON Pointer does not start with '/'"),
+            JsonPointerError::InvalidEscape { location } => {
+                write!(f, "'~' at index {} is not followed by '0' or '1'", location)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for JsonPointerError {}
+
+impl JsonPointer {
+    /// Creates a pointer to the root of a document.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Creates a pointer from unescaped reference tokens.
+    pub fn from_tokens<I, S>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            tokens: tokens.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The unescaped reference tokens of the pointer.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Whether the pointer refers to the whole document.
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Appends a reference token to the pointer.
+    pub fn push<S: Into<String>>(&mut self, token: S) {
+        self.tokens.push(token.into());
+    }
+
+    /// Returns a new pointer with `token` appended.
+    pub fn child<S: Into<String>>(&self, token: S) -> Self {
+        let mut child = self.clone();
+        child.push(token);
+        child
+    }
+
+    /// Splits the pointer into the pointer of its parent and its last token.  Returns `None`
+    /// for the root pointer.
+    pub fn split_last(&self) -> Option<(JsonPointer, &str)> {
+        self.tokens.split_last().map(|(last, parent)| {
+            (
+                Self {
+                    tokens: parent.to_vec(),
+                },
+                last.as_str(),
+            )
+        })
+    }
+
+    /// Whether `self` is equal to `prefix` or lies underneath it.
+    pub fn starts_with(&self, prefix: &JsonPointer) -> bool {
+        self.tokens.starts_with(&prefix.tokens)
+    }
+
+    /// Finds the value this pointer refers to.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonPointer, JsonValue};
+    ///
+    /// fn main() {
+    ///     let json = json_parse(r#"{ "list": [true, { "x": null }] }"#).unwrap();
+    ///     let pointer: JsonPointer = "/list/1/x".parse().unwrap();
+    ///     assert_eq!(pointer.resolve(&json), Some(&JsonValue::Null));
+    ///     assert_eq!(pointer.child("y").resolve(&json), None);
+    /// }
+    /// ```
+    pub fn resolve<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| child(value, token))
+    }
+
+    /// Finds a mutable reference to the value this pointer refers to.
+    pub fn resolve_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| child_mut(value, token))
+    }
+}
+
+/// Parses an array index token, which must have no sign or leading zeros.
+fn index(token: &str) -> Option<usize> {
+    let digits = token.bytes().all(|b| b.is_ascii_digit());
+    if !digits || token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn child<'a>(value: &'a JsonValue, token: &str) -> Option<&'a JsonValue> {
+    match value {
+        JsonValue::Array(array) => index(token).and_then(|i| array.get(i)),
+        _ => value.get(token),
+    }
+}
+
+fn child_mut<'a>(value: &'a mut JsonValue, token: &str) -> Option<&'a mut JsonValue> {
+    match value {
+        JsonValue::Array(array) => index(token).and_then(move |i| array.get_mut(i)),
+        _ => value.get_mut(token),
+    }
+}
+
+impl JsonValue {
+    /// Finds the value a JSON Pointer string refers to, or `None` if the pointer is malformed or
+    /// refers to nothing.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    /// use json_rs::JsonValue;
+    ///
+    /// fn main() {
+    ///     let json = json!({ "a/b": [1, { "m~n": true }] });
+    ///     assert_eq!(json.pointer("/a~1b/1/m~0n"), Some(&JsonValue::Boolean(true)));
+    ///     assert_eq!(json.pointer(""), Some(&json));
+    ///     assert_eq!(json.pointer("/a~1b/01"), None);
+    ///     assert_eq!(json.pointer("a~1b"), None);
+    /// }
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        pointer.parse::<JsonPointer>().ok()?.resolve(self)
+    }
+
+    /// Finds a mutable reference to the value a JSON Pointer string refers to.
+    /// ```
+    /// #[macro_use]
+    /// extern crate json_rs;
+    ///
+    /// fn main() {
+    ///     let mut json = json!({ "list": [1, 2] });
+    ///     if let Some(n) = json.pointer_mut("/list/1").and_then(|n| n.as_u64_mut()) {
+    ///         *n *= 10;
+    ///     }
+    ///     assert_eq!(json, json!({ "list": [1, 20] }));
+    /// }
+    /// ```
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        pointer.parse::<JsonPointer>().ok()?.resolve_mut(self)
+    }
+
+    /// Resolves many pointers at once, as [`JsonPointer::resolve`](struct.JsonPointer.html#method.resolve)
+    /// would one at a time, returning what each refers to in the same order.  Pointers that share
+    /// a prefix share the walk down to it, so looking up hundreds of fields of the same records
+    /// visits each record once.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, JsonPointer, JsonValue};
+    ///
+    /// fn main() {
+    ///     let json = json_parse(r#"{ "user": { "id": 7, "tags": ["a"] } }"#).unwrap();
+    ///     let pointers = ["/user/tags/0", "/user/id", "/user/name"]
+    ///         .iter()
+    ///         .map(|pointer| pointer.parse().unwrap())
+    ///         .collect::<Vec<JsonPointer>>();
+    ///
+    ///     assert_eq!(
+    ///         json.resolve_pointers(&pointers),
+    ///         [Some(&JsonValue::Text("a".into())), Some(&JsonValue::from(7)), None]
+    ///     );
+    /// }
+    /// ```
+    pub fn resolve_pointers<'a>(&'a self, pointers: &[JsonPointer]) -> Vec<Option<&'a JsonValue>> {
+        let mut order = (0..pointers.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| pointers[*a].tokens.cmp(&pointers[*b].tokens));
+        let mut found = vec![None; pointers.len()];
+        resolve_sorted(self, 0, pointers, &order, &mut found);
+        found
+    }
+}
+
+/// Resolves the pointers in `group`, which are sorted and all lead to `value` in their first
+/// `depth` tokens.
+fn resolve_sorted<'a>(
+    value: &'a JsonValue,
+    depth: usize,
+    pointers: &[JsonPointer],
+    group: &[usize],
+    found: &mut [Option<&'a JsonValue>],
+) {
+    let mut rest = group;
+    while let Some(&first) = rest.first() {
+        // those that end here sort before those that go on
+        let token = match pointers[first].tokens.get(depth) {
+            Some(token) => token,
+            None => {
+                found[first] = Some(value);
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let len = rest
+            .iter()
+            .take_while(|ind| pointers[**ind].tokens.get(depth) == Some(token))
+            .count();
+        if let Some(child) = child(value, token) {
+            resolve_sorted(child, depth + 1, pointers, &rest[..len], found);
+        }
+        rest = &rest[len..];
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = JsonPointerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::root());
+        } else if !s.starts_with('/') {
+            return Err(JsonPointerError::MissingSlash);
+        }
+
+        let mut offset = 1;
+        s[1..]
+            .split('/')
+            .map(|escaped| {
+                let token =
+                    unescape_token(escaped).map_err(|ind| JsonPointerError::InvalidEscape {
+                        location: offset + ind,
+                    });
+                offset += escaped.chars().count() + 1;
+                token
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|tokens| Self { tokens })
+    }
+}
+
+fn unescape_token(escaped: &str) -> Result<String, usize> {
+    let mut token = String::new();
+    let mut chars = escaped.chars().enumerate();
+
+    while let Some((ind, c)) = chars.next() {
+        token.push(match c {
+            '~' => match chars.next() {
+                Some((_, '0')) => '~',
+                Some((_, '1')) => '/',
+                _ => return Err(ind),
+            },
+            _ => c,
+        });
+    }
+
+    Ok(token)
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPointer, JsonPointerError};
+    use crate::json_parse;
+
+    #[test]
+    fn pointer_parse() {
+        let tests = vec![
+            ("", Ok(vec![])),
+            ("/", Ok(vec![""])),
+            ("/foo/0", Ok(vec!["foo", "0"])),
+            ("/a~1b/m~0n", Ok(vec!["a/b", "m~n"])),
+            ("/~01", Ok(vec!["~1"])),
+            ("//", Ok(vec!["", ""])),
+            ("foo", Err(JsonPointerError::MissingSlash)),
+            (
+                "/ab/c~2",
+                Err(JsonPointerError::InvalidEscape { location: 5 }),
+            ),
+            ("/~", Err(JsonPointerError::InvalidEscape { location: 1 })),
+        ];
+
+        for (st, result) in tests.into_iter() {
+            assert_eq!(
+                st.parse::<JsonPointer>(),
+                result.map(JsonPointer::from_tokens)
+            );
+        }
+
+        assert_eq!(
+            JsonPointerError::InvalidEscape { location: 5 }.to_string(),
+            "'~' at index 5 is not followed by '0' or '1'"
+        );
+    }
+
+    #[test]
+    fn pointer_display() {
+        let tests = vec!["", "/", "/foo/0", "/a~1b/m~0n", "/~01"];
+
+        for st in tests.into_iter() {
+            assert_eq!(st.parse::<JsonPointer>().unwrap().to_string(), st);
+        }
+    }
+
+    #[test]
+    fn pointer_resolve_many() {
+        let json =
+            json_parse(r#"{ "a": [{ "b": 1 }, { "b": 2, "": [] }], "a~b": null, "c": "d" }"#)
+                .unwrap();
+        let pointers = vec![
+            "/a/1/b", "", "/a/0/b", "/a/1", "/a/1/", "/a~0b", "/c/0", "/a/2/b", "/a/0/b", "/x",
+            "/a/01",
+        ]
+        .into_iter()
+        .map(|pointer| pointer.parse().unwrap())
+        .collect::<Vec<JsonPointer>>();
+
+        let one_at_a_time = pointers
+            .iter()
+            .map(|pointer| pointer.resolve(&json))
+            .collect::<Vec<_>>();
+        assert_eq!(json.resolve_pointers(&pointers), one_at_a_time);
+        assert_eq!(
+            one_at_a_time.iter().filter(|found| found.is_some()).count(),
+            7
+        );
+        assert!(json.resolve_pointers(&[]).is_empty());
+    }
+
+    #[test]
+    fn pointer_indices() {
+        let mut json = json_parse(r#"{ "0": "key", "list": [10, 11], "": { " ": 1 } }"#).unwrap();
+        let tests = vec![
+            ("/0", Some("\"key\"")),
+            ("/list/0", Some("10")),
+            ("/list/1", Some("11")),
+            ("/list/2", None),
+            ("/list/-", None),
+            ("/list/+1", None),
+            ("/list/01", None),
+            ("/list/", None),
+            ("/list/1/0", None),
+            ("/~1", None),
+            ("/", Some(r#"{" ":1}"#)),
+            ("// ", Some("1")),
+        ];
+
+        for (pointer, found) in tests.into_iter() {
+            let found = found.map(String::from);
+            assert_eq!(json.pointer(pointer).map(|v| v.to_string()), found);
+            assert_eq!(json.pointer_mut(pointer).map(|v| v.to_string()), found);
+        }
+    }
+}
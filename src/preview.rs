@@ -0,0 +1,148 @@
+use super::{escape_str, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+struct Limits {
+    max_depth: usize,
+    max_items: usize,
+    max_string_len: usize,
+}
+
+fn preview(value: &JsonValue, depth: usize, limits: &Limits, out: &mut String) {
+    match value {
+        JsonValue::Text(text) => match text.char_indices().nth(limits.max_string_len) {
+            Some((end, _)) => out.push_str(&escape_str(&format!("{}…", &text[..end]))),
+            None => out.push_str(&escape_str(text)),
+        },
+        JsonValue::Array(array) => container(
+            array.iter(),
+            array.len(),
+            ('[', ']'),
+            depth,
+            limits,
+            out,
+            |item, out| preview(item, depth + 1, limits, out),
+        ),
+        JsonValue::Object(map) => {
+            let mut members = map.iter().collect::<Vec<_>>();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+            container(
+                members.into_iter(),
+                map.len(),
+                ('{', '}'),
+                depth,
+                limits,
+                out,
+                |(key, value), out| {
+                    out.push_str(&escape_str(key));
+                    out.push_str(": ");
+                    preview(value, depth + 1, limits, out);
+                },
+            )
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Writes up to `max_items` of a container's items, then how many were left out.  A container
+/// at the depth limit, or with no items to show, is written as only the count.
+fn container<I, F>(
+    items: I,
+    len: usize,
+    (open, close): (char, char),
+    depth: usize,
+    limits: &Limits,
+    out: &mut String,
+    mut item: F,
+) where
+    I: Iterator,
+    F: FnMut(I::Item, &mut String),
+{
+    out.push(open);
+    if len > 0 && (depth >= limits.max_depth || limits.max_items == 0) {
+        out.push_str(&format!(" …{} more ", len));
+    } else {
+        for (ind, next) in items.take(limits.max_items).enumerate() {
+            if ind > 0 {
+                out.push_str(", ");
+            }
+            item(next, out);
+        }
+        if len > limits.max_items {
+            out.push_str(&format!(", …{} more", len - limits.max_items));
+        }
+    }
+    out.push(close);
+}
+
+impl JsonValue {
+    /// Renders a bounded summary of the value for logs and error messages.  It is not JSON, and
+    /// is not meant to be parsed.
+    ///
+    /// Containers nested `max_depth` levels deep are replaced by the number of items in them,
+    /// only the first `max_items` items of each array or object (whose keys are sorted) are
+    /// written, and strings are cut off after `max_string_len` characters.  Everything left out
+    /// is marked with `…`.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::json_parse;
+    ///
+    /// fn main() {
+    ///     let json = json_parse(&format!(
+    ///         r#"{{ "name": "alice", "items": [{}], "tags": ["a", "b", "c"] }}"#,
+    ///         vec!["0"; 1500].join(",")
+    ///     ))
+    ///     .unwrap();
+    ///     assert_eq!(
+    ///         json.to_string_preview(1, 2, 2),
+    ///         r#"{"items": [ …1500 more ], "name": "al…", …1 more}"#
+    ///     );
+    ///     assert_eq!(
+    ///         json.get("tags").unwrap().to_string_preview(1, 2, 2),
+    ///         r#"["a", "b", …1 more]"#
+    ///     );
+    /// }
+    /// ```
+    pub fn to_string_preview(
+        &self,
+        max_depth: usize,
+        max_items: usize,
+        max_string_len: usize,
+    ) -> String {
+        let mut out = String::new();
+        let limits = Limits {
+            max_depth,
+            max_items,
+            max_string_len,
+        };
+        preview(self, 0, &limits, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_parse;
+
+    #[test]
+    fn preview_limits() {
+        let json = json_parse(r#"[[], {}, [[1]], "ώώώ", "a\"b", { "k": [true, null] }]"#).unwrap();
+        let tests = vec![
+            ((0, 10, 10), "[ …6 more ]"),
+            (
+                (1, 10, 10),
+                r#"[[], {}, [ …1 more ], "ώώώ", "a\"b", { …1 more }]"#,
+            ),
+            (
+                (3, 10, 2),
+                r#"[[], {}, [[1]], "ώώ…", "a\"…", {"k": [true, null]}]"#,
+            ),
+            ((3, 1, 0), r#"[[], …5 more]"#),
+            ((3, 0, 0), r#"[ …6 more ]"#),
+        ];
+
+        for ((depth, items, string_len), preview) in tests.into_iter() {
+            assert_eq!(json.to_string_preview(depth, items, string_len), preview);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Parsing a document that arrives in pieces, by pushing each piece into a parser as it comes.
+use super::span::Place;
+use super::stack::array::ArrayStack;
+use super::stack::object::ObjectStack;
+use super::stack::pending::{BoolStack, NullStack, NumberStack, PendingStack, TextStack};
+use super::stack::{IntoJson, ObjArrItem, ObjArrStack, PendingItem};
+use super::{JsonError, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str;
+
+/// A parser that is fed a document in chunks of bytes, split anywhere, and builds its value as
+/// they arrive.  Only the containers that are still open and the scalar being read are held, so
+/// the document's text never has to be collected in one place.
+///
+/// Errors are reported as soon as the chunk containing them is fed, located as `json_parse`
+/// locates them, from the start of the document.  Bytes that are not valid UTF-8
+/// are reported as an unexpected `U+FFFD` at the character they would have been.  Once a chunk
+/// fails, every later call returns the same error.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonError, PushParser};
+///
+/// fn main() {
+///     let mut parser = PushParser::new();
+///     for frame in [&b"{\"na"[..], b"me\": \"caf\xc3", b"\xa9\", \"n\": 1", b"2}"].iter() {
+///         parser.feed(frame).unwrap();
+///     }
+///     assert_eq!(
+///         parser.finish(),
+///         json_parse(r#"{ "name": "café", "n": 12 }"#)
+///     );
+///
+///     let mut parser = PushParser::new();
+///     assert_eq!(
+///         parser.feed(b"[1, 2 3]"),
+///         Err(JsonError::UnexpectedToken {
+///             character: '3',
+///             location: 6,
+///             line: 0,
+///             column: 6,
+///             offset: 6,
+///         })
+///     );
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct PushParser {
+    /// The start of a character split across chunks
+    partial: Vec<u8>,
+    /// The arrays and objects that are open, innermost last
+    containers: Vec<Box<dyn ObjArrStack>>,
+    /// The string, number, boolean or `null` being read
+    scalar: Option<PendingItem>,
+    root: Option<JsonValue>,
+    place: Place,
+    error: Option<JsonError>,
+}
+
+impl PushParser {
+    /// Creates a parser that has not been fed anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the next chunk of the document.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), JsonError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        let result = self.feed_bytes(chunk);
+        if let Err(error) = &result {
+            self.error = Some(error.clone());
+        }
+        result
+    }
+
+    /// Parses the next chunk of the document from a string, which can't split a character.
+    pub fn feed_str(&mut self, chunk: &str) -> Result<(), JsonError> {
+        self.feed(chunk.as_bytes())
+    }
+
+    /// Ends the document and returns its value.
+    pub fn finish(mut self) -> Result<JsonValue, JsonError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if !self.partial.is_empty() {
+            return Err(self.place.token_err(char::REPLACEMENT_CHARACTER));
+        }
+        match self.scalar.take() {
+            Some(PendingItem::Number(stack)) => {
+                let value = Box::new(stack)
+                    .into_json()
+                    .map_err(|()| self.place.eof_err())?;
+                self.complete(value);
+            }
+            Some(_) => return Err(self.place.eof_err()),
+            None => (),
+        }
+        match self.root.take() {
+            Some(value) if self.containers.is_empty() => Ok(value),
+            _ => Err(self.place.eof_err()),
+        }
+    }
+
+    fn feed_bytes(&mut self, mut chunk: &[u8]) -> Result<(), JsonError> {
+        // finish a character left incomplete by the last chunk
+        while !self.partial.is_empty() && !chunk.is_empty() {
+            self.partial.push(chunk[0]);
+            chunk = &chunk[1..];
+            match str::from_utf8(&self.partial) {
+                Ok(text) => {
+                    let c = text.chars().next().unwrap();
+                    self.partial.clear();
+                    self.push(c)?;
+                }
+                Err(e) if e.error_len().is_some() => {
+                    return Err(self.place.token_err(char::REPLACEMENT_CHARACTER));
+                }
+                Err(_) => (),
+            }
+        }
+        if !self.partial.is_empty() {
+            return Ok(());
+        }
+
+        let (text, rest) = match str::from_utf8(chunk) {
+            Ok(text) => (text, &[][..]),
+            Err(e) => {
+                let (valid, rest) = chunk.split_at(e.valid_up_to());
+                let text = str::from_utf8(valid).unwrap();
+                if e.error_len().is_some() {
+                    text.chars().try_for_each(|c| self.push(c))?;
+                    return Err(self.place.token_err(char::REPLACEMENT_CHARACTER));
+                }
+                (text, rest)
+            }
+        };
+        text.chars().try_for_each(|c| self.push(c))?;
+        self.partial.extend_from_slice(rest);
+        Ok(())
+    }
+
+    /// Parses the next character.
+    fn push(&mut self, c: char) -> Result<(), JsonError> {
+        let place = self.place;
+        self.place.advance(c);
+
+        match self.scalar.take() {
+            Some(PendingItem::Simple(mut stack)) => {
+                if stack.push(c).map_err(|c| place.token_err(c))? {
+                    let value = stack.into_json().map_err(|()| place.token_err(c))?;
+                    self.complete(value);
+                    return Ok(());
+                }
+                self.scalar = Some(PendingItem::Simple(stack));
+                return Ok(());
+            }
+            Some(PendingItem::Number(mut stack)) => {
+                if stack.can_push(c) {
+                    stack.push(c).map_err(|c| place.token_err(c))?;
+                    self.scalar = Some(PendingItem::Number(stack));
+                    return Ok(());
+                }
+                let value = Box::new(stack)
+                    .into_json()
+                    .map_err(|()| place.token_err(c))?;
+                self.complete(value);
+            }
+            _ => (),
+        }
+
+        if c.is_whitespace() {
+            return Ok(());
+        }
+        if let Some(top) = self.containers.last_mut() {
+            if let Some(delimiter) = top.get_delimiter(c) {
+                return top.push(delimiter).map_err(|()| place.token_err(c));
+            }
+            if top.is_end_char(c) {
+                let value = self
+                    .containers
+                    .pop()
+                    .unwrap()
+                    .into_json()
+                    .map_err(|()| place.token_err(c))?;
+                self.complete(value);
+                return Ok(());
+            }
+            // an object's keys are strings, and anything else follows a colon
+            let accepts = if top.next_must_be_key() {
+                c == '"'
+            } else {
+                matches!(
+                    top.peek(),
+                    None | Some(ObjArrItem::Comma) | Some(ObjArrItem::Colon)
+                )
+            };
+            if !accepts {
+                return Err(place.token_err(c));
+            }
+        } else if self.root.is_some() {
+            return Err(place.token_err(c));
+        }
+
+        match c {
+            '"' => self.scalar = Some(PendingItem::Simple(Box::new(TextStack::new()))),
+            't' => self.scalar = Some(PendingItem::Simple(Box::new(BoolStack::init_true()))),
+            'f' => self.scalar = Some(PendingItem::Simple(Box::new(BoolStack::init_false()))),
+            'n' => self.scalar = Some(PendingItem::Simple(Box::new(NullStack::init_n()))),
+            '[' => self.containers.push(Box::new(ArrayStack::new())),
+            '{' => self.containers.push(Box::new(ObjectStack::new())),
+            '-' | '0'..='9' => {
+                let mut stack = NumberStack::new();
+                stack.push(c).unwrap();
+                self.scalar = Some(PendingItem::Number(stack));
+            }
+            _ => return Err(place.token_err(c)),
+        }
+        Ok(())
+    }
+
+    /// Adds a finished value to the container it is in, or makes it the root.  Values are only
+    /// started where their container accepts one, so adding them can't fail.
+    fn complete(&mut self, value: JsonValue) {
+        match self.containers.last_mut() {
+            Some(top) => top.push(value.into()).unwrap(),
+            None => self.root = Some(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PushParser;
+    use crate::{json_parse, JsonError};
+
+    /// Feeds `source` split at `at`, then in single bytes.
+    fn push_parse(source: &str, at: usize) -> Result<crate::JsonValue, JsonError> {
+        let mut parser = PushParser::new();
+        let (head, tail) = source.as_bytes().split_at(at);
+        parser.feed(head)?;
+        for byte in tail {
+            parser.feed(&[*byte])?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn push_parser() {
+        let sources = [
+            r#" { "a": [1, -2.5e3, {}], "é": "x\"é", "b": [true, false, null] } "#,
+            "0",
+            "\"\"",
+            "[]",
+            "[[[]], {}]",
+            "",
+            "   ",
+            "[1, 2",
+            "[1,]",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{1: 2}",
+            "{\"a\": 1]",
+            "{\"a\"}",
+            "{\"a\":}",
+            "true false",
+            "tru",
+            "nul",
+            "]",
+            "-",
+            "1.",
+            "01",
+            "1x",
+            "[1]x",
+            "\"abc",
+            "\"a\tb\"",
+        ];
+        for source in sources.iter() {
+            for at in 0..=source.len() {
+                assert_eq!(
+                    push_parse(source, at),
+                    json_parse(source),
+                    "{:?} split at {}",
+                    source,
+                    at
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn push_parser_errors() {
+        let mut parser = PushParser::new();
+        assert_eq!(
+            parser.feed(b"[\"\xe9\"]"),
+            Err(JsonError::UnexpectedToken {
+                character: '\u{fffd}',
+                location: 2,
+                line: 0,
+                column: 2,
+                offset: 2,
+            })
+        );
+        assert!(parser.feed(b"]").is_err());
+        assert!(parser.finish().is_err());
+
+        let mut parser = PushParser::new();
+        parser.feed(b"\"\xc3").unwrap();
+        assert!(parser.feed(b"(\"").is_err());
+
+        let mut parser = PushParser::new();
+        parser.feed_str("\"\u{e9}").unwrap();
+        parser.feed(b"\xc3").unwrap();
+        assert_eq!(
+            parser.finish(),
+            Err(JsonError::UnexpectedToken {
+                character: '\u{fffd}',
+                location: 2,
+                line: 0,
+                column: 2,
+                offset: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Random JSON values, for property testing code that handles JSON.
+use super::{JsonNumber, JsonValue};
+#[cfg(feature = "proptest")]
+use proptest::arbitrary::Arbitrary;
+#[cfg(feature = "proptest")]
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+use rand::distr::Distribution;
+use rand::Rng;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A `rand` distribution of JSON values, mixing every kind of value, numbers of widely varying
+/// magnitude, and strings with escapes and characters outside the BMP.  Every value it produces
+/// is finite, so serializing one and parsing it back gives an equal value.
+///
+/// With the `proptest` feature, the same limits are the parameters of `JsonValue`'s
+/// `proptest::arbitrary::Arbitrary` implementation, whose values shrink towards smaller
+/// documents when a property fails.
+/// ```
+/// extern crate json_rs;
+/// extern crate rand;
+/// use json_rs::json_parse;
+/// use json_rs::random::RandomJson;
+/// use rand::rngs::SmallRng;
+/// use rand::{Rng, SeedableRng};
+///
+/// fn main() {
+///     let generator = RandomJson { max_depth: 2, ..RandomJson::default() };
+///     for json in SmallRng::seed_from_u64(7).sample_iter(generator).take(100) {
+///         assert_eq!(json_parse(&json.to_string()), Ok(json));
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RandomJson {
+    /// How deeply arrays and objects may nest; values at this depth are never containers
+    pub max_depth: usize,
+    /// The most items an array or members an object may have
+    pub max_items: usize,
+    /// The most characters a string or key may have
+    pub max_string_len: usize,
+}
+
+impl Default for RandomJson {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_items: 8,
+            max_string_len: 16,
+        }
+    }
+}
+
+impl RandomJson {
+    fn value<R: Rng + ?Sized>(&self, depth: usize, rng: &mut R) -> JsonValue {
+        let kinds = if depth < self.max_depth { 6 } else { 4 };
+        match rng.random_range(0..kinds) {
+            0 => JsonValue::Null,
+            1 => JsonValue::Boolean(rng.random()),
+            2 => JsonValue::Number(number(rng)),
+            3 => JsonValue::Text(self.string(rng).into()),
+            4 => {
+                let len = rng.random_range(0..=self.max_items);
+                JsonValue::Array((0..len).map(|_| self.value(depth + 1, rng)).collect())
+            }
+            _ => {
+                let len = rng.random_range(0..=self.max_items);
+                JsonValue::Object(Box::new(
+                    (0..len)
+                        .map(|_| (self.string(rng).into(), self.value(depth + 1, rng)))
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    fn string<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let len = rng.random_range(0..=self.max_string_len);
+        (0..len)
+            .map(|_| match rng.random_range(0..8) {
+                0 => rng.random_range('\0'..='\x1F'),
+                1 => ['"', '\\', '/'][rng.random_range(0..3)],
+                2 => rng.random(),
+                _ => rng.random_range(' '..='~'),
+            })
+            .collect()
+    }
+}
+
+/// Picks small integers, integers across the whole 64-bit range, fractions, and arbitrary finite
+/// bit patterns, which include subnormals and `-0.0`.
+fn number<R: Rng + ?Sized>(rng: &mut R) -> JsonNumber {
+    match rng.random_range(0..5) {
+        0 => rng.random_range(-1000..=1000).into(),
+        1 => rng.random::<i64>().into(),
+        2 => JsonNumber::U64(rng.random()),
+        3 => JsonNumber::F64(rng.random_range(-1e6..1e6)),
+        _ => loop {
+            let n = f64::from_bits(rng.random());
+            if n.is_finite() {
+                break JsonNumber::F64(n);
+            }
+        },
+    }
+}
+
+impl Distribution<JsonValue> for RandomJson {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> JsonValue {
+        self.value(0, rng)
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl Arbitrary for JsonValue {
+    type Parameters = RandomJson;
+    type Strategy = BoxedStrategy<JsonValue>;
+
+    /// Values of the same kinds `RandomJson` samples, nested at most `max_depth` levels deep.
+    /// ```
+    /// #[macro_use]
+    /// extern crate proptest;
+    /// extern crate json_rs;
+    /// use json_rs::random::RandomJson;
+    /// use json_rs::{json_parse, JsonValue};
+    /// use proptest::prelude::*;
+    ///
+    /// fn shallow() -> impl Strategy<Value = JsonValue> {
+    ///     any_with::<JsonValue>(RandomJson { max_depth: 2, ..RandomJson::default() })
+    /// }
+    ///
+    /// proptest! {
+    ///     fn round_trip(json in shallow()) {
+    ///         prop_assert_eq!(json_parse(&json.to_string()), Ok(json));
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     round_trip();
+    /// }
+    /// ```
+    fn arbitrary_with(limits: RandomJson) -> Self::Strategy {
+        let leaf = prop_oneof![
+            Just(JsonValue::Null),
+            proptest::bool::ANY.prop_map(JsonValue::Boolean),
+            number_strategy().prop_map(JsonValue::Number),
+            string_strategy(limits.max_string_len).prop_map(|text| JsonValue::Text(text.into())),
+        ];
+        let items = limits.max_items;
+        leaf.prop_recursive(
+            limits.max_depth as u32,
+            (items * limits.max_depth).max(1) as u32 * 8,
+            items as u32,
+            move |inner| {
+                let key = string_strategy(limits.max_string_len);
+                prop_oneof![
+                    proptest::collection::vec(inner.clone(), 0..=items).prop_map(JsonValue::Array),
+                    proptest::collection::vec((key, inner), 0..=items).prop_map(|members| {
+                        JsonValue::Object(Box::new(
+                            members
+                                .into_iter()
+                                .map(|(key, value)| (key.into(), value))
+                                .collect(),
+                        ))
+                    }),
+                ]
+            },
+        )
+        .boxed()
+    }
+}
+
+/// Strings of the characters `RandomJson::string` picks, mostly printable ASCII.
+#[cfg(feature = "proptest")]
+fn string_strategy(max_len: usize) -> impl Strategy<Value = String> {
+    let c = prop_oneof![
+        1 => proptest::char::range('\0', '\x1F'),
+        1 => proptest::sample::select(vec!['"', '\\', '/']),
+        1 => proptest::char::any(),
+        5 => proptest::char::range(' ', '~'),
+    ];
+    proptest::collection::vec(c, 0..=max_len).prop_map(|chars| chars.into_iter().collect())
+}
+
+/// The numbers `number` picks, with every finite `f64` standing in for arbitrary bit patterns.
+#[cfg(feature = "proptest")]
+fn number_strategy() -> impl Strategy<Value = JsonNumber> {
+    use proptest::num::f64;
+
+    prop_oneof![
+        (-1000i64..=1000).prop_map(JsonNumber::from),
+        proptest::num::i64::ANY.prop_map(JsonNumber::from),
+        proptest::num::u64::ANY.prop_map(JsonNumber::U64),
+        (-1e6..1e6).prop_map(JsonNumber::F64),
+        (f64::POSITIVE | f64::NEGATIVE | f64::NORMAL | f64::SUBNORMAL | f64::ZERO)
+            .prop_map(JsonNumber::F64),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RandomJson;
+    use crate::{json_parse, json_parse_borrowed, ArcJson, JsonValue};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn depth(json: &JsonValue) -> usize {
+        match json {
+            JsonValue::Array(array) => 1 + array.iter().map(depth).max().unwrap_or(0),
+            JsonValue::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn random_round_trip() {
+        let generator = RandomJson::default();
+        for json in SmallRng::seed_from_u64(1994)
+            .sample_iter(generator)
+            .take(500)
+        {
+            let source = json.to_string();
+            assert_eq!(json_parse(&source).as_ref(), Ok(&json));
+            assert_eq!(
+                json_parse_borrowed(&source).map(|j| j.to_owned()).as_ref(),
+                Ok(&json)
+            );
+            assert_eq!(ArcJson::from(json.clone()).to_json(), json);
+        }
+    }
+
+    #[test]
+    fn random_limits() {
+        let generator = RandomJson {
+            max_depth: 2,
+            max_items: 3,
+            max_string_len: 4,
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+        let values = (0..200).map(|_| rng.sample(generator)).collect::<Vec<_>>();
+
+        assert!(values.iter().all(|json| depth(json) <= 2));
+        assert!(values.iter().any(|json| depth(json) == 2));
+        for json in values.iter() {
+            if let JsonValue::Array(array) = json {
+                assert!(array.len() <= 3);
+            }
+            if let JsonValue::Text(text) = json {
+                assert!(text.chars().count() <= 4);
+            }
+        }
+
+        let scalars = RandomJson {
+            max_depth: 0,
+            ..generator
+        };
+        assert!((0..50).all(|_| depth(&rng.sample(scalars)) == 0));
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest! {
+        #[test]
+        fn arbitrary_round_trip(json in proptest::arbitrary::any::<JsonValue>()) {
+            let source = json.to_string();
+            prop_assert_eq!(json_parse(&source), Ok(json.clone()));
+            prop_assert_eq!(json_parse_borrowed(&source).map(|j| j.to_owned()), Ok(json));
+        }
+
+        #[test]
+        fn arbitrary_limits(json in proptest::arbitrary::any_with::<JsonValue>(RandomJson {
+            max_depth: 2,
+            max_items: 3,
+            max_string_len: 4,
+        })) {
+            prop_assert!(depth(&json) <= 2);
+            match json {
+                JsonValue::Array(ref array) => prop_assert!(array.len() <= 3),
+                JsonValue::Object(ref map) => prop_assert!(map.len() <= 3),
+                JsonValue::Text(ref text) => prop_assert!(text.chars().count() <= 4),
+                _ => (),
+            }
+        }
+    }
+}
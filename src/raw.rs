@@ -0,0 +1,105 @@
+//! JSON text that is checked but kept unparsed, to be parsed later or written out unchanged.
+use super::options::ParseOptions;
+use super::visit::{json_visit_with, JsonVisitor};
+use super::{json_parse_with, JsonError, JsonNumber, JsonValue};
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str::FromStr;
+
+/// A complete JSON value held as its text.  The text is checked when the `RawJson` is made, but
+/// not parsed until [`parse`](#method.parse) is called.
+///
+/// As [`JsonValue::Raw`](enum.JsonValue.html#variant.Raw) it can sit anywhere in a value, and
+/// is written exactly as it was given, whitespace included, so a large sub-document can be
+/// passed along without being taken apart and put back together.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonValue, RawJson};
+///
+/// fn main() {
+///     let payload: RawJson = r#"{ "rows": [1, 2, 3] }"#.parse().unwrap();
+///     let mut batch = json_parse("[7]").unwrap();
+///     batch.as_array_mut().unwrap().push(JsonValue::Raw(payload));
+///     assert_eq!(batch.to_string(), r#"[7,{ "rows": [1, 2, 3] }]"#);
+///
+///     let payload = batch[1].as_raw().unwrap();
+///     assert_eq!(payload.parse()["rows"][2], JsonValue::from(3));
+///
+///     assert!("[1, 2".parse::<RawJson>().is_err());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RawJson(Box<str>);
+
+/// Checks a document without building anything.
+struct Check;
+
+impl JsonVisitor for Check {}
+
+/// The options text is checked and parsed with, which put no limit on depth.
+fn unlimited() -> ParseOptions {
+    ParseOptions {
+        max_depth: usize::MAX,
+        ..ParseOptions::default()
+    }
+}
+
+impl RawJson {
+    /// Checks that `json` holds exactly one JSON value, and keeps it as it is.
+    pub fn from_string(json: String) -> Result<Self, JsonError> {
+        json_visit_with(&json, unlimited(), &mut Check)?;
+        Ok(RawJson(json.into_boxed_str()))
+    }
+
+    /// Writes a value out to keep as text, or `None` if it holds an infinite or `NaN` number,
+    /// which JSON has no way to write.
+    pub fn from_value(value: &JsonValue) -> Option<Self> {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                JsonValue::Number(JsonNumber::F64(n)) if !n.is_finite() => return None,
+                JsonValue::Array(array) => pending.extend(array.iter()),
+                JsonValue::Object(map) => pending.extend(map.values()),
+                _ => (),
+            }
+        }
+        Some(RawJson(value.to_string().into_boxed_str()))
+    }
+
+    /// The text as it was given.
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    /// Parses the text, however deeply it is nested.
+    pub fn parse(&self) -> JsonValue {
+        // the text was checked with the same options when it was made, so it parses
+        json_parse_with(&self.0, unlimited()).expect("RawJson holds a valid document")
+    }
+
+    /// Takes the text back.
+    pub fn into_string(self) -> String {
+        self.0.into()
+    }
+}
+
+impl FromStr for RawJson {
+    type Err = JsonError;
+
+    fn from_str(json: &str) -> Result<Self, JsonError> {
+        Self::from_string(json.into())
+    }
+}
+
+impl fmt::Display for RawJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<RawJson> for JsonValue {
+    fn from(raw: RawJson) -> Self {
+        JsonValue::Raw(raw)
+    }
+}
@@ -0,0 +1,414 @@
+//! Parsing that carries on past syntax errors, so that every problem in a document can be
+//! reported at once.
+use super::events::Expect;
+use super::lexer::{Cursor, Punct};
+use super::span::Place;
+use super::{JsonError, JsonMap, JsonString, JsonValue};
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+enum Token {
+    Punct(Punct),
+    Value(JsonValue),
+    /// A scalar that could not be read
+    Bad(JsonError),
+    End,
+}
+
+enum Open {
+    Array(Vec<JsonValue>),
+    Object(JsonMap, JsonString),
+}
+
+struct Recovery<'a> {
+    source: &'a str,
+    cursor: Cursor<'a>,
+    /// A structural character that a bad scalar swallowed, to be read as the next token
+    swallowed: Option<(Punct, Place)>,
+    open: Vec<Open>,
+    root: Option<JsonValue>,
+    expect: Expect,
+    errors: Vec<JsonError>,
+}
+
+impl<'a> Recovery<'a> {
+    /// Reads the next token, along with where it starts.
+    fn next(&mut self) -> (Token, Place) {
+        if let Some((punct, place)) = self.swallowed.take() {
+            return (Token::Punct(punct), place);
+        }
+        self.cursor.whitespace();
+        let place = self.cursor.place();
+        if self.cursor.peek().is_none() {
+            return (Token::End, place);
+        }
+        if let Some((punct, _)) = self.cursor.punct() {
+            return (Token::Punct(punct), place);
+        }
+        let error = match self.cursor.scalar() {
+            Ok(scalar) => return (Token::Value(scalar.value), place),
+            Err(error) => error,
+        };
+        if let JsonError::UnexpectedToken {
+            character,
+            location,
+            line,
+            column,
+            offset,
+        } = error
+        {
+            // a scalar cut short by punctuation consumed it, but the punctuation still counts
+            if location < self.cursor.place().location {
+                let at = Place {
+                    location,
+                    line,
+                    column,
+                    offset,
+                };
+                self.swallowed = Punct::from_char(character).map(|punct| (punct, at));
+            }
+        }
+        (Token::Bad(error), place)
+    }
+
+    fn error(&mut self, error: JsonError) {
+        if self.errors.last() != Some(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Reports the token at `place` as unexpected, unless reading it already failed.
+    fn unexpected(&mut self, token: Token, place: Place) {
+        match token {
+            Token::Bad(error) => self.error(error),
+            Token::End => {
+                let error = self.cursor.eof_err();
+                self.error(error);
+            }
+            _ => {
+                let c = self.source[place.offset..].chars().next().unwrap();
+                self.error(place.token_err(c));
+            }
+        }
+    }
+
+    /// Adds a finished value to the container it is in, or makes it the root.
+    fn complete(&mut self, value: JsonValue) {
+        match self.open.last_mut() {
+            Some(Open::Array(items)) => items.push(value),
+            Some(Open::Object(members, key)) => {
+                members.insert(mem::take(key), value);
+            }
+            None => {
+                self.root = Some(value);
+                self.expect = Expect::End;
+                return;
+            }
+        }
+        self.expect = Expect::Next;
+    }
+
+    fn start(&mut self, punct: Punct) {
+        if punct == Punct::ArrayStart {
+            self.open.push(Open::Array(vec![]));
+            self.expect = Expect::Value { first: true };
+        } else {
+            self.open
+                .push(Open::Object(JsonMap::new(), JsonString::new()));
+            self.expect = Expect::Key { first: true };
+        }
+    }
+
+    /// Ends the innermost container with `punct`, reporting it if it ends the other kind.
+    fn end(&mut self, punct: Punct, place: Place) {
+        let value = match self.open.pop() {
+            Some(Open::Array(items)) => {
+                if punct != Punct::ArrayEnd {
+                    self.unexpected(Token::Punct(punct), place);
+                }
+                JsonValue::Array(items)
+            }
+            Some(Open::Object(members, _)) => {
+                if punct != Punct::ObjectEnd {
+                    self.unexpected(Token::Punct(punct), place);
+                }
+                JsonValue::Object(Box::new(members))
+            }
+            None => return self.unexpected(Token::Punct(punct), place),
+        };
+        self.complete(value);
+    }
+
+    /// Moves on from a comma, which separates whatever the innermost container holds.
+    fn separate(&mut self) {
+        self.expect = match self.open.last() {
+            Some(Open::Object(..)) => Expect::Key { first: false },
+            _ => Expect::Value { first: false },
+        };
+    }
+
+    /// Handles one token.  Returns `false` once nothing more can be read.
+    fn step(&mut self, token: Token, place: Place) -> bool {
+        use self::Expect::*;
+
+        match (self.expect, token) {
+            (_, Token::End) if self.expect == End => return false,
+            (_, Token::End) => {
+                self.unexpected(Token::End, place);
+                return false;
+            }
+            (End, token) => {
+                self.unexpected(token, place);
+                return false;
+            }
+            // recovery stops at the end of the root value rather than going on to `Done`
+            (Done, _) => return false,
+
+            (Value { .. }, Token::Punct(p @ Punct::ArrayStart))
+            | (Value { .. }, Token::Punct(p @ Punct::ObjectStart)) => self.start(p),
+            (Value { .. }, Token::Value(value)) => self.complete(value),
+            (Value { first }, Token::Punct(p @ Punct::ArrayEnd))
+            | (Value { first }, Token::Punct(p @ Punct::ObjectEnd)) => {
+                // only an empty array can end where a value should be
+                if !first || p != Punct::ArrayEnd {
+                    self.unexpected(Token::Punct(p), place);
+                }
+                if !self.open.is_empty() {
+                    self.end(p, place);
+                }
+            }
+            (Value { .. }, Token::Punct(Punct::Comma)) if !self.open.is_empty() => {
+                self.unexpected(Token::Punct(Punct::Comma), place);
+                self.complete(JsonValue::Null);
+                self.separate();
+            }
+            (Value { .. }, token @ Token::Bad(_)) => {
+                self.unexpected(token, place);
+                self.complete(JsonValue::Null);
+            }
+            (Value { .. }, token) => self.unexpected(token, place),
+
+            (Key { .. }, Token::Value(JsonValue::Text(ref mut key))) => {
+                self.set_key(mem::take(key));
+                self.expect = Colon;
+            }
+            (Key { first }, Token::Punct(p @ Punct::ObjectEnd)) => {
+                if !first {
+                    self.unexpected(Token::Punct(p), place);
+                }
+                self.end(p, place);
+            }
+            (Key { .. }, Token::Punct(p @ Punct::ArrayEnd)) => self.end(p, place),
+            (Key { .. }, Token::Punct(p @ Punct::ArrayStart))
+            | (Key { .. }, Token::Punct(p @ Punct::ObjectStart)) => {
+                self.unexpected(Token::Punct(p), place);
+                self.start(p);
+            }
+            (Key { .. }, Token::Punct(Punct::Colon)) => {
+                self.unexpected(Token::Punct(Punct::Colon), place);
+                self.expect = Value { first: false };
+            }
+            (Key { .. }, Token::Punct(Punct::Comma)) => {
+                self.unexpected(Token::Punct(Punct::Comma), place);
+            }
+            (Key { .. }, token) => {
+                // take whatever is here as the key, like an unquoted name
+                self.unexpected(token, place);
+                self.expect = Colon;
+            }
+
+            (Colon, Token::Punct(Punct::Colon)) => self.expect = Value { first: false },
+            (Colon, Token::Punct(Punct::Comma)) => {
+                self.unexpected(Token::Punct(Punct::Comma), place);
+                self.complete(JsonValue::Null);
+                self.separate();
+            }
+            (Colon, Token::Punct(p @ Punct::ArrayEnd))
+            | (Colon, Token::Punct(p @ Punct::ObjectEnd)) => {
+                self.unexpected(Token::Punct(p), place);
+                self.complete(JsonValue::Null);
+                self.end(p, place);
+            }
+            (Colon, token) => {
+                // a missing colon, so read the value it should have come before
+                self.report_missing(&token, place);
+                self.expect = Value { first: false };
+                return self.step(token, place);
+            }
+
+            (Next, Token::Punct(Punct::Comma)) => self.separate(),
+            (Next, Token::Punct(p @ Punct::ArrayEnd))
+            | (Next, Token::Punct(p @ Punct::ObjectEnd)) => self.end(p, place),
+            (Next, Token::Punct(Punct::Colon)) => {
+                self.unexpected(Token::Punct(Punct::Colon), place);
+            }
+            (Next, token) => {
+                // a missing comma, so read the item it should have come before
+                self.report_missing(&token, place);
+                self.separate();
+                return self.step(token, place);
+            }
+        }
+        true
+    }
+
+    fn set_key(&mut self, key: JsonString) {
+        if let Some(Open::Object(_, pending)) = self.open.last_mut() {
+            *pending = key;
+        }
+    }
+
+    /// Reports a token that came without the punctuation that should precede it.  A bad token is
+    /// reported when it is handled instead, so it isn't reported twice.
+    fn report_missing(&mut self, token: &Token, place: Place) {
+        if let Token::Bad(_) = token {
+            return;
+        }
+        let c = self.source[place.offset..].chars().next().unwrap();
+        self.error(place.token_err(c));
+    }
+}
+
+/// Parses a JSON string like [`json_parse`](fn.json_parse.html), but instead of stopping at the
+/// first syntax error, it resynchronizes and carries on to report every error it finds, in
+/// source order.
+///
+/// A missing comma or colon is assumed to be there, a missing value or key is skipped, and a
+/// closing bracket of the wrong kind still closes the innermost container.  Each error is reported
+/// once, but one mistake can lead to more errors after it, as with a compiler.  Valid input parses
+/// to the same value as `json_parse`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse_all_errors;
+///
+/// fn main() {
+///     let errors = json_parse_all_errors("{\n  \"a\": [1 2],\n  \"b\" true,\n  \"c\": nul\n}")
+///         .unwrap_err()
+///         .iter()
+///         .map(|error| error.to_string())
+///         .collect::<Vec<_>>();
+///
+///     assert_eq!(
+///         errors,
+///         [
+///             "unexpected character '2' at line 2, column 11",
+///             "unexpected character 't' at line 3, column 7",
+///             "unexpected character '\\n' at line 4, column 11",
+///         ]
+///     );
+/// }
+/// ```
+pub fn json_parse_all_errors(json_str: &str) -> Result<JsonValue, Vec<JsonError>> {
+    trace_span!("json_parse_all_errors", bytes = json_str.len());
+    let mut recovery = Recovery {
+        source: json_str,
+        cursor: Cursor::new(json_str),
+        swallowed: None,
+        open: vec![],
+        root: None,
+        expect: Expect::Value { first: false },
+        errors: vec![],
+    };
+    loop {
+        let (token, place) = recovery.next();
+        if !recovery.step(token, place) {
+            break;
+        }
+    }
+
+    match recovery.root {
+        Some(root) if recovery.errors.is_empty() => Ok(root),
+        _ => Err(recovery.errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::json_parse_all_errors;
+    use crate::json_parse;
+
+    #[test]
+    fn all_errors_first_matches() {
+        use std::fs::{read, read_dir};
+
+        let mut sources = read_dir("./test-json")
+            .unwrap()
+            .flatten()
+            .map(|file| String::from_utf8(read(file.path()).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        sources.extend(
+            [
+                "",
+                "  ",
+                "[1, 2",
+                "[1,]",
+                "[1 2]",
+                "{\"a\" 1}",
+                "{1: 2}",
+                "{\"a\": 1]",
+                "{\"a\"}",
+                "{\"a\":}",
+                "true false",
+                "tru",
+                "]",
+                "-",
+                "1.",
+                "01",
+                "1x",
+                "[1]x",
+                "\"abc",
+            ]
+            .iter()
+            .map(|source| source.to_string()),
+        );
+
+        for source in sources.iter() {
+            let all = json_parse_all_errors(source).map_err(|errors| errors[0].clone());
+            assert_eq!(all, json_parse(source), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn all_errors_recovers() {
+        let locations = |source| {
+            json_parse_all_errors(source)
+                .unwrap_err()
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            locations("[1,, 2 3, nul]"),
+            [
+                "unexpected character ',' at line 1, column 4",
+                "unexpected character '3' at line 1, column 8",
+                "unexpected character ']' at line 1, column 14",
+            ]
+        );
+        assert_eq!(
+            locations("{\"a\": [1}, b: 2, \"c\": {\"d\": 1,}}"),
+            [
+                "unexpected character '}' at line 1, column 9",
+                "unexpected character 'b' at line 1, column 12",
+                "unexpected character '}' at line 1, column 31",
+            ]
+        );
+        assert_eq!(
+            locations("[\"a\n\", 1] 2"),
+            [
+                "unexpected character '\\n' at line 1, column 4",
+                "unexpected end of input at line 2, column 8",
+            ]
+        );
+        assert_eq!(
+            locations("[{\"a\" 1, \"b\":}"),
+            [
+                "unexpected character '1' at line 1, column 7",
+                "unexpected character '}' at line 1, column 14",
+                "unexpected end of input at line 1, column 15",
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use super::options::ParseOptions;
+use super::tokens::{tokenize_with, Token};
+use super::JsonError;
+use std::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Pending changes around a single token.
+#[derive(Clone, Debug, Default)]
+struct Slot {
+    before: String,
+    replacement: Option<String>,
+    after: String,
+}
+
+/// Rewrites JSON source one token at a time, leaving everything that is not touched exactly as
+/// it was.
+///
+/// Edits are recorded against token indices and only applied by [`emit`](#method.emit), so the
+/// indices of [`tokens`](#method.tokens) stay valid throughout.  Nothing is checked about the
+/// text that is written in; it is up to the caller to keep the result valid JSON.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{TokenKind, TokenRewriter};
+///
+/// fn main() {
+///     let source = "[\n  { \"id\": 7, \"name\": \"a\" },\n  { \"id\": 3, \"name\": \"b\" }\n]";
+///     let mut rewriter = TokenRewriter::new(source).unwrap();
+///
+///     let mut next_id = 1;
+///     let mut after_id = false;
+///     for ind in 0..rewriter.tokens().len() {
+///         let token = rewriter.tokens()[ind];
+///         match token.kind {
+///             TokenKind::Key if token.text == r#""name""# => rewriter.replace(ind, r#""label""#),
+///             TokenKind::Key => after_id = token.text == r#""id""#,
+///             TokenKind::Number if after_id => {
+///                 rewriter.replace(ind, next_id.to_string());
+///                 next_id += 1;
+///                 after_id = false;
+///             }
+///             _ => (),
+///         }
+///     }
+///
+///     assert_eq!(
+///         rewriter.emit(),
+///         "[\n  { \"id\": 1, \"label\": \"a\" },\n  { \"id\": 2, \"label\": \"b\" }\n]"
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenRewriter<'a> {
+    tokens: Vec<Token<'a>>,
+    slots: BTreeMap<usize, Slot>,
+}
+
+impl<'a> TokenRewriter<'a> {
+    /// Tokenizes a JSON string for rewriting.
+    pub fn new(json_str: &'a str) -> Result<Self, JsonError> {
+        Self::with_options(json_str, ParseOptions::default())
+    }
+
+    /// Tokenizes a JSON string for rewriting, accepting the syntax allowed by `options`.
+    pub fn with_options(json_str: &'a str, options: ParseOptions) -> Result<Self, JsonError> {
+        let mut tokens = tokenize_with(json_str, options);
+        let collected = tokens.by_ref().collect();
+
+        match tokens.error() {
+            Some(e) => Err(e.clone()),
+            None => Ok(Self {
+                tokens: collected,
+                slots: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// The original tokens, in source order.
+    pub fn tokens(&self) -> &[Token<'a>] {
+        &self.tokens
+    }
+
+    fn slot(&mut self, ind: usize) -> &mut Slot {
+        assert!(ind < self.tokens.len(), "token index out of bounds");
+        self.slots.entry(ind).or_default()
+    }
+
+    /// Replaces the text of a token, overriding any earlier replacement or deletion.
+    ///
+    /// # Panics
+    /// If there is no token at `ind`.
+    pub fn replace<S: Into<String>>(&mut self, ind: usize, text: S) {
+        self.slot(ind).replacement = Some(text.into());
+    }
+
+    /// Removes a token.  Text inserted around it is kept.
+    ///
+    /// # Panics
+    /// If there is no token at `ind`.
+    pub fn delete(&mut self, ind: usize) {
+        self.replace(ind, "");
+    }
+
+    /// Inserts text before a token, after anything already inserted there.
+    ///
+    /// # Panics
+    /// If there is no token at `ind`.
+    pub fn insert_before<S: AsRef<str>>(&mut self, ind: usize, text: S) {
+        self.slot(ind).before.push_str(text.as_ref());
+    }
+
+    /// Inserts text after a token, after anything already inserted there.
+    ///
+    /// # Panics
+    /// If there is no token at `ind`.
+    pub fn insert_after<S: AsRef<str>>(&mut self, ind: usize, text: S) {
+        self.slot(ind).after.push_str(text.as_ref());
+    }
+
+    /// Drops every recorded edit.
+    pub fn reset(&mut self) {
+        self.slots.clear();
+    }
+
+    /// Produces the source with every edit applied.
+    pub fn emit(&self) -> String {
+        let mut out = String::new();
+        for (ind, token) in self.tokens.iter().enumerate() {
+            match self.slots.get(&ind) {
+                Some(slot) => {
+                    out.push_str(&slot.before);
+                    out.push_str(slot.replacement.as_ref().map_or(token.text, |text| text));
+                    out.push_str(&slot.after);
+                }
+                None => out.push_str(token.text),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenRewriter;
+    use crate::options::ParseOptions;
+    use crate::tokens::TokenKind;
+    use crate::JsonError;
+
+    #[test]
+    fn rewriter_edits() {
+        let source = "{ \"a\" : [1,2] } ";
+        let mut rewriter = TokenRewriter::new(source).unwrap();
+        assert_eq!(rewriter.emit(), source);
+
+        // tokens: { _ "a" _ : _ [ 1 , 2 ] _ } _
+        rewriter.delete(8);
+        rewriter.delete(9);
+        rewriter.insert_before(7, "0, ");
+        rewriter.insert_after(7, " /* one */");
+        rewriter.replace(2, "\"b\"");
+        rewriter.replace(2, "\"c\"");
+        rewriter.insert_before(0, "[");
+        rewriter.insert_before(0, "\n");
+        rewriter.insert_after(13, "]");
+        assert_eq!(rewriter.emit(), "[\n{ \"c\" : [0, 1 /* one */] } ]");
+
+        rewriter.reset();
+        assert_eq!(rewriter.emit(), source);
+    }
+
+    #[test]
+    fn rewriter_errors() {
+        assert_eq!(
+            TokenRewriter::new("[1 2]").map(|_| ()),
+            Err(JsonError::UnexpectedToken {
+                character: '2',
+                location: 3,
+                line: 0,
+                column: 3,
+                offset: 3,
+            })
+        );
+
+        let rewriter = TokenRewriter::with_options("// a\n1", ParseOptions::lenient()).unwrap();
+        assert_eq!(rewriter.tokens()[0].kind, TokenKind::Comment);
+    }
+}
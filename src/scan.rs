@@ -0,0 +1,156 @@
+use super::span::Place;
+use std::io::{self, BufRead};
+
+/// Finds the boundaries of JSON values in a byte stream without parsing them, so a reader can
+/// hold one value in memory at a time.  Only bracket depth and strings are tracked; whether a
+/// value is valid is left to the parser once its text is complete.
+///
+/// Offsets count bytes and locations, lines and columns count characters, like `Span` and
+/// `JsonError`.
+#[derive(Debug)]
+pub(crate) struct Scanner<R> {
+    reader: R,
+    place: Place,
+}
+
+impl<R: BufRead> Scanner<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            place: Place::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The byte offset of the next byte.
+    pub fn offset(&self) -> usize {
+        self.place.offset
+    }
+
+    /// Where the next byte is.
+    pub fn place(&self) -> Place {
+        self.place
+    }
+
+    pub fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.first().cloned()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Consumes the byte that `peek` returned.
+    pub fn bump(&mut self, byte: u8) {
+        self.reader.consume(1);
+        self.place.offset += 1;
+        // continuation bytes belong to the character before them
+        if byte & 0xC0 != 0x80 {
+            self.place.location += 1;
+            if byte == b'\n' {
+                self.place.line += 1;
+                self.place.column = 0;
+            } else {
+                self.place.column += 1;
+            }
+        }
+    }
+
+    /// Skips whitespace and returns the byte after it.
+    pub fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+            self.bump(byte);
+        }
+        Ok(None)
+    }
+
+    /// Consumes the text of one value, which must start at the next byte.  Containers and strings
+    /// run to their closing bracket or quote, and anything else up to whitespace or the next
+    /// structural character.  A structural character on its own is returned by itself, so that
+    /// parsing it reports it.
+    pub fn raw_value(&mut self) -> io::Result<Vec<u8>> {
+        let mut text = Vec::new();
+        self.copy_value(|byte| text.push(byte))?;
+        Ok(text)
+    }
+
+    /// Consumes one value like `raw_value`, passing each byte to `sink` instead of collecting
+    /// them, so a value can be skipped without holding it in memory.
+    pub fn copy_value<F: FnMut(u8)>(&mut self, mut sink: F) -> io::Result<()> {
+        let mut first = None;
+        let mut len = 0usize;
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escape = false;
+
+        while let Some(byte) = self.peek()? {
+            let scalar = depth == 0 && !in_string && first.is_some_and(|b| b != b'"');
+            if scalar && (byte.is_ascii_whitespace() || b"[]{},:\"".contains(&byte)) {
+                break;
+            }
+            self.bump(byte);
+            sink(byte);
+            first = first.or(Some(byte));
+            len += 1;
+
+            if escape {
+                escape = false;
+            } else if in_string {
+                match byte {
+                    b'\\' => escape = true,
+                    b'"' => in_string = false,
+                    _ => (),
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    _ => (),
+                }
+            }
+            let alone = len == 1 && b"]},:".contains(&byte);
+            if depth == 0 && !in_string && (alone || b"]}\"".contains(&byte)) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+
+    #[test]
+    fn scanner_boundaries() {
+        let mut scanner = Scanner::new(r#" {"a":"}"}[1,[2]]"x\"y"12 true,]é"#.as_bytes());
+        let mut values = Vec::new();
+        while scanner.skip_whitespace().unwrap().is_some() {
+            values.push(String::from_utf8(scanner.raw_value().unwrap()).unwrap());
+        }
+        assert_eq!(
+            values,
+            vec![
+                r#"{"a":"}"}"#,
+                "[1,[2]]",
+                r#""x\"y""#,
+                "12",
+                "true",
+                ",",
+                "]",
+                "é"
+            ]
+        );
+        assert_eq!(scanner.offset(), 34);
+        assert_eq!(scanner.place().location, 33);
+    }
+}
@@ -0,0 +1,255 @@
+//! Reading and writing JSON text sequences ([RFC 7464](https://tools.ietf.org/html/rfc7464)),
+//! the `application/json-seq` format, in which each value is preceded by an ASCII record
+//! separator (`0x1E`) and followed by a line feed.
+//!
+//! Because every record starts with a separator, a reader can recover from a damaged record,
+//! such as one cut short when a writer crashed, by skipping to the next separator.  That makes
+//! the format well suited to append-only logs.
+use super::{json_parse, JsonError, JsonValue};
+use std::io::{self, BufRead, Write};
+use std::{error, fmt, str};
+
+const RS: u8 = 0x1E;
+
+/// Describes all possible errors that could occur while reading a JSON text sequence.
+///
+/// Every error but `Io` is confined to one record, and a `SeqReader` carries on with the next.
+#[derive(Debug)]
+pub enum SeqError {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// A record was not valid UTF-8
+    InvalidUtf8 {
+        /// The zero-based index of the record, counting damaged records
+        record: usize,
+    },
+    /// A record was not a single valid JSON value
+    Parse {
+        /// The zero-based index of the record, counting damaged records
+        record: usize,
+        /// Why the record could not be parsed
+        error: JsonError,
+    },
+    /// A record holding a number, boolean or `null` did not end in whitespace, so it may have
+    /// been cut short (`12` could be the start of `1234`)
+    Truncated {
+        /// The zero-based index of the record, counting damaged records
+        record: usize,
+    },
+}
+
+/// Describes the error with records counted from 0, as they are stored.
+impl fmt::Display for SeqError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeqError::Io(error) => error.fmt(f),
+            SeqError::InvalidUtf8 { record } => write!(f, "record {} is not valid UTF-8", record),
+            SeqError::Parse { record, error } => write!(f, "in record {}: {}", record, error),
+            SeqError::Truncated { record } => {
+                write!(f, "record {} may have been cut short", record)
+            }
+        }
+    }
+}
+
+impl error::Error for SeqError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SeqError::Io(error) => Some(error),
+            SeqError::Parse { error, .. } => Some(error),
+            SeqError::InvalidUtf8 { .. } | SeqError::Truncated { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for SeqError {
+    fn from(error: io::Error) -> Self {
+        SeqError::Io(error)
+    }
+}
+
+/// An iterator over the values of a JSON text sequence.
+///
+/// Consecutive separators are ignored, as the RFC allows, and so is whitespace before the first
+/// separator.  Anything else before it is reported as a damaged record.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::seq::{SeqError, SeqReader};
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let log = "\x1e{\"id\":1}\n\x1e{\"id\":\n\x1e[2]\n\x1e3";
+///     let records = SeqReader::new(log.as_bytes()).collect::<Vec<_>>();
+///
+///     assert_eq!(records[0].as_ref().unwrap().get("id"), Some(&JsonValue::from(1)));
+///     assert!(matches!(records[1], Err(SeqError::Parse { record: 1, .. })));
+///     assert_eq!(records[2].as_ref().unwrap().get_ind(0), Some(&JsonValue::from(2)));
+///     assert!(matches!(records[3], Err(SeqError::Truncated { record: 3 })));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SeqReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    record: usize,
+    started: bool,
+}
+
+impl<R: BufRead> SeqReader<R> {
+    /// Creates a reader over a sequence.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            record: 0,
+            started: false,
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn parse(&self, record: usize) -> Result<JsonValue, SeqError> {
+        let text = str::from_utf8(&self.buffer).map_err(|_| SeqError::InvalidUtf8 { record })?;
+        let value = json_parse(text).map_err(|error| SeqError::Parse { record, error })?;
+        match value {
+            JsonValue::Number(_) | JsonValue::Boolean(_) | JsonValue::Null
+                if !text.ends_with(char::is_whitespace) =>
+            {
+                Err(SeqError::Truncated { record })
+            }
+            value => Ok(value),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SeqReader<R> {
+    type Item = Result<JsonValue, SeqError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            if let Err(error) = self.reader.read_until(RS, &mut self.buffer) {
+                return Some(Err(error.into()));
+            }
+            if self.buffer.is_empty() {
+                return None;
+            }
+            if self.buffer.last() == Some(&RS) {
+                self.buffer.pop();
+            }
+
+            let leading = !self.started;
+            self.started = true;
+            let blank = self.buffer.iter().all(|b| b.is_ascii_whitespace());
+            if self.buffer.is_empty() || (leading && blank) {
+                continue;
+            }
+
+            let record = self.record;
+            self.record += 1;
+            return Some(self.parse(record));
+        }
+    }
+}
+
+/// Writes values as a JSON text sequence.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::seq::SeqWriter;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let mut writer = SeqWriter::new(Vec::new());
+///     writer.write(&JsonValue::Boolean(true)).unwrap();
+///     writer.write(&JsonValue::Array(vec![])).unwrap();
+///     assert_eq!(writer.into_inner(), b"\x1etrue\n\x1e[]\n");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SeqWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> SeqWriter<W> {
+    /// Creates a writer that appends records to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes one value as a record.  The record is written with a single call to `write_all`,
+    /// so records from writers appending to the same file do not interleave.
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        let mut record = vec![RS];
+        record.extend_from_slice(value.to_string().as_bytes());
+        record.push(b'\n');
+        self.writer.write_all(&record)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SeqError, SeqReader, SeqWriter};
+    use crate::{json_parse, JsonError, JsonValue};
+
+    #[test]
+    fn seq_round_trip() {
+        let values = vec![
+            json_parse(r#"{ "a": [1, "two\n"] }"#).unwrap(),
+            JsonValue::from(12.5),
+            JsonValue::Null,
+        ];
+        let mut writer = SeqWriter::new(Vec::new());
+        for value in values.iter() {
+            writer.write(value).unwrap();
+        }
+        let bytes = writer.into_inner();
+        let read = SeqReader::new(&bytes[..])
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(read, values);
+    }
+
+    #[test]
+    fn seq_resynchronizes() {
+        let input =
+            b"\n\x1e\x1e\x1e\"ok\"\n\x1e{\"cut\x1e\xff\n\x1e  \x1e1 2\n\x1enull\n" as &[u8];
+        let results = SeqReader::new(input).collect::<Vec<_>>();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0].as_ref().unwrap(), &JsonValue::Text("ok".into()));
+        assert!(matches!(
+            results[1],
+            Err(SeqError::Parse {
+                record: 1,
+                error: JsonError::UnexpectedEOF { .. }
+            })
+        ));
+        assert!(matches!(
+            results[2],
+            Err(SeqError::InvalidUtf8 { record: 2 })
+        ));
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            "in record 1: unexpected end of input at line 1, column 6"
+        );
+        assert!(matches!(results[3], Err(SeqError::Parse { record: 3, .. })));
+        assert!(matches!(results[4], Err(SeqError::Parse { record: 4, .. })));
+        assert_eq!(results[5].as_ref().unwrap(), &JsonValue::Null);
+
+        let leading = SeqReader::new(&b"junk\x1e1\n"[..]).collect::<Vec<_>>();
+        assert!(matches!(leading[0], Err(SeqError::Parse { record: 0, .. })));
+        assert_eq!(leading[1].as_ref().unwrap(), &JsonValue::from(1.0));
+    }
+}
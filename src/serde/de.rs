@@ -0,0 +1,525 @@
+use super::super::convert::FromJson;
+use super::super::events::borrowed_text;
+use super::super::span::Span;
+use super::super::JsonNumber;
+use super::{Error, Event, EventParser, JsonError, JsonValue};
+use serde_crate::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use std::borrow::Cow;
+use std::mem;
+
+/// Deserializes values straight from JSON source, pulling one event at a time from the parser.
+/// Strings and keys without escapes are borrowed from the source, so types can hold `&str`s
+/// pointing into it.  Documents nested more than 128 levels deep are rejected, as `json_parse`
+/// rejects them, so that deserializing them cannot overflow the stack.
+#[derive(Debug)]
+pub struct Deserializer<'a> {
+    source: &'a str,
+    parser: EventParser<'a>,
+    peeked: Option<(Event, Span)>,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Creates a deserializer reading from a JSON string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(json_str: &'a str) -> Self {
+        Self {
+            source: json_str,
+            parser: EventParser::borrowing(json_str),
+            peeked: None,
+        }
+    }
+
+    fn peek(&mut self) -> Result<&Event, Error> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.next_spanned()?);
+        }
+        Ok(&self.peeked.as_ref().unwrap().0)
+    }
+
+    fn next(&mut self) -> Result<Event, Error> {
+        self.next_spanned().map(|(event, _)| event)
+    }
+
+    /// Takes the next event if it is a key, borrowing the key from the source if it can.
+    fn next_key(&mut self) -> Result<Option<Cow<'a, str>>, Error> {
+        if let Event::Key(_) = self.peek()? {
+            if let (Event::Key(key), span) = self.next_spanned()? {
+                return Ok(Some(borrowed_text(self.source, key, span)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_spanned(&mut self) -> Result<(Event, Span), Error> {
+        match self.peeked.take() {
+            Some(event) => Ok(event),
+            None => match self.parser.next_event()? {
+                Some(event) => Ok(event),
+                None => Err(JsonError::eof(self.source).into()),
+            },
+        }
+    }
+
+    /// Takes the next value if it is a number, along with its source text.
+    fn next_number(&mut self) -> Result<Option<(JsonNumber, &'a str)>, Error> {
+        if let Event::Value(JsonValue::Number(n)) = *self.peek()? {
+            let (_, span) = self.peeked.take().unwrap();
+            return Ok(Some((n, &self.source[span.start..span.end])));
+        }
+        Ok(None)
+    }
+
+    /// Checks that nothing but whitespace follows the value that was deserialized.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.peeked.take() {
+            Some(_) => Err(de::Error::custom("trailing data after value")),
+            None => self.parser.next_event().map(|_| ()).map_err(Error::from),
+        }
+    }
+
+    /// Consumes the end of a container after its visitor has finished with it.
+    fn close(&mut self, end: Event) -> Result<(), Error> {
+        if self.next()? == end {
+            Ok(())
+        } else {
+            Err(de::Error::custom("container has more items than expected"))
+        }
+    }
+}
+
+/// Deserializes an instance of `T` from a JSON string.
+/// ```
+/// extern crate json_rs;
+/// extern crate serde;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+///     label: Option<String>,
+/// }
+///
+/// fn main() {
+///     let point: Point = json_rs::serde::from_str(r#"{ "x": 1, "y": -2, "label": null }"#).unwrap();
+///     assert_eq!(point, Point { x: 1, y: -2, label: None });
+/// }
+/// ```
+pub fn from_str<'a, T: de::Deserialize<'a>>(json_str: &'a str) -> Result<T, Error> {
+    trace_span!("json_from_str", bytes = json_str.len());
+    let mut deserializer = Deserializer::from_str(json_str);
+    let value = trace_error!(T::deserialize(&mut deserializer))?;
+    trace_error!(deserializer.end())?;
+    Ok(value)
+}
+
+/// Offers a number to a visitor, as an integer if it is integral so that integer types accept it.
+pub(super) fn visit_number<'de, V: Visitor<'de>>(
+    n: JsonNumber,
+    visitor: V,
+) -> Result<V::Value, Error> {
+    if let Some(n) = n.as_u64() {
+        visitor.visit_u64(n)
+    } else if let Some(n) = n.as_i64() {
+        visitor.visit_i64(n)
+    } else {
+        visitor.visit_f64(n.as_f64())
+    }
+}
+
+/// Offers a number to a visitor that wants a 128-bit integer.  The digits are read from the
+/// number's source `text` when there is some, so integers beyond 64 bits arrive exactly; numbers
+/// that are not whole or out of range are offered as usual, for the visitor to reject.
+macro_rules! visit_wide {
+    ($($name:ident: $ty:ident => $visit:ident,)*) => {
+        $(
+            pub(super) fn $name<'de, V: Visitor<'de>>(
+                n: JsonNumber,
+                text: Option<&str>,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                match text
+                    .and_then(|text| text.parse().ok())
+                    .or_else(|| $ty::from_json(&JsonValue::Number(n)).ok())
+                {
+                    Some(wide) => visitor.$visit(wide),
+                    None => visit_number(n, visitor),
+                }
+            }
+        )*
+    };
+}
+
+visit_wide! {
+    visit_i128: i128 => visit_i128,
+    visit_u128: u128 => visit_u128,
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let (mut event, span) = self.next_spanned()?;
+        match event {
+            Event::Value(JsonValue::Text(ref mut text)) => {
+                match borrowed_text(self.source, mem::take(text).into(), span) {
+                    Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+                    Cow::Owned(text) => visitor.visit_string(text),
+                }
+            }
+            Event::Value(JsonValue::Number(n)) => visit_number(n, visitor),
+            Event::Value(JsonValue::Boolean(b)) => visitor.visit_bool(b),
+            Event::Value(_) => visitor.visit_unit(),
+            Event::ArrayStart => {
+                let value = visitor.visit_seq(Access { de: &mut *self })?;
+                self.close(Event::ArrayEnd)?;
+                Ok(value)
+            }
+            Event::ObjectStart => {
+                let value = visitor.visit_map(Access { de: &mut *self })?;
+                self.close(Event::ObjectEnd)?;
+                Ok(value)
+            }
+            _ => Err(de::Error::custom("expected a value")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if *self.peek()? == Event::Value(JsonValue::Null) {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let (mut event, span) = self.next_spanned()?;
+        match event {
+            Event::Value(JsonValue::Text(ref mut variant)) => {
+                let variant = borrowed_text(self.source, mem::take(variant).into(), span);
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            Event::ObjectStart => {
+                let value = visitor.visit_enum(Access { de: &mut *self })?;
+                self.close(Event::ObjectEnd)?;
+                Ok(value)
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or an object with a single key",
+            )),
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.next_number()? {
+            Some((n, text)) => visit_i128(n, Some(text), visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.next_number()? {
+            Some((n, text)) => visit_u128(n, Some(text), visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Walks the items of an array or the members of an object.
+struct Access<'b, 'a: 'b> {
+    de: &'b mut Deserializer<'a>,
+}
+
+impl<'de, 'b> SeqAccess<'de> for Access<'b, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if *self.de.peek()? == Event::ArrayEnd {
+            Ok(None)
+        } else {
+            seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+}
+
+impl<'de, 'b> MapAccess<'de> for Access<'b, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.de.next_key()? {
+            Some(key) => seed.deserialize(KeyDeserializer(key)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de, 'b> EnumAccess<'de> for Access<'b, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        match self.de.next_key()? {
+            Some(key) => Ok((seed.deserialize(KeyDeserializer(key))?, self)),
+            None => Err(de::Error::custom("expected an object with a single key")),
+        }
+    }
+}
+
+impl<'de, 'b> VariantAccess<'de> for Access<'b, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+}
+
+/// Deserializes object keys, which can also be read as numbers.
+pub(super) struct KeyDeserializer<'a>(pub(super) Cow<'a, str>);
+
+macro_rules! parse_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    parse_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_bool => visit_bool,
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_str;
+    use crate::serde::Error;
+    use crate::{JsonError, JsonValue};
+    use serde_crate::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Line(i8, i8),
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Scene {
+        name: String,
+        shapes: Vec<Shape>,
+        visible: Option<bool>,
+        ids: BTreeMap<u32, char>,
+        extra: JsonValue,
+    }
+
+    #[test]
+    fn from_str_types() {
+        let scene: Scene = from_str(
+            r#"{
+                "ignored": [1, {"a": null}],
+                "name": "s\n1",
+                "shapes": ["Empty", {"Circle": 0.5}, {"Rect": {"h": 2, "w": 3}}, {"Line": [-1, 1]}],
+                "ids": {"1": "a", "20": "b"},
+                "visible": true,
+                "extra": {"k": [false]}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene,
+            Scene {
+                name: "s\n1".into(),
+                shapes: vec![
+                    Shape::Empty,
+                    Shape::Circle(0.5),
+                    Shape::Rect { w: 3, h: 2 },
+                    Shape::Line(-1, 1),
+                ],
+                visible: Some(true),
+                ids: vec![(1, 'a'), (20, 'b')].into_iter().collect(),
+                extra: crate::json_parse(r#"{"k": [false]}"#).unwrap(),
+            }
+        );
+
+        assert_eq!(
+            from_str::<(u8, f32, ())>("[1, 2.5, null]"),
+            Ok((1, 2.5, ()))
+        );
+        assert_eq!(
+            from_str::<HashMap<String, Option<i64>>>(r#"{"a": null}"#).unwrap()["a"],
+            None
+        );
+    }
+
+    #[test]
+    fn from_str_errors() {
+        assert_eq!(
+            from_str::<Vec<u8>>("[1, 2"),
+            Err(Error::Syntax(JsonError::UnexpectedEOF {
+                line: 0,
+                column: 5,
+                offset: 5,
+            }))
+        );
+        assert_eq!(
+            from_str::<u8>("1 2"),
+            Err(Error::Syntax(JsonError::UnexpectedToken {
+                character: '2',
+                location: 2,
+                line: 0,
+                column: 2,
+                offset: 2,
+            }))
+        );
+        assert!(from_str::<u8>("256").is_err());
+        assert!(from_str::<u8>("1.5").is_err());
+        assert!(from_str::<(u8, u8)>("[1, 2, 3]").is_err());
+        assert!(from_str::<Shape>(r#"{"Circle": 1, "Empty": null}"#).is_err());
+        assert!(from_str::<String>("{}").is_err());
+    }
+
+    #[test]
+    fn from_str_wide_integers() {
+        assert_eq!(
+            from_str::<(i128, u128, u128)>(
+                "[-170141183460469231731687303715884105728, 340282366920938463463374607431768211455, 2.5e3]"
+            ),
+            Ok((i128::MIN, u128::MAX, 2500))
+        );
+        assert_eq!(
+            from_str::<Option<u128>>("18446744073709551617"),
+            Ok(Some(18_446_744_073_709_551_617))
+        );
+        assert!(from_str::<u128>("340282366920938463463374607431768211456").is_err());
+        assert!(from_str::<u128>("-1").is_err());
+        assert!(from_str::<i128>("1.5").is_err());
+        assert!(from_str::<i128>("\"1\"").is_err());
+    }
+
+    #[test]
+    fn from_str_borrowed() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Entry<'a> {
+            name: &'a str,
+            tags: Vec<&'a str>,
+            counts: HashMap<&'a str, u8>,
+        }
+
+        let source = r#"{"name": "ada", "tags": ["x", ""], "counts": {"a": 1}}"#;
+        let entry: Entry = from_str(source).unwrap();
+        assert_eq!(entry.name, "ada");
+        assert_eq!(entry.tags, ["x", ""]);
+        assert_eq!(entry.counts["a"], 1);
+        assert_eq!(entry.name.as_ptr(), source[10..].as_ptr());
+
+        // a string with escapes has to be decoded, so it can't be borrowed
+        assert!(from_str::<Entry>(r#"{"name": "a\nb", "tags": [], "counts": {}}"#).is_err());
+        assert_eq!(
+            from_str::<HashMap<String, String>>(r#"{"k\u0065y": "a\nb"}"#).unwrap()["key"],
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn from_str_depth() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(from_str::<JsonValue>(&nested(128)).is_ok());
+        assert_eq!(
+            from_str::<JsonValue>(&"[".repeat(5000)),
+            Err(Error::Syntax(JsonError::DepthLimitExceeded {
+                limit: 128,
+                location: 128,
+                line: 0,
+                column: 128,
+                offset: 128,
+            }))
+        );
+    }
+}
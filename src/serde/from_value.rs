@@ -0,0 +1,302 @@
+use super::super::JsonMap;
+use super::de::{visit_i128, visit_number, visit_u128, KeyDeserializer};
+use super::{Error, JsonValue};
+use serde_crate::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use std::mem;
+use std::vec;
+
+/// Converts a parsed document into an instance of `T` without going back through text.
+/// ```
+/// extern crate json_rs;
+/// extern crate serde;
+/// use json_rs::json_parse;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Server {
+///     host: String,
+///     ports: Vec<u16>,
+/// }
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "host": "localhost", "ports": [80, 443] }"#).unwrap();
+///     let server: Server = json_rs::serde::from_value(json).unwrap();
+///     assert_eq!(server, Server { host: "localhost".into(), ports: vec![80, 443] });
+/// }
+/// ```
+pub fn from_value<T: de::DeserializeOwned>(value: JsonValue) -> Result<T, Error> {
+    T::deserialize(value)
+}
+
+impl<'de> de::Deserializer<'de> for JsonValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            JsonValue::Text(ref mut text) => visitor.visit_string(mem::take(text).into()),
+            JsonValue::Number(n) => visit_number(n, visitor),
+            JsonValue::Boolean(b) => visitor.visit_bool(b),
+            JsonValue::Null => visitor.visit_unit(),
+            JsonValue::Raw(ref raw) => raw.parse().deserialize_any(visitor),
+            JsonValue::Array(ref mut array) => {
+                let array = mem::take(array);
+                let len = array.len();
+                let mut seq = SeqDeserializer {
+                    items: array.into_iter(),
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+                if seq.items.len() == 0 {
+                    Ok(value)
+                } else {
+                    Err(de::Error::invalid_length(len, &"fewer elements in array"))
+                }
+            }
+            JsonValue::Object(ref mut members) => {
+                let members = mem::take(&mut **members);
+                let len = members.len();
+                let mut map = MapDeserializer {
+                    members: members.into_iter(),
+                    value: None,
+                };
+                let value = visitor.visit_map(&mut map)?;
+                if map.members.len() == 0 {
+                    Ok(value)
+                } else {
+                    Err(de::Error::invalid_length(len, &"fewer members in object"))
+                }
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            JsonValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            JsonValue::Text(ref mut variant) => visitor.visit_enum(
+                IntoDeserializer::<Error>::into_deserializer(mem::take(variant).into_string()),
+            ),
+            JsonValue::Object(ref mut members) => {
+                let mut members = mem::take(&mut **members).into_iter();
+                match (members.next(), members.next()) {
+                    (Some((variant, value)), None) => visitor.visit_enum(EnumDeserializer {
+                        variant: variant.into(),
+                        value,
+                    }),
+                    _ => Err(de::Error::custom("expected an object with a single key")),
+                }
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or an object with a single key",
+            )),
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            JsonValue::Number(n) => visit_i128(n, None, visitor),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            JsonValue::Number(n) => visit_u128(n, None, visitor),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for JsonValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct SeqDeserializer {
+    items: vec::IntoIter<JsonValue>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.items
+            .next()
+            .map(|item| seed.deserialize(item))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapDeserializer {
+    members: <JsonMap as IntoIterator>::IntoIter,
+    value: Option<JsonValue>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.members.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer(key.into_string().into()))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.members.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: JsonValue,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = JsonValue;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, JsonValue), Error> {
+        let variant = seed.deserialize(KeyDeserializer(self.variant.into()))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for JsonValue {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_value;
+    use crate::serde::to_value;
+    use crate::{json_parse, JsonValue};
+    use serde_crate::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Plain,
+        Tagged(String),
+        Pair(u8, i64),
+        Named { weight: f32 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Item {
+        id: u64,
+        kinds: Vec<Kind>,
+        parent: Option<Box<Item>>,
+        attrs: HashMap<u16, bool>,
+        raw: JsonValue,
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let json = json_parse(
+            r#"{
+                "id": 7,
+                "kinds": ["Plain", {"Tagged": "t"}, {"Pair": [1, -2]}, {"Named": {"weight": 0.5}}],
+                "parent": {"id": 1, "kinds": [], "parent": null, "attrs": {}, "raw": null, "extra": 1},
+                "attrs": {"10": true},
+                "raw": [1, "two"]
+            }"#,
+        )
+        .unwrap();
+
+        let item: Item = from_value(json.clone()).unwrap();
+        assert_eq!(item.kinds[3], Kind::Named { weight: 0.5 });
+        assert_eq!(item.parent.as_ref().unwrap().id, 1);
+        assert!(item.attrs[&10]);
+
+        let mut expected = json;
+        if let JsonValue::Object(map) = &mut expected {
+            if let Some(JsonValue::Object(parent)) = map.get_mut("parent") {
+                parent.remove("extra");
+            }
+        }
+        assert_eq!(to_value(&item), Ok(expected));
+
+        assert!(from_value::<(u8, u8)>(json_parse("[1, 2, 3]").unwrap()).is_err());
+        assert!(from_value::<u8>(JsonValue::from(-1.0)).is_err());
+        assert!(
+            from_value::<Kind>(json_parse(r#"{"Plain": null, "Tagged": ""}"#).unwrap()).is_err()
+        );
+        assert_eq!(
+            from_value::<Kind>(json_parse(r#"{"Plain": null}"#).unwrap()),
+            Ok(Kind::Plain)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Integration with [serde](https://serde.rs), enabled by the `serde` feature.
+//!
+//! `JsonValue` implements `Serialize` and `Deserialize`, so it can be used as a generic value
+//! inside types handled by any serde format.  This module also implements JSON as a serde data
+//! format of its own: [`from_str`](fn.from_str.html) deserializes straight from this crate's
+//! parser and [`to_string`](fn.to_string.html) serializes straight to text, without building a
+//! `JsonValue` in between.  Documents that have already been parsed can be converted with
+//! [`from_value`](fn.from_value.html) and [`to_value`](fn.to_value.html), and
+//! [`transcode`](fn.transcode.html) streams between any two serde formats.
+use super::events::{Event, EventParser};
+use super::{escape_str, JsonError, JsonString, JsonValue};
+use std::error;
+use std::fmt::{self, Display};
+
+mod de;
+mod from_value;
+mod ser;
+mod to_value;
+mod transcode;
+mod value;
+
+pub use self::de::{from_str, Deserializer};
+pub use self::from_value::from_value;
+pub use self::ser::{to_string, Serializer};
+pub use self::to_value::to_value;
+pub use self::transcode::{transcode, Transcoder};
+
+/// Describes all possible errors that could occur while serializing or deserializing
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The input was not valid JSON
+    Syntax(JsonError),
+    /// The data did not fit the type, or the type could not be represented as JSON
+    Message(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax(error) => error.fmt(f),
+            Error::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Syntax(error) => Some(error),
+            Error::Message(_) => None,
+        }
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(error: JsonError) -> Self {
+        Error::Syntax(error)
+    }
+}
+
+impl ::serde_crate::de::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::Message(message.to_string())
+    }
+}
+
+impl ::serde_crate::ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::Message(message.to_string())
+    }
+}
@@ -0,0 +1,210 @@
+use serde_crate::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_crate::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::cell::RefCell;
+use std::fmt;
+
+/// Streams everything a deserializer produces into a serializer, without building it up
+/// in memory.
+///
+/// Works with any serde format on either side, although the deserializer has to support
+/// `deserialize_any`.  [`Deserializer`](struct.Deserializer.html) and
+/// [`Serializer`](struct.Serializer.html) can be passed here or to `serde_transcode`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::serde::{transcode, Deserializer, Serializer};
+///
+/// fn main() {
+///     let mut deserializer = Deserializer::from_str("{\n  \"a\": [1, 2.5],\n  \"b\": null\n}");
+///     let mut serializer = Serializer::new();
+///     transcode(&mut deserializer, &mut serializer).unwrap();
+///     assert_eq!(serializer.into_inner(), r#"{"a":[1,2.5],"b":null}"#);
+/// }
+/// ```
+pub fn transcode<'de, D, S>(deserializer: D, serializer: S) -> Result<S::Ok, S::Error>
+where
+    D: Deserializer<'de>,
+    S: Serializer,
+{
+    Transcoder::new(deserializer).serialize(serializer)
+}
+
+/// Wraps a deserializer so that it can be serialized, for instance as part of a larger value.
+///
+/// # Panics
+/// If serialized more than once.
+pub struct Transcoder<D>(RefCell<Option<D>>);
+
+impl<'de, D: Deserializer<'de>> Transcoder<D> {
+    /// Wraps a deserializer.
+    pub fn new(deserializer: D) -> Self {
+        Transcoder(RefCell::new(Some(deserializer)))
+    }
+}
+
+impl<'de, D: Deserializer<'de>> Serialize for Transcoder<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0
+            .borrow_mut()
+            .take()
+            .expect("Transcoder serialized more than once")
+            .deserialize_any(Forward(serializer))
+            .map_err(ser::Error::custom)
+    }
+}
+
+/// Serializes each value as it is visited.
+struct Forward<S>(S);
+
+macro_rules! forward {
+    ($($visit:ident: $ty:ty => $serialize:ident,)*) => {
+        $(
+            fn $visit<E: de::Error>(self, v: $ty) -> Result<S::Ok, E> {
+                self.0.$serialize(v).map_err(E::custom)
+            }
+        )*
+    };
+}
+
+impl<'de, S: Serializer> Visitor<'de> for Forward<S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    forward! {
+        visit_bool: bool => serialize_bool,
+        visit_i8: i8 => serialize_i8,
+        visit_i16: i16 => serialize_i16,
+        visit_i32: i32 => serialize_i32,
+        visit_i64: i64 => serialize_i64,
+        visit_i128: i128 => serialize_i128,
+        visit_u8: u8 => serialize_u8,
+        visit_u16: u16 => serialize_u16,
+        visit_u32: u32 => serialize_u32,
+        visit_u64: u64 => serialize_u64,
+        visit_u128: u128 => serialize_u128,
+        visit_f32: f32 => serialize_f32,
+        visit_f64: f64 => serialize_f64,
+        visit_char: char => serialize_char,
+        visit_str: &str => serialize_str,
+        visit_bytes: &[u8] => serialize_bytes,
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_none().map_err(E::custom)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Ok, D::Error> {
+        self.0
+            .serialize_some(&Transcoder::new(deserializer))
+            .map_err(de::Error::custom)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_unit().map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<S::Ok, D::Error> {
+        self.0
+            .serialize_newtype_struct("Transcoder", &Transcoder::new(deserializer))
+            .map_err(de::Error::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<S::Ok, A::Error> {
+        let mut out = self
+            .0
+            .serialize_seq(seq.size_hint())
+            .map_err(de::Error::custom)?;
+        while let Some(()) = seq.next_element_seed(Element(&mut out))? {}
+        out.end().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Ok, A::Error> {
+        let mut out = self
+            .0
+            .serialize_map(map.size_hint())
+            .map_err(de::Error::custom)?;
+        while let Some(()) = map.next_key_seed(Key(&mut out))? {
+            map.next_value_seed(Value(&mut out))?;
+        }
+        out.end().map_err(de::Error::custom)
+    }
+}
+
+/// Forwards one item of a sequence.
+struct Element<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeSeq> DeserializeSeed<'de> for Element<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_element(&Transcoder::new(deserializer))
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Forwards the key of a map member.
+struct Key<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for Key<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_key(&Transcoder::new(deserializer))
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Forwards the value of a map member.
+struct Value<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for Value<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_value(&Transcoder::new(deserializer))
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{transcode, Transcoder};
+    use crate::json_parse;
+    use crate::serde::{Deserializer, Serializer};
+    use serde_test::{assert_ser_tokens, Token};
+
+    #[test]
+    fn transcode_streams() {
+        assert_ser_tokens(
+            &Transcoder::new(&mut Deserializer::from_str(
+                r#"[-1, "a", {"k": true}, null]"#,
+            )),
+            &[
+                Token::Seq { len: None },
+                Token::I64(-1),
+                Token::Str("a"),
+                Token::Map { len: None },
+                Token::Str("k"),
+                Token::Bool(true),
+                Token::MapEnd,
+                Token::Unit,
+                Token::SeqEnd,
+            ],
+        );
+
+        let value = json_parse(r#"{"list": [1, 2, 3]}"#).unwrap();
+        let mut serializer = Serializer::new();
+        transcode(value, &mut serializer).unwrap();
+        assert_eq!(serializer.into_inner(), r#"{"list":[1,2,3]}"#);
+
+        assert!(transcode(&mut Deserializer::from_str("[1,"), &mut Serializer::new()).is_err());
+    }
+}
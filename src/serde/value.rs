@@ -0,0 +1,258 @@
+use super::super::{JsonMap, JsonNumber, RawJson};
+use super::{JsonString, JsonValue};
+use serde_crate::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_crate::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::Text(text) => serializer.serialize_str(text),
+            JsonValue::Number(n) => n.serialize(serializer),
+            JsonValue::Boolean(b) => serializer.serialize_bool(*b),
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Raw(raw) => raw.parse().serialize(serializer),
+            JsonValue::Array(array) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for item in array.iter() {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            JsonValue::Object(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, value) in members.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_str<E>(self, text: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::Text(text.into()))
+    }
+
+    fn visit_string<E>(self, text: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::Text(text.into()))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            array.push(item);
+        }
+        Ok(JsonValue::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut members = JsonMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry()? {
+            members.insert(key, value);
+        }
+        Ok(JsonValue::Object(Box::new(members)))
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl Serialize for JsonNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            JsonNumber::I64(n) => serializer.serialize_i64(n),
+            JsonNumber::U64(n) => serializer.serialize_u64(n),
+            JsonNumber::F64(n) => serializer.serialize_f64(n),
+            // serde has no decimal type, so only whole numbers are kept exactly
+            #[cfg(feature = "decimal")]
+            JsonNumber::Decimal(n) => match (n.to_i128(), n.to_u128()) {
+                (Some(n), _) => serializer.serialize_i128(n),
+                (_, Some(n)) => serializer.serialize_u128(n),
+                _ => serializer.serialize_f64(n.to_f64()),
+            },
+        }
+    }
+}
+
+struct NumberVisitor;
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = JsonNumber;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number")
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<JsonNumber, E> {
+        Ok(JsonNumber::from(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<JsonNumber, E> {
+        Ok(JsonNumber::from(n))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<JsonNumber, E> {
+        Ok(JsonNumber::from(n))
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+/// Serializes as the value the text holds, which other formats can then write their own way.
+impl Serialize for RawJson {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.parse().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = JsonValue::deserialize(deserializer)?;
+        RawJson::from_value(&value)
+            .ok_or_else(|| de::Error::custom("an infinite or NaN number can not be written"))
+    }
+}
+
+/// Serializes as a plain string.
+impl Serialize for JsonString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(JsonString::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JsonMap, JsonValue};
+    use serde_test::{assert_de_tokens, assert_tokens, Token};
+
+    #[test]
+    fn value_tokens() {
+        let value = JsonValue::Array(vec![
+            JsonValue::Text("a".into()),
+            JsonValue::from(1.5),
+            JsonValue::Boolean(false),
+            JsonValue::Null,
+            JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("k".into(), JsonValue::Array(vec![]));
+                map
+            })),
+        ]);
+
+        assert_tokens(
+            &value,
+            &[
+                Token::Seq { len: Some(5) },
+                Token::Str("a"),
+                Token::F64(1.5),
+                Token::Bool(false),
+                Token::Unit,
+                Token::Map { len: Some(1) },
+                Token::Str("k"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::MapEnd,
+                Token::SeqEnd,
+            ],
+        );
+
+        assert_de_tokens(
+            &JsonValue::Array(vec![
+                JsonValue::from(-3.0),
+                JsonValue::from(7.0),
+                JsonValue::Null,
+                JsonValue::Text("x".into()),
+            ]),
+            &[
+                Token::Seq { len: None },
+                Token::I8(-3),
+                Token::U64(7),
+                Token::None,
+                Token::Some,
+                Token::String("x"),
+                Token::SeqEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn number_and_raw_tokens() {
+        use crate::{JsonNumber, RawJson};
+        use serde_test::assert_de_tokens_error;
+
+        assert_tokens(&JsonNumber::U64(u64::MAX), &[Token::U64(u64::MAX)]);
+        assert_tokens(&JsonNumber::I64(-2), &[Token::I64(-2)]);
+        assert_tokens(&JsonNumber::F64(0.5), &[Token::F64(0.5)]);
+        assert_de_tokens(&JsonNumber::U64(3), &[Token::I8(3)]);
+        assert_de_tokens_error::<JsonNumber>(
+            &[Token::Str("3")],
+            "invalid type: string \"3\", expected a number",
+        );
+
+        let raw: RawJson = "[1,null]".parse().unwrap();
+        assert_tokens(
+            &raw,
+            &[
+                Token::Seq { len: Some(2) },
+                Token::U64(1),
+                Token::Unit,
+                Token::SeqEnd,
+            ],
+        );
+        assert_de_tokens_error::<RawJson>(
+            &[Token::F64(f64::INFINITY)],
+            "an infinite or NaN number can not be written",
+        );
+    }
+}
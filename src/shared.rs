@@ -0,0 +1,316 @@
+//! A persistent JSON tree whose subtrees are shared rather than copied.
+use super::{EditError, JsonNumber, JsonPointer, JsonValue};
+use std::collections::HashMap;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::sync::Arc;
+
+/// A JSON value whose strings, arrays and objects are reference counted, so cloning it, or any
+/// subtree of it, is O(1) no matter how large it is.  It is `Send` and `Sync`, which makes it
+/// suitable for caching a parsed document and handing it to many threads.
+///
+/// Values are never changed in place while shared.  [`add`](#method.add),
+/// [`replace`](#method.replace) and [`remove`](#method.remove) return a new version that copies
+/// only the arrays and objects on the path to the change, so many versions of a large document
+/// can be kept for little more than the size of each edit.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, ArcJson};
+/// use std::thread;
+///
+/// fn main() {
+///     let config = ArcJson::from(json_parse(r#"{ "workers": [{ "name": "a" }, { "name": "b" }] }"#).unwrap());
+///
+///     let handles = (0..2)
+///         .map(|ind| {
+///             let worker = config.get("workers").and_then(|w| w.get_ind(ind)).cloned().unwrap();
+///             thread::spawn(move || worker.get("name").cloned())
+///         })
+///         .collect::<Vec<_>>();
+///
+///     let names = handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>();
+///     assert_eq!(names, [Some(ArcJson::Text("a".into())), Some(ArcJson::Text("b".into()))]);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArcJson {
+    /// A JSON string value.
+    Text(Arc<str>),
+    /// A numeric JSON value.
+    Number(JsonNumber),
+    /// A JSON boolean value.
+    Boolean(bool),
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array(Arc<Vec<ArcJson>>),
+    /// A JSON object.
+    Object(Arc<HashMap<Arc<str>, ArcJson>>),
+}
+
+impl ArcJson {
+    /// Gets a reference to the JSON value at a specific key, as with
+    /// [`JsonValue::get`](enum.JsonValue.html#method.get).
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            ArcJson::Object(map) => map.get(key),
+            ArcJson::Array(array) => key.parse().ok().and_then(|i: usize| array.get(i)),
+            _ => None,
+        }
+    }
+
+    /// Gets a reference to the JSON value at a specific index, as with
+    /// [`JsonValue::get_ind`](enum.JsonValue.html#method.get_ind).
+    pub fn get_ind(&self, ind: usize) -> Option<&Self> {
+        match self {
+            ArcJson::Object(map) => map.get(&*ind.to_string()),
+            ArcJson::Array(array) => array.get(ind),
+            _ => None,
+        }
+    }
+
+    /// Whether both values are the same shared string, array or object rather than merely equal
+    /// ones.  Values without a heap part are never shared.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ArcJson::Text(a), ArcJson::Text(b)) => Arc::ptr_eq(a, b),
+            (ArcJson::Array(a), ArcJson::Array(b)) => Arc::ptr_eq(a, b),
+            (ArcJson::Object(a), ArcJson::Object(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Gets a mutable reference to the JSON value at a specific key.  If the array or object is
+    /// shared with another version it is copied first, though its children are not.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Self> {
+        match self {
+            ArcJson::Object(map) if map.contains_key(key) => Arc::make_mut(map).get_mut(key),
+            ArcJson::Array(array) => match key.parse::<usize>() {
+                Ok(i) if i < array.len() => Arc::make_mut(array).get_mut(i),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Finds the value a pointer refers to, as with
+    /// [`JsonPointer::resolve`](struct.JsonPointer.html#method.resolve).
+    pub fn pointer(&self, pointer: &JsonPointer) -> Option<&Self> {
+        pointer
+            .tokens()
+            .iter()
+            .try_fold(self, |value, token| value.get(token))
+    }
+
+    /// Finds a mutable reference to the value a pointer refers to, copying the arrays and objects
+    /// along the way that are shared with another version.  Nothing is copied if the pointer
+    /// does not resolve.
+    pub fn pointer_mut(&mut self, pointer: &JsonPointer) -> Option<&mut Self> {
+        self.pointer(pointer)?;
+        pointer
+            .tokens()
+            .iter()
+            .try_fold(self, |value, token| value.get_mut(token))
+    }
+
+    /// Returns a new version with `value` added at `path`, following the rules of
+    /// [`JsonEdit::Add`](enum.JsonEdit.html#variant.Add).  Everything off the path to the new
+    /// value is shared with `self`.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse, ArcJson};
+    ///
+    /// fn main() {
+    ///     let v1 = ArcJson::from(json_parse(r#"{ "users": [], "settings": { "theme": "dark" } }"#).unwrap());
+    ///     let v2 = v1
+    ///         .add(&"/users/-".parse().unwrap(), ArcJson::Text("ada".into()))
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(v1.get("users").unwrap().to_json(), json_parse("[]").unwrap());
+    ///     assert_eq!(v2.get("users").unwrap().to_json(), json_parse(r#"["ada"]"#).unwrap());
+    ///     assert!(v1.get("settings").unwrap().ptr_eq(v2.get("settings").unwrap()));
+    /// }
+    /// ```
+    pub fn add(&self, path: &JsonPointer, value: ArcJson) -> Result<ArcJson, EditError> {
+        let (parent_path, last) = match path.split_last() {
+            Some(split) => split,
+            None => return Ok(value),
+        };
+        let mut next = self.clone();
+        match next.pointer_mut(&parent_path) {
+            Some(ArcJson::Object(map)) => {
+                Arc::make_mut(map).insert(Arc::from(last), value);
+            }
+            Some(ArcJson::Array(array)) => {
+                let ind = if last == "-" {
+                    array.len()
+                } else {
+                    last.parse()
+                        .ok()
+                        .filter(|&i| i <= array.len())
+                        .ok_or_else(|| EditError::InvalidIndex(path.clone()))?
+                };
+                Arc::make_mut(array).insert(ind, value);
+            }
+            Some(_) => return Err(EditError::NotAContainer(parent_path)),
+            None => return Err(EditError::NotFound(parent_path)),
+        }
+        Ok(next)
+    }
+
+    /// Returns a new version with the existing value at `path` replaced by `value`.
+    pub fn replace(&self, path: &JsonPointer, value: ArcJson) -> Result<ArcJson, EditError> {
+        let mut next = self.clone();
+        *next
+            .pointer_mut(path)
+            .ok_or_else(|| EditError::NotFound(path.clone()))? = value;
+        Ok(next)
+    }
+
+    /// Returns a new version without the object key or array element at `path`.
+    pub fn remove(&self, path: &JsonPointer) -> Result<ArcJson, EditError> {
+        let (parent_path, last) = path.split_last().ok_or(EditError::RemoveRoot)?;
+        let mut next = self.clone();
+        match next.pointer_mut(&parent_path) {
+            Some(ArcJson::Object(map)) if map.contains_key(last) => {
+                Arc::make_mut(map).remove(last);
+            }
+            Some(ArcJson::Object(_)) => return Err(EditError::NotFound(path.clone())),
+            Some(ArcJson::Array(array)) => {
+                let ind = last
+                    .parse()
+                    .ok()
+                    .filter(|&i| i < array.len())
+                    .ok_or_else(|| EditError::InvalidIndex(path.clone()))?;
+                Arc::make_mut(array).remove(ind);
+            }
+            Some(_) => return Err(EditError::NotAContainer(parent_path)),
+            None => return Err(EditError::NotFound(parent_path)),
+        }
+        Ok(next)
+    }
+
+    /// Copies the value into a plain `JsonValue`.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            ArcJson::Text(text) => JsonValue::Text((**text).into()),
+            ArcJson::Number(n) => JsonValue::Number(*n),
+            ArcJson::Boolean(b) => JsonValue::Boolean(*b),
+            ArcJson::Null => JsonValue::Null,
+            ArcJson::Array(array) => JsonValue::Array(array.iter().map(ArcJson::to_json).collect()),
+            ArcJson::Object(map) => JsonValue::Object(Box::new(
+                map.iter()
+                    .map(|(key, value)| ((**key).into(), value.to_json()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl From<JsonValue> for ArcJson {
+    fn from(mut value: JsonValue) -> Self {
+        match value {
+            JsonValue::Text(ref text) => ArcJson::Text(Arc::from(text.as_str())),
+            JsonValue::Number(n) => ArcJson::Number(n),
+            JsonValue::Boolean(b) => ArcJson::Boolean(b),
+            JsonValue::Null => ArcJson::Null,
+            JsonValue::Raw(ref raw) => ArcJson::from(raw.parse()),
+            JsonValue::Array(ref mut array) => ArcJson::Array(Arc::new(
+                mem::take(array).into_iter().map(ArcJson::from).collect(),
+            )),
+            JsonValue::Object(ref mut map) => ArcJson::Object(Arc::new(
+                mem::take(&mut **map)
+                    .into_iter()
+                    .map(|(key, value)| (Arc::from(key.as_str()), ArcJson::from(value)))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArcJson;
+    use crate::{json_parse, JsonNumber};
+
+    #[test]
+    fn shared_matches_json_parse() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            let json = json_parse(&source);
+            assert_eq!(
+                json.clone()
+                    .map(ArcJson::from)
+                    .map(|shared| shared.to_json()),
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn shared_clones() {
+        fn send_sync<T: Send + Sync>(_: &T) {}
+
+        let json =
+            ArcJson::from(json_parse(r#"{ "a": [1, { "b": "text" }], "c": null }"#).unwrap());
+        send_sync(&json);
+
+        let copy = json.clone();
+        assert!(copy.ptr_eq(&json));
+        assert!(copy.get("a").unwrap().ptr_eq(json.get("a").unwrap()));
+        let text = |value: &ArcJson| value.get("a").and_then(|a| a.get_ind(1))?.get("b").cloned();
+        assert!(text(&copy).unwrap().ptr_eq(&text(&json).unwrap()));
+        assert!(!copy.get("c").unwrap().ptr_eq(json.get("c").unwrap()));
+        assert!(!ArcJson::from(copy.to_json()).ptr_eq(&json));
+        assert_eq!(ArcJson::from(copy.to_json()), json);
+    }
+
+    #[test]
+    fn shared_versions() {
+        use crate::EditError;
+
+        let p = |path: &str| path.parse().unwrap();
+        let v1 = ArcJson::from(
+            json_parse(r#"{ "a": [1, { "b": [true] }], "c": { "d": null } }"#).unwrap(),
+        );
+
+        let v2 = v1.replace(&p("/a/1/b/0"), ArcJson::Boolean(false)).unwrap();
+        let v3 = v2.remove(&p("/a/0")).unwrap();
+        let v4 = v3
+            .add(&p("/c/e"), ArcJson::Number(JsonNumber::U64(2)))
+            .unwrap();
+
+        assert_eq!(
+            v1.to_json(),
+            json_parse(r#"{ "a": [1, { "b": [true] }], "c": { "d": null } }"#).unwrap()
+        );
+        assert_eq!(
+            v4.to_json(),
+            json_parse(r#"{ "a": [{ "b": [false] }], "c": { "d": null, "e": 2 } }"#).unwrap()
+        );
+        assert!(v1.get("c").unwrap().ptr_eq(v3.get("c").unwrap()));
+        assert!(!v1.get("a").unwrap().ptr_eq(v2.get("a").unwrap()));
+        assert!(v2
+            .pointer(&p("/a/1"))
+            .unwrap()
+            .ptr_eq(v4.pointer(&p("/a/0")).unwrap()));
+
+        assert_eq!(
+            v1.replace(&p("/a/2"), ArcJson::Null),
+            Err(EditError::NotFound(p("/a/2")))
+        );
+        assert_eq!(
+            v1.add(&p("/a/3"), ArcJson::Null),
+            Err(EditError::InvalidIndex(p("/a/3")))
+        );
+        assert_eq!(
+            v1.remove(&p("/c/d/x")),
+            Err(EditError::NotAContainer(p("/c/d")))
+        );
+        assert_eq!(v1.remove(&p("")), Err(EditError::RemoveRoot));
+        assert_eq!(v1.add(&p(""), ArcJson::Null), Ok(ArcJson::Null));
+    }
+}
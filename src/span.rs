@@ -0,0 +1,604 @@
+use super::document::Comment;
+use super::events::{EventParser, Step};
+use super::lexer::Punct;
+use super::options::ParseOptions;
+use super::{JsonError, JsonMap, JsonNumber, JsonPointer, JsonValue};
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A range of bytes in JSON source text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The byte offset of the first byte
+    pub start: usize,
+    /// The byte offset just past the last byte
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a span covering `start..end`.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The number of bytes covered.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the span covers no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether `offset` lies within the span.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+/// A zero-based line and column in JSON source.  Columns count characters, like the locations
+/// in `JsonError`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    /// The line, starting from 0
+    pub line: usize,
+    /// The character within the line, starting from 0
+    pub column: usize,
+}
+
+/// A place in JSON source, counted every way a `JsonError` reports it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Place {
+    /// The character index
+    pub location: usize,
+    pub line: usize,
+    pub column: usize,
+    /// The byte offset
+    pub offset: usize,
+}
+
+impl Place {
+    /// The place `location` characters into `source`, or the end of `source` if it is shorter.
+    pub fn find(source: &str, location: usize) -> Self {
+        let mut place = Self::default();
+        for c in source.chars().take(location) {
+            place.advance(c);
+        }
+        place
+    }
+
+    /// Moves past `c`.
+    pub fn advance(&mut self, c: char) {
+        self.location += 1;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    /// The place `inner` is at, where `inner` was counted from the start of text found here.
+    pub fn then(self, inner: Place) -> Self {
+        Self {
+            location: self.location + inner.location,
+            line: self.line + inner.line,
+            column: if inner.line == 0 {
+                self.column + inner.column
+            } else {
+                inner.column
+            },
+            offset: self.offset + inner.offset,
+        }
+    }
+
+    /// An error for finding `character` here.
+    pub fn token_err(self, character: char) -> JsonError {
+        JsonError::UnexpectedToken {
+            character,
+            location: self.location,
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    /// An error for `key` appearing here a second time.
+    pub fn duplicate_err(self, key: String) -> JsonError {
+        JsonError::DuplicateKey {
+            key,
+            location: self.location,
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    /// An error for a bracket here nesting deeper than `limit`.
+    pub fn depth_err(self, limit: usize) -> JsonError {
+        JsonError::DepthLimitExceeded {
+            limit,
+            location: self.location,
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    /// An error for a number starting here that cannot be read exactly.
+    pub fn range_err(self) -> JsonError {
+        JsonError::NumberOutOfRange {
+            location: self.location,
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    /// An error for the input ending here.
+    pub fn eof_err(self) -> JsonError {
+        JsonError::UnexpectedEOF {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+}
+
+/// Where a span starts and ends, as both byte offsets and lines and columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// The byte range
+    pub span: Span,
+    /// The position of the first character
+    pub start: Position,
+    /// The position just past the last character
+    pub end: Position,
+}
+
+/// Converts byte offsets in a piece of source text into lines and columns.  Building the index
+/// takes one pass over the text; each lookup after that is a binary search.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{LineIndex, Position};
+///
+/// fn main() {
+///     let source = "{\n  \"ώ\": 1\n}";
+///     let lines = LineIndex::new(source);
+///     assert_eq!(lines.position(10), Position { line: 1, column: 7 });
+///     assert_eq!(lines.position(source.len()), Position { line: 2, column: 1 });
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes the lines of `source`.
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = Some(0)
+            .into_iter()
+            .chain(source.match_indices('\n').map(|(ind, _)| ind + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The line and column of the character starting at byte `offset`.
+    ///
+    /// # Panics
+    /// If `offset` is past the end of the source or not on a character boundary.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        Position {
+            line,
+            column: self.source[self.line_starts[line]..offset].chars().count(),
+        }
+    }
+
+    /// The lines and columns of both ends of `span`.
+    pub fn locate(&self, span: Span) -> Location {
+        Location {
+            span,
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+}
+
+/// A node along with the span of source it was parsed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    /// The node
+    pub value: T,
+    /// Where it was found
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    /// Pairs a node with its span.
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+}
+
+/// A JSON value in which every nested value and key carries its span.  Object members are kept
+/// in source order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpannedValue {
+    /// A JSON string value.
+    Text(String),
+    /// A numeric JSON value.
+    Number(JsonNumber),
+    /// A JSON boolean value.
+    Boolean(bool),
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array(Vec<Spanned<SpannedValue>>),
+    /// A JSON object.
+    Object(Vec<(Spanned<String>, Spanned<SpannedValue>)>),
+}
+
+impl SpannedValue {
+    fn scalar(mut value: JsonValue) -> Self {
+        match value {
+            JsonValue::Text(ref mut text) => SpannedValue::Text(mem::take(text).into()),
+            JsonValue::Number(n) => SpannedValue::Number(n),
+            JsonValue::Boolean(b) => SpannedValue::Boolean(b),
+            JsonValue::Null => SpannedValue::Null,
+            JsonValue::Array(_) | JsonValue::Object(_) | JsonValue::Raw(_) => unreachable!(),
+        }
+    }
+
+    /// Converts to a plain `JsonValue`, dropping every span.  As with `json_parse`, later
+    /// duplicate keys overwrite earlier ones.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            SpannedValue::Text(text) => JsonValue::Text(text.as_str().into()),
+            SpannedValue::Number(n) => JsonValue::Number(*n),
+            SpannedValue::Boolean(b) => JsonValue::Boolean(*b),
+            SpannedValue::Null => JsonValue::Null,
+            SpannedValue::Array(array) => {
+                JsonValue::Array(array.iter().map(|item| item.value.to_json()).collect())
+            }
+            SpannedValue::Object(members) => JsonValue::Object(Box::new(
+                members
+                    .iter()
+                    .map(|(key, value)| (key.value.as_str().into(), value.value.to_json()))
+                    .collect::<JsonMap>(),
+            )),
+        }
+    }
+}
+
+impl Spanned<SpannedValue> {
+    /// Gets the member with a specific key or, for arrays, the element at a specific index, as
+    /// with [`JsonValue::get`](enum.JsonValue.html#method.get).  If an object has the key more
+    /// than once the last member is returned.
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match &self.value {
+            SpannedValue::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(member, _)| member.value == key)
+                .map(|(_, value)| value),
+            SpannedValue::Array(items) => key.parse().ok().and_then(|i: usize| items.get(i)),
+            _ => None,
+        }
+    }
+
+    /// Finds the node a JSON Pointer refers to, so that problems found in the plain value can
+    /// be reported at their place in the source.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse_spanned, LineIndex, Position};
+    ///
+    /// fn main() {
+    ///     let source = "{\n  \"servers\": [\n    { \"port\": \"eighty\" }\n  ]\n}";
+    ///     let tree = json_parse_spanned(source).unwrap();
+    ///
+    ///     let port = tree.pointer(&"/servers/0/port".parse().unwrap()).unwrap();
+    ///     let location = LineIndex::new(source).locate(port.span);
+    ///     assert_eq!(location.start, Position { line: 2, column: 14 });
+    /// }
+    /// ```
+    pub fn pointer(&self, pointer: &JsonPointer) -> Option<&Self> {
+        pointer
+            .tokens()
+            .iter()
+            .try_fold(self, |node, token| node.get(token))
+    }
+
+    /// Finds the innermost node whose span contains byte `offset`, along with its path.  An
+    /// offset within an object key counts as being within that member's value.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::{json_parse_spanned, SpannedValue};
+    ///
+    /// fn main() {
+    ///     let source = r#"{ "users": [{ "name": "ada" }] }"#;
+    ///     let tree = json_parse_spanned(source).unwrap();
+    ///
+    ///     let (path, node) = tree.node_at_offset(source.find("ada").unwrap()).unwrap();
+    ///     assert_eq!(path.to_string(), "/users/0/name");
+    ///     assert_eq!(node.value, SpannedValue::Text("ada".into()));
+    ///
+    ///     let (path, _) = tree.node_at_offset(source.find("name").unwrap()).unwrap();
+    ///     assert_eq!(path.to_string(), "/users/0/name");
+    ///     assert!(tree.node_at_offset(source.len()).is_none());
+    /// }
+    /// ```
+    pub fn node_at_offset(&self, offset: usize) -> Option<(JsonPointer, &Self)> {
+        if !self.span.contains(offset) {
+            return None;
+        }
+
+        let mut path = JsonPointer::root();
+        let mut node = self;
+        loop {
+            let child = match &node.value {
+                SpannedValue::Array(items) => items
+                    .iter()
+                    .enumerate()
+                    .find(|(_, item)| item.span.contains(offset))
+                    .map(|(ind, item)| (ind.to_string(), item)),
+                SpannedValue::Object(members) => members
+                    .iter()
+                    .find(|(key, value)| key.span.contains(offset) || value.span.contains(offset))
+                    .map(|(key, value)| (key.value.clone(), value)),
+                _ => None,
+            };
+
+            match child {
+                Some((token, child)) => {
+                    path.push(token);
+                    node = child;
+                }
+                None => return Some((path, node)),
+            }
+        }
+    }
+}
+
+enum Partial {
+    Array(usize, Vec<Spanned<SpannedValue>>),
+    Object(
+        usize,
+        Vec<(Spanned<String>, Spanned<SpannedValue>)>,
+        Option<Spanned<String>>,
+    ),
+}
+
+/// Deserializes a JSON string, recording the span of every value and key.
+///
+/// This accepts exactly the documents [`json_parse`](fn.json_parse.html) does, nested at most 128
+/// levels deep.  Errors are reported at the first offending character in source order, while
+/// `json_parse` reports mismatched brackets before any error inside them.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse_spanned, Span, SpannedValue};
+///
+/// fn main() {
+///     let source = r#"{ "list": [true, null] }"#;
+///     let json = json_parse_spanned(source).unwrap();
+///     assert_eq!(json.span, Span::new(0, source.len()));
+///
+///     if let SpannedValue::Object(members) = &json.value {
+///         let (key, list) = &members[0];
+///         assert_eq!(&source[key.span.start..key.span.end], r#""list""#);
+///         assert_eq!(&source[list.span.start..list.span.end], "[true, null]");
+///     }
+/// }
+/// ```
+pub fn json_parse_spanned(json_str: &str) -> Result<Spanned<SpannedValue>, JsonError> {
+    parse_spanned(json_str, ParseOptions::default()).map(|(root, _)| root)
+}
+
+impl Partial {
+    /// The reference token of the child currently being parsed.
+    fn token(&self) -> String {
+        match self {
+            Partial::Array(_, items) => items.len().to_string(),
+            Partial::Object(_, _, key) => key
+                .as_ref()
+                .map(|key| key.value.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Builds a spanned tree, also returning every comment attached to the key or value that
+/// follows it.
+pub(crate) fn parse_spanned(
+    json_str: &str,
+    options: ParseOptions,
+) -> Result<(Spanned<SpannedValue>, Vec<Comment>), JsonError> {
+    let mut parser = EventParser::with_options(json_str, options);
+    let mut partials = vec![];
+    let mut root = None;
+    let mut comments = vec![];
+    let mut unattached = vec![];
+
+    let attach = |partials: &[Partial], unattached: &mut Vec<Span>, comments: &mut Vec<Comment>| {
+        if !unattached.is_empty() {
+            let target = JsonPointer::from_tokens(partials.iter().map(Partial::token));
+            comments.extend(
+                unattached
+                    .drain(..)
+                    .map(|span| Comment::new(json_str, span, Some(target.clone()))),
+            );
+        }
+    };
+
+    while let Some(step) = parser.step()? {
+        if parser.skipped() {
+            continue;
+        }
+        let complete = match step {
+            Step::Comment(span) => {
+                unattached.push(span);
+                continue;
+            }
+            Step::Punct(Punct::ArrayStart, span) => {
+                attach(&partials, &mut unattached, &mut comments);
+                partials.push(Partial::Array(span.start, vec![]));
+                continue;
+            }
+            Step::Punct(Punct::ObjectStart, span) => {
+                attach(&partials, &mut unattached, &mut comments);
+                partials.push(Partial::Object(span.start, vec![], None));
+                continue;
+            }
+            Step::Key(key, span) => {
+                if let Some(Partial::Object(_, _, pending)) = partials.last_mut() {
+                    *pending = Some(Spanned::new(key, span));
+                }
+                attach(&partials, &mut unattached, &mut comments);
+                continue;
+            }
+            Step::Value(value, span) => {
+                attach(&partials, &mut unattached, &mut comments);
+                Spanned::new(SpannedValue::scalar(value), span)
+            }
+            Step::Punct(Punct::ArrayEnd, span) | Step::Punct(Punct::ObjectEnd, span) => {
+                match partials.pop() {
+                    Some(Partial::Array(start, items)) => {
+                        Spanned::new(SpannedValue::Array(items), Span::new(start, span.end))
+                    }
+                    Some(Partial::Object(start, members, _)) => {
+                        Spanned::new(SpannedValue::Object(members), Span::new(start, span.end))
+                    }
+                    None => unreachable!(),
+                }
+            }
+            Step::Punct(_, _) | Step::Whitespace(_) => continue,
+        };
+
+        match partials.last_mut() {
+            Some(Partial::Array(_, items)) => items.push(complete),
+            Some(Partial::Object(_, members, key)) => members.push((key.take().unwrap(), complete)),
+            None => root = Some(complete),
+        }
+    }
+
+    comments.extend(
+        unattached
+            .into_iter()
+            .map(|span| Comment::new(json_str, span, None)),
+    );
+    root.ok_or_else(|| JsonError::eof(json_str))
+        .map(|root| (root, comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_parse_spanned, Span, Spanned, SpannedValue};
+    use crate::{json_parse, JsonNumber};
+
+    #[test]
+    fn spanned_matches_json_parse() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            assert_eq!(
+                json_parse_spanned(&source).map(|json| json.value.to_json()),
+                json_parse(&source)
+            );
+        }
+
+        // both allow 128 levels and stop at the same bracket past them
+        for depth in [128, 129] {
+            let (open, close) = ("[{\"a\":".repeat(depth / 2), "}]".repeat(depth / 2));
+            let source = format!("{}{}{}", open, "[]".repeat(depth % 2), close);
+            assert_eq!(
+                json_parse_spanned(&source).map(|json| json.value.to_json()),
+                json_parse(&source)
+            );
+        }
+    }
+
+    #[test]
+    fn spanned_positions() {
+        use super::{LineIndex, Position};
+
+        let source = "[1,\r\n {\"a\": \"ώ\", \"a\": [\n\nnull]}]";
+        let tree = json_parse_spanned(source).unwrap();
+        let lines = LineIndex::new(source);
+
+        let node = tree.pointer(&"/1/a/0".parse().unwrap()).unwrap();
+        assert_eq!(node.value, SpannedValue::Null);
+        let location = lines.locate(node.span);
+        assert_eq!(location.start, Position { line: 3, column: 0 });
+        assert_eq!(location.end, Position { line: 3, column: 4 });
+
+        let node = tree.get("1").unwrap();
+        assert_eq!(
+            lines.position(node.span.start),
+            Position { line: 1, column: 1 }
+        );
+        assert_eq!(
+            lines.position(source.len()),
+            Position { line: 3, column: 7 }
+        );
+        assert!(tree.pointer(&"/1/b".parse().unwrap()).is_none());
+        assert!(tree.get("2").is_none());
+    }
+
+    #[test]
+    fn spanned_node_at_offset() {
+        let source = r#" [0, {"a": [true], "b": null}] "#;
+        let tree = json_parse_spanned(source).unwrap();
+        let paths = (0..source.len())
+            .map(|offset| {
+                tree.node_at_offset(offset)
+                    .map(|(path, node)| (path.to_string(), node.span))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths[0], None);
+        assert_eq!(paths[1], Some(("".into(), Span::new(1, 30))));
+        assert_eq!(paths[2], Some(("/0".into(), Span::new(2, 3))));
+        assert_eq!(paths[4], Some(("".into(), Span::new(1, 30))));
+        assert_eq!(paths[5], Some(("/1".into(), Span::new(5, 29))));
+        assert_eq!(paths[7], Some(("/1/a".into(), Span::new(11, 17))));
+        assert_eq!(paths[13], Some(("/1/a/0".into(), Span::new(12, 16))));
+        assert_eq!(paths[20], Some(("/1/b".into(), Span::new(24, 28))));
+        assert_eq!(paths[30], None);
+    }
+
+    #[test]
+    fn spanned_spans() {
+        let source = "[ \"ώ\", {\"k\" : -1.5e2 } ]";
+        assert_eq!(
+            json_parse_spanned(source),
+            Ok(Spanned::new(
+                SpannedValue::Array(vec![
+                    Spanned::new(SpannedValue::Text("ώ".into()), Span::new(2, 6)),
+                    Spanned::new(
+                        SpannedValue::Object(vec![(
+                            Spanned::new("k".into(), Span::new(9, 12)),
+                            Spanned::new(
+                                SpannedValue::Number(JsonNumber::F64(-150.0)),
+                                Span::new(15, 21)
+                            ),
+                        )]),
+                        Span::new(8, 23),
+                    ),
+                ]),
+                Span::new(0, 25),
+            ))
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Reading JSON out of [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html),
+//! the `text/event-stream` format that streaming and notification APIs send JSON payloads in.
+use super::{json_parse, JsonError, JsonValue};
+use std::io::{self, BufRead};
+use std::{error, fmt, mem, str};
+
+/// One event, with its `data:` lines parsed as JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SseEvent {
+    /// The event type from an `event:` line, or `None` for the default `message` type
+    pub event: Option<String>,
+    /// The last event id set by an `id:` line, in this event or an earlier one
+    pub id: Option<String>,
+    /// The JSON value from the event's `data:` lines joined with line breaks
+    pub data: JsonValue,
+}
+
+/// Describes all possible errors that could occur while reading server-sent events.
+///
+/// Every error but `Io` is confined to one event, and the reader carries on with the next.
+#[derive(Debug)]
+pub enum SseError {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// A line was not valid UTF-8
+    InvalidUtf8 {
+        /// The one-based line number
+        line: usize,
+    },
+    /// An event's data was not a single valid JSON value
+    Parse {
+        /// The one-based line number of the event's first `data:` line
+        line: usize,
+        /// Why the data could not be parsed
+        error: JsonError,
+    },
+}
+
+impl fmt::Display for SseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SseError::Io(error) => error.fmt(f),
+            SseError::InvalidUtf8 { line } => write!(f, "line {} is not valid UTF-8", line),
+            SseError::Parse { line, error } => {
+                write!(f, "in the event data from line {}: {}", line, error)
+            }
+        }
+    }
+}
+
+impl error::Error for SseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SseError::Io(error) => Some(error),
+            SseError::Parse { error, .. } => Some(error),
+            SseError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for SseError {
+    fn from(error: io::Error) -> Self {
+        SseError::Io(error)
+    }
+}
+
+/// An iterator over the events of an event stream, created by `sse_events`.
+#[derive(Debug)]
+pub struct SseEvents<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    data: String,
+    data_line: usize,
+    event: Option<String>,
+    id: Option<String>,
+    stop: Option<String>,
+    stopped: bool,
+}
+
+/// Reads an event stream, yielding one parsed value per event that has data.  Comments, `retry:`
+/// and unknown fields are passed over, and an event left unfinished when the stream ends is
+/// dropped, as the format requires.  Lines may end in `\n` or `\r\n`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::sse::sse_events;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let stream = ": keep-alive\n\
+///                   event: delta\n\
+///                   data: {\"text\":\n\
+///                   data: \"Hi\"}\n\
+///                   \n\
+///                   data: [DONE]\n\
+///                   \n";
+///
+///     let mut events = sse_events(stream.as_bytes()).stop_at("[DONE]");
+///     let delta = events.next().unwrap().unwrap();
+///     assert_eq!(delta.event.as_deref(), Some("delta"));
+///     assert_eq!(delta.data.get("text"), Some(&JsonValue::Text("Hi".into())));
+///     assert!(events.next().is_none());
+/// }
+/// ```
+pub fn sse_events<R: BufRead>(reader: R) -> SseEvents<R> {
+    SseEvents {
+        reader,
+        buffer: Vec::new(),
+        line: 0,
+        data: String::new(),
+        data_line: 0,
+        event: None,
+        id: None,
+        stop: None,
+        stopped: false,
+    }
+}
+
+impl<R: BufRead> SseEvents<R> {
+    /// Ends the events at the first event whose data is exactly `data`, like the `[DONE]` that
+    /// some streaming APIs finish with in place of JSON.
+    pub fn stop_at<S: Into<String>>(mut self, data: S) -> Self {
+        self.stop = Some(data.into());
+        self
+    }
+
+    /// The last event id seen, which a client sends back as `Last-Event-ID` when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The number of lines read so far.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Handles one field line of the event being read.
+    fn field(&mut self, line: &str) {
+        let (name, value) = match line.find(':') {
+            Some(colon) => {
+                let value = &line[colon + 1..];
+                (&line[..colon], value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line, ""),
+        };
+        match name {
+            "data" => {
+                if self.data.is_empty() {
+                    self.data_line = self.line;
+                }
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "event" => self.event = Some(value.to_string()),
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            _ => (),
+        }
+    }
+
+    /// Finishes the event being read, or returns `None` if it has no data.
+    fn dispatch(&mut self) -> Option<Result<SseEvent, SseError>> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        self.data.pop();
+        let data = mem::take(&mut self.data);
+        if self.stop.as_ref() == Some(&data) {
+            self.stopped = true;
+            return None;
+        }
+        let line = self.data_line;
+        Some(match json_parse(&data) {
+            Ok(data) => Ok(SseEvent {
+                event,
+                id: self.id.clone(),
+                data,
+            }),
+            Err(error) => Err(SseError::Parse { line, error }),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for SseEvents<R> {
+    type Item = Result<SseEvent, SseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.stopped {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(error) => return Some(Err(error.into())),
+            }
+            self.line += 1;
+
+            let mut bytes = &self.buffer[..];
+            if self.line == 1 {
+                bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+            }
+            let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+            let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+            let line = match str::from_utf8(bytes) {
+                Ok(line) => line.to_string(),
+                Err(_) => return Some(Err(SseError::InvalidUtf8 { line: self.line })),
+            };
+
+            if line.is_empty() {
+                if let Some(result) = self.dispatch() {
+                    return Some(result);
+                }
+            } else if !line.starts_with(':') {
+                self.field(&line);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sse_events, SseError};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn sse_read() {
+        let stream = b"\xEF\xBB\xBFid: 1\r\ndata:[1,\r\ndata: 2]\r\n\r\n\
+                       event: ping\nretry: 100\n\n\
+                       data: {\nid: 2\n\n\
+                       event: x\ndata\n\n\
+                       data: \"\xff\"\n\n\
+                       data:true\n\n\
+                       data: null" as &[u8];
+        let mut events = sse_events(stream);
+
+        let first = events.next().unwrap().unwrap();
+        assert_eq!(first.data, json_parse("[1, 2]").unwrap());
+        assert_eq!(first.id.as_deref(), Some("1"));
+        assert_eq!(first.event, None);
+        assert!(matches!(
+            events.next(),
+            Some(Err(SseError::Parse { line: 8, .. }))
+        ));
+        assert_eq!(events.last_event_id(), Some("2"));
+        // a bare `data` line is data that is empty
+        assert!(matches!(
+            events.next(),
+            Some(Err(SseError::Parse { line: 12, .. }))
+        ));
+        match events.next() {
+            Some(Err(error @ SseError::InvalidUtf8 { line: 14 })) => {
+                assert_eq!(error.to_string(), "line 14 is not valid UTF-8")
+            }
+            other => panic!("{:?}", other),
+        }
+        let last = events.next().unwrap().unwrap();
+        assert_eq!(last.data, JsonValue::Boolean(true));
+        assert_eq!(last.id.as_deref(), Some("2"));
+        // the unfinished event at the end is dropped
+        assert!(events.next().is_none());
+    }
+}
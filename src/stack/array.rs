@@ -1,8 +1,12 @@
 use super::{CheckedStack, IntoJson, JsonValue, ObjArrItem, ObjArrStack, PendingItem};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
 
 #[derive(PartialEq, Debug)]
 pub struct ArrayStack {
     inner: Vec<ObjArrItem>,
+    /// Whether the last item may be followed by a comma
+    trailing_comma: bool,
 }
 
 impl From<ArrayStack> for PendingItem {
@@ -13,14 +17,21 @@ impl From<ArrayStack> for PendingItem {
 
 impl ArrayStack {
     pub fn new() -> Self {
-        Self { inner: vec![] }
+        Self::with_trailing_comma(false)
+    }
+
+    pub fn with_trailing_comma(trailing_comma: bool) -> Self {
+        Self {
+            inner: vec![],
+            trailing_comma,
+        }
     }
 }
 
 impl IntoJson for ArrayStack {
     fn into_json(self: Box<Self>) -> Result<JsonValue, ()> {
         match self.peek() {
-            Some(ObjArrItem::Comma) => Err(()),
+            Some(ObjArrItem::Comma) if !self.trailing_comma => Err(()),
             _ => Ok(JsonValue::Array(
                 self.inner
                     .into_iter()
@@ -50,16 +61,17 @@ impl CheckedStack<ObjArrItem, ()> for ArrayStack {
     fn push(&mut self, item: ObjArrItem) -> Result<(), ()> {
         use self::ObjArrItem::*;
 
-        match self.peek() {
-            Some(Comma) | None => match item {
-                Item(_) => Ok(self.inner.push(item)),
-                _ => Err(()),
-            },
-            Some(Item(_)) => match item {
-                Comma => Ok(self.inner.push(item)),
-                _ => Err(()),
-            },
-            _ => Err(()),
+        let accepted = match self.peek() {
+            Some(Comma) | None => matches!(item, Item(_)),
+            Some(Item(_)) => item == Comma,
+            _ => false,
+        };
+
+        if accepted {
+            self.inner.push(item);
+            Ok(())
+        } else {
+            Err(())
         }
     }
 
@@ -89,7 +101,6 @@ mod tests {
     #[test]
     fn array_into_json() {
         use super::IntoJson;
-        use std::collections::HashMap;
 
         let tests = vec![
             (
@@ -99,7 +110,7 @@ mod tests {
             (vec![], Ok(JsonValue::Array(vec![]))),
             (vec![Comma], Err(())),
             (
-                vec![Item(JsonValue::Object(HashMap::new())), Comma],
+                vec![Item(JsonValue::Object(Box::default())), Comma],
                 Err(()),
             ),
             (
@@ -116,8 +127,20 @@ mod tests {
         ];
 
         for (v, result) in tests.into_iter() {
-            assert_eq!(Box::new(ArrayStack { inner: v }).into_json(), result);
+            let stack = ArrayStack {
+                inner: v,
+                trailing_comma: false,
+            };
+            assert_eq!(Box::new(stack).into_json(), result);
         }
+
+        let mut stack = ArrayStack::with_trailing_comma(true);
+        stack.inner.push(Item(JsonValue::Null));
+        stack.inner.push(Comma);
+        assert_eq!(
+            Box::new(stack).into_json(),
+            Ok(JsonValue::Array(vec![JsonValue::Null]))
+        );
     }
 
     #[test]
@@ -127,12 +150,15 @@ mod tests {
         let tests = vec![
             (vec![Item(JsonValue::Null)], JsonValue::Null.into(), Err(())),
             (vec![], Comma, Err(())),
-            (vec![JsonValue::Number(10.0).into()], Comma, Ok(())),
+            (vec![JsonValue::from(10.0).into()], Comma, Ok(())),
             (vec![JsonValue::Boolean(false).into()], Colon, Err(())),
         ];
 
         for (v, push_in, result) in tests.into_iter() {
-            let mut stack = ArrayStack { inner: v };
+            let mut stack = ArrayStack {
+                inner: v,
+                trailing_comma: false,
+            };
             assert_eq!(stack.push(push_in), result);
         }
     }
@@ -1,4 +1,6 @@
-use super::JsonValue;
+use super::{JsonString, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
 
 pub mod array;
 pub mod object;
@@ -7,6 +9,7 @@ pub mod pending;
 use self::pending::PendingStack;
 use std::fmt::Debug;
 
+#[allow(dead_code)]
 pub trait CheckedStack<T, E> {
     fn peek(&self) -> Option<&T>;
     fn peek_mut(&mut self) -> Option<&mut T>;
@@ -28,12 +31,17 @@ pub trait ObjArrStack: IntoJson + Debug + CheckedStack<ObjArrItem, ()> {
     fn get_delimiter(&self, c: char) -> Option<ObjArrItem>;
     fn is_end_char(&self, c: char) -> bool;
     fn next_must_be_key(&self) -> bool;
+
+    /// The key `item` would repeat, if it is a key and repeated keys are rejected.
+    fn duplicate_key<'a>(&self, _item: &'a ObjArrItem) -> Option<&'a JsonString> {
+        None
+    }
 }
 
 #[derive(Debug)]
 pub enum PendingItem {
-    ObjArr(Box<ObjArrStack>),
-    Simple(Box<SimpleStack>),
+    ObjArr(Box<dyn ObjArrStack>),
+    Simple(Box<dyn SimpleStack>),
     Number(pending::NumberStack),
     FinalizedJsonValue(JsonValue),
 }
@@ -43,7 +51,7 @@ pub enum ObjArrItem {
     Colon,
     Comma,
     Item(JsonValue),
-    Key(String),
+    Key(JsonString),
 }
 
 impl From<JsonValue> for ObjArrItem {
@@ -58,14 +66,14 @@ impl From<JsonValue> for PendingItem {
     }
 }
 
-impl From<Box<SimpleStack>> for PendingItem {
-    fn from(stack: Box<SimpleStack>) -> Self {
+impl From<Box<dyn SimpleStack>> for PendingItem {
+    fn from(stack: Box<dyn SimpleStack>) -> Self {
         PendingItem::Simple(stack)
     }
 }
 
-impl From<Box<ObjArrStack>> for PendingItem {
-    fn from(stack: Box<ObjArrStack>) -> Self {
+impl From<Box<dyn ObjArrStack>> for PendingItem {
+    fn from(stack: Box<dyn ObjArrStack>) -> Self {
         PendingItem::ObjArr(stack)
     }
 }
@@ -73,38 +81,53 @@ impl From<Box<ObjArrStack>> for PendingItem {
 #[derive(Debug)]
 pub struct StackCounter {
     inner: String,
-    in_string: bool,
+    /// The quote that opened the string being read, if any
+    quote: Option<char>,
     escape: bool,
+    /// Whether strings may be single-quoted, as in JSON5
+    single_quotes: bool,
 }
 
 impl StackCounter {
     pub fn new() -> Self {
         Self {
             inner: String::new(),
-            in_string: false,
+            quote: None,
             escape: false,
+            single_quotes: false,
+        }
+    }
+
+    pub fn json5() -> Self {
+        Self {
+            single_quotes: true,
+            ..Self::new()
         }
     }
 
     pub fn push(&mut self, c: char) -> Result<(), ()> {
-        let res = match c {
-            '{' | '[' if !self.in_string => Ok(self.inner.push(c)),
-            '}' | ']' if !self.in_string => self
-                .inner
-                .pop()
-                .filter(|last| match last {
-                    '[' => c == ']',
-                    '{' => c == '}',
-                    _ => false,
-                })
-                .map(::std::mem::drop)
-                .ok_or(()),
-            _ if self.escape => Ok(self.escape = false),
-            '\\' if self.in_string => Ok(self.escape = true),
-            '"' => Ok(self.in_string = !self.in_string),
-            _ => Ok(()),
-        };
-        res
+        match c {
+            '{' | '[' if !self.in_string() => self.inner.push(c),
+            '}' | ']' if !self.in_string() => {
+                return self
+                    .inner
+                    .pop()
+                    .filter(|last| match last {
+                        '[' => c == ']',
+                        '{' => c == '}',
+                        _ => false,
+                    })
+                    .map(::std::mem::drop)
+                    .ok_or(())
+            }
+            _ if self.escape => self.escape = false,
+            '\\' if self.in_string() => self.escape = true,
+            _ if self.quote == Some(c) => self.quote = None,
+            '"' if !self.in_string() => self.quote = Some(c),
+            '\'' if !self.in_string() && self.single_quotes => self.quote = Some(c),
+            _ => (),
+        }
+        Ok(())
     }
 
     pub fn level(&self) -> usize {
@@ -112,7 +135,7 @@ impl StackCounter {
     }
 
     pub fn in_string(&self) -> bool {
-        self.in_string
+        self.quote.is_some()
     }
 }
 
@@ -140,6 +163,13 @@ mod tests {
             assert_eq!(counter.in_string(), in_string);
             assert_eq!(counter.level(), level);
         }
+
+        let mut counter = StackCounter::json5();
+        for c in r#"[ '"[', "'{", '\'' "#.chars() {
+            counter.push(c).unwrap();
+        }
+        assert!(!counter.in_string());
+        assert_eq!(counter.level(), 1);
     }
 
     #[test]
@@ -1,13 +1,34 @@
-use super::{CheckedStack, IntoJson, JsonValue, ObjArrItem, ObjArrStack, PendingItem};
+use super::super::options::{DuplicateKeys, ParseOptions};
+use super::super::JsonMap;
+use super::{CheckedStack, IntoJson, JsonString, JsonValue, ObjArrItem, ObjArrStack, PendingItem};
+use std::collections::HashSet;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
 
 #[derive(PartialEq, Debug)]
 pub struct ObjectStack {
     inner: Vec<ObjArrItem>,
+    /// Whether the last member may be followed by a comma
+    trailing_comma: bool,
+    /// Whether the first value for a repeated key is kept rather than the last
+    first_wins: bool,
+    /// The keys so far, kept only when repeated keys are rejected
+    keys: Option<HashSet<JsonString>>,
 }
 
 impl ObjectStack {
     pub fn new() -> Self {
-        Self { inner: vec![] }
+        Self::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            inner: vec![],
+            trailing_comma: options.trailing_commas,
+            first_wins: options.duplicate_keys == DuplicateKeys::FirstWins,
+            keys: Some(HashSet::new()).filter(|_| options.duplicate_keys == DuplicateKeys::Error),
+        }
     }
 }
 
@@ -20,26 +41,29 @@ impl From<ObjectStack> for PendingItem {
 impl IntoJson for ObjectStack {
     fn into_json(mut self: Box<Self>) -> Result<JsonValue, ()> {
         use self::ObjArrItem::*;
-        use std::collections::HashMap;
 
         match self.peek() {
-            Some(Comma) => Err(()),
+            Some(Comma) if !self.trailing_comma => Err(()),
             _ => {
-                let mut dict = HashMap::new();
+                let mut dict = JsonMap::new();
 
-                while self.inner.len() > 0 {
+                while !self.inner.is_empty() {
                     let mut s = shift_multi(&mut self.inner, 4);
                     match s.as_slice() {
                         [Key(_), Colon, Item(_), Comma] | [Key(_), Colon, Item(_)] => {
                             if let (Key(k), _, Item(v)) = (s.remove(0), s.remove(0), s.remove(0)) {
-                                dict.insert(k, v);
+                                if self.first_wins {
+                                    dict.entry(k).or_insert(v);
+                                } else {
+                                    dict.insert(k, v);
+                                }
                             }
                         }
                         _ => return Err(()),
                     }
                 }
 
-                Ok(JsonValue::Object(dict))
+                Ok(JsonValue::Object(Box::new(dict)))
             }
         }
     }
@@ -61,24 +85,22 @@ impl CheckedStack<ObjArrItem, ()> for ObjectStack {
     fn push(&mut self, item: ObjArrItem) -> Result<(), ()> {
         use self::ObjArrItem::*;
 
-        match self.peek() {
-            Some(Comma) | None => match item {
-                Key(s) | Item(JsonValue::Text(s)) => Ok(self.inner.push(Key(s))),
-                _ => Err(()),
-            },
-            Some(Item(_)) => match item {
-                Comma => Ok(self.inner.push(item)),
-                _ => Err(()),
-            },
-            Some(Colon) => match item {
-                Item(_) => Ok(self.inner.push(item)),
-                _ => Err(()),
-            },
-            Some(Key(_)) => match item {
-                Colon => Ok(self.inner.push(item)),
-                _ => Err(()),
-            },
+        let item = match (self.peek(), item) {
+            (Some(Comma), Key(ref mut s))
+            | (None, Key(ref mut s))
+            | (Some(Comma), Item(JsonValue::Text(ref mut s)))
+            | (None, Item(JsonValue::Text(ref mut s))) => Key(mem::take(s)),
+            (Some(Item(_)), Comma) => Comma,
+            (Some(Colon), item @ Item(_)) => item,
+            (Some(Key(_)), Colon) => Colon,
+            _ => return Err(()),
+        };
+
+        if let (Some(keys), Key(key)) = (&mut self.keys, &item) {
+            keys.insert(key.clone());
         }
+        self.inner.push(item);
+        Ok(())
     }
 
     fn len(&self) -> usize {
@@ -102,16 +124,22 @@ impl ObjArrStack for ObjectStack {
     fn next_must_be_key(&self) -> bool {
         use self::ObjArrItem::*;
 
-        match self.peek() {
-            Some(Comma) | None => true,
-            _ => false,
-        }
+        matches!(self.peek(), Some(Comma) | None)
+    }
+
+    fn duplicate_key<'a>(&self, item: &'a ObjArrItem) -> Option<&'a JsonString> {
+        let key = match item {
+            ObjArrItem::Key(key) | ObjArrItem::Item(JsonValue::Text(key)) => key,
+            _ => return None,
+        };
+        let keys = self.keys.as_ref()?;
+        Some(key).filter(|key| self.next_must_be_key() && keys.contains(*key))
     }
 }
 
 fn shift_multi<T>(v: &mut Vec<T>, count: usize) -> Vec<T> {
     let mut ret = vec![];
-    while v.len() > 0 && ret.len() < count {
+    while !v.is_empty() && ret.len() < count {
         ret.push(v.remove(0));
     }
     ret
@@ -177,32 +205,37 @@ mod tests {
 
         let tests: Vec<(ObjectStack, ObjArrItem, Result<(), ()>)> = vec![
             (
-                ObjectStack { inner: vec![] },
-                Item(JsonValue::Text("aaaa".to_string())),
+                ObjectStack::new(),
+                Item(JsonValue::Text("aaaa".into())),
                 Ok(()),
             ),
             (
                 ObjectStack {
-                    inner: vec![Key("k".to_string())],
+                    inner: vec![Key("k".into())],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Colon,
                 Ok(()),
             ),
             (
                 ObjectStack {
-                    inner: vec![Key("vvv#@".to_string()), Colon],
+                    inner: vec![Key("vvv#@".into()), Colon],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Item(JsonValue::Boolean(true)),
                 Ok(()),
             ),
-            (
-                ObjectStack { inner: vec![] },
-                Item(JsonValue::Boolean(true)),
-                Err(()),
-            ),
+            (ObjectStack::new(), Item(JsonValue::Boolean(true)), Err(())),
             (
                 ObjectStack {
-                    inner: vec![Key("thing".to_string())],
+                    inner: vec![Key("thing".into())],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Item(JsonValue::Null),
                 Err(()),
@@ -210,10 +243,13 @@ mod tests {
             (
                 ObjectStack {
                     inner: vec![
-                        Key("1_q_2".to_string()),
+                        Key("1_q_2".into()),
                         Colon,
-                        Item(JsonValue::Text("vskjjlds".to_string())),
+                        Item(JsonValue::Text("vskjjlds".into())),
                     ],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Comma,
                 Ok(()),
@@ -221,30 +257,34 @@ mod tests {
             (
                 ObjectStack {
                     inner: vec![
-                        Key("1_q_2".to_string()),
+                        Key("1_q_2".into()),
                         Colon,
-                        Item(JsonValue::Text("vskjjlds".to_string())),
+                        Item(JsonValue::Text("vskjjlds".into())),
                         Comma,
                     ],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
-                Key("eeeakse".to_string()),
+                Key("eeeakse".into()),
                 Ok(()),
             ),
             (
                 ObjectStack {
-                    inner: vec![
-                        Key("thing".to_string()),
-                        Colon,
-                        Item(JsonValue::Null),
-                        Comma,
-                    ],
+                    inner: vec![Key("thing".into()), Colon, Item(JsonValue::Null), Comma],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Comma,
                 Err(()),
             ),
             (
                 ObjectStack {
-                    inner: vec![Key("hhhhhh".to_string()), Colon, Item(JsonValue::Null)],
+                    inner: vec![Key("hhhhhh".into()), Colon, Item(JsonValue::Null)],
+                    trailing_comma: false,
+                    first_wins: false,
+                    keys: None,
                 },
                 Colon,
                 Err(()),
@@ -258,67 +298,66 @@ mod tests {
 
     #[test]
     fn object_into_json() {
-        use super::{IntoJson, JsonValue, ObjArrItem::*, ObjectStack};
-        use std::collections::HashMap;
+        use super::{IntoJson, JsonMap, JsonValue, ObjArrItem::*, ObjectStack};
 
         let tests = vec![
             (
                 vec![
-                    Key("j23O@".to_string()),
+                    Key("j23O@".into()),
                     Colon,
                     Item(JsonValue::Boolean(true)),
                     Comma,
-                    Key("ffff".to_string()),
+                    Key("ffff".into()),
                     Colon,
-                    Item(JsonValue::Text("aaaa".to_string())),
+                    Item(JsonValue::Text("aaaa".into())),
                     Comma,
-                    Key("qqqqq".to_string()),
+                    Key("qqqqq".into()),
                     Colon,
-                    Item(JsonValue::Object({
-                        let mut map = HashMap::new();
-                        map.insert("d29".to_string(), JsonValue::Number(10f64));
-                        map.insert("0000e".to_string(), JsonValue::Null);;
+                    Item(JsonValue::Object(Box::new({
+                        let mut map = JsonMap::new();
+                        map.insert("d29".into(), JsonValue::from(10f64));
+                        map.insert("0000e".into(), JsonValue::Null);
                         map
-                    })),
+                    }))),
                     Comma,
-                    Key("arr".to_string()),
+                    Key("arr".into()),
                     Colon,
                     Item(JsonValue::Array(vec![
-                        JsonValue::Number(10f64),
-                        JsonValue::Number(20f64),
-                        JsonValue::Number(0.2332),
+                        JsonValue::from(10f64),
+                        JsonValue::from(20f64),
+                        JsonValue::from(0.2332),
                     ])),
                 ],
                 Ok(JsonValue::Object({
-                    let mut map = HashMap::new();
-                    map.insert("j23O@".to_string(), JsonValue::Boolean(true));
-                    map.insert("ffff".to_string(), JsonValue::Text("aaaa".to_string()));
+                    let mut map = JsonMap::new();
+                    map.insert("j23O@".into(), JsonValue::Boolean(true));
+                    map.insert("ffff".into(), JsonValue::Text("aaaa".into()));
                     map.insert(
-                        "qqqqq".to_string(),
-                        JsonValue::Object({
-                            let mut map = HashMap::new();
-                            map.insert("d29".to_string(), JsonValue::Number(10f64));
-                            map.insert("0000e".to_string(), JsonValue::Null);;
+                        "qqqqq".into(),
+                        JsonValue::Object(Box::new({
+                            let mut map = JsonMap::new();
+                            map.insert("d29".into(), JsonValue::from(10f64));
+                            map.insert("0000e".into(), JsonValue::Null);
                             map
-                        }),
+                        })),
                     );
                     map.insert(
-                        "arr".to_string(),
+                        "arr".into(),
                         JsonValue::Array(vec![
-                            JsonValue::Number(10f64),
-                            JsonValue::Number(20f64),
-                            JsonValue::Number(0.2332),
+                            JsonValue::from(10f64),
+                            JsonValue::from(20f64),
+                            JsonValue::from(0.2332),
                         ]),
                     );
-                    map
+                    Box::new(map)
                 })),
             ),
-            (vec![], Ok(JsonValue::Object(HashMap::new()))),
-            (vec![Key("aaaa".to_string())], Err(())),
-            (vec![Key("aaaa".to_string()), Colon], Err(())),
+            (vec![], Ok(JsonValue::Object(Box::default()))),
+            (vec![Key("aaaa".into())], Err(())),
+            (vec![Key("aaaa".into()), Colon], Err(())),
             (
                 vec![
-                    Key("ppppp".to_string()),
+                    Key("ppppp".into()),
                     Colon,
                     Item(JsonValue::Boolean(false)),
                     Comma,
@@ -328,8 +367,28 @@ mod tests {
         ];
 
         for (inner, result) in tests.into_iter() {
-            let mut stack = ObjectStack { inner };
+            let stack = ObjectStack {
+                inner,
+                trailing_comma: false,
+                first_wins: false,
+                keys: None,
+            };
             assert_eq!(Box::new(stack).into_json(), result);
         }
+
+        let stack = ObjectStack {
+            inner: vec![Key("a".into()), Colon, Item(JsonValue::Null), Comma],
+            trailing_comma: true,
+            first_wins: false,
+            keys: None,
+        };
+        assert_eq!(
+            Box::new(stack).into_json(),
+            Ok(JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("a".into(), JsonValue::Null);
+                map
+            })))
+        );
     }
 }
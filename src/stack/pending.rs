@@ -1,7 +1,16 @@
+#[cfg(feature = "decimal")]
+use super::super::decimal::{Decimal, ParseDecimalError};
+use super::super::options::ParseOptions;
+use super::super::JsonNumber;
 use super::{IntoJson, JsonValue, SimpleStack};
+use std::convert::TryFrom;
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
 
-const BOOL_STRS: &'static [&'static str] = &["true", "false"];
-const NULL_STRS: &[&'static str] = &["null"];
+const BOOL_STRS: &[&str] = &["true", "false"];
+const NULL_STRS: &[&str] = &["null"];
+const NAMED_NUMBER_STRS: &[&str] = &["Infinity", "NaN"];
 
 pub trait PendingStack<C>: IntoJson {
     fn push(&mut self, c: C) -> Result<bool, C>;
@@ -21,9 +30,13 @@ impl StringMatcherStack {
         }
     }
 
-    fn push(&mut self, c: char) -> Result<bool, char> {
+    fn can_push(&self, c: char) -> bool {
         let concatenated = format!("{}{}", self.inner_string, c);
-        if self.matchers.iter().any(|s| s.starts_with(&concatenated)) {
+        self.matchers.iter().any(|s| s.starts_with(&concatenated))
+    }
+
+    fn push(&mut self, c: char) -> Result<bool, char> {
+        if self.can_push(c) {
             self.inner_string.push(c);
             Ok(self.matchers.contains(&&*self.inner_string))
         } else {
@@ -117,51 +130,82 @@ impl PendingStack<char> for NullStack {
 enum EscapeType {
     SimpleChar(char),
     Unicode(String),
+    /// `\x` and two hex digits, in JSON5
+    Hex(String),
+    /// A backslash before a line break, which JSON5 leaves out of the string
+    LineContinuation,
 }
 
 #[derive(PartialEq, Debug)]
 struct EscapeSequence {
     inner: Option<EscapeType>,
+    json5: bool,
 }
 
 impl EscapeSequence {
     fn new() -> Self {
-        Self { inner: None }
+        Self {
+            inner: None,
+            json5: false,
+        }
+    }
+
+    fn json5() -> Self {
+        Self {
+            inner: None,
+            json5: true,
+        }
     }
 
     fn push(&mut self, c: char) -> Result<bool, char> {
+        use self::EscapeType::*;
+
         Ok(if self.inner.is_none() {
-            self.inner = Some(if c == 'u' {
-                EscapeType::Unicode(String::new())
-            } else {
-                EscapeType::SimpleChar(match c {
-                    'n' => '\n',
-                    't' => '\t',
-                    'r' => '\r',
-                    'b' => '\x08',
-                    'f' => '\x0C',
-                    '\\' | '"' => c,
-                    _ => return Err(c),
-                })
+            self.inner = Some(match c {
+                'u' => Unicode(String::new()),
+                'x' if self.json5 => Hex(String::new()),
+                'n' => SimpleChar('\n'),
+                't' => SimpleChar('\t'),
+                'r' => SimpleChar('\r'),
+                'b' => SimpleChar('\x08'),
+                'f' => SimpleChar('\x0C'),
+                '\\' | '"' => SimpleChar(c),
+                'v' if self.json5 => SimpleChar('\x0B'),
+                '0' if self.json5 => SimpleChar('\0'),
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' if self.json5 => LineContinuation,
+                // any other character but a digit stands for itself
+                _ if self.json5 && !c.is_ascii_digit() => SimpleChar(c),
+                _ => return Err(c),
             });
-            c != 'u'
+            matches!(self.inner, Some(SimpleChar(_)) | Some(LineContinuation))
         } else {
             match &mut self.inner {
-                Some(EscapeType::Unicode(ref mut s)) if c.is_digit(16) && s.len() < 4 => {
+                Some(Unicode(ref mut s)) if c.is_ascii_hexdigit() && s.len() < 4 => {
                     s.push(c);
                     s.len() == 4
                 }
+                Some(Hex(ref mut s)) if c.is_ascii_hexdigit() && s.len() < 2 => {
+                    s.push(c);
+                    s.len() == 2
+                }
                 _ => return Err(c),
             }
         })
     }
 
+    fn continues_line(&self) -> bool {
+        self.inner == Some(EscapeType::LineContinuation)
+    }
+
     fn into_char(self) -> Result<char, ()> {
         use std::{char::from_u32, mem::drop};
         match self.inner {
             Some(EscapeType::Unicode(ref s)) if s.len() == 4 => u32::from_str_radix(s, 16)
                 .map_err(drop)
                 .and_then(|code| from_u32(code).ok_or(())),
+            Some(EscapeType::Hex(ref s)) if s.len() == 2 => u32::from_str_radix(s, 16)
+                .map_err(drop)
+                .and_then(|code| from_u32(code).ok_or(())),
             Some(EscapeType::SimpleChar(c)) => Ok(c),
             _ => Err(()),
         }
@@ -173,6 +217,11 @@ pub struct TextStack {
     inner: String,
     completed: bool,
     escape: Option<EscapeSequence>,
+    /// The character that ends the string
+    quote: char,
+    json5: bool,
+    /// Whether the last escape was a backslash before `\r`, which a `\n` may complete
+    continued_cr: bool,
 }
 
 impl SimpleStack for TextStack {}
@@ -183,6 +232,18 @@ impl TextStack {
             inner: String::new(),
             completed: false,
             escape: None,
+            quote: '"',
+            json5: false,
+            continued_cr: false,
+        }
+    }
+
+    /// A JSON5 string, which ends at `quote` and may hold JSON5's escapes and line continuations.
+    pub fn json5(quote: char) -> Self {
+        Self {
+            quote,
+            json5: true,
+            ..Self::new()
         }
     }
 }
@@ -190,7 +251,7 @@ impl TextStack {
 impl IntoJson for TextStack {
     fn into_json(self: Box<Self>) -> Result<JsonValue, ()> {
         let completed = self.completed;
-        Some(JsonValue::Text(self.inner))
+        Some(JsonValue::Text(self.inner.into()))
             .filter(|_| completed)
             .ok_or(())
     }
@@ -199,23 +260,33 @@ impl IntoJson for TextStack {
 impl PendingStack<char> for TextStack {
     fn push(&mut self, c: char) -> Result<bool, char> {
         if let Some(mut seq) = self.escape.take() {
-            if seq.push(c)? {
-                self.inner.push(seq.into_char().map_err(|()| c)?);
-            } else {
+            if !seq.push(c)? {
                 self.escape = Some(seq);
+            } else if seq.continues_line() {
+                self.continued_cr = c == '\r';
+            } else {
+                self.inner.push(seq.into_char().map_err(|()| c)?);
             }
             Ok(false)
+        } else if mem::replace(&mut self.continued_cr, false) && c == '\n' {
+            Ok(false)
         } else {
             match c {
-                '"' if !self.completed => {
+                _ if c == self.quote && !self.completed => {
                     self.completed = true;
                     Ok(true)
                 }
                 '\\' if !self.completed => {
-                    self.escape = Some(EscapeSequence::new());
+                    self.escape = Some(if self.json5 {
+                        EscapeSequence::json5()
+                    } else {
+                        EscapeSequence::new()
+                    });
                     Ok(false)
                 }
-                _ if !self.completed && !c.is_control() => {
+                // JSON5 strings may hold control characters, but not line breaks
+                '\n' | '\r' if self.json5 => Err(c),
+                _ if !self.completed && (self.json5 || c >= ' ') => {
                     self.inner.push(c);
                     Ok(false)
                 }
@@ -231,15 +302,26 @@ enum NumPosition {
     IntoDecimal,
     Decimal,
     Exponent,
+    /// The digits after JSON5's `0x`, kept in `whole`
+    Hex,
+    /// JSON5's `Infinity` or `NaN`
+    Named(StringMatcherStack),
 }
 
 #[derive(PartialEq, Debug)]
 pub struct NumberStack {
     position: NumPosition,
     positive: bool,
+    /// Whether a `-`, or in JSON5 a `+`, has been read
+    signed: bool,
     whole: String,
     decimal: String,
     exponent: String,
+    json5: bool,
+    /// Whether numbers that are not integers within 64 bits are read as a `Decimal`
+    exact: bool,
+    /// How many characters have been read, to find where the number started
+    len: usize,
 }
 
 impl NumberStack {
@@ -247,18 +329,66 @@ impl NumberStack {
         Self {
             position: NumPosition::Whole,
             positive: true,
+            signed: false,
             whole: String::new(),
             decimal: String::new(),
             exponent: String::new(),
+            json5: false,
+            exact: false,
+            len: 0,
+        }
+    }
+
+    /// A number read with the syntax and precision `options` ask for.  A JSON5 number may also be
+    /// hexadecimal, start with `+` or a decimal point, end with a decimal point, or be
+    /// `Infinity` or `NaN`.
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            json5: options.json5,
+            #[cfg(feature = "decimal")]
+            exact: options.exact_numbers,
+            ..Self::new()
+        }
+    }
+
+    /// How many characters make up the number so far.
+    pub fn chars_read(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the number is well formed but has to be read exactly and does not fit in a
+    /// `Decimal`.
+    pub fn out_of_range(&self) -> bool {
+        #[cfg(feature = "decimal")]
+        if self.exact {
+            return self.decimal() == Err(ParseDecimalError::OutOfRange);
         }
+        false
+    }
+
+    #[cfg(feature = "decimal")]
+    fn decimal(&self) -> Result<Decimal, ParseDecimalError> {
+        let text = self.stringify().map_err(|()| ParseDecimalError::Invalid)?;
+        // JSON5 numbers may start with a decimal point, which `Decimal` reads only after a zero
+        if self.whole.is_empty() {
+            text.replacen('.', "0.", 1).parse()
+        } else {
+            text.parse()
+        }
+    }
+
+    /// Whether there is a digit before or after the decimal point.
+    fn has_digits(&self) -> bool {
+        !self.decimal.is_empty() || (self.json5 && !self.whole.is_empty())
     }
 
     pub fn stringify(&self) -> Result<String, ()> {
         use self::NumPosition::*;
 
         if (self.position == Whole && self.whole.is_empty())
-            || (self.position == Decimal && self.decimal.is_empty())
+            || (self.position == Decimal && !self.has_digits())
             || (self.position == Exponent && self.exponent.is_empty())
+            || matches!(self.position, Hex | Named(_))
         {
             Err(())
         } else {
@@ -288,25 +418,87 @@ impl NumberStack {
         use self::NumPosition::*;
 
         match self.position {
+            Whole if self.json5 && self.whole.is_empty() => {
+                ((c == '-' || c == '+') && !self.signed) || matches!(c, '0'..='9' | '.' | 'I' | 'N')
+            }
             Whole => {
-                (c == '-' && self.whole.is_empty() && self.positive)
+                (c == '-' && self.whole.is_empty() && !self.signed)
                     || (c == '0' && self.whole.is_empty())
-                    || (c.is_digit(10)
-                        && ((c != '0' && self.whole.is_empty()) || self.whole.len() > 0))
-                    || (self.whole.len() > 0 && c.eq_ignore_ascii_case(&'e'))
-                    || (c == '.' && self.whole.len() > 0)
+                    || (c.is_ascii_digit()
+                        && ((c != '0' && self.whole.is_empty()) || !self.whole.is_empty()))
+                    || (!self.whole.is_empty() && c.eq_ignore_ascii_case(&'e'))
+                    || (c == '.' && !self.whole.is_empty())
+            }
+            Exponent => c.is_ascii_digit() || ((c == '+' || c == '-') && self.exponent.is_empty()),
+            Decimal => c.is_ascii_digit() || (self.has_digits() && c.eq_ignore_ascii_case(&'e')),
+            IntoDecimal => c == '.' || (self.json5 && c.eq_ignore_ascii_case(&'x')),
+            Hex => c.is_ascii_hexdigit(),
+            Named(ref matcher) => matcher.can_push(c),
+        }
+    }
+
+    fn sign(&self, n: f64) -> JsonValue {
+        JsonValue::Number(JsonNumber::F64(if self.positive { n } else { -n }))
+    }
+
+    /// The value of a whole number whose digits read as `n`.  `-0` keeps its sign as a float, and
+    /// a negative number below `i64::MIN` becomes the nearest float.
+    fn integer(&self, n: u64) -> JsonValue {
+        if self.positive {
+            JsonValue::Number(JsonNumber::U64(n))
+        } else {
+            match i64::try_from(-i128::from(n)) {
+                Ok(n) if n != 0 => JsonValue::Number(JsonNumber::I64(n)),
+                _ => self.sign(n as f64),
             }
-            Exponent => c.is_digit(10) || ((c == '+' || c == '-') && self.exponent.is_empty()),
-            Decimal => c.is_digit(10) || (self.decimal.len() > 0 && c.eq_ignore_ascii_case(&'e')),
-            IntoDecimal => c == '.',
         }
     }
+
+    /// The value of a number that is not an integer within 64 bits.
+    fn float(&self) -> Result<JsonValue, ()> {
+        #[cfg(feature = "decimal")]
+        if self.exact {
+            return self
+                .decimal()
+                .map(|n| JsonValue::Number(JsonNumber::Decimal(n)))
+                .map_err(mem::drop);
+        }
+        self.stringify().and_then(|s| {
+            s.parse()
+                .map(|n| JsonValue::Number(JsonNumber::F64(n)))
+                .map_err(::std::mem::drop)
+        })
+    }
 }
 
 impl IntoJson for NumberStack {
     fn into_json(self: Box<Self>) -> Result<JsonValue, ()> {
-        self.stringify()
-            .and_then(|s| s.parse().map(JsonValue::Number).map_err(::std::mem::drop))
+        match self.position {
+            NumPosition::Hex if !self.whole.is_empty() => {
+                match u64::from_str_radix(&self.whole, 16) {
+                    Ok(n) => Ok(self.integer(n)),
+                    Err(_) => {
+                        let n = self.whole.chars().fold(0.0, |n, digit| {
+                            n * 16.0 + f64::from(digit.to_digit(16).unwrap())
+                        });
+                        Ok(self.sign(n))
+                    }
+                }
+            }
+            NumPosition::Named(ref matcher) => match matcher.inner_string.as_str() {
+                "Infinity" => Ok(self.sign(f64::INFINITY)),
+                "NaN" => Ok(JsonValue::Number(JsonNumber::F64(f64::NAN))),
+                _ => Err(()),
+            },
+            NumPosition::Whole | NumPosition::IntoDecimal if self.exponent.is_empty() => {
+                match self.whole.parse() {
+                    // An exact number below `i64::MIN` is left to `float`
+                    Ok(n) if self.positive || !self.exact || n <= 1 << 63 => Ok(self.integer(n)),
+                    _ => self.float(),
+                }
+            }
+            _ => self.float(),
+        }
     }
 }
 
@@ -315,32 +507,54 @@ impl PendingStack<char> for NumberStack {
         use self::NumPosition::*;
 
         match self.position {
-            Whole if c == '-' && self.whole.is_empty() && self.positive => self.positive = false,
+            Whole if (c == '-' || (c == '+' && self.json5)) && self.whole.is_empty() => {
+                if self.signed {
+                    return Err(c);
+                }
+                self.signed = true;
+                self.positive = c == '+';
+            }
             Whole if c == '0' && self.whole.is_empty() => {
                 self.whole.push(c);
                 self.position = IntoDecimal
             }
             Whole
-                if c.is_digit(10)
-                    && ((self.whole.is_empty() && c != '0') || self.whole.len() > 0) =>
+                if c.is_ascii_digit()
+                    && ((self.whole.is_empty() && c != '0') || !self.whole.is_empty()) =>
             {
                 self.whole.push(c)
             }
+            Whole if (c == 'I' || c == 'N') && self.json5 && self.whole.is_empty() => {
+                let mut matcher = StringMatcherStack::new(NAMED_NUMBER_STRS);
+                matcher.push(c)?;
+                self.position = Named(matcher)
+            }
             IntoDecimal if c == '.' => self.position = Decimal,
-            Whole if c == '.' && self.whole.len() > 0 => self.position = Decimal,
-            Decimal if c.is_digit(10) => self.decimal.push(c),
-            Decimal if self.decimal.len() > 0 && c.eq_ignore_ascii_case(&'e') => {
+            IntoDecimal if self.json5 && c.eq_ignore_ascii_case(&'x') => {
+                self.whole.clear();
+                self.position = Hex
+            }
+            Whole if c == '.' && (!self.whole.is_empty() || self.json5) => self.position = Decimal,
+            Decimal if c.is_ascii_digit() => self.decimal.push(c),
+            Decimal if self.has_digits() && c.eq_ignore_ascii_case(&'e') => {
                 self.position = Exponent
             }
-            Whole if self.whole.len() > 0 && c.eq_ignore_ascii_case(&'e') => {
+            Whole if !self.whole.is_empty() && c.eq_ignore_ascii_case(&'e') => {
                 self.position = Exponent
             }
-            Exponent if c.is_digit(10) || ((c == '+' || c == '-') && self.exponent.is_empty()) => {
+            Exponent
+                if c.is_ascii_digit() || ((c == '+' || c == '-') && self.exponent.is_empty()) =>
+            {
                 self.exponent.push(c)
             }
+            Hex if c.is_ascii_hexdigit() => self.whole.push(c),
+            Named(ref mut matcher) => {
+                matcher.push(c)?;
+            }
             _ => return Err(c),
         }
 
+        self.len += 1;
         Ok(false)
     }
 }
@@ -414,8 +628,7 @@ mod tests {
             insert
                 .chars()
                 .map(|c| seq.push(c))
-                .skip_while(|r| r.is_ok())
-                .next()
+                .find(|r| r.is_err())
                 .map(|e| {
                     assert_eq!(Some(e), error);
                 })
@@ -449,8 +662,7 @@ mod tests {
             assert_eq!(
                 st.chars()
                     .map(|c| seq.push(c))
-                    .skip_while(|r| r.is_ok())
-                    .next()
+                    .find(|r| r.is_err())
                     .filter(|&e| e == error),
                 Some(error)
             );
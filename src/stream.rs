@@ -0,0 +1,713 @@
+//! Parsing JSON values from a reader one at a time, so that only the value being parsed is held
+//! in memory.
+use super::events::EventParser;
+use super::scan::Scanner;
+use super::span::{Place, Span, Spanned};
+use super::{json_parse, JsonError, JsonValue};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::{error, fmt, str};
+
+/// Describes all possible errors that could occur while parsing a stream.
+///
+/// Positions in `Parse` errors are counted from the start of the stream, not from the start of
+/// the value.  A stream stops after its first error.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// A value was not valid UTF-8
+    InvalidUtf8 {
+        /// The byte offset of the value in the stream
+        offset: usize,
+    },
+    /// The stream was not valid JSON
+    Parse(JsonError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => error.fmt(f),
+            StreamError::InvalidUtf8 { offset } => {
+                write!(f, "value at byte offset {} is not valid UTF-8", offset)
+            }
+            StreamError::Parse(error) => error.fmt(f),
+        }
+    }
+}
+
+impl error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            StreamError::Parse(error) => Some(error),
+            StreamError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+impl From<JsonError> for StreamError {
+    fn from(error: JsonError) -> Self {
+        StreamError::Parse(error)
+    }
+}
+
+/// Places an error in a value that started at `start` in the stream, with the scanner just past
+/// the value.  A value that ran out before the stream did was cut short by whatever follows it,
+/// so that is reported instead of the end of input.
+fn locate<R: BufRead>(scanner: &mut Scanner<R>, error: JsonError, start: Place) -> StreamError {
+    match error {
+        JsonError::UnexpectedToken {
+            character,
+            location,
+            line,
+            column,
+            offset,
+        } => {
+            let at = Place {
+                location,
+                line,
+                column,
+                offset,
+            };
+            StreamError::Parse(start.then(at).token_err(character))
+        }
+        JsonError::DuplicateKey {
+            key,
+            location,
+            line,
+            column,
+            offset,
+        } => {
+            let at = Place {
+                location,
+                line,
+                column,
+                offset,
+            };
+            StreamError::Parse(start.then(at).duplicate_err(key))
+        }
+        JsonError::DepthLimitExceeded {
+            limit,
+            location,
+            line,
+            column,
+            offset,
+        } => {
+            let at = Place {
+                location,
+                line,
+                column,
+                offset,
+            };
+            StreamError::Parse(start.then(at).depth_err(limit))
+        }
+        JsonError::NumberOutOfRange {
+            location,
+            line,
+            column,
+            offset,
+        } => {
+            let at = Place {
+                location,
+                line,
+                column,
+                offset,
+            };
+            StreamError::Parse(start.then(at).range_err())
+        }
+        JsonError::UnexpectedEOF { .. } => match scanner.peek() {
+            Ok(Some(_)) => unexpected(scanner),
+            Ok(None) => StreamError::Parse(scanner.place().eof_err()),
+            Err(error) => error.into(),
+        },
+    }
+}
+
+/// Parses the text of a value found by a `Scanner` that started at `start`.
+pub(crate) fn parse_raw<R: BufRead>(
+    scanner: &mut Scanner<R>,
+    text: &[u8],
+    start: Place,
+) -> Result<JsonValue, StreamError> {
+    let text = str::from_utf8(text).map_err(|_| StreamError::InvalidUtf8 {
+        offset: start.offset,
+    })?;
+    json_parse(text).map_err(|e| locate(scanner, e, start))
+}
+
+/// Checks the text of a value found by a `Scanner` without building a `JsonValue`.
+pub(crate) fn check_raw<R: BufRead>(
+    scanner: &mut Scanner<R>,
+    text: &[u8],
+    start: Place,
+) -> Result<(), StreamError> {
+    let text = str::from_utf8(text).map_err(|_| StreamError::InvalidUtf8 {
+        offset: start.offset,
+    })?;
+    let mut parser = EventParser::new(text);
+    loop {
+        match parser.step() {
+            Ok(Some(_)) => (),
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(locate(scanner, e, start)),
+        }
+    }
+}
+
+/// Reads the next value from a scanner positioned at its first byte.
+fn next_value<R: BufRead>(scanner: &mut Scanner<R>) -> Result<Spanned<JsonValue>, StreamError> {
+    let start = scanner.place();
+    let text = scanner.raw_value()?;
+    let value = parse_raw(scanner, &text, start)?;
+    Ok(Spanned::new(
+        value,
+        Span::new(start.offset, scanner.offset()),
+    ))
+}
+
+/// Parses a single JSON document from a reader, as `json_parse` does from a string.  Input is
+/// read through a buffer, so the reader need not be buffered already and the caller does not have
+/// to read the whole document into a string first.
+///
+/// Errors are located as `json_parse` locates them, from the start of the input.
+/// Anything but whitespace after the document is an error.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::stream::{json_parse_reader, StreamError};
+/// use json_rs::{json_parse, JsonError};
+///
+/// fn main() {
+///     let file: &[u8] = b"{ \"ids\": [1, 2, 3] }\n";
+///     assert_eq!(
+///         json_parse_reader(file).unwrap(),
+///         json_parse(r#"{ "ids": [1, 2, 3] }"#).unwrap()
+///     );
+///
+///     assert!(matches!(
+///         json_parse_reader(&b"[1, 2] 3"[..]),
+///         Err(StreamError::Parse(JsonError::UnexpectedToken { character: '3', location: 7, .. }))
+///     ));
+/// }
+/// ```
+pub fn json_parse_reader<R: Read>(reader: R) -> Result<JsonValue, StreamError> {
+    let mut scanner = Scanner::new(BufReader::new(reader));
+    if scanner.skip_whitespace()?.is_none() {
+        return Err(scanner.place().eof_err().into());
+    }
+    let value = next_value(&mut scanner)?.value;
+    match scanner.skip_whitespace()? {
+        Some(_) => Err(unexpected(&mut scanner)),
+        None => Ok(value),
+    }
+}
+
+/// An iterator over values written back to back, created by `json_stream`.
+#[derive(Debug)]
+pub struct JsonStream<R> {
+    scanner: Scanner<R>,
+    done: bool,
+}
+
+/// Parses a stream of JSON values that follow each other with optional whitespace between them,
+/// as written by many streaming APIs and command line tools.  Each value is yielded with its
+/// byte span in the stream.
+///
+/// Numbers, booleans and `null` must be followed by whitespace or a container or string, since
+/// `12` followed by `3` cannot be told apart from `123`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::stream::json_stream;
+/// use json_rs::{JsonValue, Span};
+///
+/// fn main() {
+///     let input = r#"{"id":1}{"id":2}
+/// [3] "four""#;
+///     let values = json_stream(input.as_bytes())
+///         .collect::<Result<Vec<_>, _>>()
+///         .unwrap();
+///     assert_eq!(values.len(), 4);
+///     assert_eq!(values[1].value.get("id"), Some(&JsonValue::from(2)));
+///     assert_eq!(values[2].span, Span::new(17, 20));
+///     assert_eq!(values[3].value, JsonValue::Text("four".into()));
+/// }
+/// ```
+pub fn json_stream<R: BufRead>(reader: R) -> JsonStream<R> {
+    JsonStream {
+        scanner: Scanner::new(reader),
+        done: false,
+    }
+}
+
+impl<R: BufRead> JsonStream<R> {
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.scanner.into_inner()
+    }
+}
+
+impl<R: BufRead> Iterator for JsonStream<R> {
+    type Item = Result<Spanned<JsonValue>, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match self.scanner.skip_whitespace() {
+            Ok(None) => None,
+            Ok(Some(_)) => Some(next_value(&mut self.scanner)),
+            Err(error) => Some(Err(error.into())),
+        };
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+/// Where an `ArrayStream` is in its array.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArrayState {
+    First,
+    Next,
+    Done,
+    /// An error has been returned
+    Failed,
+}
+
+/// An iterator over the elements of a top-level array, created by `parse_array_stream`.
+#[derive(Debug)]
+pub struct ArrayStream<R> {
+    scanner: Scanner<R>,
+    state: ArrayState,
+}
+
+/// Reports the value at the scanner as unexpected.
+pub(crate) fn unexpected<R: BufRead>(scanner: &mut Scanner<R>) -> StreamError {
+    let start = scanner.place();
+    match scanner.raw_value() {
+        Ok(text) => {
+            let character = String::from_utf8_lossy(&text).chars().next().unwrap();
+            StreamError::Parse(start.token_err(character))
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Checks that the opening `[` of an array comes first in a stream, and returns an iterator that
+/// parses its elements one at a time as they are read.  Only one element is held in memory at
+/// once, however large the array is.
+///
+/// Once the closing `]` is read the iterator checks that only whitespace follows it.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::stream::parse_array_stream;
+/// use json_rs::JsonValue;
+///
+/// fn main() {
+///     let export = r#"[{ "id": 1 }, { "id": 2 }, { "id": 3 }]"#;
+///     let ids = parse_array_stream(export.as_bytes())
+///         .unwrap()
+///         .map(|element| element.unwrap().get("id").cloned())
+///         .collect::<Vec<_>>();
+///     assert_eq!(ids[2], Some(JsonValue::from(3)));
+///
+///     assert!(parse_array_stream(&b"{}"[..]).is_err());
+/// }
+/// ```
+pub fn parse_array_stream<R: BufRead>(reader: R) -> Result<ArrayStream<R>, StreamError> {
+    let mut scanner = Scanner::new(reader);
+    match scanner.skip_whitespace()? {
+        Some(b'[') => scanner.bump(b'['),
+        Some(_) => return Err(unexpected(&mut scanner)),
+        None => return Err(scanner.place().eof_err().into()),
+    }
+    Ok(ArrayStream {
+        scanner,
+        state: ArrayState::First,
+    })
+}
+
+impl<R: BufRead> ArrayStream<R> {
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.scanner.into_inner()
+    }
+
+    /// Reads the text of the next element without parsing it, along with where it starts.
+    pub(crate) fn next_raw(&mut self) -> Result<Option<(Vec<u8>, Place)>, StreamError> {
+        let scanner = &mut self.scanner;
+        loop {
+            let next = match self.state {
+                ArrayState::Failed => None,
+                _ => scanner.skip_whitespace()?,
+            };
+            match (self.state, next) {
+                (ArrayState::Failed, _) | (ArrayState::Done, None) => return Ok(None),
+                (_, None) => return Err(scanner.place().eof_err().into()),
+                (ArrayState::Done, Some(_)) => return Err(unexpected(scanner)),
+                (_, Some(b']')) => {
+                    scanner.bump(b']');
+                    self.state = ArrayState::Done;
+                }
+                (ArrayState::Next, Some(b',')) => {
+                    scanner.bump(b',');
+                    if let Some(b']') = scanner.skip_whitespace()? {
+                        return Err(unexpected(scanner));
+                    }
+                    self.state = ArrayState::First;
+                }
+                (ArrayState::Next, Some(_)) => return Err(unexpected(scanner)),
+                (ArrayState::First, Some(_)) => {
+                    let start = scanner.place();
+                    let text = scanner.raw_value()?;
+                    self.state = ArrayState::Next;
+                    return Ok(Some((text, start)));
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayStream<R> {
+    type Item = Result<JsonValue, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self
+            .next_raw()
+            .and_then(|raw| match raw {
+                Some((text, start)) => parse_raw(&mut self.scanner, &text, start).map(Some),
+                None => Ok(None),
+            })
+            .transpose();
+        if let Some(Err(_)) = result {
+            self.state = ArrayState::Failed;
+        }
+        result
+    }
+}
+
+/// How many elements `split_array` puts in each chunk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitLimit {
+    /// At most this many elements per chunk
+    Elements(usize),
+    /// At most about this many bytes per chunk.  A chunk is closed before an element that would
+    /// take it over the limit, so only an element that is larger than the limit by itself makes
+    /// a larger chunk.
+    Bytes(usize),
+}
+
+/// Splits a top-level array into smaller arrays, each written to a writer from `open`, which is
+/// given the zero-based index of the chunk.  Returns the number of chunks written; an empty
+/// array writes none.
+///
+/// Elements are checked for validity but copied as they are, without being parsed into
+/// `JsonValue`s, so whitespace inside them is kept.  Only one element is held in memory at a
+/// time.  If the input turns out to be invalid, the chunks written before the error are left as
+/// they are and the last one is left unterminated.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::stream::{split_array, SplitLimit};
+/// use std::fs::{self, File};
+///
+/// fn main() {
+///     let dir = std::env::temp_dir().join("json-rs-split-example");
+///     fs::create_dir_all(&dir).unwrap();
+///
+///     let export = r#"[1, {"a": 2}, "three", [4], 5]"#;
+///     let count = split_array(export.as_bytes(), SplitLimit::Elements(2), |ind| {
+///         File::create(dir.join(format!("part-{}.json", ind)))
+///     });
+///     assert_eq!(count.unwrap(), 3);
+///     assert_eq!(
+///         fs::read_to_string(dir.join("part-1.json")).unwrap(),
+///         r#"["three",[4]]"#
+///     );
+///     fs::remove_dir_all(&dir).unwrap();
+/// }
+/// ```
+pub fn split_array<R, W, F>(reader: R, limit: SplitLimit, mut open: F) -> Result<usize, StreamError>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(usize) -> io::Result<W>,
+{
+    let mut elements = parse_array_stream(reader)?;
+    let mut chunks = 0;
+    // the open chunk's writer, element count and length once its `]` is written
+    let mut current: Option<(W, usize, usize)> = None;
+
+    while let Some((text, start)) = elements.next_raw()? {
+        check_raw(&mut elements.scanner, &text, start)?;
+
+        let full = match (&current, limit) {
+            (Some((_, count, _)), SplitLimit::Elements(max)) => *count >= max,
+            (Some((_, _, bytes)), SplitLimit::Bytes(max)) => *bytes + 1 + text.len() > max,
+            (None, _) => false,
+        };
+        if full {
+            let (mut writer, _, _) = current.take().unwrap();
+            writer.write_all(b"]")?;
+            writer.flush()?;
+        }
+
+        let (writer, count, bytes) = match current {
+            Some(ref mut chunk) => {
+                chunk.0.write_all(b",")?;
+                chunk
+            }
+            None => {
+                let mut writer = open(chunks)?;
+                chunks += 1;
+                writer.write_all(b"[")?;
+                current.get_or_insert((writer, 0, 1))
+            }
+        };
+        writer.write_all(&text)?;
+        *count += 1;
+        *bytes += text.len() + 1;
+    }
+
+    if let Some((mut writer, _, _)) = current {
+        writer.write_all(b"]")?;
+        writer.flush()?;
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        json_parse_reader, json_stream, parse_array_stream, split_array, SplitLimit, StreamError,
+    };
+    use crate::{json_parse, JsonError, JsonValue, Span};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    /// Appends to the last of a shared list of chunks.
+    struct Chunk(Rc<RefCell<Vec<String>>>);
+
+    impl Write for Chunk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut chunks = self.0.borrow_mut();
+            chunks
+                .last_mut()
+                .unwrap()
+                .push_str(std::str::from_utf8(buf).unwrap());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stream_values() {
+        let input = " 1\n\"é\"[true,{\"a\":null}]{}\tnull ";
+        let values = json_stream(input.as_bytes())
+            .map(Result::unwrap)
+            .map(|spanned| (spanned.value, spanned.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                (JsonValue::from(1.0), Span::new(1, 2)),
+                (JsonValue::Text("é".into()), Span::new(3, 7)),
+                (
+                    json_parse(r#"[true, { "a": null }]"#).unwrap(),
+                    Span::new(7, 24)
+                ),
+                (json_parse("{}").unwrap(), Span::new(24, 26)),
+                (JsonValue::Null, Span::new(27, 31)),
+            ]
+        );
+
+        let mut errors = json_stream("[1] \"é\" [1 2]".as_bytes());
+        assert!(errors.nth(1).unwrap().is_ok());
+        assert!(matches!(
+            errors.next(),
+            Some(Err(StreamError::Parse(JsonError::UnexpectedToken {
+                character: '2',
+                location: 11,
+                ..
+            })))
+        ));
+        assert!(errors.next().is_none());
+
+        let mut truncated = json_stream(&b"{} {\"a\""[..]).skip(1);
+        assert!(matches!(
+            truncated.next(),
+            Some(Err(StreamError::Parse(JsonError::UnexpectedEOF { .. })))
+        ));
+        assert!(matches!(
+            json_stream(&b"\"\xff\""[..]).next(),
+            Some(Err(StreamError::InvalidUtf8 { offset: 0 }))
+        ));
+    }
+
+    #[test]
+    fn parse_reader() {
+        let sources = [
+            " [1, {\"é\": null}] \n",
+            "\"text\"",
+            "12",
+            "",
+            "  ",
+            "[1, 2",
+            "{} {}",
+            "[1, x]",
+            "1 2",
+            "tru",
+        ];
+        for source in sources.iter() {
+            // one byte at a time, so every value spans many reads
+            let reader = io::BufReader::with_capacity(1, source.as_bytes());
+            let parsed = match json_parse_reader(reader) {
+                Ok(value) => Ok(value),
+                Err(StreamError::Parse(error)) => Err(error),
+                Err(error) => panic!("{:?}", error),
+            };
+            assert_eq!(parsed, json_parse(source), "{:?}", source);
+        }
+        let error = json_parse_reader(&b"[\"\xff\"]"[..]).unwrap_err();
+        assert!(matches!(error, StreamError::InvalidUtf8 { offset: 0 }));
+        assert_eq!(
+            error.to_string(),
+            "value at byte offset 0 is not valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn array_stream() {
+        let elements = parse_array_stream(" [ 1 , \"]\" ,[[]],{\"a\":[2]} ]\n".as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            JsonValue::Array(elements),
+            json_parse(r#"[1, "]", [[]], { "a": [2] }]"#).unwrap()
+        );
+        assert_eq!(parse_array_stream(&b"[]"[..]).unwrap().count(), 0);
+
+        let errors = vec![
+            (
+                "[1 2]",
+                JsonError::UnexpectedToken {
+                    character: '2',
+                    location: 3,
+                    line: 0,
+                    column: 3,
+                    offset: 3,
+                },
+            ),
+            (
+                "[1,]",
+                JsonError::UnexpectedToken {
+                    character: ']',
+                    location: 3,
+                    line: 0,
+                    column: 3,
+                    offset: 3,
+                },
+            ),
+            (
+                "[,1]",
+                JsonError::UnexpectedToken {
+                    character: ',',
+                    location: 1,
+                    line: 0,
+                    column: 1,
+                    offset: 1,
+                },
+            ),
+            (
+                "[1] x",
+                JsonError::UnexpectedToken {
+                    character: 'x',
+                    location: 4,
+                    line: 0,
+                    column: 4,
+                    offset: 4,
+                },
+            ),
+            (
+                "[1, [2",
+                JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 6,
+                    offset: 6,
+                },
+            ),
+            (
+                "[1",
+                JsonError::UnexpectedEOF {
+                    line: 0,
+                    column: 2,
+                    offset: 2,
+                },
+            ),
+        ];
+        for (input, expected) in errors.into_iter() {
+            let mut stream = parse_array_stream(input.as_bytes()).unwrap();
+            let error = stream.find_map(Result::err);
+            match error {
+                Some(StreamError::Parse(error)) => assert_eq!(error, expected, "{}", input),
+                other => panic!("{}: {:?}", input, other),
+            }
+            assert!(stream.next().is_none());
+        }
+        assert!(matches!(
+            parse_array_stream(&b" {}"[..]),
+            Err(StreamError::Parse(JsonError::UnexpectedToken {
+                character: '{',
+                location: 1,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn array_split() {
+        let input = r#"[ "aaaa", "bb", { "c" : [1] }, 2 ]"#;
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let count = split_array(input.as_bytes(), SplitLimit::Bytes(13), |ind| {
+            assert_eq!(ind, chunks.borrow().len());
+            chunks.borrow_mut().push(String::new());
+            Ok(Chunk(chunks.clone()))
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+        let chunks = chunks.borrow().clone();
+        assert_eq!(
+            chunks,
+            vec![r#"["aaaa","bb"]"#, r#"[{ "c" : [1] }]"#, "[2]"]
+        );
+
+        let mut written = 0;
+        let empty = split_array(&b" [ ] "[..], SplitLimit::Elements(1), |_| {
+            written += 1;
+            Ok(Vec::new())
+        });
+        assert_eq!(empty.unwrap(), 0);
+        assert_eq!(written, 0);
+
+        let invalid = split_array(&b"[1, {\"a\" 1}]"[..], SplitLimit::Elements(1), |_| {
+            Ok(Vec::new())
+        });
+        assert!(matches!(
+            invalid,
+            Err(StreamError::Parse(JsonError::UnexpectedToken {
+                character: '1',
+                location: 9,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,300 @@
+//! The string type of `JsonValue` text and object keys.
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::str;
+
+const INLINE_CAPACITY: usize = 15;
+
+#[derive(Clone)]
+enum Repr {
+    /// Up to `INLINE_CAPACITY` bytes of UTF-8, which fit beside `String`'s capacity niche
+    Inline(u8, [u8; INLINE_CAPACITY]),
+    Heap(String),
+}
+
+/// A string that keeps short text inline instead of on the heap.
+///
+/// Most object keys and many string values are only a few bytes long, so any string of up to
+/// [`INLINE_CAPACITY`](#associatedconstant.INLINE_CAPACITY) bytes is stored in the value itself,
+/// which is no bigger than a `String`, and costs no allocation.  It dereferences to `str`, and as
+/// a map key it can be looked up by `&str`.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::JsonString;
+///
+/// fn main() {
+///     let mut text = JsonString::from("short");
+///     assert!(text.is_inline());
+///     assert_eq!(text, "short");
+///
+///     text.push_str(", and then much longer");
+///     assert!(!text.is_inline());
+///     assert_eq!(text.len(), 27);
+///     assert_eq!(String::from(text), "short, and then much longer");
+/// }
+/// ```
+#[derive(Clone)]
+pub struct JsonString(Repr);
+
+impl JsonString {
+    /// The longest string, in bytes, that is stored without allocating.
+    pub const INLINE_CAPACITY: usize = INLINE_CAPACITY;
+
+    /// Creates an empty string.
+    pub fn new() -> Self {
+        JsonString(Repr::Inline(0, [0; INLINE_CAPACITY]))
+    }
+
+    /// The string as a `str`.
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            // only ever filled from whole `str`s, so the bytes are valid UTF-8
+            Repr::Inline(len, bytes) => unsafe {
+                str::from_utf8_unchecked(&bytes[..*len as usize])
+            },
+            Repr::Heap(string) => string,
+        }
+    }
+
+    /// The bytes the string has allocated on the heap, which is zero while it is inline.
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.0 {
+            Repr::Inline(..) => 0,
+            Repr::Heap(string) => string.capacity(),
+        }
+    }
+
+    /// Whether the string is stored inline rather than on the heap.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline(..))
+    }
+
+    /// Appends `text`, moving the string to the heap if it no longer fits inline.
+    pub fn push_str(&mut self, text: &str) {
+        match &mut self.0 {
+            Repr::Inline(len, bytes) if *len as usize + text.len() <= INLINE_CAPACITY => {
+                let start = *len as usize;
+                bytes[start..start + text.len()].copy_from_slice(text.as_bytes());
+                *len += text.len() as u8;
+            }
+            Repr::Inline(..) => {
+                let mut string = String::with_capacity(self.len() + text.len());
+                string.push_str(self.as_str());
+                string.push_str(text);
+                self.0 = Repr::Heap(string);
+            }
+            Repr::Heap(string) => string.push_str(text),
+        }
+    }
+
+    /// Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Converts into a `String`, which allocates if the string was inline.
+    pub fn into_string(self) -> String {
+        match self.0 {
+            Repr::Inline(..) => self.as_str().to_string(),
+            Repr::Heap(string) => string,
+        }
+    }
+}
+
+impl Default for JsonString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for JsonString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for JsonString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for JsonString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> From<&'a str> for JsonString {
+    fn from(text: &'a str) -> Self {
+        let mut string = JsonString::new();
+        string.push_str(text);
+        string
+    }
+}
+
+impl From<String> for JsonString {
+    /// Moves short strings inline, freeing their buffer, and keeps longer ones as they are.
+    fn from(text: String) -> Self {
+        if text.len() <= INLINE_CAPACITY {
+            JsonString::from(text.as_str())
+        } else {
+            JsonString(Repr::Heap(text))
+        }
+    }
+}
+
+impl From<char> for JsonString {
+    fn from(c: char) -> Self {
+        JsonString::from(&*c.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl From<JsonString> for String {
+    fn from(text: JsonString) -> Self {
+        text.into_string()
+    }
+}
+
+impl FromIterator<char> for JsonString {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut string = JsonString::new();
+        iter.into_iter().for_each(|c| string.push(c));
+        string
+    }
+}
+
+impl<'a> FromIterator<&'a str> for JsonString {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut string = JsonString::new();
+        iter.into_iter().for_each(|text| string.push_str(text));
+        string
+    }
+}
+
+impl fmt::Debug for JsonString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for JsonString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for JsonString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for JsonString {}
+
+impl PartialOrd for JsonString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsonString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for JsonString {
+    /// Hashes like the `str` it holds, so that maps keyed by `JsonString` can be searched by
+    /// `&str`.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for JsonString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for JsonString {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for JsonString {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<JsonString> for str {
+    fn eq(&self, other: &JsonString) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<JsonString> for &str {
+    fn eq(&self, other: &JsonString) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<JsonString> for String {
+    fn eq(&self, other: &JsonString) -> bool {
+        self == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonString;
+    use std::collections::HashMap;
+    use std::mem::size_of;
+
+    #[test]
+    fn string_inline() {
+        assert_eq!(size_of::<JsonString>(), size_of::<String>());
+
+        let fits = "012345678901234";
+        assert!(JsonString::from(fits).is_inline());
+        assert!(JsonString::from(fits.to_string()).is_inline());
+        assert!(!JsonString::from("0123456789012345").is_inline());
+
+        let mut text = JsonString::new();
+        for c in "ώώώώώώώ".chars() {
+            text.push(c);
+        }
+        assert!(text.is_inline());
+        text.push('ώ');
+        assert!(!text.is_inline());
+        assert_eq!(text, "ώώώώώώώώ");
+        assert_eq!(text.chars().rev().collect::<JsonString>(), "ώώώώώώώώ");
+    }
+
+    #[test]
+    fn string_keys() {
+        let mut map = HashMap::new();
+        map.insert(JsonString::from("short"), 1);
+        map.insert(JsonString::from("a key too long to be inline"), 2);
+        assert_eq!(map.get("short"), Some(&1));
+        assert_eq!(map.get("a key too long to be inline"), Some(&2));
+        let mut keys = map.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["a key too long to be inline", "short"]);
+        assert_eq!(
+            format!("{:?} {}", JsonString::from("a\n"), JsonString::from('b')),
+            "\"a\\n\" b"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Sharing object keys between documents through a reusable symbol table.
+use super::events::{borrowed_text, EventParser, Step};
+use super::lexer::Punct;
+use super::{JsonError, JsonNumber, JsonString, JsonValue};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+use std::sync::Arc;
+
+/// A shared object key.  Every copy of the same key from the same table points to one string.
+pub type Symbol = Arc<str>;
+
+/// How often a [`SymbolTable`](struct.SymbolTable.html) found a key it already had.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SymbolStats {
+    /// The number of distinct keys in the table
+    pub symbols: usize,
+    /// Lookups that found the key already in the table
+    pub hits: u64,
+    /// Lookups that added a new key
+    pub misses: u64,
+}
+
+impl SymbolStats {
+    /// The share of lookups that were hits, from 0 to 1, or 0 if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A set of object keys to be reused across many documents parsed over time, so that each
+/// distinct key is stored once however many documents and objects it appears in.
+///
+/// Pass the same table to every call of
+/// [`json_parse_with_symbols`](fn.json_parse_with_symbols.html).  Keys stay in the table until
+/// it is cleared, or until [`retain_used`](#method.retain_used) finds that no value holds them
+/// anymore.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashSet<Symbol>,
+    hits: u64,
+    misses: u64,
+}
+
+impl SymbolTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of distinct keys in the table.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Whether the table has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Gets the shared copy of `key`, adding it if the table does not have it yet.
+    pub fn intern(&mut self, key: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(key) {
+            self.hits += 1;
+            return symbol.clone();
+        }
+        self.misses += 1;
+        let symbol = Symbol::from(key);
+        self.symbols.insert(symbol.clone());
+        symbol
+    }
+
+    /// Gets the shared copy of `key` if the table has it, without counting a hit or a miss.
+    pub fn get(&self, key: &str) -> Option<Symbol> {
+        self.symbols.get(key).cloned()
+    }
+
+    /// The table's size and hit counts so far.
+    pub fn stats(&self) -> SymbolStats {
+        SymbolStats {
+            symbols: self.symbols.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    /// Sets the hit and miss counts back to zero, keeping every key.
+    pub fn reset_stats(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    /// Drops every key that only the table still holds, returning how many were dropped.
+    pub fn retain_used(&mut self) -> usize {
+        let len = self.symbols.len();
+        self.symbols.retain(|symbol| Arc::strong_count(symbol) > 1);
+        len - self.symbols.len()
+    }
+
+    /// Drops every key and sets the counts back to zero.
+    pub fn clear(&mut self) {
+        self.symbols.clear();
+        self.reset_stats();
+    }
+}
+
+/// A JSON value whose object keys are symbols from a
+/// [`SymbolTable`](struct.SymbolTable.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolValue {
+    /// A JSON string value.
+    Text(String),
+    /// A numeric JSON value.
+    Number(JsonNumber),
+    /// A JSON boolean value.
+    Boolean(bool),
+    /// The JSON null value.
+    Null,
+    /// A JSON array.
+    Array(Vec<SymbolValue>),
+    /// A JSON object.
+    Object(HashMap<Symbol, SymbolValue>),
+}
+
+impl SymbolValue {
+    /// Gets a reference to the JSON value at a specific key, as with
+    /// [`JsonValue::get`](enum.JsonValue.html#method.get).
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            SymbolValue::Object(map) => map.get(key),
+            SymbolValue::Array(array) => key.parse().ok().and_then(|i: usize| array.get(i)),
+            _ => None,
+        }
+    }
+
+    /// Copies the value into a plain `JsonValue`.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            SymbolValue::Text(text) => JsonValue::Text(text.as_str().into()),
+            SymbolValue::Number(n) => JsonValue::Number(*n),
+            SymbolValue::Boolean(b) => JsonValue::Boolean(*b),
+            SymbolValue::Null => JsonValue::Null,
+            SymbolValue::Array(array) => {
+                JsonValue::Array(array.iter().map(SymbolValue::to_json).collect())
+            }
+            SymbolValue::Object(map) => JsonValue::Object(Box::new(
+                map.iter()
+                    .map(|(key, value)| (JsonString::from(&**key), value.to_json()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+enum Partial {
+    Array(Vec<SymbolValue>),
+    Object(HashMap<Symbol, SymbolValue>, Option<Symbol>),
+}
+
+/// Deserializes a JSON string, taking its object keys from `symbols`.
+///
+/// A key the table already has costs no allocation at all, which for streams of similar
+/// documents such as log lines or queue messages is nearly every key.  This accepts exactly the
+/// documents [`json_parse`](fn.json_parse.html) does, with errors reported in source order as in
+/// [`json_parse_spanned`](fn.json_parse_spanned.html).
+/// ```
+/// extern crate json_rs;
+/// use json_rs::symbols::{json_parse_with_symbols, SymbolTable};
+/// use std::sync::Arc;
+///
+/// fn main() {
+///     let mut symbols = SymbolTable::new();
+///     let lines = vec![
+///         r#"{ "level": "info", "msg": "started" }"#,
+///         r#"{ "level": "warn", "msg": "slow" }"#,
+///     ];
+///     let logs = lines
+///         .into_iter()
+///         .map(|line| json_parse_with_symbols(line, &mut symbols).unwrap())
+///         .collect::<Vec<_>>();
+///
+///     let stats = symbols.stats();
+///     assert_eq!((stats.symbols, stats.hits, stats.misses), (2, 2, 2));
+///     assert_eq!(stats.hit_rate(), 0.5);
+///
+///     let level = symbols.get("level").unwrap();
+///     assert_eq!(Arc::strong_count(&level), 4);
+///     drop(logs);
+///     assert_eq!(symbols.retain_used(), 1);
+/// }
+/// ```
+pub fn json_parse_with_symbols(
+    json_str: &str,
+    symbols: &mut SymbolTable,
+) -> Result<SymbolValue, JsonError> {
+    let mut parser = EventParser::borrowing(json_str);
+    let mut partials = vec![];
+    let mut root = None;
+
+    while let Some(mut step) = parser.step()? {
+        let complete = match step {
+            Step::Punct(Punct::ArrayStart, _) => {
+                partials.push(Partial::Array(vec![]));
+                continue;
+            }
+            Step::Punct(Punct::ObjectStart, _) => {
+                partials.push(Partial::Object(HashMap::new(), None));
+                continue;
+            }
+            Step::Key(key, span) => {
+                let key = symbols.intern(&borrowed_text(json_str, key, span));
+                if let Some(Partial::Object(_, pending)) = partials.last_mut() {
+                    *pending = Some(key);
+                }
+                continue;
+            }
+            Step::Value(JsonValue::Text(ref mut decoded), span) => SymbolValue::Text(
+                borrowed_text(json_str, mem::take(decoded).into(), span).into_owned(),
+            ),
+            Step::Value(JsonValue::Number(n), _) => SymbolValue::Number(n),
+            Step::Value(JsonValue::Boolean(b), _) => SymbolValue::Boolean(b),
+            Step::Value(_, _) => SymbolValue::Null,
+            Step::Punct(Punct::ArrayEnd, _) | Step::Punct(Punct::ObjectEnd, _) => {
+                match partials.pop() {
+                    Some(Partial::Array(items)) => SymbolValue::Array(items),
+                    Some(Partial::Object(map, _)) => SymbolValue::Object(map),
+                    None => unreachable!(),
+                }
+            }
+            Step::Punct(_, _) | Step::Whitespace(_) | Step::Comment(_) => continue,
+        };
+
+        match partials.last_mut() {
+            Some(Partial::Array(items)) => items.push(complete),
+            Some(Partial::Object(map, key)) => {
+                map.insert(key.take().unwrap(), complete);
+            }
+            None => root = Some(complete),
+        }
+    }
+    root.ok_or_else(|| JsonError::eof(json_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_parse_with_symbols, SymbolTable, SymbolValue};
+    use crate::{json_parse, JsonNumber};
+    use std::sync::Arc;
+
+    #[test]
+    fn symbols_match_json_parse() {
+        use std::fs::{read, read_dir};
+
+        let mut symbols = SymbolTable::new();
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            assert_eq!(
+                json_parse_with_symbols(&source, &mut symbols).map(|json| json.to_json()),
+                json_parse(&source)
+            );
+        }
+    }
+
+    #[test]
+    fn symbols_shared() {
+        let mut symbols = SymbolTable::new();
+        let first = json_parse_with_symbols(r#"[{ "id": 1 }, { "id": 2 }]"#, &mut symbols).unwrap();
+        let second = json_parse_with_symbols(r#"{ "id": 3, "tags": [] }"#, &mut symbols).unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.stats().hits, 2);
+
+        let id = symbols.get("id").unwrap();
+        assert_eq!(Arc::strong_count(&id), 5);
+        drop(second);
+        assert_eq!(symbols.retain_used(), 1);
+        assert_eq!(symbols.get("tags"), None);
+        assert_eq!(
+            first.get("1").and_then(|item| item.get("id")),
+            Some(&SymbolValue::Number(JsonNumber::U64(2)))
+        );
+
+        symbols.reset_stats();
+        assert_eq!(symbols.stats().hit_rate(), 0.0);
+        symbols.clear();
+        assert!(symbols.is_empty());
+    }
+}
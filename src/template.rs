@@ -0,0 +1,340 @@
+//! Placeholder substitution inside JSON values, for configuration and deployment templates.
+use super::{JsonMap, JsonPointer, JsonValue};
+use std::collections::HashMap;
+use std::{env, error, fmt};
+
+/// What to do with a placeholder whose variable is not set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MissingVariables {
+    /// Fail with [`TemplateError::Missing`](enum.TemplateError.html#variant.Missing)
+    #[default]
+    Strict,
+    /// Leave the placeholder as it was written
+    Lenient,
+}
+
+/// Describes the ways expanding a template can fail
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A placeholder names a variable that is not set and has no default
+    Missing {
+        /// The string containing the placeholder
+        at: JsonPointer,
+        /// The variable, as written in the placeholder
+        variable: String,
+    },
+    /// A string has a `${` without a `}` after it
+    Unclosed {
+        /// The string containing the placeholder
+        at: JsonPointer,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Missing { at, variable } => write!(
+                f,
+                "variable {:?} in the string at {:?} is not set",
+                variable,
+                at.to_string()
+            ),
+            TemplateError::Unclosed { at } => write!(
+                f,
+                "placeholder in the string at {:?} is not closed",
+                at.to_string()
+            ),
+        }
+    }
+}
+
+impl error::Error for TemplateError {}
+
+/// The variables placeholders are expanded from.
+///
+/// Placeholders are written `${name}` inside strings.  `name` is either `env:VAR`, for an
+/// environment variable, or a variable followed by the keys or indices to walk into it, separated
+/// by dots, such as `params.regions.0`.  `${name:-text}` falls back to `text` when the variable is
+/// not set, and `$${` writes a literal `${`.
+///
+/// A string that is nothing but one placeholder is replaced by the variable's value, whatever its
+/// type, so `"${params.port}"` can become the number `8080`.  Elsewhere in a string, strings are
+/// inserted as they are and other values as JSON.  Object keys are left alone.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::template::TemplateContext;
+///
+/// fn main() {
+///     let template = json_parse(r#"{
+///         "bucket": "logs-${params.region}",
+///         "port": "${params.port}",
+///         "home": "${env:HOME}",
+///         "replicas": "${params.replicas:-1}"
+///     }"#)
+///     .unwrap();
+///
+///     let context = TemplateContext::new()
+///         .var("params", json_parse(r#"{ "region": "eu-west-1", "port": 8080 }"#).unwrap())
+///         .env_vars(vec![("HOME".into(), "/home/ada".into())]);
+///
+///     assert_eq!(
+///         context.expand(&template),
+///         Ok(json_parse(r#"{
+///             "bucket": "logs-eu-west-1",
+///             "port": 8080,
+///             "home": "/home/ada",
+///             "replicas": "1"
+///         }"#)
+///         .unwrap())
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TemplateContext {
+    vars: HashMap<String, JsonValue>,
+    env: Option<HashMap<String, String>>,
+    missing: MissingVariables,
+}
+
+/// A piece of a template string.
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder {
+        written: &'a str,
+        name: &'a str,
+        default: Option<&'a str>,
+    },
+}
+
+impl TemplateContext {
+    /// Creates a context with no variables, reading `env:` placeholders from the process
+    /// environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a variable that placeholders can name.
+    pub fn var<S: Into<String>>(mut self, name: S, value: JsonValue) -> Self {
+        self.vars.insert(name.into(), value);
+        self
+    }
+
+    /// Reads `env:` placeholders from `vars` instead of the process environment.
+    pub fn env_vars<I>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.env = Some(vars.into_iter().collect());
+        self
+    }
+
+    /// Sets what happens to placeholders whose variable is not set.
+    pub fn missing(mut self, policy: MissingVariables) -> Self {
+        self.missing = policy;
+        self
+    }
+
+    /// Expands every placeholder in `template`.
+    pub fn expand(&self, template: &JsonValue) -> Result<JsonValue, TemplateError> {
+        self.expand_at(template, &JsonPointer::root())
+    }
+
+    fn expand_at(
+        &self,
+        template: &JsonValue,
+        at: &JsonPointer,
+    ) -> Result<JsonValue, TemplateError> {
+        Ok(match template {
+            JsonValue::Text(text) => self.expand_str(text, at)?,
+            JsonValue::Array(array) => {
+                let mut expanded = Vec::with_capacity(array.len());
+                for (ind, item) in array.iter().enumerate() {
+                    expanded.push(self.expand_at(item, &at.child(ind.to_string()))?);
+                }
+                JsonValue::Array(expanded)
+            }
+            JsonValue::Object(map) => {
+                let mut expanded = JsonMap::with_capacity(map.len());
+                for (key, value) in map.iter() {
+                    expanded.insert(key.clone(), self.expand_at(value, &at.child(key.as_str()))?);
+                }
+                JsonValue::Object(Box::new(expanded))
+            }
+            scalar => scalar.clone(),
+        })
+    }
+
+    fn expand_str(&self, text: &str, at: &JsonPointer) -> Result<JsonValue, TemplateError> {
+        let parts = split(text).ok_or_else(|| TemplateError::Unclosed { at: at.clone() })?;
+
+        if let [Part::Placeholder {
+            written,
+            name,
+            default,
+        }] = parts[..]
+        {
+            return match (self.lookup(name), default) {
+                (Some(value), _) => Ok(value),
+                (None, Some(default)) => Ok(JsonValue::Text(default.into())),
+                (None, None) => self
+                    .unset(written, name, at)
+                    .map(|text| JsonValue::Text(text.into())),
+            };
+        }
+
+        let mut expanded = String::with_capacity(text.len());
+        for part in parts.into_iter() {
+            match part {
+                Part::Text(text) => expanded.push_str(text),
+                Part::Placeholder {
+                    written,
+                    name,
+                    default,
+                } => match (self.lookup(name), default) {
+                    (Some(JsonValue::Text(ref value)), _) => expanded.push_str(value),
+                    (Some(value), _) => expanded.push_str(&value.to_string()),
+                    (None, Some(default)) => expanded.push_str(default),
+                    (None, None) => expanded.push_str(&self.unset(written, name, at)?),
+                },
+            }
+        }
+        Ok(JsonValue::Text(expanded.into()))
+    }
+
+    /// Resolves a placeholder name, without the `${` and `}` around it.
+    fn lookup(&self, name: &str) -> Option<JsonValue> {
+        if let Some(var) = name.strip_prefix("env:") {
+            let value = match &self.env {
+                Some(vars) => vars.get(var).cloned(),
+                None => env::var(var).ok(),
+            };
+            return value.map(|value| JsonValue::Text(value.into()));
+        }
+
+        let mut path = name.split('.');
+        let root = self.vars.get(path.next()?)?;
+        path.try_fold(root, |value, key| value.get(key)).cloned()
+    }
+
+    /// What an unset placeholder expands to under the missing variable policy.
+    fn unset(&self, written: &str, name: &str, at: &JsonPointer) -> Result<String, TemplateError> {
+        match self.missing {
+            MissingVariables::Strict => Err(TemplateError::Missing {
+                at: at.clone(),
+                variable: name.into(),
+            }),
+            MissingVariables::Lenient => Ok(written.into()),
+        }
+    }
+}
+
+/// Splits a string into text and placeholders, or returns `None` if a placeholder is unclosed.
+fn split(text: &str) -> Option<Vec<Part<'_>>> {
+    let mut parts = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            parts.push(Part::Text(&rest[..start]));
+            parts.push(Part::Text("{"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let end = start + rest[start..].find('}')?;
+        let inner = &rest[start + 2..end];
+        let (name, default) = match inner.find(":-") {
+            Some(ind) => (&inner[..ind], Some(&inner[ind + 2..])),
+            None => (inner, None),
+        };
+
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        parts.push(Part::Placeholder {
+            written: &rest[start..=end],
+            name,
+            default,
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Some(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MissingVariables, TemplateContext, TemplateError};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn template_expand() {
+        let context = TemplateContext::new()
+            .var(
+                "params",
+                json_parse(r#"{ "zones": ["a", "b"], "tags": { "team": "core" }, "on": true }"#)
+                    .unwrap(),
+            )
+            .var("count", JsonValue::from(3.0))
+            .env_vars(vec![("USER".into(), "ada".into())]);
+
+        let tests = vec![
+            (r#""${params.zones}""#, r#"["a", "b"]"#),
+            (r#""${params.zones.1}""#, r#""b""#),
+            (r#""${count}""#, "3"),
+            (r#""x${count}x""#, r#""x3x""#),
+            (r#""${params.tags}!""#, r#""{\"team\":\"core\"}!""#),
+            (r#""${params.on}-${env:USER}""#, r#""true-ada""#),
+            (r#""${params.nope:-}""#, r#""""#),
+            (r#""${env:NOPE:-a:-b}""#, r#""a:-b""#),
+            (r#""$${count} ${count}""#, r#""${count} 3""#),
+            (r#""$$""#, r#""$$""#),
+            (r#""plain""#, r#""plain""#),
+            (
+                r#"[1, null, { "${count}": "${count}" }]"#,
+                r#"[1, null, { "${count}": 3 }]"#,
+            ),
+        ];
+        for (template, expanded) in tests.into_iter() {
+            assert_eq!(
+                context.expand(&json_parse(template).unwrap()),
+                Ok(json_parse(expanded).unwrap()),
+                "{}",
+                template
+            );
+        }
+
+        let template =
+            json_parse(r#"{ "a": [0, "${params.zones.2}"], "b": "x${unset}" }"#).unwrap();
+        let error = context.expand(&template).unwrap_err();
+        assert!(matches!(
+            &error,
+            TemplateError::Missing { at, variable }
+                if (at.to_string() == "/a/1" && variable == "params.zones.2")
+                    || (at.to_string() == "/b" && variable == "unset")
+        ));
+        assert_eq!(
+            context
+                .clone()
+                .missing(MissingVariables::Lenient)
+                .expand(&template),
+            Ok(template)
+        );
+
+        assert_eq!(
+            context.expand(&json_parse(r#"["ok", "${count"]"#).unwrap()),
+            Err(TemplateError::Unclosed {
+                at: "/1".parse().unwrap()
+            })
+        );
+        assert_eq!(
+            TemplateError::Unclosed {
+                at: "/1".parse().unwrap()
+            }
+            .to_string(),
+            r#"placeholder in the string at "/1" is not closed"#
+        );
+    }
+}
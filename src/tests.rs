@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use super::JsonMap;
 use std::ffi::OsString;
 use std::mem::drop;
 
@@ -26,196 +26,206 @@ fn str_escape() {
 
 #[test]
 fn parse() {
-    use super::{json_parse, JsonError, JsonValue};
+    use super::{json_parse, json_parse_borrowed, JsonError, JsonValue};
     use std::fs::{read, read_dir};
 
     let results = vec![
         Ok(JsonValue::Object({
-            let mut map = HashMap::new();
+            let mut map = JsonMap::new();
             map.insert(
-                "thing".to_string(),
+                "thing".into(),
                 JsonValue::Array(vec![
-                    JsonValue::Number(10f64),
-                    JsonValue::Number(20f64),
-                    JsonValue::Number(230e20),
+                    JsonValue::from(10f64),
+                    JsonValue::from(20f64),
+                    JsonValue::from(230e20),
                 ]),
             );
-            map.insert("mmmmm".to_string(), JsonValue::Object(HashMap::new()));
-            map.insert("__1ew".to_string(), JsonValue::Text(",, []".to_string()));
-            map
+            map.insert("mmmmm".into(), JsonValue::Object(Box::default()));
+            map.insert("__1ew".into(), JsonValue::Text(",, []".into()));
+            Box::new(map)
         })),
-        Err(JsonError::UnexpectedEOF),
+        Err(JsonError::UnexpectedEOF {
+            line: 0,
+            column: 28,
+            offset: 28,
+        }),
         Ok(JsonValue::Array(vec![
-            JsonValue::Number(10.0),
-            JsonValue::Text(", \" 2{]0".to_string()),
-            JsonValue::Number(30.0),
-            JsonValue::Object({
-                let mut map = HashMap::new();
-                map.insert("f".to_string(), JsonValue::Boolean(false));
-                map.insert("t".to_string(), JsonValue::Boolean(true));
+            JsonValue::from(10.0),
+            JsonValue::Text(", \" 2{]0".into()),
+            JsonValue::from(30.0),
+            JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("f".into(), JsonValue::Boolean(false));
+                map.insert("t".into(), JsonValue::Boolean(true));
                 map
-            }),
-            JsonValue::Object({
-                let mut map = HashMap::new();
-                map.insert("e}".to_string(), JsonValue::Number(2.0));
+            })),
+            JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("e}".into(), JsonValue::from(2.0));
                 map.insert(
-                    "v".to_string(),
+                    "v".into(),
                     JsonValue::Array(vec![JsonValue::Null, JsonValue::Array(vec![])]),
                 );
                 map
-            }),
+            })),
         ])),
         Err(JsonError::UnexpectedToken {
             character: 'I',
             location: 20,
+            line: 0,
+            column: 20,
+            offset: 20,
         }),
-        Ok(JsonValue::Object({
-            let mut map = HashMap::new();
-            map.insert("jss".to_string(), JsonValue::Number(-0.30e20));
-            map.insert(
-                "faa".to_string(),
-                JsonValue::Text("\t\u{8}  \u{c} oώo".to_string()),
-            );
+        Ok(JsonValue::Object(Box::new({
+            let mut map = JsonMap::new();
+            map.insert("jss".into(), JsonValue::from(-0.30e20));
+            map.insert("faa".into(), JsonValue::Text("\t\u{8}  \u{c} oώo".into()));
             map.insert(
-                "m,".to_string(),
+                "m,".into(),
                 JsonValue::Array(vec![JsonValue::Null, JsonValue::Boolean(true)]),
             );
             map
-        })),
+        }))),
         Ok(JsonValue::Array(vec![
             JsonValue::Object({
-                let mut map = HashMap::new();
-                map.insert("title".to_string(), JsonValue::Text("EEEEE".to_string()));
-                map.insert(
-                    "author".to_string(),
-                    JsonValue::Text("Richard V.".to_string()),
-                );
+                let mut map = JsonMap::new();
+                map.insert("title".into(), JsonValue::Text("EEEEE".into()));
+                map.insert("author".into(), JsonValue::Text("Richard V.".into()));
                 map.insert(
-                    "ratings".to_string(),
+                    "ratings".into(),
                     JsonValue::Array(vec![
-                        JsonValue::Object({
-                            let mut map = HashMap::new();
-                            map.insert("stars".to_string(), JsonValue::Number(5f64));
+                        JsonValue::Object(Box::new({
+                            let mut map = JsonMap::new();
+                            map.insert("stars".into(), JsonValue::from(5f64));
                             map.insert(
-                                "message".to_string(),
-                                JsonValue::Text("Loved it!".to_string()),
+                                "message".into(),
+                                JsonValue::Text("Loved it!".into()),
                             );
                             map.insert(
-                                "author".to_string(),
-                                JsonValue::Text("rater_1".to_string()),
+                                "author".into(),
+                                JsonValue::Text("rater_1".into()),
                             );
                             map
-                        }),
-                        JsonValue::Object({
-                            let mut map = HashMap::new();
-                            map.insert("stars".to_string(), JsonValue::Number(3.4));
+                        })),
+                        JsonValue::Object(Box::new({
+                            let mut map = JsonMap::new();
+                            map.insert("stars".into(), JsonValue::from(3.4));
                             map.insert(
-                            "message".to_string(),
-                            JsonValue::Text("Eh.  This was an okay book.  The fact that the book only used the capital letter \"E\" felt a bit unoriginal.  Nevertheless, I enjoyed some parts of it.".to_string()),
+                            "message".into(),
+                            JsonValue::Text("Eh.  This was an okay book.  The fact that the book only used the capital letter \"E\" felt a bit unoriginal.  Nevertheless, I enjoyed some parts of it.".into()),
                         );
                             map.insert(
-                                "author".to_string(),
-                                JsonValue::Text("floof_77".to_string()),
+                                "author".into(),
+                                JsonValue::Text("floof_77".into()),
                             );
                             map
-                        }),
+                        })),
                     ]),
                 );
-                map
+                Box::new(map)
             }),
             JsonValue::Object({
-                let mut map = HashMap::new();
+                let mut map = JsonMap::new();
                 map.insert(
-                    "title".to_string(),
+                    "title".into(),
                     JsonValue::Text(
                         "243 Pages of James Complaining About That Dog That Just Won't Shut Up"
-                            .to_string(),
+                            .into(),
                     ),
                 );
+                map.insert("author".into(), JsonValue::Text("James J. Jimmy".into()));
                 map.insert(
-                    "author".to_string(),
-                    JsonValue::Text("James J. Jimmy".to_string()),
-                );
-                map.insert(
-                    "ratings".to_string(),
+                    "ratings".into(),
                     JsonValue::Array(vec![
-                        JsonValue::Object({
-                            let mut map = HashMap::new();
-                            map.insert("stars".to_string(), JsonValue::Number(5f64));
+                        JsonValue::Object(Box::new({
+                            let mut map = JsonMap::new();
+                            map.insert("stars".into(), JsonValue::from(5f64));
                             map.insert(
-                                "message".to_string(),
-                                JsonValue::Text("This book resonated with me on a spiritual level.  5/5, no questions asked.".to_string()),
+                                "message".into(),
+                                JsonValue::Text("This book resonated with me on a spiritual level.  5/5, no questions asked.".into()),
                             );
                             map.insert(
-                                "author".to_string(),
-                                JsonValue::Text("floof_77".to_string()),
+                                "author".into(),
+                                JsonValue::Text("floof_77".into()),
                             );
                             map
-                        }),
-                        JsonValue::Object({
-                            let mut map = HashMap::new();
-                            map.insert("stars".to_string(), JsonValue::Number(0f64));
+                        })),
+                        JsonValue::Object(Box::new({
+                            let mut map = JsonMap::new();
+                            map.insert("stars".into(), JsonValue::from(0f64));
                             map.insert(
-                                "message".to_string(),
+                                "message".into(),
                                 JsonValue::Text(
-                                    "He Was Mean To DOGS That's So Rud de :(:(L(L(".to_string(),
+                                    "He Was Mean To DOGS That's So Rud de :(:(L(L(".into(),
                                 ),
                             );
                             map.insert(
-                                "author".to_string(),
-                                JsonValue::Text("jjjjjj_26".to_string()),
+                                "author".into(),
+                                JsonValue::Text("jjjjjj_26".into()),
                             );
                             map
-                        }),
+                        })),
                     ]),
                 );
-                map
+                Box::new(map)
             }),
         ])),
-        Ok(JsonValue::Object({
-            let mut map = HashMap::new();
+        Ok(JsonValue::Object(Box::new({
+            let mut map = JsonMap::new();
             map.insert(
-                "articles".to_string(),
+                "articles".into(),
                 JsonValue::Array(vec![
-                    JsonValue::Text("a".to_string()),
-                    JsonValue::Text("the".to_string()),
-                    JsonValue::Text("an".to_string()),
+                    JsonValue::Text("a".into()),
+                    JsonValue::Text("the".into()),
+                    JsonValue::Text("an".into()),
                 ]),
             );
             map.insert(
-                "cc".to_string(),
+                "cc".into(),
                 JsonValue::Array(vec![
-                    JsonValue::Text("and".to_string()),
-                    JsonValue::Text("but".to_string()),
-                    JsonValue::Text("for".to_string()),
-                    JsonValue::Text("nor".to_string()),
-                    JsonValue::Text("or".to_string()),
-                    JsonValue::Text("so".to_string()),
-                    JsonValue::Text("yet".to_string()),
+                    JsonValue::Text("and".into()),
+                    JsonValue::Text("but".into()),
+                    JsonValue::Text("for".into()),
+                    JsonValue::Text("nor".into()),
+                    JsonValue::Text("or".into()),
+                    JsonValue::Text("so".into()),
+                    JsonValue::Text("yet".into()),
                 ]),
             );
             map
-        })),
+        }))),
         Err(JsonError::UnexpectedToken {
             character: '[',
             location: 25,
+            line: 2,
+            column: 8,
+            offset: 25,
         }),
         Err(JsonError::UnexpectedToken {
             character: ',',
             location: 19,
+            line: 1,
+            column: 17,
+            offset: 19,
         }),
         Err(JsonError::UnexpectedToken {
             character: '.',
             location: 24,
+            line: 1,
+            column: 23,
+            offset: 24,
         }),
         Err(JsonError::UnexpectedToken {
             character: '0',
             location: 40,
+            line: 3,
+            column: 16,
+            offset: 40,
         }),
         Ok(JsonValue::Object({
-            let mut map = HashMap::new();
+            let mut map = JsonMap::new();
             map.insert(
-                "animals".to_string(),
+                "animals".into(),
                 JsonValue::Array(vec![
                     JsonValue::Text("dog".into()),
                     JsonValue::Text("frog".into()),
@@ -224,34 +234,47 @@ fn parse() {
                 ]),
             );
             map.insert(
-                "members".to_string(),
-                JsonValue::Object({
-                    let mut map = HashMap::new();
-                    map.insert("John".into(), JsonValue::Number(1f64));
-                    map.insert("Ferris".into(), JsonValue::Number(3f64));
+                "members".into(),
+                JsonValue::Object(Box::new({
+                    let mut map = JsonMap::new();
+                    map.insert("John".into(), JsonValue::from(1f64));
+                    map.insert("Ferris".into(), JsonValue::from(3f64));
                     map
-                }),
+                })),
             );
-            map
+            Box::new(map)
         })),
-        Err(JsonError::UnexpectedEOF),
+        Err(JsonError::UnexpectedEOF {
+            line: 0,
+            column: 9,
+            offset: 9,
+        }),
         Err(JsonError::UnexpectedToken {
             character: '\n',
             location: 34,
+            line: 1,
+            column: 25,
+            offset: 34,
         }),
         Err(JsonError::UnexpectedToken {
             character: 'f',
             location: 6,
+            line: 0,
+            column: 6,
+            offset: 6,
         }),
         Ok(JsonValue::Text("as asdlkajd \" \u{c}|\t".into())),
         Ok(JsonValue::Boolean(true)),
         Err(JsonError::UnexpectedToken {
             character: 'e',
             location: 11,
+            line: 0,
+            column: 11,
+            offset: 11,
         }),
     ];
 
-    for file in read_dir("./test-json").unwrap().flat_map(|r| r) {
+    for file in read_dir("./test-json").unwrap().flatten() {
         let index = strip_extension(file.file_name())
             .and_then(|s| s.parse().map_err(drop))
             .map(|i: usize| i - 1)
@@ -261,6 +284,14 @@ fn parse() {
             json_parse(&String::from_utf8(read(file.path()).unwrap()).unwrap())
         )
     }
+    // only U+0000 to U+001F must be escaped, not the other control characters
+    let text = "\u{7f} \u{85} \u{9f}";
+    let source = format!("{{\"{}\": \"{}\"}}", text, text);
+    assert_eq!(json_parse(&source), Ok(json!({ text: text })));
+    assert_eq!(
+        json_parse_borrowed(&source).map(|json| json.to_owned()),
+        json_parse(&source)
+    );
 }
 
 fn strip_extension(s: OsString) -> Result<String, ()> {
@@ -279,25 +310,25 @@ fn stringify() {
     let tests = vec![
         (
             JsonValue::Array(vec![
-                JsonValue::Number(10f64),
-                JsonValue::Number(20f64),
-                JsonValue::Number(30f64),
+                JsonValue::from(10f64),
+                JsonValue::from(20f64),
+                JsonValue::from(30f64),
             ]),
             "[10,20,30]",
         ),
         (
             JsonValue::Object({
-                let mut map = HashMap::new();
+                let mut map = JsonMap::new();
                 map.insert(
                     "aaaa".into(),
                     JsonValue::Array(vec![
                         JsonValue::Null,
-                        JsonValue::Object(HashMap::new()),
+                        JsonValue::Object(Box::default()),
                         JsonValue::Boolean(false),
-                        JsonValue::Array(vec![JsonValue::Array(vec![]), JsonValue::Number(10e10)]),
+                        JsonValue::Array(vec![JsonValue::Array(vec![]), JsonValue::from(10e10)]),
                     ]),
                 );
-                map
+                Box::new(map)
             }),
             r#"{"aaaa":[null,{},false,[[],100000000000]]}"#,
         ),
@@ -308,3 +339,806 @@ fn stringify() {
         assert_eq!(value.to_string(), stringified);
     }
 }
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn stringify_in_order() {
+    use super::{json_parse, JsonValue};
+
+    let source = r#"{"z":1,"a":{"y":[],"b":null,"x":{"c":true}},"m":"text"}"#;
+    let mut json = json_parse(source).unwrap();
+    assert_eq!(json.to_string(), source);
+    assert_eq!(
+        json!({ "z": 1, "a": { "y": [], "b": null, "x": { "c": true } }, "m": "text" }).to_string(),
+        source
+    );
+
+    // a replaced member keeps its place, and a removed one leaves the rest in order
+    let object = json.as_object_mut().unwrap();
+    object.insert("z".into(), JsonValue::Null);
+    object.remove("a");
+    object.insert("a".into(), JsonValue::from(2.0));
+    assert_eq!(json.to_string(), r#"{"z":null,"m":"text","a":2}"#);
+}
+
+#[test]
+fn stringify_pretty() {
+    use std::fmt::Write;
+
+    let json = json!({ "aaaa": [null, {}, { "k": "\u{1}" }, [[], 1.5]] });
+    assert_eq!(
+        format!("{:#}", json),
+        r#"{
+  "aaaa": [
+    null,
+    {},
+    {
+      "k": "\u0001"
+    },
+    [
+      [],
+      1.5
+    ]
+  ]
+}"#
+    );
+
+    let mut out = String::from("value: ");
+    write!(out, "{}", json["aaaa"][2]).unwrap();
+    assert_eq!(out, r#"value: {"k":"\u0001"}"#);
+}
+
+#[test]
+fn total_eq() {
+    use super::{JsonNumber, JsonValue};
+    const NAN: f64 = f64::NAN;
+
+    let tests = vec![
+        (JsonValue::from(NAN), JsonValue::from(-NAN), true),
+        (JsonValue::from(0.0), JsonValue::from(-0.0), false),
+        (JsonValue::from(1.5), JsonValue::from(1.5), true),
+        (JsonValue::Null, JsonValue::Boolean(false), false),
+        (
+            JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("n".into(), JsonValue::from(NAN));
+                map
+            })),
+            JsonValue::Object(Box::new({
+                let mut map = JsonMap::new();
+                map.insert("n".into(), JsonValue::from(NAN));
+                map
+            })),
+            true,
+        ),
+        (
+            JsonValue::Array(vec![JsonValue::from(NAN)]),
+            JsonValue::Array(vec![JsonValue::from(NAN), JsonValue::Null]),
+            false,
+        ),
+    ];
+
+    for (a, b, result) in tests.into_iter() {
+        assert_eq!(a.total_eq(&b), result);
+        assert_eq!(b.total_eq(&a), result);
+        assert!(a.total_eq(&a));
+    }
+
+    let mut normalized = JsonValue::Array(vec![JsonValue::from(-0.0), JsonValue::from(-NAN)]);
+    normalized.normalize();
+    if let Some(JsonValue::Number(JsonNumber::F64(n))) = normalized.get_ind(0) {
+        assert!(n.is_sign_positive());
+    }
+    if let Some(JsonValue::Number(JsonNumber::F64(n))) = normalized.get_ind(1) {
+        assert_eq!(n.to_bits(), NAN.to_bits());
+    }
+}
+
+#[test]
+fn value_size() {
+    use super::JsonValue;
+    use std::mem::size_of;
+
+    // no variant is wider than a `String` or a `Vec`
+    assert_eq!(
+        size_of::<JsonValue>(),
+        size_of::<String>() + size_of::<usize>()
+    );
+    assert_eq!(size_of::<Option<JsonValue>>(), size_of::<JsonValue>());
+}
+
+#[test]
+fn value_deep_size() {
+    use super::{json_parse, JsonValue};
+    use std::mem::size_of;
+
+    assert_eq!(JsonValue::Null.deep_size_of(), 0);
+    assert_eq!(JsonValue::Array(vec![]).deep_size_of(), 0);
+
+    let long = "a string too long to keep inline";
+    assert_eq!(JsonValue::Text(long.into()).deep_size_of(), long.len());
+
+    let mut array = Vec::with_capacity(10);
+    array.push(JsonValue::Text(long.into()));
+    assert_eq!(
+        JsonValue::Array(array).deep_size_of(),
+        10 * size_of::<JsonValue>() + long.len()
+    );
+
+    let object = json_parse(&format!(r#"{{ "{}": [null] }}"#, long)).unwrap();
+    assert!(object.deep_size_of() >= long.len() + 2 * size_of::<JsonValue>());
+}
+
+#[test]
+fn drop_deeply_nested() {
+    use super::JsonValue;
+    use std::thread;
+
+    // nested far deeper than a recursive drop could go on this thread's stack
+    let dropped = thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(|| {
+            let mut json = JsonValue::Null;
+            for level in 0..200_000 {
+                json = if level % 2 == 0 {
+                    JsonValue::Array(vec![JsonValue::from(level as f64), json])
+                } else {
+                    let mut map = JsonMap::new();
+                    map.insert("next".into(), json);
+                    JsonValue::Object(Box::new(map))
+                };
+            }
+            drop(json);
+        })
+        .unwrap()
+        .join();
+    assert!(dropped.is_ok());
+
+    let mut json = JsonValue::Array(vec![JsonValue::Boolean(true)]);
+    assert_eq!(
+        json.take(),
+        JsonValue::Array(vec![JsonValue::Boolean(true)])
+    );
+    assert_eq!(json, JsonValue::Null);
+}
+
+#[test]
+fn json_macro() {
+    use super::{json_parse, JsonValue};
+
+    assert_eq!(json!(null), JsonValue::Null);
+    assert_eq!(json!([]), JsonValue::Array(vec![]));
+    assert_eq!(json!({}), JsonValue::Object(Box::default()));
+    assert_eq!(json!(1.5), JsonValue::from(1.5));
+    assert_eq!(json!("text"), JsonValue::Text("text".into()));
+
+    let nested = json!({ "inner": [1, 2] });
+    let name = "key";
+    let tests = vec![
+        (
+            json!([null, true, false, [], {}]),
+            "[null, true, false, [], {}]",
+        ),
+        (json!([1, -2, 3.5,]), "[1, -2, 3.5]"),
+        (json!([[1], { "a": [] }, "x"]), r#"[[1], { "a": [] }, "x"]"#),
+        (
+            json!({ "a": null, "b": true, "c": false, }),
+            r#"{ "a": null, "b": true, "c": false }"#,
+        ),
+        (
+            json!({ "a": { "b": { "c": [{}] } } }),
+            r#"{ "a": { "b": { "c": [{}] } } }"#,
+        ),
+        (
+            json!({ name: 1, "a": 1, "a": 2 }),
+            r#"{ "key": 1, "a": 2 }"#,
+        ),
+        (
+            json!({ format!("{}{}", name, 2): 2 - 1 }),
+            r#"{ "key2": 1 }"#,
+        ),
+        (
+            json!([nested, Some(vec![1, 2]), None::<u8>]),
+            r#"[{ "inner": [1, 2] }, [1, 2], null]"#,
+        ),
+    ];
+    for (value, expected) in tests.into_iter() {
+        assert_eq!(value, json_parse(expected).unwrap(), "{}", expected);
+    }
+}
+
+#[test]
+fn value_index() {
+    use super::JsonValue;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let json = json!({ "a": [1, { "b": null }], "0": "zero" });
+    assert_eq!(json["a"][1]["b"], JsonValue::Null);
+    assert_eq!(json["a"]["0"], json!(1));
+    assert_eq!(json[0], json!("zero"));
+    assert_eq!(json["a"][2], JsonValue::Null);
+    assert_eq!(json["a"][0]["deeper"][3], JsonValue::Null);
+    assert_eq!(json["missing"], JsonValue::Null);
+
+    let mut json = json;
+    json["a"][1]["b"] = json!(true);
+    json["a"]["0"] = json!(2);
+    json[0] = json!("replaced");
+    json[1]["new"] = json!([]);
+    json["c"]["d"]["e"] = json!(null);
+    assert_eq!(
+        json,
+        json!({
+            "a": [2, { "b": true }],
+            "0": "replaced",
+            "1": { "new": [] },
+            "c": { "d": { "e": null } }
+        })
+    );
+
+    let panics = |f: &dyn Fn(&mut JsonValue)| {
+        let mut json = json!({ "list": [1], "n": 1 });
+        catch_unwind(AssertUnwindSafe(|| f(&mut json))).is_err()
+    };
+    assert!(panics(&|json| json["list"][1] = json!(0)));
+    assert!(panics(&|json| json["list"]["x"] = json!(0)));
+    assert!(panics(&|json| json["n"]["x"] = json!(0)));
+    assert!(panics(&|json| json["n"][0] = json!(0)));
+    assert!(!panics(&|json| json["list"][0] = json!(0)));
+}
+
+#[test]
+fn value_accessors() {
+    use super::{JsonNumber, JsonValue};
+
+    let mut json = json!({ "s": "text", "n": 1.5, "b": false, "a": [null], "o": {} });
+    assert_eq!(json["s"].as_str(), Some("text"));
+    assert_eq!(json["n"].as_f64(), Some(1.5));
+    assert_eq!(json["b"].as_bool(), Some(false));
+    assert_eq!(json["a"].as_array(), Some(&vec![JsonValue::Null]));
+    assert!(json["o"].as_object().unwrap().is_empty());
+    for key in &["s", "n", "b", "a", "o"] {
+        let value = &json[*key];
+        let found = [
+            value.as_str().is_some(),
+            value.as_f64().is_some(),
+            value.as_bool().is_some(),
+            value.as_array().is_some(),
+            value.as_object().is_some(),
+        ];
+        assert_eq!(found.iter().filter(|found| **found).count(), 1, "{}", key);
+        let kinds = [
+            value.is_string(),
+            value.is_number(),
+            value.is_boolean(),
+            value.is_array(),
+            value.is_object(),
+        ];
+        assert_eq!(kinds, found, "{}", key);
+        assert!(!value.is_null());
+    }
+
+    json["s"].as_text_mut().unwrap().push_str("s");
+    *json["n"].as_f64_mut().unwrap() *= 2.0;
+    *json["b"].as_bool_mut().unwrap() = true;
+    json["a"].as_array_mut().unwrap().clear();
+    json["o"]
+        .as_object_mut()
+        .unwrap()
+        .insert("k".into(), json!(1));
+    assert_eq!(
+        json,
+        json!({ "s": "texts", "n": 3, "b": true, "a": [], "o": { "k": 1 } })
+    );
+    assert_eq!(JsonValue::Null.as_str(), None);
+
+    // integers are not turned into floats to be borrowed as one
+    let mut json = json!([-1, 1]);
+    assert_eq!(json[0].as_f64_mut(), None);
+    assert_eq!(json[1].as_i64_mut(), None);
+    *json[0].as_i64_mut().unwrap() -= 1;
+    *json[1].as_u64_mut().unwrap() += 1;
+    assert_eq!(json[0].as_number(), Some(JsonNumber::I64(-2)));
+    assert_eq!(json[1].as_number(), Some(JsonNumber::U64(2)));
+}
+
+#[test]
+fn write_to_io() {
+    use super::FormatConfig;
+    use std::io::{self, Write};
+
+    /// Accepts a few bytes, then fails every write.
+    struct Full(usize);
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+            }
+            let len = buf.len().min(self.0);
+            self.0 -= len;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let json = json!({ "items": vec!["x".repeat(10_000); 4] });
+    let mut out = Vec::new();
+    json.to_writer(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), json.to_string());
+
+    let format = FormatConfig {
+        indent_width: 4,
+        ..FormatConfig::default()
+    };
+    let mut out = Vec::new();
+    json.to_writer_with(&mut out, format).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), json.to_string_with(format));
+
+    let error = json.to_writer_pretty(Full(100)).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn error_positions() {
+    use super::stream::{json_parse_reader, StreamError};
+    use super::{json_parse, json_parse_spanned, JsonError, PushParser};
+
+    let source = "{\n  \"é\": [1,\n  2 3]\n}";
+    let error = JsonError::UnexpectedToken {
+        character: '3',
+        location: 17,
+        line: 2,
+        column: 4,
+        offset: 18,
+    };
+    assert_eq!(json_parse(source), Err(error.clone()));
+    assert_eq!(json_parse_spanned(source).map(|_| ()), Err(error.clone()));
+
+    let mut parser = PushParser::new();
+    for byte in source.bytes() {
+        if let Err(e) = parser.feed(&[byte]) {
+            assert_eq!(e, error);
+        }
+    }
+    match json_parse_reader(source.as_bytes()) {
+        Err(StreamError::Parse(e)) => assert_eq!(e, error),
+        other => panic!("{:?}", other),
+    }
+
+    let eof = JsonError::UnexpectedEOF {
+        line: 1,
+        column: 3,
+        offset: 6,
+    };
+    assert_eq!(json_parse("[\n\"é\""), Err(eof.clone()));
+    let mut parser = PushParser::new();
+    parser.feed_str("[\n\"é\"").unwrap();
+    assert_eq!(parser.finish(), Err(eof));
+}
+
+#[test]
+fn parse_comments() {
+    use super::document::json_parse_document;
+    use super::{json_parse, json_parse_with, JsonError, ParseOptions};
+
+    let sources = [
+        "// leading\n[1, /* one */ 2]",
+        "{ \"a\": 1 /* after a number */, \"b\" /* before a colon */: [true//\n] }",
+        "{ \"url\": \"http://x/*y*/\", \"//\": \"/* kept */\" }",
+        "/**/0/***/",
+        "[\"a\" /* \"quoted\" ] */, // \"also\" ]\n \"b\"]",
+        "{ \"nested\": [[/* */], { /* \n */ }] } // trailing",
+    ];
+    for source in sources.iter() {
+        let expected = json_parse_document(source, ParseOptions::lenient())
+            .map(|document| document.root.value.to_json());
+        assert_eq!(
+            json_parse_with(source, ParseOptions::lenient()),
+            expected,
+            "{:?}",
+            source
+        );
+    }
+    assert_eq!(
+        json_parse(sources[2]),
+        json_parse_with(sources[2], ParseOptions::lenient())
+    );
+
+    // positions after a comment are those of the source as written
+    assert_eq!(
+        json_parse_with("[/* é\n */ 1 2]", ParseOptions::lenient()),
+        Err(JsonError::UnexpectedToken {
+            character: '2',
+            location: 12,
+            line: 1,
+            column: 6,
+            offset: 13,
+        })
+    );
+    assert_eq!(
+        json_parse_with("[1 / 2]", ParseOptions::lenient()),
+        json_parse("[1 / 2]")
+    );
+    assert_eq!(
+        json_parse_with("[1] /* open", ParseOptions::lenient()),
+        Err(JsonError::UnexpectedEOF {
+            line: 0,
+            column: 11,
+            offset: 11,
+        })
+    );
+    assert!(json_parse_with("[1 /* open", ParseOptions::lenient()).is_err());
+}
+
+#[test]
+fn parse_trailing_commas() {
+    use super::document::json_parse_document;
+    use super::{json_parse, json_parse_with, ParseOptions};
+
+    let options = ParseOptions {
+        trailing_commas: true,
+        ..ParseOptions::default()
+    };
+    let sources = [
+        ("[1, 2, 3,]", true),
+        ("{ \"a\": 1, }", true),
+        ("[[],\n{ \"b\": [true,], },\n]", true),
+        ("[\",\",]", true),
+        ("[,]", false),
+        ("{,}", false),
+        ("[1,,]", false),
+        ("{ \"a\": 1,, }", false),
+        ("{ \"a\", }", false),
+        ("[1] ,", false),
+    ];
+    for &(source, ok) in sources.iter() {
+        let parsed = json_parse_with(source, options);
+        assert_eq!(parsed.is_ok(), ok, "{:?}", source);
+        let expected =
+            json_parse_document(source, options).map(|document| document.root.value.to_json());
+        assert_eq!(parsed, expected, "{:?}", source);
+        if ok {
+            assert!(json_parse(source).is_err(), "{:?}", source);
+        }
+    }
+}
+
+#[test]
+fn parse_json5() {
+    use super::{json_parse, json_parse_with, JsonError, JsonNumber, JsonValue, ParseOptions};
+
+    let source = r#"{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+}"#;
+    assert_eq!(
+        json_parse_with(source, ParseOptions::json5()),
+        Ok(json!({
+            "unquoted": "and you can quote me on that",
+            "singleQuotes": "I can use \"double quotes\" here",
+            "lineBreaks": "Look, Mom! No \\n's!",
+            "hexadecimal": 912559,
+            "leadingDecimalPoint": 0.8675309,
+            "andTrailing": 8675309,
+            "positiveSign": 1,
+            "trailingComma": "in objects",
+            "andIn": ["arrays"],
+            "backwardsCompatible": "with JSON"
+        }))
+    );
+
+    assert_eq!(
+        json_parse_with(
+            "[Infinity, -Infinity, +0x1F, -.5e1, 5.e-1]",
+            ParseOptions::json5()
+        ),
+        Ok(json!([f64::INFINITY, f64::NEG_INFINITY, 31, -5, 0.5]))
+    );
+    match json_parse_with("NaN", ParseOptions::json5()) {
+        Ok(JsonValue::Number(JsonNumber::F64(n))) => assert!(n.is_nan()),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        json_parse_with("[Infinity, -Infinity, NaN, 1.5]", ParseOptions::json5())
+            .unwrap()
+            .to_string(),
+        "[null,null,null,1.5]"
+    );
+    let strings = r#"{ $_a1: 'it\'s', é: '\x41\v\q', 'b"]': "a'}", true: null }"#;
+    assert_eq!(
+        json_parse_with(strings, ParseOptions::json5()),
+        Ok(json!({ "$_a1": "it's", "é": "A\u{b}q", "b\"]": "a'}", "true": null }))
+    );
+    assert!(json_parse(strings).is_err());
+    assert_eq!(
+        json_parse_with("'line\\\r\nbreak'", ParseOptions::json5()),
+        Ok(json!("linebreak"))
+    );
+
+    let invalid = [
+        "0x",
+        "+-1",
+        "--1",
+        "++1",
+        ".",
+        "Infinit",
+        "nan",
+        "01",
+        "'a\nb'",
+        "'\\1'",
+        "{ a b: 1 }",
+        "{ 1a: 1 }",
+        "[a]",
+        "'a\"",
+    ];
+    for source in invalid.iter() {
+        assert!(
+            json_parse_with(source, ParseOptions::json5()).is_err(),
+            "{:?}",
+            source
+        );
+    }
+    assert_eq!(
+        json_parse_with("{ a: 1 b: 2 }", ParseOptions::json5()),
+        Err(JsonError::UnexpectedToken {
+            character: 'b',
+            location: 7,
+            line: 0,
+            column: 7,
+            offset: 7,
+        })
+    );
+}
+
+#[test]
+fn parse_duplicate_keys() {
+    use super::{json_parse, json_parse_with, DuplicateKeys, JsonError, ParseOptions};
+
+    let source = "{ \"a\": 1, \"b\": { \"a\": 2 },\n  \"a\": 3 }";
+    let with = |duplicate_keys| ParseOptions {
+        duplicate_keys,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        json_parse_with(source, with(DuplicateKeys::LastWins)),
+        Ok(json!({ "a": 3, "b": { "a": 2 } }))
+    );
+    assert_eq!(
+        json_parse_with(source, with(DuplicateKeys::LastWins)),
+        json_parse(source)
+    );
+    assert_eq!(
+        json_parse_with(source, with(DuplicateKeys::FirstWins)),
+        Ok(json!({ "a": 1, "b": { "a": 2 } }))
+    );
+
+    let error = json_parse_with(source, with(DuplicateKeys::Error)).unwrap_err();
+    assert_eq!(
+        error,
+        JsonError::DuplicateKey {
+            key: "a".into(),
+            location: 29,
+            line: 1,
+            column: 2,
+            offset: 29,
+        }
+    );
+    assert_eq!(error.to_string(), "duplicate key \"a\" at line 2, column 3");
+    assert_eq!(
+        json_parse_with(
+            "[{ \"x\": { \"y\": 1, \"y\": 2 } }]",
+            with(DuplicateKeys::Error)
+        ),
+        Err(JsonError::DuplicateKey {
+            key: "y".into(),
+            location: 18,
+            line: 0,
+            column: 18,
+            offset: 18,
+        })
+    );
+    assert!(json_parse_with(
+        "[{ \"a\": 1 }, { \"a\": \"a\" }]",
+        with(DuplicateKeys::Error)
+    )
+    .is_ok());
+
+    let json5 = ParseOptions {
+        duplicate_keys: DuplicateKeys::Error,
+        ..ParseOptions::json5()
+    };
+    assert!(matches!(
+        json_parse_with("{ a: 1, 'a': 2 }", json5),
+        Err(JsonError::DuplicateKey { location: 8, .. })
+    ));
+}
+
+#[test]
+fn parse_max_depth() {
+    use super::{json_parse, json_parse_with, JsonError, ParseOptions};
+
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(json_parse(&nested(128)).is_ok());
+    let error = json_parse(&nested(129)).unwrap_err();
+    assert_eq!(
+        error,
+        JsonError::DepthLimitExceeded {
+            limit: 128,
+            location: 128,
+            line: 0,
+            column: 128,
+            offset: 128,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "nested more than 128 levels deep at line 1, column 129"
+    );
+
+    // rejected as soon as the limit is passed, however deep the document goes
+    let attack = "[{\"a\":".repeat(500_000);
+    assert!(matches!(
+        json_parse(&attack),
+        Err(JsonError::DepthLimitExceeded { location: 384, .. })
+    ));
+
+    let shallow = ParseOptions {
+        max_depth: 2,
+        ..ParseOptions::default()
+    };
+    assert!(json_parse_with("[{ \"a\": \"[[[\" }, []]", shallow).is_ok());
+    assert!(matches!(
+        json_parse_with("[{ \"a\": [1] }]", shallow),
+        Err(JsonError::DepthLimitExceeded { location: 8, .. })
+    ));
+    let scalars = ParseOptions {
+        max_depth: 0,
+        ..ParseOptions::default()
+    };
+    assert_eq!(json_parse_with("1", scalars), Ok(json!(1)));
+    assert!(json_parse_with("[]", scalars).is_err());
+
+    // without a limit, depth is bounded by memory rather than the call stack
+    let unlimited = ParseOptions {
+        max_depth: usize::MAX,
+        ..ParseOptions::default()
+    };
+    let json = json_parse_with(&nested(1_000_000), unlimited).unwrap();
+    let innermost = (1..1_000_000).fold(&json, |json, _| &json[0]);
+    assert_eq!(innermost, &json!([]));
+    assert!(matches!(
+        json_parse_with(&attack, unlimited),
+        Err(JsonError::UnexpectedEOF { .. })
+    ));
+}
+
+#[test]
+fn parse_integers() {
+    use super::{json_parse, json_parse_with, JsonNumber, JsonValue, ParseOptions};
+
+    let json =
+        json_parse("[18446744073709551615, -9223372036854775808, 9007199254740993, 0]").unwrap();
+    assert_eq!(
+        json.as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_number().unwrap())
+            .collect::<Vec<_>>(),
+        [
+            JsonNumber::U64(u64::MAX),
+            JsonNumber::I64(i64::MIN),
+            JsonNumber::U64(9_007_199_254_740_993),
+            JsonNumber::U64(0),
+        ]
+    );
+    assert_eq!(
+        json.to_string(),
+        "[18446744073709551615,-9223372036854775808,9007199254740993,0]"
+    );
+    assert_eq!(json[2].as_i64(), Some(9_007_199_254_740_993));
+    assert_eq!(json[0].as_i64(), None);
+
+    // anything else, including integers out of range, is a float
+    for source in [
+        "18446744073709551616",
+        "-9223372036854775809",
+        "1.0",
+        "1e2",
+        "-0",
+    ]
+    .iter()
+    {
+        match json_parse(source) {
+            Ok(JsonValue::Number(JsonNumber::F64(_))) => (),
+            other => panic!("{}: {:?}", source, other),
+        }
+    }
+    assert_eq!(json_parse("-0").unwrap().to_string(), "-0");
+    assert_eq!(json_parse("1e2").unwrap().as_u64(), Some(100));
+
+    // whole floats too large to hold every integer are written so they read back as floats
+    let big = JsonValue::from(2f64.powi(60) + 2048.0);
+    assert_eq!(big.to_string(), "1.152921504606849e18");
+    assert_eq!(json_parse(&big.to_string()), Ok(big));
+
+    assert_eq!(
+        json_parse_with("[0xFFFFFFFFFFFFFFFF, -0x10]", ParseOptions::json5()),
+        Ok(JsonValue::Array(vec![
+            JsonValue::Number(JsonNumber::U64(u64::MAX)),
+            JsonValue::Number(JsonNumber::I64(-16)),
+        ]))
+    );
+}
+
+#[test]
+fn raw_json() {
+    use super::{json_parse, JsonValue, RawJson};
+
+    let raw: RawJson = "[1,  {\"a\": true}]".parse().unwrap();
+    let json = JsonValue::Array(vec![JsonValue::from(raw.clone()), JsonValue::Null]);
+    assert_eq!(json.to_string(), "[[1,  {\"a\": true}],null]");
+    assert_eq!(
+        json.to_string_pretty(),
+        "[\n  [1,  {\"a\": true}],\n  null\n]"
+    );
+    assert_eq!(raw.parse(), json_parse("[1, {\"a\": true}]").unwrap());
+    assert_eq!(json[0].as_raw(), Some(&raw));
+
+    for source in ["", "1 2", "[1,]", "{\"a\" 1}"].iter() {
+        assert!(source.parse::<RawJson>().is_err(), "{}", source);
+    }
+    let value = json_parse(r#"{ "b": [null] }"#).unwrap();
+    assert_eq!(
+        RawJson::from_value(&value).map(RawJson::into_string),
+        Some(r#"{"b":[null]}"#.into())
+    );
+    let value = JsonValue::Array(vec![JsonValue::Null, JsonValue::from(f64::NAN)]);
+    assert_eq!(RawJson::from_value(&value), None);
+    assert_eq!(RawJson::from_value(&JsonValue::from(f64::INFINITY)), None);
+
+    // the check has no depth limit, so neither does parsing
+    let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+    let raw: RawJson = deep.parse().unwrap();
+    let innermost = (1..200).fold(&raw.parse(), |json, _| &json[0]).clone();
+    assert_eq!(innermost, JsonValue::Array(vec![]));
+}
+
+#[test]
+fn parse_nested_errors() {
+    use super::{json_parse, JsonError};
+
+    // an error in a member is reported when the member ends, unless the array or object around
+    // it fails first
+    let tests = [
+        ("[[1, tru], 2]", ']', 8),
+        ("[[1, tru] }", '}', 10),
+        (r#"{"a": [1 2], "b" 3}"#, '2', 9),
+        (r#"[[["x" 1]]"#, '1', 7),
+        ("[1, [2, [3, 4x]]]", 'x', 13),
+    ];
+    for (source, character, location) in tests.iter() {
+        match json_parse(source) {
+            Err(JsonError::UnexpectedToken {
+                character: c,
+                location: l,
+                ..
+            }) if c == *character && l == *location => (),
+            other => panic!("{}: {:?}", source, other),
+        }
+    }
+
+    let deep = format!("[{}1{}]", "[".repeat(120), "]".repeat(120)).repeat(1000);
+    assert!(json_parse(&format!("[{}]", deep.replace("][", "],["))).is_ok());
+}
@@ -0,0 +1,471 @@
+use super::events::{EventParser, Step};
+use super::lexer::Cursor;
+use super::options::ParseOptions;
+use super::span::Span;
+use super::{JsonError, JsonValue};
+
+/// What a piece of JSON source is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// An object key, including its quotes
+    Key,
+    /// A string value, including its quotes
+    String,
+    /// A number
+    Number,
+    /// `true` or `false`
+    Boolean,
+    /// `null`
+    Null,
+    /// One of `[ ] { } : ,`
+    Punctuation,
+    /// A run of whitespace
+    Whitespace,
+    /// A line or block comment
+    Comment,
+    /// Everything from the first syntax error to the end of the input
+    Error,
+}
+
+/// A classified piece of JSON source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    /// What the token is
+    pub kind: TokenKind,
+    /// The source text of the token
+    pub text: &'a str,
+    /// Where the token is in the source
+    pub span: Span,
+}
+
+/// An iterator over the tokens of a JSON string.  See [`tokenize`](fn.tokenize.html).
+#[derive(Debug)]
+pub struct Tokens<'a> {
+    source: &'a str,
+    parser: EventParser<'a>,
+    error: Option<JsonError>,
+}
+
+impl<'a> Tokens<'a> {
+    /// The syntax error behind the `Error` token, once it has been produced.
+    pub fn error(&self) -> Option<&JsonError> {
+        self.error.as_ref()
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.parser.offset();
+        let (kind, span) = match self.parser.step() {
+            Ok(step) => match step? {
+                Step::Whitespace(span) => (TokenKind::Whitespace, span),
+                Step::Comment(span) => (TokenKind::Comment, span),
+                Step::Punct(_, span) => (TokenKind::Punctuation, span),
+                Step::Key(_, span) => (TokenKind::Key, span),
+                Step::Value(value, span) => (
+                    match value {
+                        JsonValue::Text(_) => TokenKind::String,
+                        JsonValue::Number(_) => TokenKind::Number,
+                        JsonValue::Boolean(_) => TokenKind::Boolean,
+                        _ => TokenKind::Null,
+                    },
+                    span,
+                ),
+            },
+            Err(e) => {
+                self.error = Some(e);
+                self.parser.halt();
+                (TokenKind::Error, Span::new(start, self.source.len()))
+            }
+        };
+
+        Some(Token {
+            kind,
+            text: &self.source[span.start..span.end],
+            span,
+        })
+    }
+}
+
+/// Splits a JSON string into classified tokens for syntax highlighting.
+///
+/// The tokens cover the input exactly, so concatenating their text reproduces it.  They are
+/// produced by the event parser behind [`json_parse_spanned`](fn.json_parse_spanned.html) rather
+/// than the state machine inside [`json_parse`](fn.json_parse.html), but the two are tested
+/// against each other: the input contains an `Error` token exactly when `json_parse` fails, and
+/// [`Tokens::error`](struct.Tokens.html#method.error) is the error it returns.  If the input ends
+/// early the error token is empty.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{tokenize, TokenKind};
+///
+/// fn main() {
+///     let kinds = tokenize(r#"{"a": [1, nul]}"#)
+///         .map(|token| (token.kind, token.text))
+///         .collect::<Vec<_>>();
+///
+///     assert_eq!(
+///         kinds,
+///         vec![
+///             (TokenKind::Punctuation, "{"),
+///             (TokenKind::Key, r#""a""#),
+///             (TokenKind::Punctuation, ":"),
+///             (TokenKind::Whitespace, " "),
+///             (TokenKind::Punctuation, "["),
+///             (TokenKind::Number, "1"),
+///             (TokenKind::Punctuation, ","),
+///             (TokenKind::Whitespace, " "),
+///             (TokenKind::Error, "nul]}"),
+///         ]
+///     );
+/// }
+/// ```
+pub fn tokenize(json_str: &str) -> Tokens<'_> {
+    tokenize_with(json_str, ParseOptions::default())
+}
+
+/// Splits a JSON string into classified tokens, accepting the syntax allowed by `options`.
+///
+/// The event parser doesn't read `json5`, so unlike
+/// [`json_parse_with`](fn.json_parse_with.html) this still stops at the first JSON5 extension.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{tokenize_with, ParseOptions, TokenKind};
+///
+/// fn main() {
+///     let options = ParseOptions::lenient();
+///     let comments = tokenize_with("[1 /* one */]", options)
+///         .filter(|token| token.kind == TokenKind::Comment)
+///         .count();
+///     assert_eq!(comments, 1);
+/// }
+/// ```
+pub fn tokenize_with(json_str: &str, options: ParseOptions) -> Tokens<'_> {
+    Tokens {
+        source: json_str,
+        parser: EventParser::with_options(json_str, options),
+        error: None,
+    }
+}
+
+/// A lexer that splits JSON source into tokens one at a time, without checking how they are
+/// arranged.
+///
+/// Unlike [`tokenize`](fn.tokenize.html), the lexer doesn't follow the grammar, so it carries on
+/// past mistakes: each character or partial scalar it can't make sense of becomes its own `Error`
+/// token and lexing resumes after it.  Every string is a `String` token, since telling keys apart
+/// needs the grammar.  An unterminated string or block comment is an `Error` token running to the
+/// end of the input.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{JsonLexer, TokenKind};
+///
+/// fn main() {
+///     let kinds = JsonLexer::new(r#"]"a": nul,"#)
+///         .map(|token| (token.kind, token.text))
+///         .collect::<Vec<_>>();
+///
+///     assert_eq!(
+///         kinds,
+///         vec![
+///             (TokenKind::Punctuation, "]"),
+///             (TokenKind::String, r#""a""#),
+///             (TokenKind::Punctuation, ":"),
+///             (TokenKind::Whitespace, " "),
+///             (TokenKind::Error, "nul,"),
+///         ]
+///     );
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JsonLexer<'a> {
+    source: &'a str,
+    cursor: Cursor<'a>,
+    options: ParseOptions,
+}
+
+impl<'a> JsonLexer<'a> {
+    /// Creates a lexer over standard JSON.
+    pub fn new(json_str: &'a str) -> Self {
+        Self::with_options(json_str, ParseOptions::default())
+    }
+
+    /// Creates a lexer that accepts the syntax allowed by `options`.
+    pub fn with_options(json_str: &'a str, options: ParseOptions) -> Self {
+        let mut cursor = Cursor::new(json_str);
+        cursor.borrow_text();
+        Self {
+            source: json_str,
+            cursor,
+            options,
+        }
+    }
+
+    /// Consumes a comment if they are allowed, or the rest of the input if one is unterminated.
+    fn comment(&mut self) -> Option<TokenKind> {
+        if !self.options.comments {
+            return None;
+        }
+        match self.cursor.comment() {
+            Ok(found) => found.map(|_| TokenKind::Comment),
+            Err(_) => {
+                self.cursor.skip_rest();
+                Some(TokenKind::Error)
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for JsonLexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.cursor.offset();
+        self.cursor.peek()?;
+        let kind = if self.cursor.whitespace().is_some() {
+            TokenKind::Whitespace
+        } else if self.cursor.punct().is_some() {
+            TokenKind::Punctuation
+        } else if let Some(kind) = self.comment() {
+            kind
+        } else {
+            match self.cursor.scalar() {
+                Ok(scalar) => match scalar.value {
+                    JsonValue::Text(_) => TokenKind::String,
+                    JsonValue::Number(_) => TokenKind::Number,
+                    JsonValue::Boolean(_) => TokenKind::Boolean,
+                    _ => TokenKind::Null,
+                },
+                Err(_) => TokenKind::Error,
+            }
+        };
+
+        let span = Span::new(start, self.cursor.offset());
+        Some(Token {
+            kind,
+            text: &self.source[span.start..span.end],
+            span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, tokenize_with, JsonLexer, TokenKind};
+    use crate::options::ParseOptions;
+    use crate::span::Span;
+    use crate::{json_parse, JsonError};
+
+    #[test]
+    fn tokens_lossless() {
+        use std::fs::{read, read_dir};
+
+        for file in read_dir("./test-json").unwrap().flatten() {
+            let source = String::from_utf8(read(file.path()).unwrap()).unwrap();
+            let tokens = tokenize(&source).collect::<Vec<_>>();
+
+            let mut end = 0;
+            for token in tokens.iter() {
+                assert_eq!(token.span.start, end);
+                end = token.span.end;
+            }
+            assert_eq!(end, source.len());
+            assert_eq!(
+                tokens.iter().any(|token| token.kind == TokenKind::Error),
+                json_parse(&source).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn tokens_match_json_parse() {
+        use crate::{json_parse_with, DuplicateKeys};
+
+        let mut sources = vec![
+            "",
+            " ",
+            "1",
+            "-",
+            "01",
+            "1.5e",
+            "1e400",
+            "\"a\\u00e9\"",
+            "\"\\ud800\"",
+            "\"\t\"",
+            "[1,]",
+            "{\"a\":1,}",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{1:2}",
+            "[}",
+            "]",
+            "[[[",
+            "{\"a\":",
+            "nul",
+            "truex",
+            "[true false]",
+            "1 2",
+            "[1] // c",
+            "/* c */ [1]",
+            "[1 /* c",
+            "/",
+            "{\"a\":1,\"a\":2}",
+            "[[1]]",
+            "\u{feff}1",
+            "[1]\n\t ",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+        for file in std::fs::read_dir("./test-json").unwrap().flatten() {
+            sources.push(std::fs::read_to_string(file.path()).unwrap());
+        }
+
+        let option_sets = vec![
+            ParseOptions::default(),
+            ParseOptions::lenient(),
+            ParseOptions {
+                duplicate_keys: DuplicateKeys::Error,
+                max_depth: 1,
+                ..ParseOptions::default()
+            },
+        ];
+        for options in option_sets {
+            for source in sources.iter() {
+                let mut tokens = tokenize_with(source, options);
+                tokens.by_ref().for_each(drop);
+                assert_eq!(
+                    tokens.error(),
+                    json_parse_with(source, options).err().as_ref(),
+                    "{:?}",
+                    source
+                );
+            }
+        }
+
+        let last = tokenize_with("{a: 1}", ParseOptions::json5())
+            .last()
+            .unwrap();
+        assert_eq!((last.kind, last.text), (TokenKind::Error, "a: 1}"));
+    }
+
+    #[test]
+    fn tokens_kinds() {
+        let options = ParseOptions::lenient();
+        let kinds = tokenize_with("// c\n[\"s\", true,null]", options)
+            .map(|token| (token.kind, token.span))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Comment, Span::new(0, 4)),
+                (TokenKind::Whitespace, Span::new(4, 5)),
+                (TokenKind::Punctuation, Span::new(5, 6)),
+                (TokenKind::String, Span::new(6, 9)),
+                (TokenKind::Punctuation, Span::new(9, 10)),
+                (TokenKind::Whitespace, Span::new(10, 11)),
+                (TokenKind::Boolean, Span::new(11, 15)),
+                (TokenKind::Punctuation, Span::new(15, 16)),
+                (TokenKind::Null, Span::new(16, 20)),
+                (TokenKind::Punctuation, Span::new(20, 21)),
+            ]
+        );
+
+        let mut strict = tokenize("// c\n1");
+        assert_eq!(strict.next().map(|t| t.kind), Some(TokenKind::Error));
+        assert_eq!(
+            strict.error(),
+            Some(&JsonError::UnexpectedToken {
+                character: '/',
+                location: 0,
+                line: 0,
+                column: 0,
+                offset: 0,
+            })
+        );
+        assert_eq!(strict.next(), None);
+        let eof = tokenize("[").map(|t| (t.kind, t.span)).collect::<Vec<_>>();
+        assert_eq!(
+            eof,
+            vec![
+                (TokenKind::Punctuation, Span::new(0, 1)),
+                (TokenKind::Error, Span::new(1, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_recovers() {
+        let kinds = JsonLexer::new("[tru, \"a\" 1.} x")
+            .map(|token| (token.kind, token.text))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Punctuation, "["),
+                (TokenKind::Error, "tru,"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::String, "\"a\""),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "1."),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "x"),
+            ]
+        );
+
+        let options = ParseOptions::lenient();
+        let kinds = JsonLexer::with_options("/ // a\n/* b", options)
+            .map(|token| (token.kind, token.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Error, Span::new(0, 1)),
+                (TokenKind::Whitespace, Span::new(1, 2)),
+                (TokenKind::Comment, Span::new(2, 6)),
+                (TokenKind::Whitespace, Span::new(6, 7)),
+                (TokenKind::Error, Span::new(7, 11)),
+            ]
+        );
+
+        for file in std::fs::read_dir("./test-json").unwrap().flatten() {
+            let source = std::fs::read_to_string(file.path()).unwrap();
+            let text = JsonLexer::new(&source).map(|t| t.text).collect::<String>();
+            assert_eq!(text, source);
+        }
+    }
+
+    #[test]
+    fn tokens_options() {
+        use crate::DuplicateKeys;
+
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            max_depth: 1,
+            ..ParseOptions::default()
+        };
+        let mut tokens = tokenize_with(r#"{"a": 1, "a": [2]}"#, options);
+        let last = tokens.by_ref().last().unwrap();
+        assert_eq!((last.kind, last.span), (TokenKind::Error, Span::new(9, 18)));
+        assert!(matches!(
+            tokens.error(),
+            Some(JsonError::DuplicateKey { location: 9, .. })
+        ));
+
+        let mut tokens = tokenize_with(r#"{"a": [2]}"#, options);
+        let last = tokens.by_ref().last().unwrap();
+        assert_eq!((last.kind, last.span), (TokenKind::Error, Span::new(6, 10)));
+        assert!(matches!(
+            tokens.error(),
+            Some(JsonError::DepthLimitExceeded {
+                limit: 1,
+                location: 6,
+                ..
+            })
+        ));
+    }
+}
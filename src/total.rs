@@ -0,0 +1,239 @@
+//! A wrapper that gives `JsonValue` the `Eq`, `Hash` and `Ord` that `f64` keeps it from having.
+use super::{JsonString, JsonValue};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A `JsonValue` compared with [`total_eq`](enum.JsonValue.html#method.total_eq), so it can be
+/// kept in a `HashSet`, used as a `BTreeMap` key, or sorted.
+///
+/// Every `NaN` is equal to every other `NaN` and `-0.0` is distinct from `0.0`, as with
+/// `total_eq`; call [`normalize`](enum.JsonValue.html#method.normalize) first if `-0.0` and `0.0`
+/// should be the same.  Values of different kinds are ordered null, booleans, numbers, strings,
+/// arrays, objects and then raw JSON.  Numbers are ordered by value with every `NaN` last, and
+/// objects as their members sorted by key.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, TotalJson};
+/// use std::collections::HashSet;
+///
+/// fn main() {
+///     let json = json_parse(r#"[1, {"a": 2, "b": 3}, 1.0, {"b": 3, "a": 2}, "1"]"#).unwrap();
+///     let unique = json
+///         .as_array()
+///         .unwrap()
+///         .iter()
+///         .cloned()
+///         .map(TotalJson)
+///         .collect::<HashSet<_>>();
+///     assert_eq!(unique.len(), 3);
+///
+///     let mut sorted = unique.into_iter().collect::<Vec<_>>();
+///     sorted.sort();
+///     assert_eq!(sorted[0].0, json[0]);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TotalJson(pub JsonValue);
+
+/// Where each kind of value sorts relative to the others.
+fn rank(value: &JsonValue) -> u8 {
+    match value {
+        JsonValue::Null => 0,
+        JsonValue::Boolean(_) => 1,
+        JsonValue::Number(_) => 2,
+        JsonValue::Text(_) => 3,
+        JsonValue::Array(_) => 4,
+        JsonValue::Object(_) => 5,
+        JsonValue::Raw(_) => 6,
+    }
+}
+
+/// An object's members sorted by key, which are unique, so equal objects list the same members.
+fn sorted_members(value: &JsonValue) -> Vec<(&JsonString, &JsonValue)> {
+    let mut members = value
+        .as_object()
+        .map(|map| map.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    members.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    members
+}
+
+fn total_cmp(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Boolean(a), JsonValue::Boolean(b)) => a.cmp(b),
+        (JsonValue::Number(a), JsonValue::Number(b)) => a.total_cmp(b),
+        (JsonValue::Text(a), JsonValue::Text(b)) => a.cmp(b),
+        (JsonValue::Array(a), JsonValue::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| total_cmp(a, b))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (JsonValue::Object(_), JsonValue::Object(_)) => {
+            let (a, b) = (sorted_members(a), sorted_members(b));
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.0.cmp(b.0).then_with(|| total_cmp(a.1, b.1)))
+                .find(|ord| ord.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+        (JsonValue::Raw(a), JsonValue::Raw(b)) => a.get().cmp(b.get()),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn total_hash<H: Hasher>(value: &JsonValue, state: &mut H) {
+    rank(value).hash(state);
+    match value {
+        JsonValue::Boolean(b) => b.hash(state),
+        // equal numbers have the same `f64`, which is all that is hashed
+        JsonValue::Number(n) => {
+            let n = n.as_f64();
+            let n = if n.is_nan() { f64::NAN } else { n };
+            n.to_bits().hash(state);
+        }
+        JsonValue::Text(text) => text.hash(state),
+        JsonValue::Array(array) => {
+            array.len().hash(state);
+            array.iter().for_each(|value| total_hash(value, state));
+        }
+        JsonValue::Object(_) => {
+            let members = sorted_members(value);
+            members.len().hash(state);
+            for (key, value) in members {
+                key.hash(state);
+                total_hash(value, state);
+            }
+        }
+        JsonValue::Raw(raw) => raw.hash(state),
+        JsonValue::Null => (),
+    }
+}
+
+impl PartialEq for TotalJson {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_eq(&other.0)
+    }
+}
+
+impl Eq for TotalJson {}
+
+impl PartialOrd for TotalJson {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalJson {
+    fn cmp(&self, other: &Self) -> Ordering {
+        total_cmp(&self.0, &other.0)
+    }
+}
+
+impl Hash for TotalJson {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        total_hash(&self.0, state);
+    }
+}
+
+impl From<JsonValue> for TotalJson {
+    fn from(value: JsonValue) -> Self {
+        TotalJson(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TotalJson;
+    use crate::{json_parse, JsonNumber, JsonValue};
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    fn hash(value: &TotalJson) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn total_json_set() {
+        let nan = || JsonValue::from(f64::NAN);
+        let values = vec![
+            nan(),
+            JsonValue::from(-f64::NAN),
+            JsonValue::from(0.0),
+            JsonValue::from(-0.0),
+            JsonValue::Number(JsonNumber::U64(0)),
+            JsonValue::Array(vec![nan()]),
+            JsonValue::Array(vec![nan()]),
+            json_parse(r#"{"a": 1, "b": [null]}"#).unwrap(),
+            json_parse(r#"{"b": [null], "a": 1.0}"#).unwrap(),
+            json_parse(r#"{"b": [null], "a": -1}"#).unwrap(),
+            JsonValue::Text("1".into()),
+        ];
+        let set = values
+            .iter()
+            .cloned()
+            .map(TotalJson)
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 7);
+        assert!(set.contains(&TotalJson(nan())));
+        assert!(!set.contains(&TotalJson(JsonValue::Null)));
+
+        for a in values.iter().cloned().map(TotalJson) {
+            for b in values.iter().cloned().map(TotalJson) {
+                assert_eq!(a == b, a.cmp(&b).is_eq());
+                assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+                if a == b {
+                    assert_eq!(hash(&a), hash(&b));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn total_json_order() {
+        let mut values = json_parse(
+            r#"[{"a": 1}, [1, 2], "b", 2, true, null, [1], -0.5, {"b": 0}, "a", false]"#,
+        )
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .clone();
+        values.push(JsonValue::from(-0.0));
+        values.push(JsonValue::from(f64::NAN));
+        values.push(JsonValue::from(0.0));
+
+        let mut sorted = values.into_iter().map(TotalJson).collect::<Vec<_>>();
+        sorted.sort();
+        let written = sorted
+            .iter()
+            .map(|value| match value.0.as_f64() {
+                Some(n) if n == 0.0 => format!("{:?}", n),
+                _ => value.0.to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            written,
+            [
+                "null",
+                "false",
+                "true",
+                "-0.5",
+                "-0.0",
+                "0.0",
+                "2",
+                "null",
+                "\"a\"",
+                "\"b\"",
+                "[1]",
+                "[1,2]",
+                r#"{"a":1}"#,
+                r#"{"b":0}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! `tracing` instrumentation, which expands to nothing without the `tracing` feature.
+
+/// Enters a debug-level span that lasts until the end of the enclosing block, so that a
+/// subscriber can time it.
+macro_rules! trace_span {
+    ($($span:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::debug_span!($($span)*).entered();
+    };
+}
+
+/// Emits a debug-level event.
+macro_rules! trace_event {
+    ($($event:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($event)*);
+    };
+}
+
+/// Emits an event if `result` is an error, then evaluates to `result`.
+macro_rules! trace_error {
+    ($result:expr) => {{
+        let result = $result;
+        #[cfg(feature = "tracing")]
+        {
+            if let Err(ref error) = result {
+                ::tracing::debug!(?error, "failed");
+            }
+        }
+        result
+    }};
+}
+
+/// Passes text through to another writer, counting the bytes, so that serializing can report
+/// its output size.
+#[cfg(feature = "tracing")]
+pub(crate) struct Counted<'a, W: ?Sized> {
+    inner: &'a mut W,
+    pub bytes: usize,
+}
+
+#[cfg(feature = "tracing")]
+impl<'a, W: ::std::fmt::Write + ?Sized> Counted<'a, W> {
+    pub fn new(inner: &'a mut W) -> Self {
+        Counted { inner, bytes: 0 }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<'a, W: ::std::fmt::Write + ?Sized> ::std::fmt::Write for Counted<'a, W> {
+    fn write_str(&mut self, s: &str) -> ::std::fmt::Result {
+        self.bytes += s.len();
+        self.inner.write_str(s)
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::json_parse;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{subscriber, Event, Metadata, Subscriber};
+
+    /// Writes down every span and event as its name and fields.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            let mut fields = Fields(vec![span.metadata().name().to_string()]);
+            span.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0.join(" "));
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(vec![]);
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0.join(" "));
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn trace_parse_and_serialize() {
+        let recorder = Recorder::default();
+        subscriber::with_default(recorder.clone(), || {
+            let json = json_parse("[1, 2]").unwrap();
+            assert_eq!(json.to_string(), "[1,2]");
+            assert!(json_parse("[1,").is_err());
+        });
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "json_parse bytes=6",
+                "json_serialize",
+                "message=serialized bytes=5",
+                "json_parse bytes=3",
+                "message=failed error=UnexpectedEOF { line: 0, column: 3, offset: 3 }",
+            ]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use super::{EditError, JsonEdit, JsonPointer, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// A set of edits to a `JsonValue` that can be undone, redone, and either committed or rolled
+/// back as a whole.
+///
+/// Edits are applied to the document immediately so that it can be inspected (and validated)
+/// through [`value`](#method.value) before committing.  Every edit records its inverse, which
+/// is what undo, redo, and rollback replay.  Dropping a transaction without committing it rolls
+/// it back.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_parse, JsonTransaction, JsonValue};
+///
+/// fn main() {
+///     let mut json = json_parse(r#"{ "name": "a" }"#).unwrap();
+///
+///     let mut tx = JsonTransaction::new(&mut json);
+///     tx.replace("/name".parse().unwrap(), JsonValue::Text("b".into())).unwrap();
+///     tx.add("/count".parse().unwrap(), JsonValue::from(1)).unwrap();
+///     tx.undo();
+///     assert_eq!(tx.value(), &json_parse(r#"{ "name": "b" }"#).unwrap());
+///     tx.commit();
+///
+///     assert_eq!(json, json_parse(r#"{ "name": "b" }"#).unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JsonTransaction<'a> {
+    target: &'a mut JsonValue,
+    undo_stack: Vec<JsonEdit>,
+    redo_stack: Vec<JsonEdit>,
+    committed: bool,
+}
+
+impl<'a> JsonTransaction<'a> {
+    /// Starts a transaction on `target`.
+    pub fn new(target: &'a mut JsonValue) -> Self {
+        Self {
+            target,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            committed: false,
+        }
+    }
+
+    /// The document with every edit that hasn't been undone applied.
+    pub fn value(&self) -> &JsonValue {
+        self.target
+    }
+
+    /// Applies an edit.  Applying an edit clears the redo history.
+    pub fn apply(&mut self, edit: JsonEdit) -> Result<(), EditError> {
+        let inverse = edit.apply(self.target)?;
+        self.undo_stack.push(inverse);
+        self.redo_stack.clear();
+        Ok(())
+    }
+
+    /// Adds a value at `path`.  See [`JsonEdit::Add`](enum.JsonEdit.html#variant.Add).
+    pub fn add(&mut self, path: JsonPointer, value: JsonValue) -> Result<(), EditError> {
+        self.apply(JsonEdit::Add { path, value })
+    }
+
+    /// Removes the value at `path`.
+    pub fn remove(&mut self, path: JsonPointer) -> Result<(), EditError> {
+        self.apply(JsonEdit::Remove { path })
+    }
+
+    /// Replaces the existing value at `path`.
+    pub fn replace(&mut self, path: JsonPointer, value: JsonValue) -> Result<(), EditError> {
+        self.apply(JsonEdit::Replace { path, value })
+    }
+
+    /// Whether there is an edit to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Whether there is an undone edit to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Undoes the most recent edit.  Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        swap_edit(self.target, &mut self.undo_stack, &mut self.redo_stack)
+    }
+
+    /// Reapplies the most recently undone edit.  Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        swap_edit(self.target, &mut self.redo_stack, &mut self.undo_stack)
+    }
+
+    /// Keeps every edit that hasn't been undone.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Reverts the document to its state before the transaction began.
+    pub fn rollback(self) {}
+}
+
+fn swap_edit(target: &mut JsonValue, from: &mut Vec<JsonEdit>, to: &mut Vec<JsonEdit>) -> bool {
+    from.pop()
+        .map(|edit| {
+            // inverses are only ever applied to the state they were produced from
+            to.push(edit.apply(target).unwrap());
+        })
+        .is_some()
+}
+
+impl<'a> Drop for JsonTransaction<'a> {
+    fn drop(&mut self) {
+        if !self.committed {
+            while self.undo() {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonTransaction, JsonValue};
+    use crate::json_parse;
+
+    #[test]
+    fn transaction_rollback() {
+        let original = json_parse(r#"{ "a": [1, 2], "b": { "c": null } }"#).unwrap();
+        let mut json = original.clone();
+
+        {
+            let mut tx = JsonTransaction::new(&mut json);
+            tx.remove("/a/0".parse().unwrap()).unwrap();
+            tx.add("/b/d".parse().unwrap(), JsonValue::Boolean(true))
+                .unwrap();
+            tx.replace("/b".parse().unwrap(), JsonValue::Null).unwrap();
+            assert!(tx.remove("/b/d".parse().unwrap()).is_err());
+            assert_eq!(
+                tx.value(),
+                &json_parse(r#"{ "a": [2], "b": null }"#).unwrap()
+            );
+            tx.rollback();
+        }
+        assert_eq!(json, original);
+
+        {
+            let mut tx = JsonTransaction::new(&mut json);
+            tx.add("/e".parse().unwrap(), JsonValue::Null).unwrap();
+        }
+        assert_eq!(json, original);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn transaction_keeps_order() {
+        let source = r#"{"a":1,"b":2,"c":3}"#;
+        let mut json = json_parse(source).unwrap();
+
+        let mut tx = JsonTransaction::new(&mut json);
+        tx.remove("/a".parse().unwrap()).unwrap();
+        tx.remove("/c".parse().unwrap()).unwrap();
+        assert!(tx.undo());
+        assert_eq!(tx.value().to_string(), r#"{"b":2,"c":3}"#);
+        assert!(tx.undo());
+        assert_eq!(tx.value().to_string(), source);
+        assert!(tx.redo());
+        assert!(tx.undo());
+        assert_eq!(tx.value().to_string(), source);
+
+        tx.remove("/b".parse().unwrap()).unwrap();
+        tx.add("/b".parse().unwrap(), JsonValue::Null).unwrap();
+        tx.rollback();
+        assert_eq!(json.to_string(), source);
+    }
+
+    #[test]
+    fn transaction_undo_redo() {
+        let mut json = json_parse("[]").unwrap();
+
+        let mut tx = JsonTransaction::new(&mut json);
+        assert!(!tx.redo());
+        for i in 0..3 {
+            tx.add("/-".parse().unwrap(), JsonValue::from(f64::from(i)))
+                .unwrap();
+        }
+        assert!(tx.undo());
+        assert!(tx.undo());
+        assert_eq!(tx.value(), &json_parse("[0]").unwrap());
+        assert!(tx.redo());
+        assert_eq!(tx.value(), &json_parse("[0, 1]").unwrap());
+        assert!(tx.can_redo());
+
+        tx.add("/0".parse().unwrap(), JsonValue::Null).unwrap();
+        assert!(!tx.can_redo());
+        assert!(!tx.redo());
+        tx.commit();
+
+        assert_eq!(json, json_parse("[null, 0, 1]").unwrap());
+    }
+}
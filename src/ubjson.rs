@@ -0,0 +1,513 @@
+//! [Universal Binary JSON](https://ubjson.org) encoding of `JsonValue`, enabled by the `ubjson`
+//! feature.
+use super::{JsonMap, JsonNumber, JsonString, JsonValue};
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Describes all possible errors that could occur while decoding UBJSON
+#[derive(Clone, Debug, PartialEq)]
+pub enum UbjsonError {
+    /// The input ended in the middle of a value
+    UnexpectedEOF,
+    /// A byte that is not a valid type marker where one was expected
+    InvalidMarker {
+        /// The byte that was found
+        marker: u8,
+        /// Its offset in the input
+        offset: usize,
+    },
+    /// A length or count was negative
+    InvalidLength {
+        /// The offset of the length in the input
+        offset: usize,
+    },
+    /// A string or character was not valid UTF-8
+    InvalidUtf8 {
+        /// The offset of the string in the input
+        offset: usize,
+    },
+    /// A high-precision number was not a valid number
+    InvalidNumber {
+        /// The offset of the number in the input
+        offset: usize,
+    },
+    /// Arrays and objects were nested more deeply than the decoder allows
+    DepthLimitExceeded {
+        /// The depth that was allowed
+        limit: usize,
+        /// The offset of the contents of the array or object that went too deep, just past its
+        /// marker if it has one
+        offset: usize,
+    },
+    /// More input followed the value
+    TrailingBytes {
+        /// The offset of the first byte after the value
+        offset: usize,
+    },
+}
+
+impl fmt::Display for UbjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UbjsonError::UnexpectedEOF => f.write_str("unexpected end of UBJSON input"),
+            UbjsonError::InvalidMarker { marker, offset } => {
+                write!(f, "invalid marker {:#04x} at offset {}", marker, offset)
+            }
+            UbjsonError::InvalidLength { offset } => {
+                write!(f, "negative length at offset {}", offset)
+            }
+            UbjsonError::InvalidUtf8 { offset } => {
+                write!(f, "string at offset {} is not valid UTF-8", offset)
+            }
+            UbjsonError::InvalidNumber { offset } => {
+                write!(f, "high-precision number at offset {} is not valid", offset)
+            }
+            UbjsonError::DepthLimitExceeded { limit, offset } => write!(
+                f,
+                "nested more than {} levels deep at offset {}",
+                limit, offset
+            ),
+            UbjsonError::TrailingBytes { offset } => {
+                write!(f, "unexpected bytes after the value at offset {}", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for UbjsonError {}
+
+/// Encodes a value as UBJSON.
+///
+/// Whole numbers that fit in 64 bits are written as the smallest integer type that holds them,
+/// and every other number as a 64-bit float.  Containers are written without the optional
+/// count and type headers.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::ubjson::{from_ubjson, to_ubjson};
+///
+/// fn main() {
+///     let json = json_parse(r#"[1, -200, "hi", null]"#).unwrap();
+///     let bytes = to_ubjson(&json);
+///     assert_eq!(bytes, b"[U\x01I\xff\x38SU\x02hiZ]");
+///     assert_eq!(from_ubjson(&bytes), Ok(json));
+/// }
+/// ```
+pub fn to_ubjson(value: &JsonValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(value, &mut out);
+    out
+}
+
+fn encode_int(i: i64, out: &mut Vec<u8>) {
+    if (0..=0xff).contains(&i) {
+        out.extend_from_slice(&[b'U', i as u8]);
+    } else if i >= i64::from(i8::MIN) && i < 0 {
+        out.extend_from_slice(&[b'i', i as u8]);
+    } else if i >= i64::from(i16::MIN) && i <= i64::from(i16::MAX) {
+        out.push(b'I');
+        out.extend_from_slice(&(i as i16).to_be_bytes());
+    } else if i >= i64::from(i32::MIN) && i <= i64::from(i32::MAX) {
+        out.push(b'l');
+        out.extend_from_slice(&(i as i32).to_be_bytes());
+    } else {
+        out.push(b'L');
+        out.extend_from_slice(&i.to_be_bytes());
+    }
+}
+
+/// Writes a string without its `S` marker, as object keys are.
+fn encode_str(s: &str, out: &mut Vec<u8>) {
+    encode_int(s.len() as i64, out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_number(n: JsonNumber, out: &mut Vec<u8>) {
+    // as an integer, `-0.0` would lose its sign
+    let negative_zero = matches!(n, JsonNumber::F64(f) if f == 0.0 && f.is_sign_negative());
+    match (n.as_i64(), n) {
+        (Some(i), _) if !negative_zero => encode_int(i, out),
+        // beyond `i64`, a high-precision number keeps every digit
+        (None, JsonNumber::U64(n)) => {
+            out.push(b'H');
+            encode_str(&n.to_string(), out);
+        }
+        #[cfg(feature = "decimal")]
+        (None, JsonNumber::Decimal(n)) => {
+            out.push(b'H');
+            encode_str(&n.to_string(), out);
+        }
+        _ => {
+            out.push(b'D');
+            out.extend_from_slice(&n.as_f64().to_bits().to_be_bytes());
+        }
+    }
+}
+
+fn encode(value: &JsonValue, out: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => out.push(b'Z'),
+        JsonValue::Boolean(false) => out.push(b'F'),
+        JsonValue::Boolean(true) => out.push(b'T'),
+        JsonValue::Number(n) => encode_number(*n, out),
+        JsonValue::Text(s) => {
+            out.push(b'S');
+            encode_str(s, out);
+        }
+        JsonValue::Raw(raw) => encode(&raw.parse(), out),
+        JsonValue::Array(array) => {
+            out.push(b'[');
+            for item in array.iter() {
+                encode(item, out);
+            }
+            out.push(b']');
+        }
+        JsonValue::Object(map) => {
+            out.push(b'{');
+            for (key, item) in map.iter() {
+                encode_str(key, out);
+                encode(item, out);
+            }
+            out.push(b'}');
+        }
+    }
+}
+
+/// How many arrays and objects may be open at once while decoding, as many as
+/// [`ParseOptions::max_depth`](../options/struct.ParseOptions.html#structfield.max_depth) allows
+/// by default.  The decoder recurses once per level, so this keeps hostile input from
+/// overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Decodes a single UBJSON value, which must take up all of `bytes` apart from no-op markers.
+///
+/// Containers with count and type headers are supported, nested up to 128 deep.  High-precision
+/// numbers are read exactly if they are integers that fit in 64 bits, and otherwise as `f64`,
+/// which may lose precision.
+pub fn from_ubjson(bytes: &[u8]) -> Result<JsonValue, UbjsonError> {
+    let mut decoder = Decoder {
+        bytes,
+        offset: 0,
+        depth: 0,
+    };
+    let marker = decoder.marker()?;
+    let value = decoder.value(marker)?;
+    while decoder.bytes.get(decoder.offset) == Some(&b'N') {
+        decoder.offset += 1;
+    }
+    if decoder.offset < bytes.len() {
+        return Err(UbjsonError::TrailingBytes {
+            offset: decoder.offset,
+        });
+    }
+    Ok(value)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// How many arrays and objects are open
+    depth: usize,
+}
+
+/// How the items of a container are laid out.
+struct Header {
+    /// The marker shared by every item, which is then left out before each of them
+    marker: Option<u8>,
+    /// The number of items, if the container has no end marker
+    count: Option<usize>,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], UbjsonError> {
+        if self.bytes.len() - self.offset < len {
+            return Err(UbjsonError::UnexpectedEOF);
+        }
+        let taken = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, UbjsonError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Reads the next marker, skipping any no-ops before it.
+    fn marker(&mut self) -> Result<u8, UbjsonError> {
+        loop {
+            match self.byte()? {
+                b'N' => (),
+                marker => return Ok(marker),
+            }
+        }
+    }
+
+    /// Opens an array or object whose contents are next, if that is not one too many.
+    fn enter(&mut self) -> Result<(), UbjsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(UbjsonError::DepthLimitExceeded {
+                limit: MAX_DEPTH,
+                offset: self.offset,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn invalid(&self, marker: u8) -> UbjsonError {
+        UbjsonError::InvalidMarker {
+            marker,
+            offset: self.offset - 1,
+        }
+    }
+
+    /// Reads the payload of an integer marker.
+    fn int(&mut self, marker: u8) -> Result<i64, UbjsonError> {
+        let (len, signed) = match marker {
+            b'i' => (1, true),
+            b'U' => (1, false),
+            b'I' => (2, true),
+            b'l' => (4, true),
+            b'L' => (8, true),
+            _ => return Err(self.invalid(marker)),
+        };
+        let bits = self
+            .take(len)?
+            .iter()
+            .fold(0u64, |n, &byte| (n << 8) | u64::from(byte));
+        let shift = 64 - 8 * len as u32;
+        Ok(if signed {
+            ((bits << shift) as i64) >> shift
+        } else {
+            bits as i64
+        })
+    }
+
+    fn length(&mut self) -> Result<usize, UbjsonError> {
+        let offset = self.offset;
+        let marker = self.marker()?;
+        let len = self.int(marker)?;
+        if len < 0 {
+            return Err(UbjsonError::InvalidLength { offset });
+        }
+        Ok(len as usize)
+    }
+
+    fn str(&mut self) -> Result<String, UbjsonError> {
+        let len = self.length()?;
+        let offset = self.offset;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| UbjsonError::InvalidUtf8 { offset })
+    }
+
+    fn header(&mut self) -> Result<Header, UbjsonError> {
+        let mut header = Header {
+            marker: None,
+            count: None,
+        };
+        if self.bytes.get(self.offset) == Some(&b'$') {
+            self.offset += 1;
+            header.marker = Some(self.byte()?);
+            match self.byte()? {
+                b'#' => (),
+                marker => return Err(self.invalid(marker)),
+            }
+            header.count = Some(self.length()?);
+        } else if self.bytes.get(self.offset) == Some(&b'#') {
+            self.offset += 1;
+            header.count = Some(self.length()?);
+        }
+        Ok(header)
+    }
+
+    /// Checks whether a container has more items, consuming its end marker if it does not.
+    fn more(&mut self, header: &Header, read: usize, end: u8) -> Result<bool, UbjsonError> {
+        match header.count {
+            Some(count) => Ok(read < count),
+            None => {
+                while self.bytes.get(self.offset) == Some(&b'N') {
+                    self.offset += 1;
+                }
+                if self.bytes.get(self.offset) == Some(&end) {
+                    self.offset += 1;
+                    Ok(false)
+                } else {
+                    Ok(true)
+                }
+            }
+        }
+    }
+
+    fn item(&mut self, header: &Header) -> Result<JsonValue, UbjsonError> {
+        let marker = match header.marker {
+            Some(marker) => marker,
+            None => self.marker()?,
+        };
+        self.value(marker)
+    }
+
+    fn value(&mut self, marker: u8) -> Result<JsonValue, UbjsonError> {
+        Ok(match marker {
+            b'Z' => JsonValue::Null,
+            b'T' => JsonValue::Boolean(true),
+            b'F' => JsonValue::Boolean(false),
+            b'i' | b'U' | b'I' | b'l' | b'L' => JsonValue::from(self.int(marker)?),
+            b'd' => {
+                let bits = self.take(4)?;
+                JsonValue::from(f32::from_be_bytes([bits[0], bits[1], bits[2], bits[3]]))
+            }
+            b'D' => {
+                let mut bits = [0; 8];
+                bits.copy_from_slice(self.take(8)?);
+                JsonValue::from(f64::from_be_bytes(bits))
+            }
+            b'H' => {
+                let offset = self.offset;
+                let n = self.str()?;
+                let number = n
+                    .parse()
+                    .map(JsonNumber::U64)
+                    .or_else(|_| n.parse().map(JsonNumber::I64))
+                    .or_else(|_| n.parse().map(JsonNumber::F64));
+                match number {
+                    Ok(n) => JsonValue::Number(n),
+                    Err(_) => return Err(UbjsonError::InvalidNumber { offset }),
+                }
+            }
+            b'C' => {
+                let offset = self.offset;
+                match self.byte()? {
+                    c if c.is_ascii() => JsonValue::Text(JsonString::from(c as char)),
+                    _ => return Err(UbjsonError::InvalidUtf8 { offset }),
+                }
+            }
+            b'S' => JsonValue::Text(self.str()?.into()),
+            b'[' => {
+                self.enter()?;
+                let header = self.header()?;
+                // every item takes at least a byte unless they share a marker
+                let capacity = header
+                    .count
+                    .unwrap_or(0)
+                    .min(self.bytes.len() - self.offset);
+                let mut array = Vec::with_capacity(capacity);
+                while self.more(&header, array.len(), b']')? {
+                    array.push(self.item(&header)?);
+                }
+                self.depth -= 1;
+                JsonValue::Array(array)
+            }
+            b'{' => {
+                self.enter()?;
+                let header = self.header()?;
+                let mut map = JsonMap::new();
+                let mut read = 0;
+                while self.more(&header, read, b'}')? {
+                    let key = self.str()?;
+                    let value = self.item(&header)?;
+                    map.insert(key.into(), value);
+                    read += 1;
+                }
+                self.depth -= 1;
+                JsonValue::Object(Box::new(map))
+            }
+            _ => return Err(self.invalid(marker)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_ubjson, to_ubjson, UbjsonError};
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn ubjson_round_trip() {
+        let json = json_parse(
+            r#"{
+                "ints": [0, 255, 256, -1, -128, -129, 32767, 32768, -2147483649],
+                "floats": [0.5, -0.0, 1e300, 9223372036854775808],
+                "text": ["", "é", "a longer string"],
+                "nested": { "empty": {}, "list": [[], null, false, true] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(from_ubjson(&to_ubjson(&json)), Ok(json));
+
+        let long = JsonValue::Text("x".repeat(300).into());
+        assert_eq!(&to_ubjson(&long)[..4], b"SI\x01\x2c");
+    }
+
+    #[test]
+    fn ubjson_decode() {
+        // counted and typed containers, no-ops, characters, and high-precision numbers
+        assert_eq!(
+            from_ubjson(b"N[$U#U\x03\x01\x02\x03]"),
+            Err(UbjsonError::TrailingBytes { offset: 10 })
+        );
+        assert_eq!(
+            from_ubjson(b"N[$U#U\x03\x01\x02\x03N"),
+            Ok(json_parse("[1, 2, 3]").unwrap())
+        );
+        assert_eq!(
+            from_ubjson(b"{#U\x02U\x01aCzU\x01bHU\x041e10"),
+            Ok(json_parse(r#"{ "a": "z", "b": 1e10 }"#).unwrap())
+        );
+        assert_eq!(
+            from_ubjson(b"[d\x3f\x00\x00\x00NZ]"),
+            Ok(json_parse("[0.5, null]").unwrap())
+        );
+
+        assert_eq!(from_ubjson(b"[U\x01"), Err(UbjsonError::UnexpectedEOF));
+        assert_eq!(
+            from_ubjson(b"[X]"),
+            Err(UbjsonError::InvalidMarker {
+                marker: b'X',
+                offset: 1,
+            })
+        );
+        assert_eq!(
+            from_ubjson(b"Si\xff"),
+            Err(UbjsonError::InvalidLength { offset: 1 })
+        );
+        assert_eq!(
+            UbjsonError::InvalidLength { offset: 1 }.to_string(),
+            "negative length at offset 1"
+        );
+        assert_eq!(
+            from_ubjson(b"SU\x01\xff"),
+            Err(UbjsonError::InvalidUtf8 { offset: 3 })
+        );
+        assert_eq!(
+            from_ubjson(b"HU\x01x"),
+            Err(UbjsonError::InvalidNumber { offset: 1 })
+        );
+
+        let nested = b"[".repeat(1_000_000);
+        assert_eq!(
+            from_ubjson(&nested),
+            Err(UbjsonError::DepthLimitExceeded {
+                limit: 128,
+                offset: 129,
+            })
+        );
+        // in a typed array of arrays, the inner arrays have no markers of their own
+        let typed = |depth: usize| {
+            let mut nested = b"[".to_vec();
+            nested.extend_from_slice(&b"$[#U\x01".repeat(depth - 1));
+            nested.extend_from_slice(b"$Z#U\x01");
+            nested
+        };
+        assert_eq!(
+            from_ubjson(&typed(128)).map(|json| json.to_string()),
+            Ok(format!("{}null{}", "[".repeat(128), "]".repeat(128)))
+        );
+        assert!(matches!(
+            from_ubjson(&typed(129)),
+            Err(UbjsonError::DepthLimitExceeded { .. })
+        ));
+    }
+}
@@ -0,0 +1,459 @@
+//! Conversion between `application/x-www-form-urlencoded` data, as sent by HTML forms and in
+//! query strings, and JSON objects.
+//!
+//! Keys use the bracketed nesting convention of PHP, Rails and `qs`:
+//!
+//! * `a[b]=1` sets the member `b` of the object `a`.
+//! * `a[]=1` appends to the array `a`.  Further brackets after `[]` fill in the last object of
+//!   the array until a key repeats, so `a[][x]=1&a[][y]=2&a[][x]=3` is
+//!   `{ "a": [{ "x": "1", "y": "2" }, { "x": "3" }] }`.
+//! * `a[0]=1` sets an element of an array by index.  Indices can only refer to an element that
+//!   exists or the one after the last.
+//! * A repeated key without brackets collects its values into an array.
+//!
+//! Values are always strings, except that a key with no `=` is `null`.
+use super::{JsonMap, JsonPointer, JsonValue};
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Describes all possible errors that could occur while converting form data
+#[derive(Clone, Debug, PartialEq)]
+pub enum UrlencodedError {
+    /// A `%` was not followed by two hexadecimal digits
+    InvalidEscape {
+        /// The offset of the `%` in the input
+        offset: usize,
+    },
+    /// A key or value did not decode to valid UTF-8
+    InvalidUtf8 {
+        /// The offset of the key or value in the input
+        offset: usize,
+    },
+    /// A key was empty, or its brackets were not balanced
+    MalformedKey(String),
+    /// A key did not fit with the keys before it, such as `a[b]` after `a`, or an index past the
+    /// end of an array
+    Conflict(String),
+    /// The value to write was not an object
+    NotAnObject,
+    /// A value cannot be written so that it reads back the same, such as an empty array inside
+    /// an array or a key containing brackets
+    Unrepresentable(JsonPointer),
+}
+
+impl fmt::Display for UrlencodedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UrlencodedError::InvalidEscape { offset } => {
+                write!(
+                    f,
+                    "'%' at offset {} is not followed by two hex digits",
+                    offset
+                )
+            }
+            UrlencodedError::InvalidUtf8 { offset } => {
+                write!(f, "text at offset {} is not valid UTF-8", offset)
+            }
+            UrlencodedError::MalformedKey(key) => write!(f, "malformed key {:?}", key),
+            UrlencodedError::Conflict(key) => {
+                write!(f, "key {:?} conflicts with an earlier key", key)
+            }
+            UrlencodedError::NotAnObject => {
+                f.write_str("only an object can be written as form data")
+            }
+            UrlencodedError::Unrepresentable(path) => write!(
+                f,
+                "the value at {:?} cannot be written as form data",
+                path.to_string()
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for UrlencodedError {}
+
+/// One bracketed part of a key.
+enum Segment {
+    Name(String),
+    Append,
+}
+
+fn decode(text: &str, offset: usize) -> Result<String, UrlencodedError> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ind = 0;
+    while ind < bytes.len() {
+        match bytes[ind] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = text
+                    .get(ind + 1..ind + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(UrlencodedError::InvalidEscape {
+                        offset: offset + ind,
+                    })?;
+                decoded.push(byte);
+                ind += 2;
+            }
+            b => decoded.push(b),
+        }
+        ind += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| UrlencodedError::InvalidUtf8 { offset })
+}
+
+fn encode(text: &str, out: &mut String) {
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                out.push(b as char)
+            }
+            b' ' => out.push('+'),
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+/// Splits a decoded key into its name and bracketed segments.
+fn segments(key: &str) -> Option<(&str, Vec<Segment>)> {
+    let (name, mut rest) = match key.find('[') {
+        Some(start) => (&key[..start], &key[start..]),
+        None => (key, ""),
+    };
+    if name.is_empty() || name.contains(']') {
+        return None;
+    }
+
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        let end = rest.find(']')?;
+        let segment = rest.get(1..end).filter(|_| rest.starts_with('['))?;
+        if segment.contains('[') {
+            return None;
+        }
+        segments.push(if segment.is_empty() {
+            Segment::Append
+        } else {
+            Segment::Name(segment.to_string())
+        });
+        rest = &rest[end + 1..];
+    }
+    Some((name, segments))
+}
+
+fn index(segment: &str) -> Option<usize> {
+    segment
+        .parse::<usize>()
+        .ok()
+        .filter(|ind| ind.to_string() == segment)
+}
+
+/// Builds the value a key's remaining segments describe when nothing is there yet.
+fn build(segments: &[Segment], value: JsonValue) -> Option<JsonValue> {
+    let (first, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return Some(value),
+    };
+    Some(match first {
+        Segment::Append => JsonValue::Array(vec![build(rest, value)?]),
+        Segment::Name(name) => match index(name) {
+            Some(0) => JsonValue::Array(vec![build(rest, value)?]),
+            Some(_) => return None,
+            None => {
+                let mut map = JsonMap::new();
+                map.insert(name.as_str().into(), build(rest, value)?);
+                JsonValue::Object(Box::new(map))
+            }
+        },
+    })
+}
+
+fn insert(map: &mut JsonMap, name: &str, segments: &[Segment], value: JsonValue) -> Option<()> {
+    match map.get_mut(name) {
+        Some(existing) => merge(existing, segments, value),
+        None => {
+            map.insert(name.into(), build(segments, value)?);
+            Some(())
+        }
+    }
+}
+
+/// Adds a value at a key's remaining segments to what earlier keys have built.
+fn merge(existing: &mut JsonValue, segments: &[Segment], value: JsonValue) -> Option<()> {
+    let (first, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            match existing {
+                JsonValue::Array(items) => items.push(value),
+                JsonValue::Object(_) => return None,
+                scalar => {
+                    let first = ::std::mem::replace(scalar, JsonValue::Null);
+                    *scalar = JsonValue::Array(vec![first, value]);
+                }
+            }
+            return Some(());
+        }
+    };
+
+    match (first, existing) {
+        (Segment::Append, JsonValue::Array(items)) => {
+            if let (Some(Segment::Name(name)), Some(JsonValue::Object(last))) =
+                (rest.first(), items.last_mut())
+            {
+                if !last.contains_key(name.as_str()) {
+                    return insert(last, name, &rest[1..], value);
+                }
+            }
+            items.push(build(rest, value)?);
+            Some(())
+        }
+        (Segment::Name(name), JsonValue::Object(map)) => insert(map, name, rest, value),
+        (Segment::Name(name), JsonValue::Array(items)) => match index(name)? {
+            ind if ind < items.len() => merge(&mut items[ind], rest, value),
+            ind if ind == items.len() => {
+                items.push(build(rest, value)?);
+                Some(())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reads form data as an object.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::urlencoded::from_urlencoded;
+///
+/// fn main() {
+///     assert_eq!(
+///         from_urlencoded("user[name]=Ada+L&user[langs][]=en&user[langs][]=fr&q=a%26b&flag"),
+///         Ok(json_parse(r#"{
+///             "user": { "name": "Ada L", "langs": ["en", "fr"] },
+///             "q": "a&b",
+///             "flag": null
+///         }"#)
+///         .unwrap())
+///     );
+/// }
+/// ```
+pub fn from_urlencoded(input: &str) -> Result<JsonValue, UrlencodedError> {
+    let mut map = JsonMap::new();
+    let mut offset = 0;
+    for pair in input.split('&') {
+        let start = offset;
+        offset += pair.len() + 1;
+        if pair.is_empty() {
+            continue;
+        }
+
+        let (key, value) = match pair.find('=') {
+            Some(eq) => (
+                decode(&pair[..eq], start)?,
+                JsonValue::Text(decode(&pair[eq + 1..], start + eq + 1)?.into()),
+            ),
+            None => (decode(pair, start)?, JsonValue::Null),
+        };
+        let (name, segments) = match segments(&key) {
+            Some(parsed) => parsed,
+            None => return Err(UrlencodedError::MalformedKey(key)),
+        };
+        if insert(&mut map, name, &segments, value).is_none() {
+            return Err(UrlencodedError::Conflict(key));
+        }
+    }
+    Ok(JsonValue::Object(Box::new(map)))
+}
+
+fn write(
+    key: &str,
+    value: &JsonValue,
+    path: &JsonPointer,
+    in_array: bool,
+    out: &mut Vec<String>,
+) -> Result<(), UrlencodedError> {
+    let mut pair = key.to_string();
+    match value {
+        JsonValue::Object(map) if map.is_empty() && in_array => {
+            return Err(UrlencodedError::Unrepresentable(path.clone()))
+        }
+        JsonValue::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            for member in keys.into_iter() {
+                let path = path.child(member.as_str());
+                if member.is_empty() || member.contains(&['[', ']'][..]) || index(member).is_some()
+                {
+                    return Err(UrlencodedError::Unrepresentable(path));
+                }
+                let mut key = format!("{}[", key);
+                encode(member, &mut key);
+                key.push(']');
+                write(&key, &map[member], &path, false, out)?;
+            }
+            return Ok(());
+        }
+        JsonValue::Array(items) if items.is_empty() && in_array => {
+            return Err(UrlencodedError::Unrepresentable(path.clone()))
+        }
+        JsonValue::Array(items) => {
+            let nested = items
+                .iter()
+                .any(|item| matches!(item, JsonValue::Array(_) | JsonValue::Object(_)));
+            for (ind, item) in items.iter().enumerate() {
+                let key = if nested {
+                    format!("{}[{}]", key, ind)
+                } else {
+                    format!("{}[]", key)
+                };
+                write(&key, item, &path.child(ind.to_string()), true, out)?;
+            }
+            return Ok(());
+        }
+        JsonValue::Raw(raw) => return write(key, &raw.parse(), path, in_array, out),
+        JsonValue::Null => (),
+        JsonValue::Boolean(b) => pair.push_str(if *b { "=true" } else { "=false" }),
+        JsonValue::Number(n) => pair.push_str(&format!("={}", n)),
+        JsonValue::Text(text) => {
+            pair.push('=');
+            encode(text, &mut pair);
+        }
+    }
+    out.push(pair);
+    Ok(())
+}
+
+/// Writes an object as form data, with keys in order.
+///
+/// Numbers and booleans are written as text and `null` as a key with no `=`.  Empty arrays and
+/// objects in an object are left out.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::urlencoded::to_urlencoded;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "tags": ["a b", "c"], "page": { "size": 20, "token": null } }"#);
+///     assert_eq!(
+///         to_urlencoded(&json.unwrap()),
+///         Ok("page[size]=20&page[token]&tags[]=a+b&tags[]=c".to_string())
+///     );
+/// }
+/// ```
+pub fn to_urlencoded(value: &JsonValue) -> Result<String, UrlencodedError> {
+    let map = match value {
+        JsonValue::Object(map) => map,
+        _ => return Err(UrlencodedError::NotAnObject),
+    };
+    let mut keys = map.keys().collect::<Vec<_>>();
+    keys.sort();
+
+    let mut pairs = Vec::new();
+    for key in keys.into_iter() {
+        let path = JsonPointer::root().child(key.as_str());
+        if key.is_empty() || key.contains(&['[', ']'][..]) {
+            return Err(UrlencodedError::Unrepresentable(path));
+        }
+        let mut encoded = String::new();
+        encode(key, &mut encoded);
+        write(&encoded, &map[key], &path, false, &mut pairs)?;
+    }
+    Ok(pairs.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_urlencoded, to_urlencoded, UrlencodedError};
+    use crate::json_parse;
+
+    #[test]
+    fn urlencoded_read() {
+        assert_eq!(
+            from_urlencoded(
+                "a=1&a=2&&b[][x]=1&b[][y]=2&b[][x]=3&c[0][]=p&c[0][]=q&c[1][k]=v&d%5Be%5D=%C3%A9&f="
+            ),
+            Ok(json_parse(
+                r#"{
+                    "a": ["1", "2"],
+                    "b": [{ "x": "1", "y": "2" }, { "x": "3" }],
+                    "c": [["p", "q"], { "k": "v" }],
+                    "d": { "e": "é" },
+                    "f": ""
+                }"#
+            )
+            .unwrap())
+        );
+
+        assert_eq!(
+            from_urlencoded("a=%2"),
+            Err(UrlencodedError::InvalidEscape { offset: 2 })
+        );
+        assert_eq!(
+            UrlencodedError::InvalidEscape { offset: 2 }.to_string(),
+            "'%' at offset 2 is not followed by two hex digits"
+        );
+        assert_eq!(
+            from_urlencoded("a=1&b=%FF"),
+            Err(UrlencodedError::InvalidUtf8 { offset: 6 })
+        );
+        assert_eq!(
+            from_urlencoded("a[b=1"),
+            Err(UrlencodedError::MalformedKey("a[b".into()))
+        );
+        assert_eq!(
+            from_urlencoded("[a]=1"),
+            Err(UrlencodedError::MalformedKey("[a]".into()))
+        );
+        assert_eq!(
+            from_urlencoded("a=1&a[b]=2"),
+            Err(UrlencodedError::Conflict("a[b]".into()))
+        );
+        assert_eq!(
+            from_urlencoded("a[2]=1"),
+            Err(UrlencodedError::Conflict("a[2]".into()))
+        );
+    }
+
+    #[test]
+    fn urlencoded_write() {
+        let json = json_parse(
+            r#"{
+                "list": [[1, 2], { "k": true }],
+                "name": "x&y=z/é",
+                "skip": {}
+            }"#,
+        )
+        .unwrap();
+        let encoded = to_urlencoded(&json).unwrap();
+        assert_eq!(
+            encoded,
+            "list[0][]=1&list[0][]=2&list[1][k]=true&name=x%26y%3Dz%2F%C3%A9"
+        );
+        assert_eq!(
+            from_urlencoded(&encoded),
+            Ok(
+                json_parse(r#"{ "list": [["1", "2"], { "k": "true" }], "name": "x&y=z/é" }"#)
+                    .unwrap()
+            )
+        );
+
+        assert_eq!(
+            to_urlencoded(&json_parse("[]").unwrap()),
+            Err(UrlencodedError::NotAnObject)
+        );
+        assert_eq!(
+            to_urlencoded(&json_parse(r#"{ "a": { "0": 1 } }"#).unwrap()),
+            Err(UrlencodedError::Unrepresentable("/a/0".parse().unwrap()))
+        );
+        assert_eq!(
+            to_urlencoded(&json_parse(r#"{ "a": [[]] }"#).unwrap()),
+            Err(UrlencodedError::Unrepresentable("/a/0".parse().unwrap()))
+        );
+    }
+}
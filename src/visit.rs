@@ -0,0 +1,238 @@
+//! Event-driven parsing, which reports the structure of a document to a visitor as it is read
+//! instead of building a `JsonValue`.
+use super::events::{Event, EventParser};
+use super::options::ParseOptions;
+use super::{JsonError, JsonNumber, JsonValue};
+
+/// Receives the parts of a document in the order they appear.  Every method does nothing by
+/// default, so a visitor only implements the ones it cares about.
+///
+/// Each `object_start` or `array_start` is matched by an `object_end` or `array_end` once the
+/// container's members or items have been visited.  Every member of an object is a `key`
+/// followed by the events of its value.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::{json_visit, JsonNumber, JsonVisitor};
+///
+/// /// Sums every number under a key named "price", at any depth.
+/// #[derive(Default)]
+/// struct Prices {
+///     in_price: bool,
+///     total: f64,
+/// }
+///
+/// impl JsonVisitor for Prices {
+///     fn key(&mut self, key: &str) {
+///         self.in_price = key == "price";
+///     }
+///
+///     fn number(&mut self, value: JsonNumber) {
+///         if self.in_price {
+///             self.total += value.as_f64();
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut prices = Prices::default();
+///     json_visit(
+///         r#"[{ "price": 2.5, "qty": 4 }, { "item": { "price": 1.5 } }]"#,
+///         &mut prices,
+///     )
+///     .unwrap();
+///     assert_eq!(prices.total, 4.0);
+/// }
+/// ```
+pub trait JsonVisitor {
+    /// An object begins.
+    fn object_start(&mut self) {}
+
+    /// The object that began last ends.
+    fn object_end(&mut self) {}
+
+    /// An array begins.
+    fn array_start(&mut self) {}
+
+    /// The array that began last ends.
+    fn array_end(&mut self) {}
+
+    /// The key of an object member, whose value follows.
+    fn key(&mut self, _key: &str) {}
+
+    /// A string value.
+    fn string(&mut self, _value: &str) {}
+
+    /// A number value.
+    fn number(&mut self, _value: JsonNumber) {}
+
+    /// A boolean value.
+    fn boolean(&mut self, _value: bool) {}
+
+    /// A `null` value.
+    fn null(&mut self) {}
+}
+
+/// Parses a JSON string, reporting its structure to `visitor` without building a `JsonValue`.
+///
+/// Open containers are tracked on an explicit stack rather than the call stack, so
+/// [`json_visit_with`](fn.json_visit_with.html) can allow any depth; this allows the 128 levels
+/// `json_parse` does.  When the document is invalid, the visitor has already seen every event
+/// before the error.
+pub fn json_visit<V: JsonVisitor + ?Sized>(
+    json_str: &str,
+    visitor: &mut V,
+) -> Result<(), JsonError> {
+    json_visit_with(json_str, ParseOptions::default(), visitor)
+}
+
+/// Parses a JSON string as `json_visit` does, accepting the syntax and depth allowed by
+/// `options`.  A member whose key was already seen in its object is reported or left out as
+/// `options.duplicate_keys` asks.
+pub fn json_visit_with<V: JsonVisitor + ?Sized>(
+    json_str: &str,
+    options: ParseOptions,
+    visitor: &mut V,
+) -> Result<(), JsonError> {
+    trace_span!("json_visit", bytes = json_str.len());
+    let mut parser = EventParser::with_options(json_str, options);
+    while let Some((event, _)) = trace_error!(parser.next_event())? {
+        match event {
+            Event::ObjectStart => visitor.object_start(),
+            Event::ObjectEnd => visitor.object_end(),
+            Event::ArrayStart => visitor.array_start(),
+            Event::ArrayEnd => visitor.array_end(),
+            Event::Key(key) => visitor.key(&key),
+            Event::Value(value) => match value {
+                JsonValue::Text(ref text) => visitor.string(text),
+                JsonValue::Number(n) => visitor.number(n),
+                JsonValue::Boolean(b) => visitor.boolean(b),
+                _ => visitor.null(),
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_visit, json_visit_with, JsonVisitor};
+    use crate::{JsonError, JsonNumber, ParseOptions};
+
+    /// Writes down every event.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl JsonVisitor for Recorder {
+        fn object_start(&mut self) {
+            self.0.push("{".into());
+        }
+
+        fn object_end(&mut self) {
+            self.0.push("}".into());
+        }
+
+        fn array_start(&mut self) {
+            self.0.push("[".into());
+        }
+
+        fn array_end(&mut self) {
+            self.0.push("]".into());
+        }
+
+        fn key(&mut self, key: &str) {
+            self.0.push(format!("key {}", key));
+        }
+
+        fn string(&mut self, value: &str) {
+            self.0.push(format!("string {}", value));
+        }
+
+        fn number(&mut self, value: JsonNumber) {
+            self.0.push(format!("number {}", value));
+        }
+
+        fn boolean(&mut self, value: bool) {
+            self.0.push(format!("boolean {}", value));
+        }
+
+        fn null(&mut self) {
+            self.0.push("null".into());
+        }
+    }
+
+    #[test]
+    fn visit_events() {
+        let mut recorder = Recorder::default();
+        json_visit(
+            r#"{ "a": [1, "two", {}], "b\n": [true, null, []] }"#,
+            &mut recorder,
+        )
+        .unwrap();
+        // members come in source order, whatever order an object's map would keep them in
+        assert_eq!(
+            recorder.0,
+            [
+                "{",
+                "key a",
+                "[",
+                "number 1",
+                "string two",
+                "{",
+                "}",
+                "]",
+                "key b\n",
+                "[",
+                "boolean true",
+                "null",
+                "[",
+                "]",
+                "]",
+                "}",
+            ]
+        );
+
+        let mut recorder = Recorder::default();
+        assert_eq!(
+            json_visit("[1, 2 3]", &mut recorder),
+            Err(JsonError::UnexpectedToken {
+                character: '3',
+                location: 6,
+                line: 0,
+                column: 6,
+                offset: 6,
+            })
+        );
+        assert_eq!(recorder.0, ["[", "number 1", "number 2"]);
+
+        let mut recorder = Recorder::default();
+        json_visit_with("[0 /* zero */]", ParseOptions::lenient(), &mut recorder).unwrap();
+        assert_eq!(recorder.0, ["[", "number 0", "]"]);
+    }
+
+    #[test]
+    fn visit_options() {
+        use crate::DuplicateKeys;
+
+        let first_wins = ParseOptions {
+            duplicate_keys: DuplicateKeys::FirstWins,
+            ..ParseOptions::default()
+        };
+        let mut recorder = Recorder::default();
+        json_visit_with(r#"{"a": 1, "a": [2], "b": 3}"#, first_wins, &mut recorder).unwrap();
+        assert_eq!(
+            recorder.0,
+            ["{", "key a", "number 1", "key b", "number 3", "}"]
+        );
+
+        let shallow = ParseOptions {
+            max_depth: 1,
+            ..ParseOptions::default()
+        };
+        let mut recorder = Recorder::default();
+        assert!(matches!(
+            json_visit_with("[1, [2]]", shallow, &mut recorder),
+            Err(JsonError::DepthLimitExceeded { location: 4, .. })
+        ));
+        assert_eq!(recorder.0, ["[", "number 1"]);
+    }
+}
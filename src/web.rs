@@ -0,0 +1,367 @@
+//! A `Json<T>` extractor and responder for web frameworks, enabled by the `axum` and
+//! `actix-web` features.
+//!
+//! Request bodies are parsed by `http::parse_body` with a limit of `http::DEFAULT_BODY_LIMIT`
+//! and converted with `FromJson`, and responses are written with `ToJson`, so types only need the
+//! conversion traits of this crate and no `serde` implementations.
+use super::convert::{FromJson, FromJsonError};
+use super::http::HttpError;
+use super::JsonValue;
+use std::fmt;
+
+/// A request body parsed into a `T`, or a `T` sent back as a response body.
+///
+/// As an extractor the request must have a JSON content type, either `application/json` or a
+/// type ending in `+json`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Json<T>(pub T);
+
+/// Describes all possible errors that could occur while extracting a `Json<T>`.
+#[derive(Debug)]
+pub enum JsonRejection {
+    /// The request did not have a JSON content type
+    ContentType,
+    /// The body could not be read or parsed
+    Body(HttpError),
+    /// The body was valid JSON but not a valid `T`
+    Convert(FromJsonError),
+}
+
+impl JsonRejection {
+    /// The status code the rejection is answered with: 415 for a missing content type, 413 for
+    /// a body over the limit, 422 for a value of the wrong shape and 400 otherwise.
+    pub fn status(&self) -> u16 {
+        match *self {
+            JsonRejection::ContentType => 415,
+            JsonRejection::Body(HttpError::TooLarge { .. }) => 413,
+            JsonRejection::Body(_) => 400,
+            JsonRejection::Convert(_) => 422,
+        }
+    }
+}
+
+impl fmt::Display for JsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonRejection::ContentType => write!(f, "expected a JSON content type"),
+            JsonRejection::Body(HttpError::TooLarge { limit }) => {
+                write!(f, "request body is larger than {} bytes", limit)
+            }
+            JsonRejection::Body(HttpError::Parse(ref e)) => {
+                write!(f, "request body is not valid JSON: {:?}", e)
+            }
+            JsonRejection::Body(ref e) => write!(f, "request body could not be read: {:?}", e),
+            JsonRejection::Convert(ref e) => {
+                write!(f, "request body has the wrong shape: {:?}", e)
+            }
+        }
+    }
+}
+
+impl From<HttpError> for JsonRejection {
+    fn from(error: HttpError) -> Self {
+        JsonRejection::Body(error)
+    }
+}
+
+/// Whether a `Content-Type` header names a JSON media type.
+fn is_json(content_type: Option<&[u8]>) -> bool {
+    let content_type = match content_type {
+        Some(content_type) => content_type,
+        None => return false,
+    };
+    let essence = content_type
+        .split(|b| *b == b';')
+        .next()
+        .unwrap_or_default()
+        .trim_ascii()
+        .to_ascii_lowercase();
+    essence == b"application/json"
+        || (essence.starts_with(b"application/") && essence.ends_with(b"+json"))
+}
+
+fn convert<T: FromJson>(result: Result<JsonValue, HttpError>) -> Result<Json<T>, JsonRejection> {
+    let value = result?;
+    T::from_json(&value)
+        .map(Json)
+        .map_err(JsonRejection::Convert)
+}
+
+#[cfg(feature = "axum")]
+mod axum_impl {
+    use super::{convert, is_json, Json, JsonRejection};
+    use crate::convert::{FromJson, ToJson};
+    use crate::http::{parse_body, ParseBody, DEFAULT_BODY_LIMIT};
+    use axum::body::Body;
+    use axum::extract::{FromRequest, Request};
+    use axum::http::header::{HeaderValue, CONTENT_TYPE};
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use std::future::Future;
+    use std::marker::PhantomData;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Parses and converts a request body, or rejects it outright.
+    enum Extract<T> {
+        Parse(ParseBody<Body>, PhantomData<fn() -> T>),
+        Reject(Option<JsonRejection>),
+    }
+
+    impl<T: FromJson> Future for Extract<T> {
+        type Output = Result<Json<T>, JsonRejection>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            match *self {
+                Extract::Parse(ref mut parse, _) => Pin::new(parse).poll(cx).map(convert),
+                Extract::Reject(ref mut rejection) => {
+                    Poll::Ready(Err(rejection.take().expect("polled after completion")))
+                }
+            }
+        }
+    }
+
+    impl<T: FromJson, S: Sync> FromRequest<S> for Json<T> {
+        type Rejection = JsonRejection;
+
+        fn from_request(
+            req: Request,
+            _state: &S,
+        ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+            let content_type = req.headers().get(CONTENT_TYPE).map(HeaderValue::as_bytes);
+            if is_json(content_type) {
+                Extract::Parse(parse_body(req.into_body(), DEFAULT_BODY_LIMIT), PhantomData)
+            } else {
+                Extract::Reject(Some(JsonRejection::ContentType))
+            }
+        }
+    }
+
+    impl IntoResponse for JsonRejection {
+        fn into_response(self) -> Response {
+            let status = StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST);
+            (status, self.to_string()).into_response()
+        }
+    }
+
+    impl<T: ToJson> IntoResponse for Json<T> {
+        fn into_response(self) -> Response {
+            (
+                [(CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+                self.0.to_json().to_string(),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+mod actix_impl {
+    use super::{convert, is_json, HttpError, Json, JsonRejection};
+    use crate::convert::{FromJson, ToJson};
+    use crate::http::{parse, push, DEFAULT_BODY_LIMIT};
+    use actix_web::body::BoxBody;
+    use actix_web::dev::Payload;
+    use actix_web::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use actix_web::http::StatusCode;
+    use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+    use futures_core::Stream;
+    use std::future::Future;
+    use std::marker::PhantomData;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// The future that extracts a `Json<T>` from an actix-web payload.
+    pub struct JsonExtract<T> {
+        payload: Option<Payload>,
+        bytes: Vec<u8>,
+        rejection: Option<JsonRejection>,
+        marker: PhantomData<fn() -> T>,
+    }
+
+    impl<T: FromJson> Future for JsonExtract<T> {
+        type Output = Result<Json<T>, JsonRejection>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let this = &mut *self;
+            if let Some(rejection) = this.rejection.take() {
+                return Poll::Ready(Err(rejection));
+            }
+            let payload = this.payload.as_mut().expect("polled after completion");
+            loop {
+                match Pin::new(&mut *payload).poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        if let Err(error) = push(&mut this.bytes, &chunk, DEFAULT_BODY_LIMIT) {
+                            this.payload = None;
+                            return Poll::Ready(Err(error.into()));
+                        }
+                    }
+                    Poll::Ready(Some(Err(error))) => {
+                        this.payload = None;
+                        return Poll::Ready(Err(JsonRejection::Body(HttpError::Body(Box::new(
+                            error,
+                        )))));
+                    }
+                    Poll::Ready(None) => {
+                        this.payload = None;
+                        return Poll::Ready(convert(parse(&this.bytes)));
+                    }
+                }
+            }
+        }
+    }
+
+    impl<T: FromJson> FromRequest for Json<T> {
+        type Error = JsonRejection;
+        type Future = JsonExtract<T>;
+
+        fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+            let content_type = req.headers().get(CONTENT_TYPE).map(|v| v.as_bytes());
+            let content_length = req
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<usize>().ok());
+            let rejection = if !is_json(content_type) {
+                Some(JsonRejection::ContentType)
+            } else if content_length.is_some_and(|len| len > DEFAULT_BODY_LIMIT) {
+                Some(JsonRejection::Body(HttpError::TooLarge {
+                    limit: DEFAULT_BODY_LIMIT,
+                }))
+            } else {
+                None
+            };
+            JsonExtract {
+                payload: Some(payload.take()),
+                bytes: Vec::new(),
+                rejection,
+                marker: PhantomData,
+            }
+        }
+    }
+
+    impl ResponseError for JsonRejection {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST)
+        }
+    }
+
+    impl<T: ToJson> Responder for Json<T> {
+        type Body = BoxBody;
+
+        fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(self.0.to_json().to_string())
+        }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+pub use self::actix_impl::JsonExtract;
+
+#[cfg(test)]
+mod tests {
+    use super::{is_json, Json, JsonRejection};
+    #[cfg(feature = "axum")]
+    use crate::json_parse;
+    #[cfg(feature = "actix-web")]
+    use crate::JsonValue;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn web_content_type() {
+        assert!(is_json(Some(b"application/json")));
+        assert!(is_json(Some(b"Application/JSON; charset=utf-8")));
+        assert!(is_json(Some(b"application/problem+json")));
+        assert!(!is_json(Some(b"text/plain")));
+        assert!(!is_json(Some(b"text/json+xml")));
+        assert!(!is_json(None));
+        assert_eq!(JsonRejection::ContentType.status(), 415);
+        assert_eq!(Json(1), Json(1));
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn web_axum() {
+        use axum::body::{to_bytes, Body};
+        use axum::extract::{FromRequest, Request};
+        use axum::response::IntoResponse;
+
+        let runtime = runtime();
+        let request = |content_type: &str, body: &'static str| {
+            Request::builder()
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let extract = |request| runtime.block_on(Json::<Vec<f64>>::from_request(request, &()));
+
+        assert_eq!(
+            extract(request("application/json", "[1, 2]")).unwrap(),
+            Json(vec![1.0, 2.0])
+        );
+        let rejection = extract(request("text/plain", "[1, 2]")).unwrap_err();
+        assert_eq!(rejection.into_response().status(), 415);
+        let rejection = extract(request("application/json", "[1,")).unwrap_err();
+        assert_eq!(rejection.into_response().status(), 400);
+        let rejection = extract(request("application/json", "{}")).unwrap_err();
+        assert_eq!(rejection.into_response().status(), 422);
+
+        let response = Json(json_parse(r#"{"ok":true}"#).unwrap()).into_response();
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = runtime
+            .block_on(to_bytes(response.into_body(), usize::MAX))
+            .unwrap();
+        assert_eq!(&body[..], b"{\"ok\":true}");
+    }
+
+    #[cfg(feature = "actix-web")]
+    #[test]
+    fn web_actix() {
+        use actix_web::body::to_bytes;
+        use actix_web::test::TestRequest;
+        use actix_web::{FromRequest, Responder, ResponseError};
+
+        let runtime = runtime();
+        let extract = |content_type: &str, body: &'static str| {
+            let (req, mut payload) = TestRequest::default()
+                .insert_header(("content-type", content_type))
+                .set_payload(body)
+                .to_http_parts();
+            runtime.block_on(Json::<Vec<f64>>::from_request(&req, &mut payload))
+        };
+
+        assert_eq!(
+            extract("application/json", "[1, 2]").unwrap(),
+            Json(vec![1.0, 2.0])
+        );
+        let rejection = extract("text/plain", "[1, 2]").unwrap_err();
+        assert_eq!(rejection.status_code().as_u16(), 415);
+        let rejection = extract("application/json", "[true]").unwrap_err();
+        assert_eq!(rejection.status_code().as_u16(), 422);
+        let (req, mut payload) = TestRequest::default()
+            .insert_header(("content-type", "application/json"))
+            .insert_header(("content-length", "3000000"))
+            .to_http_parts();
+        let rejection = runtime
+            .block_on(Json::<Vec<f64>>::from_request(&req, &mut payload))
+            .unwrap_err();
+        assert_eq!(rejection.status_code().as_u16(), 413);
+
+        let req = TestRequest::default().to_http_request();
+        let response = Json(JsonValue::Array(vec![JsonValue::Null])).respond_to(&req);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        let body = runtime.block_on(to_bytes(response.into_body())).unwrap();
+        assert_eq!(&body[..], b"[null]");
+    }
+}
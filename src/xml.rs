@@ -0,0 +1,379 @@
+//! Conversion between simple XML documents and `JsonValue`, enabled by the `xml` feature.
+//!
+//! Both directions use the same mapping:
+//!
+//! * The document is an object with a single member, named after the root element.
+//! * An element with only text is a string, and an empty element is `null`.
+//! * Any other element is an object.  Its attributes are members prefixed with `@`, its text is
+//!   the `#text` member, and each child element is a member named after it.  Children that
+//!   share a name are collected into an array, in document order.
+//!
+//! Text is never converted to other types, so numbers and booleans turn into strings on the
+//! way through XML.  Elements in mixed content lose their position relative to the text around
+//! them, and comments and processing instructions are dropped.  Element names are written as
+//! they are, so keys must be valid XML names.  Object members are written in key order.
+use super::{JsonMap, JsonPointer, JsonValue};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::{error, fmt, str};
+
+/// Describes all possible errors that could occur while converting XML
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlError {
+    /// The XML was not well-formed
+    Syntax {
+        /// What was wrong
+        message: String,
+        /// The byte offset the problem was found at
+        position: u64,
+    },
+    /// There was no root element or more than one, or the JSON was not an object with a single
+    /// member
+    Root,
+    /// A value has no XML equivalent, such as an attribute that is not a string, number or
+    /// boolean, or an array directly inside an array
+    Unrepresentable(JsonPointer),
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XmlError::Syntax { message, position } => {
+                write!(f, "{} at byte offset {}", message, position)
+            }
+            XmlError::Root => f.write_str("a document needs exactly one root element"),
+            XmlError::Unrepresentable(path) => {
+                write!(
+                    f,
+                    "the value at {:?} has no XML equivalent",
+                    path.to_string()
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for XmlError {}
+
+/// An element that has been started but not ended.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<(String, JsonValue)>,
+}
+
+impl Element {
+    fn start(tag: &BytesStart) -> Result<Self, String> {
+        let name = str::from_utf8(tag.name().as_ref())
+            .map_err(|e| e.to_string())?
+            .to_string();
+        let attributes = tag
+            .attributes()
+            .map(|attr| {
+                let attr = attr.map_err(|e| e.to_string())?;
+                let key = str::from_utf8(attr.key.as_ref()).map_err(|e| e.to_string())?;
+                let value = attr.unescape_value().map_err(|e| e.to_string())?;
+                Ok((key.to_string(), value.into_owned()))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Element {
+            name,
+            attributes,
+            text: String::new(),
+            children: Vec::new(),
+        })
+    }
+
+    fn end(self) -> (String, JsonValue) {
+        if self.attributes.is_empty() && self.children.is_empty() {
+            let value = if self.text.is_empty() {
+                JsonValue::Null
+            } else {
+                JsonValue::Text(self.text.into())
+            };
+            return (self.name, value);
+        }
+
+        let mut map = JsonMap::new();
+        for (key, value) in self.attributes.into_iter() {
+            map.insert(format!("@{}", key).into(), JsonValue::Text(value.into()));
+        }
+        if !self.text.is_empty() {
+            map.insert("#text".into(), JsonValue::Text(self.text.into()));
+        }
+        for (name, child) in self.children.into_iter() {
+            match map.get_mut(name.as_str()) {
+                None => {
+                    map.insert(name.into(), child);
+                }
+                Some(JsonValue::Array(repeated)) => repeated.push(child),
+                Some(first) => *first = JsonValue::Array(vec![first.take(), child]),
+            }
+        }
+        (self.name, JsonValue::Object(Box::new(map)))
+    }
+}
+
+/// Converts an XML document to JSON.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::xml::from_xml;
+///
+/// fn main() {
+///     let xml = r#"<?xml version="1.0"?>
+///         <order id="7">
+///             <item sku="a1">Pen</item>
+///             <item sku="b2">Ink</item>
+///             <note>Gift &amp; wrap</note>
+///             <rush/>
+///         </order>"#;
+///     assert_eq!(
+///         from_xml(xml),
+///         Ok(json_parse(r##"{
+///             "order": {
+///                 "@id": "7",
+///                 "item": [{ "@sku": "a1", "#text": "Pen" }, { "@sku": "b2", "#text": "Ink" }],
+///                 "note": "Gift & wrap",
+///                 "rush": null
+///             }
+///         }"##)
+///         .unwrap())
+///     );
+/// }
+/// ```
+pub fn from_xml(xml: &str) -> Result<JsonValue, XmlError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let syntax = |reader: &Reader<&[u8]>, message: String| XmlError::Syntax {
+        message,
+        position: reader.error_position(),
+    };
+
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| syntax(&reader, e.to_string()))?;
+        let ended = match event {
+            Event::Start(tag) => {
+                stack.push(Element::start(&tag).map_err(|e| syntax(&reader, e))?);
+                None
+            }
+            Event::Empty(tag) => Some(Element::start(&tag).map_err(|e| syntax(&reader, e))?),
+            Event::End(_) => stack.pop(),
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| syntax(&reader, e.to_string()))?;
+                match stack.last_mut() {
+                    Some(element) => element.text.push_str(&text),
+                    None => return Err(syntax(&reader, "text outside the root element".into())),
+                }
+                None
+            }
+            Event::CData(data) => {
+                let data = data.decode().map_err(|e| syntax(&reader, e.to_string()))?;
+                match stack.last_mut() {
+                    Some(element) => element.text.push_str(&data),
+                    None => return Err(syntax(&reader, "text outside the root element".into())),
+                }
+                None
+            }
+            Event::Eof if !stack.is_empty() => {
+                return Err(syntax(&reader, "unclosed element".into()))
+            }
+            Event::Eof => break,
+            _ => None,
+        };
+
+        if let Some(element) = ended {
+            let (name, value) = element.end();
+            match stack.last_mut() {
+                Some(parent) => parent.children.push((name, value)),
+                None if root.is_none() => root = Some((name, value)),
+                None => return Err(XmlError::Root),
+            }
+        }
+    }
+
+    let (name, value) = root.ok_or(XmlError::Root)?;
+    let mut map = JsonMap::new();
+    map.insert(name.into(), value);
+    Ok(JsonValue::Object(Box::new(map)))
+}
+
+fn scalar(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Text(text) => Some(text.to_string()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Boolean(b) => Some(b.to_string()),
+        JsonValue::Raw(raw) => scalar(&raw.parse()),
+        _ => None,
+    }
+}
+
+fn write(
+    name: &str,
+    value: &JsonValue,
+    path: &JsonPointer,
+    out: &mut String,
+) -> Result<(), XmlError> {
+    let map = match value {
+        JsonValue::Null => {
+            out.push_str(&format!("<{}/>", name));
+            return Ok(());
+        }
+        JsonValue::Array(items) => {
+            for (ind, item) in items.iter().enumerate() {
+                if let JsonValue::Array(_) = item {
+                    return Err(XmlError::Unrepresentable(path.child(ind.to_string())));
+                }
+                write(name, item, &path.child(ind.to_string()), out)?;
+            }
+            return Ok(());
+        }
+        JsonValue::Object(map) => map,
+        JsonValue::Raw(raw) => return write(name, &raw.parse(), path, out),
+        scalar_value => {
+            let text = scalar(scalar_value).unwrap();
+            out.push_str(&format!("<{}>{}</{}>", name, escape(text.as_str()), name));
+            return Ok(());
+        }
+    };
+
+    let mut keys = map.keys().collect::<Vec<_>>();
+    keys.sort();
+    out.push('<');
+    out.push_str(name);
+    for key in keys.iter().filter(|key| key.starts_with('@')) {
+        let value = scalar(&map[*key])
+            .ok_or_else(|| XmlError::Unrepresentable(path.child(key.as_str())))?;
+        out.push_str(&format!(" {}=\"{}\"", &key[1..], escape(value.as_str())));
+    }
+
+    let text = match map.get("#text") {
+        Some(text) => {
+            Some(scalar(text).ok_or_else(|| XmlError::Unrepresentable(path.child("#text")))?)
+        }
+        None => None,
+    };
+    let children = keys
+        .iter()
+        .filter(|key| !key.starts_with('@') && key.as_str() != "#text")
+        .collect::<Vec<_>>();
+    if text.is_none() && children.is_empty() {
+        out.push_str("/>");
+        return Ok(());
+    }
+
+    out.push('>');
+    if let Some(text) = text {
+        out.push_str(&escape(text.as_str()));
+    }
+    for key in children.into_iter() {
+        write(key, &map[*key], &path.child(key.as_str()), out)?;
+    }
+    out.push_str(&format!("</{}>", name));
+    Ok(())
+}
+
+/// Converts JSON to an XML document, without an XML declaration.
+/// ```
+/// extern crate json_rs;
+/// use json_rs::json_parse;
+/// use json_rs::xml::to_xml;
+///
+/// fn main() {
+///     let json = json_parse(r#"{ "feed": { "@lang": "en", "entry": ["a", "b < c"], "empty": null } }"#);
+///     assert_eq!(
+///         to_xml(&json.unwrap()),
+///         Ok(r#"<feed lang="en"><empty/><entry>a</entry><entry>b &lt; c</entry></feed>"#.to_string())
+///     );
+/// }
+/// ```
+pub fn to_xml(value: &JsonValue) -> Result<String, XmlError> {
+    let (name, value) = match value {
+        JsonValue::Object(map) if map.len() == 1 => map.iter().next().unwrap(),
+        _ => return Err(XmlError::Root),
+    };
+    if let JsonValue::Array(_) = value {
+        return Err(XmlError::Root);
+    }
+    let mut out = String::new();
+    write(
+        name,
+        value,
+        &JsonPointer::root().child(name.as_str()),
+        &mut out,
+    )?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_xml, to_xml, XmlError};
+    use crate::json_parse;
+
+    #[test]
+    fn xml_round_trip() {
+        let json = json_parse(
+            r##"{
+                "a": {
+                    "@x": "1",
+                    "#text": "t \"q\" & more",
+                    "b": [null, "two", { "@y": "'", "c": "deep" }],
+                    "d": { "e": null }
+                }
+            }"##,
+        )
+        .unwrap();
+        let xml = to_xml(&json).unwrap();
+        assert_eq!(
+            xml,
+            r#"<a x="1">t &quot;q&quot; &amp; more<b/><b>two</b><b y="&apos;"><c>deep</c></b><d><e/></d></a>"#
+        );
+        assert_eq!(from_xml(&xml), Ok(json));
+
+        assert_eq!(
+            from_xml("<!-- c --><r><![CDATA[<raw>]]><s>1</s>tail</r>"),
+            Ok(json_parse(r##"{ "r": { "#text": "<raw>tail", "s": "1" } }"##).unwrap())
+        );
+        assert_eq!(
+            to_xml(&json_parse(r#"{ "n": { "v": [1, true] } }"#).unwrap()),
+            Ok("<n><v>1</v><v>true</v></n>".to_string())
+        );
+    }
+
+    #[test]
+    fn xml_errors() {
+        assert!(matches!(
+            from_xml("<a><b></a>"),
+            Err(XmlError::Syntax { .. })
+        ));
+        assert!(matches!(from_xml("<a>"), Err(XmlError::Syntax { .. })));
+        assert_eq!(from_xml("<a/><b/>"), Err(XmlError::Root));
+        assert_eq!(from_xml(""), Err(XmlError::Root));
+
+        assert_eq!(to_xml(&json_parse("[]").unwrap()), Err(XmlError::Root));
+        assert_eq!(
+            to_xml(&json_parse(r#"{ "a": 1, "b": 2 }"#).unwrap()),
+            Err(XmlError::Root)
+        );
+        assert_eq!(
+            to_xml(&json_parse(r#"{ "a": { "@x": [] } }"#).unwrap()),
+            Err(XmlError::Unrepresentable("/a/@x".parse().unwrap()))
+        );
+        assert_eq!(
+            to_xml(&json_parse(r#"{ "a": { "b": [[1]] } }"#).unwrap()),
+            Err(XmlError::Unrepresentable("/a/b/0".parse().unwrap()))
+        );
+        assert_eq!(
+            XmlError::Unrepresentable("/a/@x".parse().unwrap()).to_string(),
+            r#"the value at "/a/@x" has no XML equivalent"#
+        );
+    }
+}
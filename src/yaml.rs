@@ -0,0 +1,246 @@
+use super::{escape_str, JsonNumber, JsonValue};
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+/// Whether a string can be written without quotes and still be read back as the same string,
+/// including by YAML 1.1 parsers that treat words like `yes` and `off` as booleans.
+fn plain(s: &str) -> bool {
+    let first = match s.chars().next() {
+        Some(first) => first,
+        None => return false,
+    };
+    let lower = s.to_ascii_lowercase();
+    let reserved = [
+        "null", "~", "true", "false", "yes", "no", "on", "off", "y", "n", "<<",
+    ];
+
+    !reserved.contains(&lower.as_str())
+        && !first.is_ascii_digit()
+        && !"-?:,[]{}#&*!|>'\"%@`.+~ ".contains(first)
+        && !s.ends_with(|c: char| c == ':' || c.is_whitespace())
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.contains(char::is_control)
+}
+
+/// Whether a string can be written as a literal block: it spans several lines, its first line
+/// with text does not start with a space (which would be taken as indentation), and it has
+/// nothing a literal block cannot hold.
+fn literal(s: &str) -> bool {
+    s.contains('\n')
+        && s.split('\n')
+            .find(|line| !line.is_empty())
+            .is_some_and(|line| !line.starts_with(' '))
+        && !s.contains(|c: char| c.is_control() && c != '\n' && c != '\t')
+}
+
+fn scalar(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "null".to_string(),
+        JsonValue::Boolean(b) => b.to_string(),
+        JsonValue::Number(JsonNumber::F64(n)) if n.is_nan() => ".nan".to_string(),
+        JsonValue::Number(JsonNumber::F64(n)) if n.is_infinite() && *n > 0.0 => ".inf".to_string(),
+        JsonValue::Number(JsonNumber::F64(n)) if n.is_infinite() => "-.inf".to_string(),
+        JsonValue::Number(n) => n.to_string(),
+        JsonValue::Text(s) if plain(s) => s.to_string(),
+        JsonValue::Text(s) => escape_str(s),
+        JsonValue::Array(_) => "[]".to_string(),
+        JsonValue::Object(_) => "{}".to_string(),
+        JsonValue::Raw(raw) => scalar(&raw.parse()),
+    }
+}
+
+/// Writes a value after a `key:` or `-` indicator.  `compact` allows an object or array to
+/// start on the indicator's line, as it can in a sequence.
+fn node(value: &JsonValue, indent: usize, compact: bool, out: &mut String) {
+    match value {
+        JsonValue::Text(s) if literal(s) => {
+            let body = s.trim_end_matches('\n');
+            out.push_str(match s.len() - body.len() {
+                0 => " |-\n",
+                1 => " |\n",
+                _ => " |+\n",
+            });
+            for line in body.split('\n') {
+                if !line.is_empty() {
+                    out.push_str(&" ".repeat(indent));
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+            for _ in 1..s.len() - body.len() {
+                out.push('\n');
+            }
+        }
+        JsonValue::Array(array) if !array.is_empty() => {
+            if compact {
+                out.push(' ');
+            } else {
+                out.push('\n');
+            }
+            block(value, indent, compact, out);
+        }
+        JsonValue::Object(map) if !map.is_empty() => {
+            if compact {
+                out.push(' ');
+            } else {
+                out.push('\n');
+            }
+            block(value, indent, compact, out);
+        }
+        JsonValue::Raw(raw) => node(&raw.parse(), indent, compact, out),
+        _ => {
+            out.push(' ');
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes the entries of a non-empty object or array at `indent`, except that the first entry
+/// continues the current line if `inline` is set.
+fn block(value: &JsonValue, indent: usize, inline: bool, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        JsonValue::Array(array) => {
+            for (ind, item) in array.iter().enumerate() {
+                if ind > 0 || !inline {
+                    out.push_str(&pad);
+                }
+                out.push('-');
+                node(item, indent + 2, true, out);
+            }
+        }
+        JsonValue::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            for (ind, key) in keys.into_iter().enumerate() {
+                if ind > 0 || !inline {
+                    out.push_str(&pad);
+                }
+                if plain(key) {
+                    out.push_str(key);
+                } else {
+                    out.push_str(&escape_str(key));
+                }
+                out.push(':');
+                node(&map[key], indent + 2, false, out);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+impl JsonValue {
+    /// Serializes a JsonValue as block-style YAML.  Enabled by the `yaml` feature.
+    ///
+    /// Object keys are sorted, strings are only quoted where YAML would otherwise read them as
+    /// something else, and strings spanning several lines are written as literal blocks.
+    /// ```
+    /// extern crate json_rs;
+    /// use json_rs::json_parse;
+    ///
+    /// fn main() {
+    ///     let json = json_parse(r#"{
+    ///         "kind": "ConfigMap",
+    ///         "metadata": { "name": "app", "labels": { "tier": "web" } },
+    ///         "data": { "port": "8080", "script": "echo hi\necho bye\n" },
+    ///         "ports": [{ "name": "http", "port": 80 }, { "name": "no", "port": 81 }]
+    ///     }"#).unwrap();
+    ///     assert_eq!(
+    ///         json.to_yaml_string(),
+    ///         "\
+    /// data:
+    ///   port: \"8080\"
+    ///   script: |
+    ///     echo hi
+    ///     echo bye
+    /// kind: ConfigMap
+    /// metadata:
+    ///   labels:
+    ///     tier: web
+    ///   name: app
+    /// ports:
+    ///   - name: http
+    ///     port: 80
+    ///   - name: \"no\"
+    ///     port: 81
+    /// "
+    ///     );
+    /// }
+    /// ```
+    pub fn to_yaml_string(&self) -> String {
+        let mut out = String::new();
+        match self {
+            JsonValue::Array(array) if !array.is_empty() => block(self, 0, false, &mut out),
+            JsonValue::Object(map) if !map.is_empty() => block(self, 0, false, &mut out),
+            JsonValue::Raw(raw) => return raw.parse().to_yaml_string(),
+            _ => {
+                out.push_str(&scalar(self));
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json_parse, JsonValue};
+
+    #[test]
+    fn yaml_block_style() {
+        let json = json_parse(
+            r#"{
+                "nested": [[1, [2]], [], {}, { "a": { "b": null } }],
+                "quoted": ["", "yes", "1.5", "- x", "a: b", "trailing ", "tab\there", "~"],
+                "plain": ["hello world", "a-b", "key:value"],
+                "text": ["no newline\nat end", "two\n\n", "\n leading space"],
+                "odd key": true,
+                "": false
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            json.to_yaml_string(),
+            r#""": false
+nested:
+  - - 1
+    - - 2
+  - []
+  - {}
+  - a:
+      b: null
+odd key: true
+plain:
+  - hello world
+  - a-b
+  - key:value
+quoted:
+  - ""
+  - "yes"
+  - "1.5"
+  - "- x"
+  - "a: b"
+  - "trailing "
+  - "tab\there"
+  - "~"
+text:
+  - |-
+    no newline
+    at end
+  - |+
+    two
+
+  - "\n leading space"
+"#
+        );
+
+        assert_eq!(JsonValue::Text("x".into()).to_yaml_string(), "x\n");
+        assert_eq!(JsonValue::Array(vec![]).to_yaml_string(), "[]\n");
+        assert_eq!(
+            JsonValue::Array(vec![JsonValue::from(f64::NEG_INFINITY)]).to_yaml_string(),
+            "- -.inf\n"
+        );
+    }
+}